@@ -0,0 +1,7 @@
+-- Per-feed HTTP cache validators used for conditional GET requests.
+CREATE TABLE IF NOT EXISTS feed_cache (
+    feed_url      TEXT PRIMARY KEY,
+    etag          TEXT,
+    last_modified TEXT,
+    updated_at    TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,3 @@
+-- SHA-256 of the last fetched body, for servers that ignore conditional GET.
+ALTER TABLE feed_cache
+    ADD COLUMN IF NOT EXISTS body_hash TEXT;
@@ -0,0 +1,3 @@
+-- Lead image (media thumbnail, image enclosure, or first <img>) for card-style display.
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS image_url TEXT;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS image_url TEXT;
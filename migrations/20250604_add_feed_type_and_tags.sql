@@ -0,0 +1,8 @@
+-- Source classification from config::Feed, carried onto every stored item.
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS feed_type TEXT;
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS feed_tags TEXT[];
+ALTER TABLE current ADD COLUMN IF NOT EXISTS feed_type TEXT;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS feed_tags TEXT[];
+
+CREATE INDEX IF NOT EXISTS idx_current_feed_type ON current(feed_type);
+CREATE INDEX IF NOT EXISTS idx_current_feed_tags ON current USING GIN (feed_tags);
@@ -0,0 +1,9 @@
+-- Daily per-feed resource usage, for quota enforcement and cost breakdowns.
+CREATE TABLE IF NOT EXISTS feed_costs (
+    feed_name     TEXT   NOT NULL,
+    day           DATE   NOT NULL DEFAULT CURRENT_DATE,
+    fetches       BIGINT NOT NULL DEFAULT 0,
+    bytes_fetched BIGINT NOT NULL DEFAULT 0,
+    rows_written  BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (feed_name, day)
+);
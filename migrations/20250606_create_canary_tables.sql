@@ -0,0 +1,16 @@
+-- Configuration fingerprint and canary countdown for every configured feed.
+CREATE TABLE IF NOT EXISTS feed_registry (
+    feed_name          TEXT PRIMARY KEY,
+    config_hash        TEXT NOT NULL,
+    canary_cycles_left INT  NOT NULL DEFAULT 0,
+    updated_at         TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Items from feeds still in canary, held back from archive/current and sinks.
+CREATE TABLE IF NOT EXISTS canary_entries (
+    feed_name   TEXT  NOT NULL,
+    guid        TEXT  NOT NULL,
+    item        JSONB NOT NULL,
+    inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (feed_name, guid)
+);
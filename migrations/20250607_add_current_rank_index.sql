@@ -0,0 +1,4 @@
+-- Index matching db_utils::RANK_ORDER so ranked listings and keyset pagination
+-- over `current` don't sort the whole table.
+CREATE INDEX IF NOT EXISTS current_rank_idx
+    ON current ((COALESCE(published, '-infinity'::timestamp)) DESC, inserted_at DESC, id DESC);
@@ -0,0 +1,8 @@
+-- Locally cached feed icons (or site favicons), served from /icons/{feed}.
+CREATE TABLE IF NOT EXISTS feed_icons (
+    feed_name    TEXT PRIMARY KEY,
+    source_url   TEXT  NOT NULL,
+    content_type TEXT  NOT NULL,
+    data         BYTEA NOT NULL,
+    fetched_at   TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,10 @@
+-- Products and version ranges extracted from entry text (see enrich.rs).
+CREATE TABLE IF NOT EXISTS affected_products (
+    guid         TEXT NOT NULL,
+    product      TEXT NOT NULL,
+    versions     TEXT NOT NULL,
+    extracted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (guid, product, versions)
+);
+CREATE INDEX IF NOT EXISTS affected_products_product_idx
+    ON affected_products (lower(product), extracted_at);
@@ -0,0 +1,3 @@
+-- TLP marking (CLEAR, GREEN, AMBER, AMBER+STRICT, RED) from feed config or entry markers.
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS tlp TEXT;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS tlp TEXT;
@@ -0,0 +1,9 @@
+-- Full-text search over `current` (see db_utils::search_entries); title matches
+-- weigh most, then summary, then content. HTML tags are skipped by the parser.
+ALTER TABLE current ADD COLUMN IF NOT EXISTS search tsvector
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', COALESCE(title, '')), 'A') ||
+        setweight(to_tsvector('english', COALESCE(summary, '')), 'B') ||
+        setweight(to_tsvector('english', COALESCE(content, '')), 'C')
+    ) STORED;
+CREATE INDEX IF NOT EXISTS current_search_idx ON current USING GIN (search);
@@ -0,0 +1,14 @@
+-- One row per feed run (see history.rs), trimmed to the newest N per feed.
+CREATE TABLE IF NOT EXISTS fetch_history (
+    id           BIGSERIAL PRIMARY KEY,
+    feed_name    TEXT      NOT NULL,
+    started_at   TIMESTAMP NOT NULL,
+    status       TEXT      NOT NULL,
+    http_status  INT,
+    entries_seen INT       NOT NULL DEFAULT 0,
+    entries_new  INT       NOT NULL DEFAULT 0,
+    errors       INT       NOT NULL DEFAULT 0,
+    duration_ms  BIGINT    NOT NULL,
+    error        TEXT
+);
+CREATE INDEX IF NOT EXISTS fetch_history_feed_idx ON fetch_history (feed_name, id DESC);
@@ -0,0 +1,8 @@
+-- Items deferred by the per-cycle hard limit (see spillover.rs), stored oldest first.
+CREATE TABLE IF NOT EXISTS spillover_entries (
+    id        BIGSERIAL PRIMARY KEY,
+    feed_name TEXT  NOT NULL,
+    guid      TEXT  NOT NULL UNIQUE,
+    item      JSONB NOT NULL,
+    queued_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
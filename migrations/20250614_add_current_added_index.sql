@@ -0,0 +1,3 @@
+-- Index for TAXII paging (db_utils::list_added), which follows the order entries
+-- were written to `current`.
+CREATE INDEX IF NOT EXISTS current_added_idx ON current (inserted_at, id);
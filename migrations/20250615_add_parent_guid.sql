@@ -0,0 +1,5 @@
+-- Parent entry of replies in threaded sources (Atom thr:in-reply-to).
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS parent_guid TEXT;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS parent_guid TEXT;
+CREATE INDEX IF NOT EXISTS current_parent_guid_idx ON current (parent_guid)
+    WHERE parent_guid IS NOT NULL;
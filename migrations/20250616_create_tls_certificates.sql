@@ -0,0 +1,11 @@
+-- Last TLS certificate seen per feed host (see certs.rs), to spot unexpected changes.
+CREATE TABLE IF NOT EXISTS tls_certificates (
+    host                 TEXT PRIMARY KEY,
+    fingerprint          TEXT      NOT NULL,
+    issuer               TEXT      NOT NULL,
+    not_after            TIMESTAMP,
+    previous_fingerprint TEXT,
+    first_seen           TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_seen            TIMESTAMP NOT NULL DEFAULT NOW(),
+    changed_at           TIMESTAMP
+);
@@ -0,0 +1,3 @@
+-- License or rights statement an entry is published under (see license.rs).
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS license TEXT;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS license TEXT;
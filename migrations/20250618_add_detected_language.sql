@@ -0,0 +1,4 @@
+-- ISO 639-3 code of the language detected in an entry's text (see enrich.rs).
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS detected_language TEXT;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS detected_language TEXT;
+CREATE INDEX IF NOT EXISTS idx_current_detected_language ON current(detected_language);
@@ -0,0 +1,11 @@
+-- Analyst summaries written by the configured summarizer (see summarize.rs), and the
+-- tokens it used per day for `daily_token_budget`.
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS summary_ai TEXT;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS summary_ai TEXT;
+
+CREATE TABLE IF NOT EXISTS summarizer_usage (
+    day               DATE   PRIMARY KEY DEFAULT CURRENT_DATE,
+    requests          BIGINT NOT NULL DEFAULT 0,
+    prompt_tokens     BIGINT NOT NULL DEFAULT 0,
+    completion_tokens BIGINT NOT NULL DEFAULT 0
+);
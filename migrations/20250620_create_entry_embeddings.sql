@@ -0,0 +1,19 @@
+-- Entry embeddings (see embed.rs) for finding related entries. They need the pgvector
+-- extension: where it is not available this migration does nothing, and enabling
+-- `[embeddings]` later means installing pgvector and running this block by hand.
+-- The column has no fixed dimension so any embedding model fits; only vectors from
+-- the same model are compared.
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM pg_available_extensions WHERE name = 'vector') THEN
+        CREATE EXTENSION IF NOT EXISTS vector;
+        CREATE TABLE IF NOT EXISTS entry_embeddings (
+            guid        TEXT PRIMARY KEY,
+            model       TEXT      NOT NULL,
+            embedding   vector    NOT NULL,
+            embedded_at TIMESTAMP NOT NULL DEFAULT NOW()
+        );
+        CREATE INDEX IF NOT EXISTS entry_embeddings_model_idx ON entry_embeddings (model);
+    END IF;
+END
+$$;
@@ -0,0 +1,10 @@
+-- SimHash of each entry's text, and the earlier entry a near duplicate was linked to
+-- (see dedup.rs). Candidates are recent entries that are not duplicates themselves.
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS simhash BIGINT;
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS duplicate_of TEXT;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS simhash BIGINT;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS duplicate_of TEXT;
+
+CREATE INDEX IF NOT EXISTS archive_simhash_candidates_idx
+    ON archive (inserted_at)
+    WHERE simhash IS NOT NULL AND duplicate_of IS NULL;
@@ -0,0 +1,4 @@
+-- Feed body bytes per run, as transferred and decoded (see http.rs), for bandwidth
+-- budgeting and spotting feeds worth moving to conditional GET or polling less often.
+ALTER TABLE fetch_history ADD COLUMN IF NOT EXISTS bytes_compressed BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE fetch_history ADD COLUMN IF NOT EXISTS bytes_decompressed BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Selected response headers of each run (http.capture_headers), e.g. Server, X-Cache
+-- and Retry-After, for telling CDN caching and throttling apart per source over time.
+ALTER TABLE fetch_history ADD COLUMN IF NOT EXISTS response_headers JSONB;
@@ -0,0 +1,3 @@
+-- Tags assigned by the configured tag rules (see tagging.rs).
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS tags TEXT[];
+ALTER TABLE current ADD COLUMN IF NOT EXISTS tags TEXT[];
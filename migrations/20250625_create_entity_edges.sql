@@ -0,0 +1,14 @@
+-- Co-occurrence graph of the CVEs, products and tags entries name (see graph.rs),
+-- rebuilt by `rust_feed_ingestor graph`.
+CREATE TABLE IF NOT EXISTS entity_edges (
+    source_kind TEXT NOT NULL,
+    source      TEXT NOT NULL,
+    target_kind TEXT NOT NULL,
+    target      TEXT NOT NULL,
+    weight      BIGINT NOT NULL,
+    first_seen  TIMESTAMP NOT NULL,
+    last_seen   TIMESTAMP NOT NULL,
+    PRIMARY KEY (source_kind, source, target_kind, target)
+);
+
+CREATE INDEX IF NOT EXISTS entity_edges_target_idx ON entity_edges (target_kind, target);
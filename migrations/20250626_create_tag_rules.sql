@@ -0,0 +1,9 @@
+-- Tag rules managed at runtime through /admin/tag-rules, applied after the
+-- configured [[tag_rules]] (see tagging.rs).
+CREATE TABLE IF NOT EXISTS tag_rules (
+    id         BIGSERIAL PRIMARY KEY,
+    tag        TEXT NOT NULL,
+    keywords   TEXT[] NOT NULL DEFAULT '{}',
+    pattern    TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,3 @@
+-- Feed found on the web page configured as a feed's URL, fetched in its place.
+ALTER TABLE feed_cache
+    ADD COLUMN IF NOT EXISTS discovered_url TEXT;
@@ -0,0 +1,17 @@
+-- Files each entry carries (RSS enclosures, Atom enclosure links, JSON Feed
+-- attachments) as a JSON array of {url, content_type, length, title}.
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS enclosures JSONB;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS enclosures JSONB;
+
+-- Enclosures downloaded with [enclosures] configured (see enclosures.rs), each once.
+CREATE TABLE IF NOT EXISTS enclosure_files (
+    url           TEXT PRIMARY KEY,
+    guid          TEXT NOT NULL,
+    path          TEXT NOT NULL,
+    content_type  TEXT,
+    bytes         BIGINT NOT NULL,
+    sha256        TEXT NOT NULL,
+    downloaded_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS enclosure_files_guid_idx ON enclosure_files (guid);
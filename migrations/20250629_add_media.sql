@@ -0,0 +1,7 @@
+-- Media RSS content of each entry (see media.rs): its first thumbnail, and a JSON
+-- array of {url, content_type, width, height, duration_s, title, description,
+-- thumbnail_url}.
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS thumbnail_url TEXT;
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS media JSONB;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS thumbnail_url TEXT;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS media JSONB;
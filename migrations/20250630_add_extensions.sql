@@ -0,0 +1,4 @@
+-- Vendor namespace fields picked out per feed by its `extensions` paths, as a JSON
+-- object of name -> value (see extensions.rs).
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS extensions JSONB;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS extensions JSONB;
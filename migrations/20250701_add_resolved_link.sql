@@ -0,0 +1,12 @@
+-- Where an entry's link leads when it goes through a URL shortener (see links.rs).
+-- Entries reaching the same page through a shortener are linked as near duplicates,
+-- looked up by either column. Links have no length limit, so they are indexed by
+-- hash: a btree index entry cannot exceed about 2.7 kB, and a longer link would fail
+-- the insert.
+ALTER TABLE archive ADD COLUMN IF NOT EXISTS resolved_link TEXT;
+ALTER TABLE current ADD COLUMN IF NOT EXISTS resolved_link TEXT;
+
+CREATE INDEX IF NOT EXISTS archive_resolved_link_idx
+    ON archive USING hash (resolved_link)
+    WHERE resolved_link IS NOT NULL;
+CREATE INDEX IF NOT EXISTS archive_link_idx ON archive USING hash (link);
@@ -0,0 +1,16 @@
+-- Versions of an entry after the first (kept in archive) whose title, summary or
+-- content changed, as each arrived (see revisions.rs). diff_bytes is the size of the
+-- change from the version before.
+CREATE TABLE IF NOT EXISTS revisions (
+    id            BIGSERIAL PRIMARY KEY,
+    guid          TEXT NOT NULL,
+    title         TEXT NOT NULL,
+    link          TEXT NOT NULL,
+    summary       TEXT,
+    content       TEXT,
+    entry_updated TIMESTAMP,
+    diff_bytes    BIGINT NOT NULL,
+    recorded_at   TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS revisions_guid_idx ON revisions (guid, recorded_at);
@@ -0,0 +1,6 @@
+-- Feeds none of whose icon candidates yielded an icon, and when to look again
+-- (see icons.rs), so a feed without one is not probed on every fetch.
+CREATE TABLE IF NOT EXISTS feed_icon_misses (
+    feed_name   TEXT PRIMARY KEY,
+    retry_after TIMESTAMP NOT NULL
+);
@@ -0,0 +1,15 @@
+-- Each feed's scheduling state as of its latest run (see scheduler.rs), loaded at
+-- startup so a restart neither re-enables a disabled feed nor forgets its failure
+-- streak, backoff and health.
+CREATE TABLE IF NOT EXISTS feed_schedule (
+    feed_name            TEXT PRIMARY KEY,
+    url                  TEXT NOT NULL,
+    interval_ms          BIGINT NOT NULL,
+    last_run             TIMESTAMP,
+    next_run             TIMESTAMP NOT NULL,
+    consecutive_failures INTEGER NOT NULL,
+    last_error           TEXT,
+    health               DOUBLE PRECISION NOT NULL,
+    disabled             BOOLEAN NOT NULL,
+    updated_at           TIMESTAMP NOT NULL DEFAULT NOW()
+);
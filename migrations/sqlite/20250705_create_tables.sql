@@ -0,0 +1,153 @@
+-- The SQLite schema (see storage/sqlite.rs): the tables of the PostgreSQL migrations
+-- that the SQLite backend implements, as of 20250704. Lists (categories, feed_tags,
+-- tags) and structured values (enclosures, media, extensions, response_headers) are
+-- JSON text; timestamps are text in UTC, which sorts chronologically.
+
+CREATE TABLE IF NOT EXISTS archive (
+    id                BLOB PRIMARY KEY,
+    guid              TEXT NOT NULL UNIQUE,
+    title             TEXT NOT NULL,
+    link              TEXT NOT NULL,
+    published         TEXT,
+    content           TEXT,
+    summary           TEXT,
+    author            TEXT,
+    categories        TEXT,
+    entry_updated     TEXT,
+    image_url         TEXT,
+    feed_url          TEXT NOT NULL,
+    feed_title        TEXT,
+    feed_description  TEXT,
+    feed_language     TEXT,
+    feed_icon         TEXT,
+    feed_updated      TEXT,
+    feed_type         TEXT,
+    feed_tags         TEXT,
+    tlp               TEXT,
+    parent_guid       TEXT,
+    license           TEXT,
+    detected_language TEXT,
+    summary_ai        TEXT,
+    simhash           INTEGER,
+    duplicate_of      TEXT,
+    tags              TEXT,
+    enclosures        TEXT,
+    thumbnail_url     TEXT,
+    media             TEXT,
+    extensions        TEXT,
+    resolved_link     TEXT,
+    inserted_at       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS current (
+    id                BLOB PRIMARY KEY,
+    guid              TEXT NOT NULL UNIQUE,
+    title             TEXT NOT NULL,
+    link              TEXT NOT NULL,
+    published         TEXT,
+    content           TEXT,
+    summary           TEXT,
+    author            TEXT,
+    categories        TEXT,
+    entry_updated     TEXT,
+    image_url         TEXT,
+    feed_url          TEXT NOT NULL,
+    feed_title        TEXT,
+    feed_description  TEXT,
+    feed_language     TEXT,
+    feed_icon         TEXT,
+    feed_updated      TEXT,
+    feed_type         TEXT,
+    feed_tags         TEXT,
+    tlp               TEXT,
+    parent_guid       TEXT,
+    license           TEXT,
+    detected_language TEXT,
+    summary_ai        TEXT,
+    simhash           INTEGER,
+    duplicate_of      TEXT,
+    tags              TEXT,
+    enclosures        TEXT,
+    thumbnail_url     TEXT,
+    media             TEXT,
+    extensions        TEXT,
+    resolved_link     TEXT,
+    inserted_at       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Matches the rank order of listings (db_utils::RANK_ORDER; undated entries last)
+CREATE INDEX IF NOT EXISTS current_rank_idx
+    ON current (COALESCE(published, '') DESC, inserted_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS archive_rank_idx
+    ON archive (COALESCE(published, '') DESC, inserted_at DESC, id DESC);
+
+CREATE TABLE IF NOT EXISTS feed_cache (
+    feed_url       TEXT PRIMARY KEY,
+    etag           TEXT,
+    last_modified  TEXT,
+    body_hash      TEXT,
+    discovered_url TEXT,
+    updated_at     TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS revisions (
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    guid          TEXT NOT NULL,
+    title         TEXT NOT NULL,
+    link          TEXT NOT NULL,
+    summary       TEXT,
+    content       TEXT,
+    entry_updated TEXT,
+    diff_bytes    INTEGER NOT NULL,
+    recorded_at   TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS revisions_guid_idx ON revisions (guid, recorded_at);
+
+CREATE TABLE IF NOT EXISTS affected_products (
+    guid         TEXT NOT NULL,
+    product      TEXT NOT NULL,
+    versions     TEXT NOT NULL,
+    extracted_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (guid, product, versions)
+);
+CREATE INDEX IF NOT EXISTS affected_products_product_idx
+    ON affected_products (lower(product), extracted_at);
+
+CREATE TABLE IF NOT EXISTS fetch_history (
+    id                 INTEGER PRIMARY KEY AUTOINCREMENT,
+    feed_name          TEXT    NOT NULL,
+    started_at         TEXT    NOT NULL,
+    status             TEXT    NOT NULL,
+    http_status        INTEGER,
+    entries_seen       INTEGER NOT NULL DEFAULT 0,
+    entries_new        INTEGER NOT NULL DEFAULT 0,
+    errors             INTEGER NOT NULL DEFAULT 0,
+    duration_ms        INTEGER NOT NULL,
+    error              TEXT,
+    bytes_compressed   INTEGER NOT NULL DEFAULT 0,
+    bytes_decompressed INTEGER NOT NULL DEFAULT 0,
+    response_headers   TEXT
+);
+CREATE INDEX IF NOT EXISTS fetch_history_feed_idx ON fetch_history (feed_name, id DESC);
+
+CREATE TABLE IF NOT EXISTS feed_costs (
+    feed_name     TEXT    NOT NULL,
+    day           TEXT    NOT NULL DEFAULT (date('now')),
+    fetches       INTEGER NOT NULL DEFAULT 0,
+    bytes_fetched INTEGER NOT NULL DEFAULT 0,
+    rows_written  INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (feed_name, day)
+);
+
+CREATE TABLE IF NOT EXISTS feed_schedule (
+    feed_name            TEXT PRIMARY KEY,
+    url                  TEXT    NOT NULL,
+    interval_ms          INTEGER NOT NULL,
+    last_run             TEXT,
+    next_run             TEXT    NOT NULL,
+    consecutive_failures INTEGER NOT NULL,
+    last_error           TEXT,
+    health               REAL    NOT NULL,
+    disabled             INTEGER NOT NULL,
+    updated_at           TEXT    NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,115 @@
+//! Canary mode: newly added or modified feeds spend a number of successful runs
+//! with their items staged in `canary_entries` before they reach the main tables and sinks.
+
+use sqlx::PgPool;
+use tracing::info;
+
+use crate::config::Feed;
+use crate::errors::IngestError;
+use crate::ingestor::FeedItem;
+
+/// On first use, register every configured feed as live so that enabling canary
+/// mode does not put an existing deployment's feeds into canary.
+pub async fn bootstrap(pool: &PgPool, feeds: &[Feed]) -> Result<(), IngestError> {
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM feed_registry")
+        .fetch_one(pool)
+        .await?;
+    if count > 0 {
+        return Ok(());
+    }
+    for feed in feeds {
+        sqlx::query(
+            "INSERT INTO feed_registry (feed_name, config_hash, canary_cycles_left)
+            VALUES ($1, $2, 0) ON CONFLICT (feed_name) DO NOTHING",
+        )
+        .bind(&feed.name)
+        .bind(feed.fingerprint())
+        .execute(pool)
+        .await?;
+    }
+    info!("Registered {} existing feeds as live", feeds.len());
+    Ok(())
+}
+
+/// Remaining canary runs for `feed` (0 = live). A feed that is new, or whose
+/// configuration changed since it was last seen, (re)enters canary for `cycles` runs.
+pub async fn remaining_cycles(pool: &PgPool, feed: &Feed, cycles: u32) -> Result<u32, IngestError> {
+    let hash = feed.fingerprint();
+    let row: Option<(String, i32)> = sqlx::query_as(
+        "SELECT config_hash, canary_cycles_left FROM feed_registry WHERE feed_name = $1",
+    )
+    .bind(&feed.name)
+    .fetch_optional(pool)
+    .await?;
+
+    match row {
+        Some((stored, left)) if stored == hash => Ok(left.max(0) as u32),
+        _ => {
+            sqlx::query(
+                "INSERT INTO feed_registry (feed_name, config_hash, canary_cycles_left, updated_at)
+                VALUES ($1, $2, $3, NOW())
+                ON CONFLICT (feed_name) DO UPDATE SET
+                    config_hash = EXCLUDED.config_hash,
+                    canary_cycles_left = EXCLUDED.canary_cycles_left,
+                    updated_at = EXCLUDED.updated_at",
+            )
+            .bind(&feed.name)
+            .bind(&hash)
+            .bind(cycles as i32)
+            .execute(pool)
+            .await?;
+            if cycles > 0 {
+                info!(feed = %feed.name, cycles, "Feed is new or modified; entering canary");
+            }
+            Ok(cycles)
+        }
+    }
+}
+
+/// Hold `item` back until the feed is promoted.
+pub async fn stage(pool: &PgPool, feed_name: &str, item: &FeedItem) -> Result<(), IngestError> {
+    sqlx::query(
+        "INSERT INTO canary_entries (feed_name, guid, item) VALUES ($1, $2, $3)
+        ON CONFLICT (feed_name, guid) DO UPDATE SET item = EXCLUDED.item, inserted_at = NOW()",
+    )
+    .bind(feed_name)
+    .bind(&item.guid)
+    .bind(sqlx::types::Json(item))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Count down one successful canary run; returns the runs still remaining.
+pub async fn complete_cycle(pool: &PgPool, feed_name: &str) -> Result<u32, IngestError> {
+    let (left,): (i32,) = sqlx::query_as(
+        "UPDATE feed_registry SET canary_cycles_left = GREATEST(canary_cycles_left - 1, 0),
+            updated_at = NOW()
+        WHERE feed_name = $1
+        RETURNING canary_cycles_left",
+    )
+    .bind(feed_name)
+    .fetch_one(pool)
+    .await?;
+    Ok(left as u32)
+}
+
+/// Staged items for `feed_name`, oldest first.
+pub async fn staged_items(pool: &PgPool, feed_name: &str) -> Result<Vec<FeedItem>, IngestError> {
+    let rows: Vec<(sqlx::types::Json<FeedItem>,)> =
+        sqlx::query_as("SELECT item FROM canary_entries WHERE feed_name = $1 ORDER BY inserted_at")
+            .bind(feed_name)
+            .fetch_all(pool)
+            .await?;
+    Ok(rows.into_iter().map(|(item,)| item.0).collect())
+}
+
+/// Drop a promoted item from the staging table.
+pub async fn unstage(pool: &PgPool, feed_name: &str, guid: &str) -> Result<(), IngestError> {
+    sqlx::query("DELETE FROM canary_entries WHERE feed_name = $1 AND guid = $2")
+        .bind(feed_name)
+        .bind(guid)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,133 @@
+//! TLS certificate monitoring for feed hosts. Every fetch notes the certificate the
+//! host presented (SHA-256 of its DER encoding); once per cycle the notes are compared
+//! with `tls_certificates`. A new certificate is expected once the old one nears
+//! expiry; one appearing earlier may mean a hijacked or spoofed source, and is logged
+//! as a warning and counted in `tls_certificate_changes_total{expected="false"}`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use reqwest::tls::TlsInfo;
+use reqwest::Response;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+use crate::errors::IngestError;
+use crate::metrics::TLS_CERT_CHANGES;
+
+/// A replacement within this long of the old certificate's expiry is routine renewal.
+const RENEWAL_WINDOW: Duration = Duration::days(30);
+
+/// Certificates seen since the last `record_observed`, by host.
+static OBSERVED: Lazy<Mutex<HashMap<String, Certificate>>> = Lazy::new(Mutex::default);
+
+/// What is kept of a host's certificate.
+#[derive(Debug, Clone)]
+pub struct Certificate {
+    /// Hex SHA-256 of the DER encoding
+    pub fingerprint: String,
+    pub issuer: String,
+    pub not_after: Option<NaiveDateTime>,
+}
+
+impl Certificate {
+    fn from_der(der: &[u8]) -> Self {
+        let parsed = x509_parser::parse_x509_certificate(der)
+            .ok()
+            .map(|(_, c)| c);
+        Certificate {
+            fingerprint: format!("{:x}", Sha256::digest(der)),
+            issuer: parsed
+                .as_ref()
+                .map(|c| c.issuer().to_string())
+                .unwrap_or_default(),
+            not_after: parsed
+                .as_ref()
+                .and_then(|c| DateTime::from_timestamp(c.validity().not_after.timestamp(), 0))
+                .map(|t| t.naive_utc()),
+        }
+    }
+}
+
+/// Note the certificate behind `response`, if it came over TLS.
+pub fn observe(response: &Response) {
+    let Some(host) = response.url().host_str() else {
+        return;
+    };
+    let Some(der) = response
+        .extensions()
+        .get::<TlsInfo>()
+        .and_then(TlsInfo::peer_certificate)
+    else {
+        return;
+    };
+    OBSERVED
+        .lock()
+        .expect("certificate notes poisoned")
+        .insert(host.to_string(), Certificate::from_der(der));
+}
+
+/// Compare the certificates noted since the last call with the recorded ones, alert
+/// on changes and record what was seen. Returns the number of changed hosts.
+pub async fn record_observed(pool: &PgPool) -> Result<usize, IngestError> {
+    let observed = std::mem::take(&mut *OBSERVED.lock().expect("certificate notes poisoned"));
+    let mut changed = 0;
+    for (host, cert) in observed {
+        let previous: Option<(String, String, Option<NaiveDateTime>)> = sqlx::query_as(
+            "SELECT fingerprint, issuer, not_after FROM tls_certificates WHERE host = $1",
+        )
+        .bind(&host)
+        .fetch_optional(pool)
+        .await?;
+        match previous {
+            None => {
+                info!(host = %host, fingerprint = %cert.fingerprint, issuer = %cert.issuer, "Recorded TLS certificate");
+            }
+            Some((fingerprint, _, _)) if fingerprint == cert.fingerprint => {}
+            Some((fingerprint, issuer, not_after)) => {
+                changed += 1;
+                let expected =
+                    not_after.is_some_and(|t| t - RENEWAL_WINDOW <= Utc::now().naive_utc());
+                TLS_CERT_CHANGES
+                    .with_label_values(&[host.as_str(), if expected { "true" } else { "false" }])
+                    .inc();
+                if expected {
+                    info!(host = %host, previous = %fingerprint, fingerprint = %cert.fingerprint, "TLS certificate renewed");
+                } else {
+                    warn!(
+                        host = %host,
+                        previous = %fingerprint,
+                        fingerprint = %cert.fingerprint,
+                        previous_issuer = %issuer,
+                        issuer = %cert.issuer,
+                        previous_not_after = ?not_after,
+                        "TLS certificate changed before the previous one was due for renewal"
+                    );
+                }
+            }
+        }
+        sqlx::query(
+            "INSERT INTO tls_certificates (host, fingerprint, issuer, not_after)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (host) DO UPDATE SET
+                previous_fingerprint = CASE WHEN tls_certificates.fingerprint = EXCLUDED.fingerprint
+                    THEN tls_certificates.previous_fingerprint ELSE tls_certificates.fingerprint END,
+                changed_at = CASE WHEN tls_certificates.fingerprint = EXCLUDED.fingerprint
+                    THEN tls_certificates.changed_at ELSE NOW() END,
+                fingerprint = EXCLUDED.fingerprint,
+                issuer = EXCLUDED.issuer,
+                not_after = EXCLUDED.not_after,
+                last_seen = NOW()",
+        )
+        .bind(&host)
+        .bind(&cert.fingerprint)
+        .bind(&cert.issuer)
+        .bind(cert.not_after)
+        .execute(pool)
+        .await?;
+    }
+    Ok(changed)
+}
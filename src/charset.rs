@@ -0,0 +1,83 @@
+//! Transcoding of feed bodies to UTF-8 before parsing. Bodies that already are UTF-8
+//! are kept; others are decoded from the charset their byte order mark, the
+//! `Content-Type` header or the XML declaration names, in that order, or failing
+//! those from the charset `chardetng` guesses (with the feed host's TLD as a hint).
+//! Either way the XML declaration is made to say UTF-8, as the parser decodes by it.
+//! mbox archives are skipped: each message declares its own charset.
+
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use encoding_rs::{Encoding, UTF_8};
+use once_cell::sync::Lazy;
+use regex::bytes::Regex;
+
+use crate::mbox;
+
+/// The encoding named in an XML declaration.
+static XML_ENCODING: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^\s*<\?xml[^>]*?\sencoding\s*=\s*["']([A-Za-z0-9._:-]+)["']"#).unwrap()
+});
+
+/// `body` as UTF-8, with the encoding it was transcoded from (`None` if it was not).
+/// `url` is where the body came from.
+pub fn to_utf8(
+    body: Vec<u8>,
+    content_type: Option<&str>,
+    url: &str,
+) -> (Vec<u8>, Option<&'static Encoding>) {
+    if mbox::is_mbox(&body) {
+        return (body, None);
+    }
+    let (encoding, bom) = match Encoding::for_bom(&body) {
+        Some((encoding, _)) if encoding == UTF_8 => return (body, None),
+        Some((encoding, bom)) => (encoding, bom),
+        None if body.is_ascii() => return (body, None),
+        None if std::str::from_utf8(&body).is_ok() => return (with_utf8_declaration(body), None),
+        None => {
+            let declared = content_type
+                .and_then(|ct| mbox::parameter(ct, "charset"))
+                .or_else(|| xml_encoding(&body))
+                .and_then(|label| Encoding::for_label(label.trim().as_bytes()));
+            match declared {
+                Some(encoding) if encoding != UTF_8 => (encoding, 0),
+                // Undeclared, or declared as UTF-8 but isn't
+                _ => (detect(&body, url), 0),
+            }
+        }
+    };
+    let (text, _) = encoding.decode_without_bom_handling(&body[bom..]);
+    (
+        with_utf8_declaration(text.into_owned().into_bytes()),
+        Some(encoding),
+    )
+}
+
+fn xml_encoding(body: &[u8]) -> Option<String> {
+    let head = &body[..body.len().min(1024)];
+    let label = XML_ENCODING.captures(head)?.get(1)?;
+    Some(String::from_utf8_lossy(label.as_bytes()).into_owned())
+}
+
+/// `body` with the encoding in its XML declaration (if any) replaced by UTF-8.
+fn with_utf8_declaration(body: Vec<u8>) -> Vec<u8> {
+    let Some(label) = XML_ENCODING.captures(&body).and_then(|c| c.get(1)) else {
+        return body;
+    };
+    if Encoding::for_label(label.as_bytes()) == Some(UTF_8) {
+        return body;
+    }
+    let mut out = Vec::with_capacity(body.len());
+    out.extend_from_slice(&body[..label.start()]);
+    out.extend_from_slice(b"UTF-8");
+    out.extend_from_slice(&body[label.end()..]);
+    out
+}
+
+fn detect(body: &[u8], url: &str) -> &'static Encoding {
+    let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+    detector.feed(body, true);
+    let tld = url::Url::parse(url).ok().and_then(|url| {
+        let tld = url.host_str()?.rsplit('.').next()?.to_ascii_lowercase();
+        tld.bytes().all(|b| b.is_ascii_lowercase()).then_some(tld)
+    });
+    detector.guess(tld.as_deref().map(str::as_bytes), Utf8Detection::Deny)
+}
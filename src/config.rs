@@ -4,8 +4,12 @@
 use config::{Config, ConfigError, File};
 use humantime;
 use humantime_serde;
-use serde::Deserialize;
-use std::{env, time::Duration};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, env, time::Duration};
+
+use crate::sanitize::{LengthLimits, SanitizePolicy, SanitizeProfile};
+use crate::tlp::Tlp;
 
 /// Top-level application settings loaded from `Config.toml`
 /// and then overridden (where applicable) by environment variables.
@@ -22,11 +26,852 @@ pub struct Settings {
     pub server_bind: String,
 
     /// List of all RSS/Atom sources to ingest, each carrying metadata.
+    #[serde(default)]
     pub feeds: Vec<Feed>,
+
+    /// OPML subscription lists whose feeds are merged into `feeds`
+    #[serde(default)]
+    pub opml: Vec<String>,
+
+    /// Daily limits across all feeds combined
+    #[serde(default)]
+    pub quota: Quota,
+
+    /// Number of successful runs a new or modified feed spends in canary
+    /// (items staged, not stored or published); 0 disables canary mode
+    #[serde(default)]
+    pub canary_cycles: u32,
+
+    /// Overall deadline for storing one entry; slower entries are abandoned
+    #[serde(with = "humantime_serde", default = "default_entry_timeout")]
+    pub entry_timeout: Duration,
+
+    /// Apply pending migrations at startup; when false, the operator migrates and
+    /// ingestion waits (with `/readyz` failing) until the schema matches
+    #[serde(default = "default_true")]
+    pub run_migrations: bool,
+
+    /// How long an instance waits for another one's migrations to finish before giving up
+    #[serde(with = "humantime_serde", default = "default_migration_wait")]
+    pub migration_wait: Duration,
+
+    /// Fetch, parse, sanitize and enrich as usual but only log what would be stored:
+    /// nothing is written to the database (migrations included) or published to sinks
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// Caps on the number of items stored per ingestion cycle
+    #[serde(default)]
+    pub cycle_limits: CycleLimits,
+
+    /// Local disk buffer for entries written while Postgres is unreachable
+    #[serde(default)]
+    pub spool: SpoolSettings,
+
+    /// Runs kept per feed in `fetch_history`; 0 disables history
+    #[serde(default = "default_fetch_history_rows")]
+    pub fetch_history_rows: u32,
+
+    /// Default proxy for feed fetches (`http://`, `socks5://`, ...); feeds may override
+    #[serde(default)]
+    pub proxy: Option<String>,
+
+    /// User-Agent and default headers for feed fetches
+    #[serde(default)]
+    pub http: HttpSettings,
+
+    /// Which licenses keep entries from sinks set to `redistributable_only`
+    #[serde(default)]
+    pub licensing: LicensingSettings,
+
+    /// Retry policy for transient fetch failures
+    #[serde(default)]
+    pub retry: RetrySettings,
+
+    /// Backoff and automatic disabling for feeds that keep failing; unset retries
+    /// failing feeds every cycle
+    #[serde(default)]
+    pub feed_health: Option<FeedHealthSettings>,
+
+    /// Archive deduplication tuning
+    #[serde(default)]
+    pub dedup: DedupSettings,
+
+    /// HTML sanitization for feeds with `sanitize = "custom"`
+    #[serde(default)]
+    pub sanitize_policy: Option<SanitizePolicy>,
+
+    /// Rules tagging entries by keyword or pattern, stored in `tags`; more can be
+    /// added at runtime through `/admin/tag-rules`
+    #[serde(default)]
+    pub tag_rules: Vec<TagRule>,
+
+    /// Access to the `/admin/` endpoints
+    #[serde(default)]
+    pub admin: AdminSettings,
+
+    /// Publisher pings (`/ping`, `/websub/{feed}`) fetching a feed between cycles;
+    /// unset disables those endpoints
+    #[serde(default)]
+    pub pings: Option<PingSettings>,
+
+    /// Refuse requests to loopback, private and other internal addresses unless
+    /// allowed; on unless `enabled = false`
+    #[serde(default)]
+    pub url_safety: UrlSafetySettings,
+
+    /// Deliver a story to the sinks at most once per this window, however many
+    /// feeds carry it (matched on normalized title); unset disables suppression
+    #[serde(default, with = "humantime_serde")]
+    pub suppression_window: Option<Duration>,
+
+    /// User templates for notification payloads
+    #[serde(default)]
+    pub templates: TemplateSettings,
+
+    /// Publish processed entries to Kafka (requires the `kafka` feature)
+    #[serde(default)]
+    pub kafka: Option<KafkaSettings>,
+
+    /// POST every entry new to the archive (and, per webhook, every update) to these URLs
+    #[serde(default)]
+    pub webhooks: Vec<WebhookSettings>,
+
+    /// The TAXII 2.1 collection served under `/taxii2/`
+    #[serde(default)]
+    pub taxii: TaxiiSettings,
+
+    /// LLM analyst summaries of new entries, stored in `summary_ai`
+    #[serde(default)]
+    pub summarizer: Option<SummarizerSettings>,
+
+    /// Vector embeddings of new entries, stored in `entry_embeddings` (needs pgvector)
+    #[serde(default)]
+    pub embeddings: Option<EmbeddingSettings>,
+
+    /// Download the enclosures of new entries; unset only records them in `enclosures`
+    #[serde(default)]
+    pub enclosures: Option<EnclosureSettings>,
+
+    /// Follow shortener and tracking redirects in entry links, stored in `resolved_link`
+    #[serde(default)]
+    pub unshorten: Option<UnshortenSettings>,
 }
 
-/// Represents one RSS/Atom feed source and its metadata.
+fn default_true() -> bool {
+    true
+}
+
+fn default_entry_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_migration_wait() -> Duration {
+    Duration::from_secs(600)
+}
+
+fn default_fetch_history_rows() -> u32 {
+    500
+}
+
+/// Per-cycle item volume limits, protecting the database from sudden floods.
+#[derive(Debug, Deserialize, Clone)]
+pub struct CycleLimits {
+    /// Warn (and count `cycle_soft_limit_exceeded_total`) once a cycle stores more items
+    #[serde(default)]
+    pub soft_items: Option<usize>,
+    /// Defer items beyond this many per cycle to the spillover queue
+    #[serde(default)]
+    pub hard_items: Option<usize>,
+    /// Spilled items stored at the start of each cycle, ahead of fresh ones
+    #[serde(default = "default_drain_per_cycle")]
+    pub drain_per_cycle: usize,
+}
+
+impl Default for CycleLimits {
+    fn default() -> Self {
+        CycleLimits {
+            soft_items: None,
+            hard_items: None,
+            drain_per_cycle: default_drain_per_cycle(),
+        }
+    }
+}
+
+fn default_drain_per_cycle() -> usize {
+    1000
+}
+
+/// Where and how much to buffer on disk during a database outage.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SpoolSettings {
+    /// Directory holding spooled entries; unset disables spooling (entries that
+    /// cannot be written fail and are refetched next cycle)
+    #[serde(default)]
+    pub dir: Option<std::path::PathBuf>,
+    /// Entries held at most; further ones fail as they would without a spool
+    #[serde(default = "default_spool_max_items")]
+    pub max_items: u64,
+    /// Bytes of spooled JSON held at most
+    #[serde(default = "default_spool_max_bytes")]
+    pub max_bytes: u64,
+}
+
+impl Default for SpoolSettings {
+    fn default() -> Self {
+        SpoolSettings {
+            dir: None,
+            max_items: default_spool_max_items(),
+            max_bytes: default_spool_max_bytes(),
+        }
+    }
+}
+
+fn default_spool_max_items() -> u64 {
+    100_000
+}
+
+fn default_spool_max_bytes() -> u64 {
+    1 << 30
+}
+
+/// Exponential backoff for feed fetches: attempt `n` waits
+/// `base_delay * 2^(n-1)`, randomized by ±`jitter`, then capped at `max_delay`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RetrySettings {
+    /// Total attempts per fetch, including the first (1 disables retries)
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(with = "humantime_serde", default = "default_base_delay")]
+    pub base_delay: Duration,
+    #[serde(with = "humantime_serde", default = "default_max_delay")]
+    pub max_delay: Duration,
+    /// Fraction of each delay to randomize (0.0–1.0)
+    #[serde(default = "default_jitter")]
+    pub jitter: f64,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        RetrySettings {
+            max_attempts: default_max_attempts(),
+            base_delay: default_base_delay(),
+            max_delay: default_max_delay(),
+            jitter: default_jitter(),
+        }
+    }
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_base_delay() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_max_delay() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_jitter() -> f64 {
+    0.2
+}
+
+/// What happens to a feed after consecutive failed runs: the `n`th failure puts off
+/// its next run by `ingest_interval * 2^(n-1)` (capped at `max_backoff`), and
+/// `disable_after` failures disable it until a probe every `probe_interval` succeeds.
+#[derive(Debug, Deserialize, Clone)]
+pub struct FeedHealthSettings {
+    #[serde(with = "humantime_serde", default = "default_max_backoff")]
+    pub max_backoff: Duration,
+    /// Consecutive failures that disable a feed; 0 never disables
+    #[serde(default = "default_disable_after")]
+    pub disable_after: u32,
+    #[serde(with = "humantime_serde", default = "default_probe_interval")]
+    pub probe_interval: Duration,
+}
+
+impl Default for FeedHealthSettings {
+    fn default() -> Self {
+        FeedHealthSettings {
+            max_backoff: default_max_backoff(),
+            disable_after: default_disable_after(),
+            probe_interval: default_probe_interval(),
+        }
+    }
+}
+
+fn default_max_backoff() -> Duration {
+    Duration::from_secs(6 * 3600)
+}
+
+fn default_disable_after() -> u32 {
+    10
+}
+
+fn default_probe_interval() -> Duration {
+    Duration::from_secs(24 * 3600)
+}
+
+/// How feed requests present themselves.
+#[derive(Deserialize, Clone)]
+pub struct HttpSettings {
+    /// `User-Agent` sent with every fetch
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String,
+    /// Headers sent with every fetch (e.g. `Accept-Language`); a feed's `headers`
+    /// override them
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Deadline for each fetch attempt, from connecting to the end of the body; a
+    /// feed's `timeout` overrides it
+    #[serde(with = "humantime_serde", default = "default_fetch_timeout")]
+    pub timeout: Duration,
+    /// Response headers stored with each run in `fetch_history`; empty stores none
+    #[serde(default = "default_capture_headers")]
+    pub capture_headers: Vec<String>,
+}
+
+// Headers may carry API keys, so only their names are logged
+impl std::fmt::Debug for HttpSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpSettings")
+            .field("user_agent", &self.user_agent)
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("timeout", &self.timeout)
+            .field("capture_headers", &self.capture_headers)
+            .finish()
+    }
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        HttpSettings {
+            user_agent: default_user_agent(),
+            headers: HashMap::new(),
+            timeout: default_fetch_timeout(),
+            capture_headers: default_capture_headers(),
+        }
+    }
+}
+
+fn default_capture_headers() -> Vec<String> {
+    ["Server", "X-Cache", "Retry-After", "Content-Type"]
+        .map(String::from)
+        .to_vec()
+}
+
+fn default_fetch_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_user_agent() -> String {
+    concat!("rust_feed_ingestor/", env!("CARGO_PKG_VERSION")).into()
+}
+
+/// What counts as a license forbidding redistribution.
+#[derive(Debug, Deserialize, Clone)]
+pub struct LicensingSettings {
+    /// Case-insensitive phrases marking a license or rights statement that forbids
+    /// redistribution
+    #[serde(default = "default_restricted_licenses")]
+    pub restricted: Vec<String>,
+    /// Whether entries without any license may be redistributed
+    #[serde(default = "default_true")]
+    pub allow_unlicensed: bool,
+}
+
+impl Default for LicensingSettings {
+    fn default() -> Self {
+        LicensingSettings {
+            restricted: default_restricted_licenses(),
+            allow_unlicensed: true,
+        }
+    }
+}
+
+fn default_restricted_licenses() -> Vec<String> {
+    [
+        "all rights reserved",
+        "not for redistribution",
+        "no redistribution",
+        "do not redistribute",
+        "proprietary",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+/// Archive deduplication settings.
+#[derive(Debug, Deserialize, Clone)]
+pub struct DedupSettings {
+    /// Keep an in-memory Bloom filter of archived GUIDs to skip most `EXISTS` queries
+    #[serde(default)]
+    pub bloom_filter: bool,
+    /// Target false-positive rate of the filter (each costs one `EXISTS` query)
+    #[serde(default = "default_false_positive_rate")]
+    pub false_positive_rate: f64,
+    /// Link each new entry whose SimHash is at most this many bits from an earlier
+    /// entry's to it (`duplicate_of`); unset turns near-duplicate detection off
+    #[serde(default)]
+    pub near_duplicate_distance: Option<u32>,
+    /// How far back, by time stored, earlier entries are compared
+    #[serde(with = "humantime_serde", default = "default_near_duplicate_window")]
+    pub near_duplicate_window: Duration,
+}
+
+impl Default for DedupSettings {
+    fn default() -> Self {
+        DedupSettings {
+            bloom_filter: false,
+            false_positive_rate: default_false_positive_rate(),
+            near_duplicate_distance: None,
+            near_duplicate_window: default_near_duplicate_window(),
+        }
+    }
+}
+
+fn default_false_positive_rate() -> f64 {
+    0.01
+}
+
+fn default_near_duplicate_window() -> Duration {
+    Duration::from_secs(30 * 24 * 3600)
+}
+
+/// A tag given to entries whose text matches any of `keywords` or `pattern`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TagRule {
+    pub tag: String,
+    /// Words or phrases, matched case-insensitively as whole words
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Regular expression, case-sensitive unless it starts with `(?i)`
+    #[serde(default)]
+    pub pattern: Option<String>,
+}
+
+/// Bearer token for the `/admin/` endpoints, which are disabled without one.
+#[derive(Deserialize, Clone, Default)]
+pub struct AdminSettings {
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl std::fmt::Debug for AdminSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminSettings")
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/// Publisher pings: a pinged feed is fetched at once, at most every `min_interval`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PingSettings {
+    #[serde(with = "humantime_serde", default = "default_ping_interval")]
+    pub min_interval: Duration,
+}
+
+impl Default for PingSettings {
+    fn default() -> Self {
+        PingSettings {
+            min_interval: default_ping_interval(),
+        }
+    }
+}
+
+fn default_ping_interval() -> Duration {
+    Duration::from_secs(300)
+}
+
+/// SSRF protection (see `urlguard`).
+#[derive(Debug, Deserialize, Clone)]
+pub struct UrlSafetySettings {
+    /// `false` fetches any address
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Hostnames, addresses and CIDR ranges (`10.1.0.0/16`) fetched even though internal
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+impl Default for UrlSafetySettings {
+    fn default() -> Self {
+        UrlSafetySettings {
+            enabled: true,
+            allow: Vec::new(),
+        }
+    }
+}
+
+/// TAXII 2.1 server settings.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct TaxiiSettings {
+    /// Leave entries marked above this TLP out of the collection
+    #[serde(default)]
+    pub max_tlp: Option<Tlp>,
+}
+
+/// Where notification templates live and how dates in them are rendered.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TemplateSettings {
+    /// Directory holding minijinja templates, referenced by relative path
+    #[serde(default)]
+    pub dir: Option<String>,
+    /// Default locale for the `date` filter (e.g. "en_US", "de_DE")
+    #[serde(default = "default_locale")]
+    pub locale: String,
+}
+
+impl Default for TemplateSettings {
+    fn default() -> Self {
+        TemplateSettings {
+            dir: None,
+            locale: default_locale(),
+        }
+    }
+}
+
+fn default_locale() -> String {
+    "en_US".into()
+}
+
+/// Kafka output sink settings.
+#[derive(Debug, Deserialize, Clone)]
+pub struct KafkaSettings {
+    /// Comma-separated `host:port` bootstrap list
+    pub brokers: String,
+    pub topic: String,
+    /// Which field becomes the message key (controls partitioning)
+    #[serde(default)]
+    pub key: KafkaKey,
+    /// How long to wait for delivery of each message
+    #[serde(with = "humantime_serde", default = "default_kafka_timeout")]
+    pub send_timeout: Duration,
+    /// Extra librdkafka properties (e.g. `security.protocol`, `sasl.mechanisms`)
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+    /// Render each message from this template instead of the entry's JSON
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Withhold items marked above this TLP (unmarked items count as CLEAR)
+    #[serde(default)]
+    pub max_tlp: Option<Tlp>,
+    /// Send titles, summaries and content with indicators defanged (`hxxp://`, `[.]`)
+    #[serde(default)]
+    pub defang: bool,
+    /// Withhold items whose license forbids redistribution (see `[licensing]`)
+    #[serde(default)]
+    pub redistributable_only: bool,
+}
+
+/// An outbound webhook receiving each entry new to the archive as JSON.
+#[derive(Deserialize, Clone)]
+pub struct WebhookSettings {
+    pub url: String,
+    /// Also receive known entries whose title, summary or content changed
+    #[serde(default)]
+    pub updates: bool,
+    /// Shared secret; bodies are then signed with HMAC-SHA256 in `X-Signature-256`
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// Deadline for each delivery attempt
+    #[serde(with = "humantime_serde", default = "default_webhook_timeout")]
+    pub timeout: Duration,
+    /// Backoff for failed deliveries (5xx, 429, timeouts, connection errors)
+    #[serde(default)]
+    pub retry: RetrySettings,
+    /// Withhold items marked above this TLP (unmarked items count as CLEAR)
+    #[serde(default)]
+    pub max_tlp: Option<Tlp>,
+    /// Send titles, summaries and content with indicators defanged (`hxxp://`, `[.]`)
+    #[serde(default)]
+    pub defang: bool,
+    /// Withhold items whose license forbids redistribution (see `[licensing]`)
+    #[serde(default)]
+    pub redistributable_only: bool,
+    /// Render each body from this template instead of the entry's JSON (or, with
+    /// `digest`, the digest's body)
+    #[serde(default)]
+    pub template: Option<String>,
+    /// POST the entries of each such period together, rendered by `template`, instead
+    /// of one request per entry
+    #[serde(default, with = "humantime_serde")]
+    pub digest: Option<Duration>,
+}
+
+fn default_webhook_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+impl std::fmt::Debug for WebhookSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookSettings")
+            .field("url", &self.url)
+            .field("updates", &self.updates)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("timeout", &self.timeout)
+            .field("retry", &self.retry)
+            .field("max_tlp", &self.max_tlp)
+            .field("defang", &self.defang)
+            .field("redistributable_only", &self.redistributable_only)
+            .field("template", &self.template)
+            .field("digest", &self.digest)
+            .finish()
+    }
+}
+
+/// An OpenAI-compatible chat completions API summarizing each entry new to the archive.
+#[derive(Deserialize, Clone)]
+pub struct SummarizerSettings {
+    /// API base URL; requests go to `<endpoint>/chat/completions`
+    pub endpoint: String,
+    pub model: String,
+    /// Sent as `Authorization: Bearer <key>`
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// System prompt preceding each entry's text
+    #[serde(default = "default_summary_prompt")]
+    pub prompt: String,
+    /// Entry text is cut to this many characters before it is sent
+    #[serde(default = "default_summary_input_chars")]
+    pub max_input_chars: usize,
+    /// Longest summary, in completion tokens
+    #[serde(default = "default_summary_max_tokens")]
+    pub max_tokens: u32,
+    /// Requests started per minute, at most
+    #[serde(default = "default_summary_rate")]
+    pub requests_per_minute: u32,
+    /// Cost guard: no more requests for the rest of the day once this many prompt
+    /// plus completion tokens were used
+    #[serde(default)]
+    pub daily_token_budget: Option<u64>,
+    /// Entries waiting for a summary; new entries beyond it are not summarized
+    #[serde(default = "default_summary_queue")]
+    pub queue: usize,
+    /// Deadline for each request
+    #[serde(with = "humantime_serde", default = "default_summary_timeout")]
+    pub timeout: Duration,
+    /// Never send items marked above this TLP (unmarked items count as CLEAR)
+    #[serde(default)]
+    pub max_tlp: Option<Tlp>,
+    /// Only summarize entries in these languages (`detected_language` codes, `und`
+    /// for undetected); unset summarizes every language
+    #[serde(default)]
+    pub languages: Option<Vec<String>>,
+    /// System prompt for entries in a given language instead of `prompt`, e.g. to
+    /// translate before summarizing
+    #[serde(default)]
+    pub prompts: HashMap<String, String>,
+}
+
+fn default_summary_prompt() -> String {
+    "You are a threat intelligence analyst. Summarize the following article in two or \
+    three sentences for a security team: what happened, who or what is affected, and \
+    what to do about it."
+        .to_string()
+}
+
+fn default_summary_input_chars() -> usize {
+    8_000
+}
+
+fn default_summary_max_tokens() -> u32 {
+    200
+}
+
+fn default_summary_rate() -> u32 {
+    20
+}
+
+fn default_summary_queue() -> usize {
+    1_000
+}
+
+fn default_summary_timeout() -> Duration {
+    Duration::from_secs(60)
+}
+
+impl std::fmt::Debug for SummarizerSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SummarizerSettings")
+            .field("endpoint", &self.endpoint)
+            .field("model", &self.model)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("max_input_chars", &self.max_input_chars)
+            .field("max_tokens", &self.max_tokens)
+            .field("requests_per_minute", &self.requests_per_minute)
+            .field("daily_token_budget", &self.daily_token_budget)
+            .field("queue", &self.queue)
+            .field("timeout", &self.timeout)
+            .field("max_tlp", &self.max_tlp)
+            .field("languages", &self.languages)
+            .field("prompt_languages", &self.prompts.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// An OpenAI-compatible embeddings API embedding each entry new to the archive.
+#[derive(Deserialize, Clone)]
+pub struct EmbeddingSettings {
+    /// API base URL; requests go to `<endpoint>/embeddings`
+    pub endpoint: String,
+    pub model: String,
+    /// Sent as `Authorization: Bearer <key>`
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Entry text is cut to this many characters before it is sent
+    #[serde(default = "default_embedding_input_chars")]
+    pub max_input_chars: usize,
+    /// Requests started per minute, at most
+    #[serde(default = "default_embedding_rate")]
+    pub requests_per_minute: u32,
+    /// Entries waiting for an embedding; new entries beyond it are not embedded
+    #[serde(default = "default_embedding_queue")]
+    pub queue: usize,
+    /// Deadline for each request
+    #[serde(with = "humantime_serde", default = "default_embedding_timeout")]
+    pub timeout: Duration,
+    /// Never send items marked above this TLP (unmarked items count as CLEAR)
+    #[serde(default)]
+    pub max_tlp: Option<Tlp>,
+    /// Only embed entries in these languages (`detected_language` codes, `und` for
+    /// undetected); unset embeds every language
+    #[serde(default)]
+    pub languages: Option<Vec<String>>,
+}
+
+fn default_embedding_input_chars() -> usize {
+    8_000
+}
+
+fn default_embedding_rate() -> u32 {
+    60
+}
+
+fn default_embedding_queue() -> usize {
+    1_000
+}
+
+fn default_embedding_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+/// Local copies of the files entries carry, recorded in `enclosure_files`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct EnclosureSettings {
+    /// Directory the files are written to, named by their SHA-256
+    pub dir: std::path::PathBuf,
+    /// Larger files (as stated by the feed or the server, or as received) are skipped
+    #[serde(default = "default_enclosure_max_bytes")]
+    pub max_bytes: u64,
+    /// Only download these media types, matched as prefixes (`application/pdf`,
+    /// `audio/`); empty downloads every type
+    #[serde(default)]
+    pub content_types: Vec<String>,
+    /// Entries waiting for their downloads; new entries beyond it are not downloaded
+    #[serde(default = "default_enclosure_queue")]
+    pub queue: usize,
+    /// Deadline for each download
+    #[serde(with = "humantime_serde", default = "default_enclosure_timeout")]
+    pub timeout: Duration,
+}
+
+fn default_enclosure_max_bytes() -> u64 {
+    20 * 1024 * 1024
+}
+
+fn default_enclosure_queue() -> usize {
+    1_000
+}
+
+fn default_enclosure_timeout() -> Duration {
+    Duration::from_secs(60)
+}
+
+/// Redirect resolution for links through URL shorteners and feed proxies.
 #[derive(Debug, Deserialize, Clone)]
+pub struct UnshortenSettings {
+    /// Hosts whose links are resolved, subdomains included
+    #[serde(default = "default_unshorten_hosts")]
+    pub hosts: Vec<String>,
+    /// Deadline for each link, all redirects included
+    #[serde(with = "humantime_serde", default = "default_unshorten_timeout")]
+    pub timeout: Duration,
+    /// Links resolved at once per feed
+    #[serde(default = "default_unshorten_concurrency")]
+    pub concurrency: usize,
+    /// Resolved links remembered, so links seen every cycle are requested once
+    #[serde(default = "default_unshorten_cache")]
+    pub cache: usize,
+}
+
+fn default_unshorten_hosts() -> Vec<String> {
+    [
+        "feedproxy.google.com",
+        "t.co",
+        "bit.ly",
+        "ow.ly",
+        "buff.ly",
+        "dlvr.it",
+        "ift.tt",
+        "lnkd.in",
+        "tinyurl.com",
+        "trib.al",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn default_unshorten_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+fn default_unshorten_concurrency() -> usize {
+    4
+}
+
+fn default_unshorten_cache() -> usize {
+    10_000
+}
+
+impl std::fmt::Debug for EmbeddingSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmbeddingSettings")
+            .field("endpoint", &self.endpoint)
+            .field("model", &self.model)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("max_input_chars", &self.max_input_chars)
+            .field("requests_per_minute", &self.requests_per_minute)
+            .field("queue", &self.queue)
+            .field("timeout", &self.timeout)
+            .field("max_tlp", &self.max_tlp)
+            .field("languages", &self.languages)
+            .finish()
+    }
+}
+
+/// Message key strategy for the Kafka sink.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaKey {
+    /// Entry GUID: updates to one entry land on the same partition
+    #[default]
+    Guid,
+    /// Feed URL: per-feed ordering
+    FeedUrl,
+    /// No key: round-robin partitioning
+    None,
+}
+
+fn default_kafka_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+/// Represents one RSS/Atom feed source and its metadata.
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct Feed {
     /// Human-friendly name of this feed (e.g. "Krebs on Security")
     pub name: String,
@@ -41,6 +886,215 @@ pub struct Feed {
     /// Tags to help you filter or group feeds in your code
     #[serde(default)]
     pub tags: Vec<String>,
+
+    /// Pre-parse adjustments for sources emitting almost-valid XML
+    #[serde(default)]
+    pub parser: ParserOptions,
+
+    /// Overrides for fields the source puts in nonstandard elements
+    #[serde(default)]
+    pub mapping: FieldMapping,
+
+    /// Vendor fields stored in `extensions`: a name for each raw element path, e.g.
+    /// `cvss = "vuln:cvss/cvss:base_metrics/cvss:score"` (see `extensions::EntryElements`)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub extensions: HashMap<String, String>,
+
+    /// Daily limits for this feed alone
+    #[serde(default)]
+    pub quota: Quota,
+
+    /// Credentials sent with every request for this feed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<FeedAuth>,
+
+    /// Request headers for this feed, overriding `http.headers` (and `http.user_agent`,
+    /// given a `User-Agent`)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+
+    /// Deadline for each fetch attempt of this feed, overriding `http.timeout`
+    #[serde(
+        default,
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timeout: Option<Duration>,
+
+    /// Proxy for this feed, overriding the global `proxy`; "direct" bypasses it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+
+    /// License or rights statement for this feed's items, overriding what the feed declares
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+
+    /// Minimum TLP for this feed's items; markers in an item can only raise it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tlp: Option<Tlp>,
+
+    /// HTML sanitization profile: "strict", "article" (default), "code-friendly",
+    /// "custom" (the `sanitize_policy`) or "none" (trusted, pre-sanitized feeds only)
+    #[serde(default, skip_serializing_if = "SanitizeProfile::is_default")]
+    pub sanitize: SanitizeProfile,
+
+    /// Maximum title, summary and content lengths, and whether longer ones are
+    /// truncated or the entry rejected
+    #[serde(default, skip_serializing_if = "LengthLimits::is_default")]
+    pub limits: LengthLimits,
+
+    /// Store this feed's `http` links as `https`, for sources alternating between the two
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub prefer_https: bool,
+}
+
+/// HTTP authentication for a feed; any combination may be set.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct FeedAuth {
+    /// HTTP Basic auth user
+    #[serde(default)]
+    pub username: Option<String>,
+    /// HTTP Basic auth password (requires `username`)
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Sent as `Authorization: Bearer <token>`
+    #[serde(default)]
+    pub bearer_token: Option<String>,
+    /// Extra headers, e.g. `{ "X-API-Key" = "..." }`
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+// Settings are logged at startup, so never print the secrets themselves
+impl std::fmt::Debug for FeedAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redacted = |v: &Option<String>| v.as_ref().map(|_| "<redacted>");
+        f.debug_struct("FeedAuth")
+            .field("username", &self.username)
+            .field("password", &redacted(&self.password))
+            .field("bearer_token", &redacted(&self.bearer_token))
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+// Headers may carry API keys, so only their names are logged
+impl std::fmt::Debug for Feed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Feed")
+            .field("name", &self.name)
+            .field("url", &self.url)
+            .field("feed_type", &self.feed_type)
+            .field("tags", &self.tags)
+            .field("parser", &self.parser)
+            .field("mapping", &self.mapping)
+            .field("extensions", &self.extensions)
+            .field("quota", &self.quota)
+            .field("auth", &self.auth)
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("timeout", &self.timeout)
+            .field("proxy", &self.proxy)
+            .field("license", &self.license)
+            .field("tlp", &self.tlp)
+            .field("sanitize", &self.sanitize)
+            .field("limits", &self.limits)
+            .field("prefer_https", &self.prefer_https)
+            .finish()
+    }
+}
+
+impl Feed {
+    /// Stable fingerprint of this feed's configuration, used to detect modified feeds.
+    pub fn fingerprint(&self) -> String {
+        // serde_json objects are key-sorted, so the encoding is deterministic
+        let value = serde_json::to_value(self).expect("feed config serializes");
+        format!("{:x}", Sha256::digest(value.to_string().as_bytes()))
+    }
+}
+
+/// Daily resource limits; a feed is skipped for the rest of the day once one is reached.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Quota {
+    #[serde(default)]
+    pub max_bytes_per_day: Option<u64>,
+    #[serde(default)]
+    pub max_rows_per_day: Option<u64>,
+}
+
+/// Per-feed field overrides, each naming a raw entry element such as `dc:date` (or an
+/// attribute, as `media:credit@role`, or a nested element, as `media:group/media:title`).
+/// When the named element is present and usable it wins over the standard field.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FieldMapping {
+    /// Element holding the publication date (RFC 3339, RFC 2822 or `YYYY-MM-DD`)
+    #[serde(default)]
+    pub published_from: Option<String>,
+
+    /// Element holding the last-updated date
+    #[serde(default)]
+    pub updated_from: Option<String>,
+
+    /// Element holding the author name
+    #[serde(default)]
+    pub author_from: Option<String>,
+}
+
+/// Per-feed parser tweaks, applied to the raw body before parsing.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ParserOptions {
+    /// Skip junk before the root element and strip control characters XML forbids
+    #[serde(default)]
+    pub lenient: bool,
+
+    /// Regex find/replace pairs applied to the body, in order
+    #[serde(default)]
+    pub fixups: Vec<Fixup>,
+
+    /// Namespace URI rewrites (e.g. a misspelt namespace to the canonical one)
+    #[serde(default)]
+    pub namespaces: HashMap<String, String>,
+}
+
+/// A single regex replacement over the raw feed body.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Fixup {
+    pub pattern: FixupPattern,
+    #[serde(default)]
+    pub replacement: String,
+}
+
+/// A fixup's regex, compiled once as the configuration is loaded.
+#[derive(Clone)]
+pub struct FixupPattern(regex::bytes::Regex);
+
+impl FixupPattern {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        regex::bytes::Regex::new(pattern).map(FixupPattern)
+    }
+
+    pub fn regex(&self) -> &regex::bytes::Regex {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for FixupPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.as_str().fmt(f)
+    }
+}
+
+impl Serialize for FixupPattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for FixupPattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        FixupPattern::new(&pattern)
+            .map_err(|e| serde::de::Error::custom(format!("invalid fixup: {}", e)))
+    }
 }
 
 impl Settings {
@@ -71,6 +1125,299 @@ impl Settings {
             settings.server_bind = val;
         }
 
+        // 3) Merge feeds from any OPML subscription lists
+        for path in settings.opml.clone() {
+            let feeds = load_opml(&path)?;
+            settings.merge_feeds(feeds);
+        }
+
+        // 4) Storage is PostgreSQL, or SQLite without the features that need Postgres,
+        //    so fail here rather than inside sqlx on the first query
+        if crate::storage::is_sqlite(&settings.database_url) {
+            let postgres_only = [
+                ("canary_cycles", settings.canary_cycles > 0),
+                (
+                    "cycle_limits.hard_items",
+                    settings.cycle_limits.hard_items.is_some(),
+                ),
+                ("spool.dir", settings.spool.dir.is_some()),
+                ("dedup.bloom_filter", settings.dedup.bloom_filter),
+                (
+                    "dedup.near_duplicate_distance",
+                    settings.dedup.near_duplicate_distance.is_some(),
+                ),
+                ("admin.token", settings.admin.token.is_some()),
+                ("summarizer", settings.summarizer.is_some()),
+                ("embeddings", settings.embeddings.is_some()),
+                ("enclosures", settings.enclosures.is_some()),
+            ];
+            if let Some((name, _)) = postgres_only.iter().find(|(_, set)| *set) {
+                return Err(ConfigError::Message(format!(
+                    "{}: needs a PostgreSQL database_url, not SQLite",
+                    name
+                )));
+            }
+        } else if !(settings.database_url.starts_with("postgres://")
+            || settings.database_url.starts_with("postgresql://"))
+        {
+            return Err(ConfigError::Message(
+                "database_url: must be postgres://, postgresql:// or sqlite://".into(),
+            ));
+        }
+
+        // 5) Reject bad auth headers and nonsensical tuning values (fixups that would never
+        //    compile fail deserialization)
+        if settings.retry.max_attempts == 0 || !(0.0..=1.0).contains(&settings.retry.jitter) {
+            return Err(ConfigError::Message(
+                "retry: max_attempts must be at least 1 and jitter within 0.0-1.0".into(),
+            ));
+        }
+        if let Some(health) = &settings.feed_health {
+            if health.max_backoff.is_zero() || health.probe_interval.is_zero() {
+                return Err(ConfigError::Message(
+                    "feed_health: max_backoff and probe_interval must be positive".into(),
+                ));
+            }
+        }
+        for rule in &settings.tag_rules {
+            if rule.tag.trim().is_empty() || (rule.keywords.is_empty() && rule.pattern.is_none()) {
+                return Err(ConfigError::Message(format!(
+                    "tag rule '{}': needs a tag and keywords or a pattern",
+                    rule.tag
+                )));
+            }
+        }
+        crate::tagging::Tagger::new(&settings.tag_rules)
+            .map_err(|e| ConfigError::Message(format!("tag_rules: {}", e)))?;
+        if let Some(policy) = &settings.sanitize_policy {
+            policy.validate().map_err(ConfigError::Message)?;
+        } else if let Some(feed) = settings
+            .feeds
+            .iter()
+            .find(|f| f.sanitize == SanitizeProfile::Custom)
+        {
+            return Err(ConfigError::Message(format!(
+                "feed '{}': sanitize = \"custom\" needs a [sanitize_policy]",
+                feed.name
+            )));
+        }
+        crate::urlguard::validate(&settings.url_safety).map_err(ConfigError::Message)?;
+        for webhook in &settings.webhooks {
+            if url::Url::parse(&webhook.url).is_err() || webhook.retry.max_attempts == 0 {
+                return Err(ConfigError::Message(format!(
+                    "webhook '{}': invalid url or retry.max_attempts",
+                    webhook.url
+                )));
+            }
+            if webhook
+                .digest
+                .is_some_and(|d| d.is_zero() || webhook.template.is_none())
+            {
+                return Err(ConfigError::Message(format!(
+                    "webhook '{}': digest needs a non-zero period and a template",
+                    webhook.url
+                )));
+            }
+        }
+        let limits = &settings.cycle_limits;
+        if limits.hard_items == Some(0)
+            || limits.drain_per_cycle == 0
+            || matches!((limits.soft_items, limits.hard_items), (Some(s), Some(h)) if s > h)
+        {
+            return Err(ConfigError::Message(
+                "cycle_limits: hard_items and drain_per_cycle must be positive, soft_items <= hard_items".into(),
+            ));
+        }
+        if !valid_header("User-Agent", &settings.http.user_agent) {
+            return Err(ConfigError::Message("http: invalid user_agent".into()));
+        }
+        for (name, value) in &settings.http.headers {
+            if !valid_header(name, value) {
+                return Err(ConfigError::Message(format!(
+                    "http: invalid header '{}'",
+                    name
+                )));
+            }
+        }
+        for name in &settings.http.capture_headers {
+            if !valid_header(name, "") {
+                return Err(ConfigError::Message(format!(
+                    "http: invalid capture_headers name '{}'",
+                    name
+                )));
+            }
+        }
+        if let Some(summarizer) = &settings.summarizer {
+            if url::Url::parse(&summarizer.endpoint).is_err()
+                || summarizer.requests_per_minute == 0
+                || summarizer.queue == 0
+            {
+                return Err(ConfigError::Message(
+                    "summarizer: invalid endpoint, or zero requests_per_minute or queue".into(),
+                ));
+            }
+            let codes = summarizer.languages.iter().flatten();
+            if let Some(code) = codes
+                .chain(summarizer.prompts.keys())
+                .find(|code| !is_language_code(code))
+            {
+                return Err(ConfigError::Message(format!(
+                    "summarizer: '{}' is not an ISO 639-3 language code like eng or zho",
+                    code
+                )));
+            }
+        }
+        if let Some(embeddings) = &settings.embeddings {
+            if url::Url::parse(&embeddings.endpoint).is_err()
+                || embeddings.requests_per_minute == 0
+                || embeddings.queue == 0
+            {
+                return Err(ConfigError::Message(
+                    "embeddings: invalid endpoint, or zero requests_per_minute or queue".into(),
+                ));
+            }
+            if let Some(code) = embeddings
+                .languages
+                .iter()
+                .flatten()
+                .find(|code| !is_language_code(code))
+            {
+                return Err(ConfigError::Message(format!(
+                    "embeddings: '{}' is not an ISO 639-3 language code like eng or zho",
+                    code
+                )));
+            }
+        }
+        if settings
+            .unshorten
+            .as_ref()
+            .is_some_and(|u| u.concurrency == 0)
+        {
+            return Err(ConfigError::Message(
+                "unshorten: concurrency must be positive".into(),
+            ));
+        }
+        if settings
+            .dedup
+            .near_duplicate_distance
+            .is_some_and(|d| d >= 32)
+        {
+            return Err(ConfigError::Message(
+                "dedup: near_duplicate_distance must be below 32".into(),
+            ));
+        }
+        let fp_rate = settings.dedup.false_positive_rate;
+        if !(fp_rate > 0.0 && fp_rate < 1.0) {
+            return Err(ConfigError::Message(
+                "dedup: false_positive_rate must be between 0 and 1".into(),
+            ));
+        }
+        for feed in &settings.feeds {
+            if let Some(auth) = &feed.auth {
+                if auth.password.is_some() && auth.username.is_none() {
+                    return Err(ConfigError::Message(format!(
+                        "feed '{}': auth.password requires auth.username",
+                        feed.name
+                    )));
+                }
+                for (name, value) in &auth.headers {
+                    if !valid_header(name, value) {
+                        return Err(ConfigError::Message(format!(
+                            "feed '{}': invalid auth header '{}'",
+                            feed.name, name
+                        )));
+                    }
+                }
+            }
+            if let Some((name, _)) = feed
+                .extensions
+                .iter()
+                .find(|(name, path)| name.trim().is_empty() || path.trim().is_empty())
+            {
+                return Err(ConfigError::Message(format!(
+                    "feed '{}': extension '{}' needs a name and an element path",
+                    feed.name, name
+                )));
+            }
+            for (name, value) in &feed.headers {
+                if !valid_header(name, value) {
+                    return Err(ConfigError::Message(format!(
+                        "feed '{}': invalid header '{}'",
+                        feed.name, name
+                    )));
+                }
+            }
+        }
+
         Ok(settings)
     }
+
+    /// Append `feeds`, skipping any whose URL is already configured.
+    pub fn merge_feeds(&mut self, feeds: Vec<Feed>) {
+        for feed in feeds {
+            if !self.feeds.iter().any(|f| f.url == feed.url) {
+                self.feeds.push(feed);
+            }
+        }
+    }
+}
+
+fn valid_header(name: &str, value: &str) -> bool {
+    reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_ok()
+        && reqwest::header::HeaderValue::from_str(value).is_ok()
+}
+
+/// Whether `code` looks like a `detected_language` value: three lowercase letters.
+fn is_language_code(code: &str) -> bool {
+    code.len() == 3 && code.bytes().all(|b| b.is_ascii_lowercase())
+}
+
+/// Load feed subscriptions from an OPML file.
+///
+/// Every `<outline>` carrying an `xmlUrl` becomes a `Feed`; the titles of enclosing
+/// folder outlines and any `category` attribute (`/a/b,/c`) become its tags.
+pub fn load_opml(path: &str) -> Result<Vec<Feed>, ConfigError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| ConfigError::Message(format!("failed to read OPML {}: {}", path, e)))?;
+    let doc = roxmltree::Document::parse(&text)
+        .map_err(|e| ConfigError::Message(format!("invalid OPML {}: {}", path, e)))?;
+
+    let mut feeds = Vec::new();
+    for node in doc.descendants().filter(|n| n.has_tag_name("outline")) {
+        let Some(url) = node.attribute("xmlUrl") else {
+            continue;
+        };
+        let name = node
+            .attribute("title")
+            .or_else(|| node.attribute("text"))
+            .unwrap_or(url);
+
+        let mut tags: Vec<String> = node
+            .ancestors()
+            .skip(1)
+            .filter(|a| a.has_tag_name("outline") && a.attribute("xmlUrl").is_none())
+            .filter_map(|a| a.attribute("title").or_else(|| a.attribute("text")))
+            .map(str::to_string)
+            .collect();
+        tags.reverse();
+        if let Some(categories) = node.attribute("category") {
+            tags.extend(
+                categories
+                    .split(',')
+                    .flat_map(|c| c.split('/'))
+                    .map(str::trim)
+                    .filter(|c| !c.is_empty())
+                    .map(str::to_string),
+            );
+        }
+        tags.dedup();
+
+        feeds.push(Feed {
+            name: name.to_string(),
+            url: url.to_string(),
+            tags,
+            ..Default::default()
+        });
+    }
+    Ok(feeds)
 }
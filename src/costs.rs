@@ -0,0 +1,133 @@
+//! Per-feed resource accounting (`feed_costs`) and daily quota enforcement.
+
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::config::{Feed, Quota};
+use crate::errors::IngestError;
+use crate::storage::Storage;
+
+/// Resource usage accumulated over some window.
+#[derive(Debug, Clone, Default, Serialize, sqlx::FromRow)]
+pub struct Usage {
+    pub fetches: i64,
+    pub bytes_fetched: i64,
+    pub rows_written: i64,
+}
+
+/// One row of the cost breakdown served by `GET /costs`.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct FeedCost {
+    pub feed_name: String,
+    pub fetches: i64,
+    pub bytes_fetched: i64,
+    pub rows_written: i64,
+}
+
+/// Add one fetch's usage to today's row for `feed_name`.
+pub async fn record_usage(
+    pool: &PgPool,
+    feed_name: &str,
+    bytes: u64,
+    rows: u64,
+) -> Result<(), IngestError> {
+    sqlx::query(
+        "INSERT INTO feed_costs (feed_name, day, fetches, bytes_fetched, rows_written)
+        VALUES ($1, CURRENT_DATE, 1, $2, $3)
+        ON CONFLICT (feed_name, day) DO UPDATE SET
+            fetches = feed_costs.fetches + 1,
+            bytes_fetched = feed_costs.bytes_fetched + EXCLUDED.bytes_fetched,
+            rows_written = feed_costs.rows_written + EXCLUDED.rows_written",
+    )
+    .bind(feed_name)
+    .bind(bytes as i64)
+    .bind(rows as i64)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Today's usage for one feed, or across all feeds when `feed_name` is `None`.
+pub async fn usage_today(pool: &PgPool, feed_name: Option<&str>) -> Result<Usage, IngestError> {
+    let usage = sqlx::query_as(
+        "SELECT COALESCE(SUM(fetches), 0)::BIGINT AS fetches,
+                COALESCE(SUM(bytes_fetched), 0)::BIGINT AS bytes_fetched,
+                COALESCE(SUM(rows_written), 0)::BIGINT AS rows_written
+        FROM feed_costs
+        WHERE day = CURRENT_DATE AND ($1::TEXT IS NULL OR feed_name = $1)",
+    )
+    .bind(feed_name)
+    .fetch_one(pool)
+    .await?;
+    Ok(usage)
+}
+
+/// Why `feed` must be skipped today, if its own or the global quota is exhausted.
+pub async fn quota_exceeded(
+    storage: &dyn Storage,
+    feed: &Feed,
+    global: &Quota,
+) -> Result<Option<String>, IngestError> {
+    if feed.quota.is_unlimited() && global.is_unlimited() {
+        return Ok(None);
+    }
+    if !feed.quota.is_unlimited() {
+        let usage = storage.usage_today(Some(&feed.name)).await?;
+        if let Some(reason) = feed.quota.exceeded_by(&usage) {
+            return Ok(Some(format!("feed quota: {}", reason)));
+        }
+    }
+    if !global.is_unlimited() {
+        let usage = storage.usage_today(None).await?;
+        if let Some(reason) = global.exceeded_by(&usage) {
+            return Ok(Some(format!("global quota: {}", reason)));
+        }
+    }
+    Ok(None)
+}
+
+/// Per-feed usage summed over the last `days` days (including today), most bytes first.
+pub async fn cost_breakdown(pool: &PgPool, days: i32) -> Result<Vec<FeedCost>, IngestError> {
+    let rows = sqlx::query_as(
+        "SELECT feed_name,
+                SUM(fetches)::BIGINT AS fetches,
+                SUM(bytes_fetched)::BIGINT AS bytes_fetched,
+                SUM(rows_written)::BIGINT AS rows_written
+        FROM feed_costs
+        WHERE day > CURRENT_DATE - $1
+        GROUP BY feed_name
+        ORDER BY bytes_fetched DESC, feed_name",
+    )
+    .bind(days)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+impl Quota {
+    /// Whether no limit is configured at all.
+    pub fn is_unlimited(&self) -> bool {
+        self.max_bytes_per_day.is_none() && self.max_rows_per_day.is_none()
+    }
+
+    /// Describe the first limit `usage` has reached, if any.
+    pub fn exceeded_by(&self, usage: &Usage) -> Option<String> {
+        if let Some(max) = self.max_bytes_per_day {
+            if usage.bytes_fetched as u64 >= max {
+                return Some(format!(
+                    "{} of {} bytes fetched today",
+                    usage.bytes_fetched, max
+                ));
+            }
+        }
+        if let Some(max) = self.max_rows_per_day {
+            if usage.rows_written as u64 >= max {
+                return Some(format!(
+                    "{} of {} rows written today",
+                    usage.rows_written, max
+                ));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,419 @@
+//! Small database helpers for per-feed operational state.
+
+use chrono::{DateTime, NaiveDateTime};
+use serde::Serialize;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::errors::IngestError;
+use crate::ingestor::{parse_date, CacheValidators, FeedItem};
+use crate::tlp::Tlp;
+
+/// Columns selected when reading `FeedItem`s back out of `current`/`archive`.
+pub const FEED_ITEM_COLUMNS: &str = "id, guid, title, link, published, content, summary, author, \
+    categories, entry_updated, image_url, feed_url, feed_title, feed_description, feed_language, \
+    feed_icon, feed_updated, feed_type, feed_tags, tlp, parent_guid, license, detected_language, \
+    summary_ai, simhash, duplicate_of, tags, enclosures, thumbnail_url, media, extensions, \
+    resolved_link, inserted_at";
+
+/// Sort key defining the rank of stored entries: newest publication first (undated
+/// entries last), ties broken by insertion time and then id so the order is total.
+/// Every listing of entries orders by this, descending, so pages never overlap or skip.
+pub const RANK_KEY: &str = "COALESCE(published, '-infinity'::timestamp), inserted_at, id";
+
+/// `ORDER BY` clause for `RANK_KEY`.
+pub const RANK_ORDER: &str =
+    "COALESCE(published, '-infinity'::timestamp) DESC, inserted_at DESC, id DESC";
+
+/// An entry's position in rank order, handed to API clients as an opaque cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub published: Option<NaiveDateTime>,
+    pub inserted_at: NaiveDateTime,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    /// Cursor pointing just past `item`.
+    pub fn after(item: &FeedItem) -> Self {
+        Cursor {
+            published: item.published,
+            inserted_at: item.inserted_at,
+            id: item.id,
+        }
+    }
+
+    /// `<published µs>~<inserted_at µs>~<id>`, with an empty first field for undated entries.
+    pub fn encode(&self) -> String {
+        format!(
+            "{}~{}~{}",
+            self.published
+                .map(|p| p.and_utc().timestamp_micros().to_string())
+                .unwrap_or_default(),
+            self.inserted_at.and_utc().timestamp_micros(),
+            self.id.simple()
+        )
+    }
+
+    pub fn decode(s: &str) -> Option<Self> {
+        let micros =
+            |v: &str| DateTime::from_timestamp_micros(v.parse().ok()?).map(|d| d.naive_utc());
+        let mut parts = s.splitn(3, '~');
+        let published = match parts.next()? {
+            "" => None,
+            v => Some(micros(v)?),
+        };
+        let inserted_at = micros(parts.next()?)?;
+        let id = Uuid::parse_str(parts.next()?).ok()?;
+        Some(Cursor {
+            published,
+            inserted_at,
+            id,
+        })
+    }
+}
+
+/// Filters and pagination for listing stored entries.
+#[derive(Debug, Clone)]
+pub struct EntryQuery {
+    pub feed_url: Option<String>,
+    pub feed_type: Option<String>,
+    pub tag: Option<String>,
+    /// Only replies to the entry with this GUID
+    pub parent_guid: Option<String>,
+    /// Only entries naming this product (see `enrich::affected_products`), any case
+    pub product: Option<String>,
+    /// Only entries published at or after this time
+    pub since: Option<NaiveDateTime>,
+    /// Only entries published before this time
+    pub until: Option<NaiveDateTime>,
+    /// Case-insensitive text match over title, summary and content
+    pub q: Option<String>,
+    /// Only entries marked at most this TLP (unmarked entries count as CLEAR)
+    pub max_tlp: Option<Tlp>,
+    /// Return entries with their indicators defanged (see `enrich::defang`)
+    pub defang: bool,
+    pub limit: i64,
+    pub offset: i64,
+    /// Resume after this entry (keyset pagination); takes precedence over `offset`
+    pub cursor: Option<Cursor>,
+    /// List entries as first seen, from `archive`, instead of as last seen
+    pub archive: bool,
+}
+
+impl Default for EntryQuery {
+    fn default() -> Self {
+        EntryQuery {
+            feed_url: None,
+            feed_type: None,
+            tag: None,
+            parent_guid: None,
+            product: None,
+            since: None,
+            until: None,
+            q: None,
+            max_tlp: None,
+            defang: false,
+            limit: 50,
+            offset: 0,
+            cursor: None,
+            archive: false,
+        }
+    }
+}
+
+impl EntryQuery {
+    /// Upper bound on `limit`, whatever the caller asks for.
+    pub const MAX_LIMIT: i64 = 500;
+
+    /// Build a query from URL query-string pairs, rejecting malformed values.
+    pub fn from_pairs(pairs: impl IntoIterator<Item = (String, String)>) -> Result<Self, String> {
+        let mut query = EntryQuery::default();
+        for (key, value) in pairs {
+            match key.as_str() {
+                "feed_url" => query.feed_url = Some(value),
+                "feed_type" => query.feed_type = Some(value),
+                "max_tlp" => query.max_tlp = Some(value.parse()?),
+                "tag" => query.tag = Some(value),
+                "parent_guid" => query.parent_guid = Some(value),
+                "product" => query.product = Some(value),
+                "q" => query.q = Some(value),
+                "defang" => {
+                    query.defang = value
+                        .parse()
+                        .map_err(|_| format!("invalid defang: {}", value))?
+                }
+                "since" => {
+                    query.since = Some(
+                        parse_date(&value).ok_or_else(|| format!("invalid since: {}", value))?,
+                    )
+                }
+                "until" => {
+                    query.until = Some(
+                        parse_date(&value).ok_or_else(|| format!("invalid until: {}", value))?,
+                    )
+                }
+                "limit" => {
+                    let limit: i64 = value
+                        .parse()
+                        .map_err(|_| format!("invalid limit: {}", value))?;
+                    query.limit = limit.clamp(1, Self::MAX_LIMIT);
+                }
+                "offset" => {
+                    query.offset = value
+                        .parse::<i64>()
+                        .map_err(|_| format!("invalid offset: {}", value))?
+                        .max(0);
+                }
+                "cursor" => {
+                    query.cursor = Some(
+                        Cursor::decode(&value)
+                            .ok_or_else(|| format!("invalid cursor: {}", value))?,
+                    )
+                }
+                _ => {}
+            }
+        }
+        Ok(query)
+    }
+}
+
+/// List entries from `current` (or `archive`) matching `query`, in rank order (see
+/// `RANK_KEY`).
+pub async fn list_entries(pool: &PgPool, query: &EntryQuery) -> Result<Vec<FeedItem>, IngestError> {
+    let mut qb: QueryBuilder<Postgres> = QueryBuilder::new("SELECT ");
+    qb.push(FEED_ITEM_COLUMNS);
+    qb.push(if query.archive {
+        " FROM archive WHERE TRUE"
+    } else {
+        " FROM current WHERE TRUE"
+    });
+    push_filters(&mut qb, query);
+    if let Some(q) = &query.q {
+        let pattern = format!(
+            "%{}%",
+            q.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        qb.push(" AND (title ILIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR summary ILIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR content ILIKE ")
+            .push_bind(pattern)
+            .push(")");
+    }
+    if let Some(cursor) = &query.cursor {
+        qb.push(format_args!(" AND ({}) < (COALESCE(", RANK_KEY))
+            .push_bind(cursor.published)
+            .push("::timestamp, '-infinity'::timestamp), ")
+            .push_bind(cursor.inserted_at)
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(")");
+    }
+    qb.push(format_args!(" ORDER BY {}", RANK_ORDER));
+    qb.push(" LIMIT ").push_bind(query.limit);
+    if query.cursor.is_none() {
+        qb.push(" OFFSET ").push_bind(query.offset);
+    }
+
+    let items = qb.build_query_as::<FeedItem>().fetch_all(pool).await?;
+    Ok(items)
+}
+
+/// An entry matching a full-text search, with its relevance.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub item: FeedItem,
+    /// `ts_rank_cd` of the match; title hits weigh most, then summary, then content
+    pub rank: f32,
+}
+
+/// Full-text search over title, summary and content of entries in `current`, most
+/// relevant first. `q` uses web-search syntax (`"exact phrase"`, `or`, `-exclude`);
+/// the other filters apply as in `list_entries`, and paging is by `offset` only.
+pub async fn search_entries(
+    pool: &PgPool,
+    q: &str,
+    query: &EntryQuery,
+) -> Result<Vec<SearchHit>, IngestError> {
+    let mut qb: QueryBuilder<Postgres> = QueryBuilder::new("SELECT ");
+    qb.push(FEED_ITEM_COLUMNS);
+    qb.push(", ts_rank_cd(search, websearch_to_tsquery('english', ")
+        .push_bind(q)
+        .push(")) AS rank FROM current WHERE search @@ websearch_to_tsquery('english', ")
+        .push_bind(q)
+        .push(")");
+    push_filters(&mut qb, query);
+    qb.push(format_args!(" ORDER BY rank DESC, {}", RANK_ORDER));
+    qb.push(" LIMIT ").push_bind(query.limit);
+    qb.push(" OFFSET ").push_bind(query.offset);
+
+    let hits = qb.build_query_as::<SearchHit>().fetch_all(pool).await?;
+    Ok(hits)
+}
+
+/// An entry related to another by its embedding.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SimilarEntry {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub item: FeedItem,
+    /// Cosine distance between the two embeddings: 0 for the same direction, up to 2
+    pub distance: f64,
+}
+
+/// The `k` entries of `current` whose embeddings are nearest to that of the entry with
+/// `id`, closest first, from any feed. Only vectors of the same model are compared;
+/// empty if the entry has no embedding (yet). Needs the pgvector `entry_embeddings`
+/// table (see `embed`); the search is exact, with no vector index.
+pub async fn similar_entries(
+    pool: &PgPool,
+    id: Uuid,
+    k: i64,
+) -> Result<Vec<SimilarEntry>, IngestError> {
+    let sql = format!(
+        "SELECT {FEED_ITEM_COLUMNS}, near.distance FROM current JOIN (
+            SELECT other.guid AS near_guid, other.embedding <=> this.embedding AS distance
+            FROM entry_embeddings this
+            JOIN entry_embeddings other ON other.model = this.model
+                AND other.guid <> this.guid
+                AND vector_dims(other.embedding) = vector_dims(this.embedding)
+            WHERE this.guid = (SELECT guid FROM current WHERE id = $1)
+        ) near ON near.near_guid = current.guid
+        ORDER BY near.distance, current.id
+        LIMIT $2"
+    );
+    let similar = sqlx::query_as::<_, SimilarEntry>(&sql)
+        .bind(id)
+        .bind(k)
+        .fetch_all(pool)
+        .await?;
+    Ok(similar)
+}
+
+/// Entries written to `current` after `added_after`, oldest first, resuming after the
+/// `(inserted_at, id)` of the previous page's last entry (for TAXII, which pages by
+/// date added).
+pub async fn list_added(
+    pool: &PgPool,
+    added_after: Option<NaiveDateTime>,
+    resume_after: Option<(NaiveDateTime, Uuid)>,
+    max_tlp: Option<Tlp>,
+    limit: i64,
+) -> Result<Vec<FeedItem>, IngestError> {
+    let filters = EntryQuery {
+        max_tlp,
+        ..Default::default()
+    };
+    let mut qb: QueryBuilder<Postgres> = QueryBuilder::new("SELECT ");
+    qb.push(FEED_ITEM_COLUMNS);
+    qb.push(" FROM current WHERE TRUE");
+    push_filters(&mut qb, &filters);
+    if let Some(added_after) = added_after {
+        qb.push(" AND inserted_at > ").push_bind(added_after);
+    }
+    if let Some((inserted_at, id)) = resume_after {
+        qb.push(" AND (inserted_at, id) > (")
+            .push_bind(inserted_at)
+            .push(", ")
+            .push_bind(id)
+            .push(")");
+    }
+    qb.push(" ORDER BY inserted_at, id LIMIT ").push_bind(limit);
+
+    let items = qb.build_query_as::<FeedItem>().fetch_all(pool).await?;
+    Ok(items)
+}
+
+/// `AND` clauses for the feed, tag, parent, TLP and date filters of `query`.
+fn push_filters<'a>(qb: &mut QueryBuilder<'a, Postgres>, query: &'a EntryQuery) {
+    if let Some(feed_url) = &query.feed_url {
+        qb.push(" AND feed_url = ").push_bind(feed_url);
+    }
+    if let Some(feed_type) = &query.feed_type {
+        qb.push(" AND feed_type = ").push_bind(feed_type);
+    }
+    if let Some(tag) = &query.tag {
+        qb.push(" AND ").push_bind(tag).push(" = ANY(feed_tags)");
+    }
+    if let Some(parent_guid) = &query.parent_guid {
+        qb.push(" AND parent_guid = ").push_bind(parent_guid);
+    }
+    if let Some(product) = &query.product {
+        qb.push(" AND guid IN (SELECT guid FROM affected_products WHERE lower(product) = lower(")
+            .push_bind(product)
+            .push("))");
+    }
+    if let Some(max) = query.max_tlp {
+        let allowed: Vec<&str> = Tlp::ALL
+            .iter()
+            .filter(|t| **t <= max)
+            .map(|t| t.as_str())
+            .collect();
+        qb.push(" AND (tlp IS NULL OR tlp = ANY(")
+            .push_bind(allowed)
+            .push("))");
+    }
+    if let Some(since) = query.since {
+        qb.push(" AND published >= ").push_bind(since);
+    }
+    if let Some(until) = query.until {
+        qb.push(" AND published < ").push_bind(until);
+    }
+}
+
+/// Whether `guid` is already in the archive.
+pub async fn is_archived(pool: &PgPool, guid: &str) -> Result<bool, IngestError> {
+    let (exists,): (bool,) = sqlx::query_as("SELECT EXISTS(SELECT 1 FROM archive WHERE guid = $1)")
+        .bind(guid)
+        .fetch_one(pool)
+        .await?;
+    Ok(exists)
+}
+
+/// Load the ETag/Last-Modified pair (and body hash and discovered feed) stored for
+/// `feed_url`, if any.
+pub async fn load_validators(
+    pool: &PgPool,
+    feed_url: &str,
+) -> Result<CacheValidators, IngestError> {
+    let row: Option<CacheValidators> = sqlx::query_as(
+        "SELECT etag, last_modified, body_hash, discovered_url FROM feed_cache
+            WHERE feed_url = $1",
+    )
+    .bind(feed_url)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.unwrap_or_default())
+}
+
+/// Persist the validators returned by the latest successful fetch of `feed_url`.
+pub async fn store_validators(
+    pool: &PgPool,
+    feed_url: &str,
+    validators: &CacheValidators,
+) -> Result<(), IngestError> {
+    sqlx::query(
+        "INSERT INTO feed_cache (feed_url, etag, last_modified, body_hash, discovered_url, updated_at)
+        VALUES ($1, $2, $3, $4, $5, NOW())
+        ON CONFLICT (feed_url) DO UPDATE SET
+            etag = EXCLUDED.etag,
+            last_modified = EXCLUDED.last_modified,
+            body_hash = EXCLUDED.body_hash,
+            discovered_url = EXCLUDED.discovered_url,
+            updated_at = EXCLUDED.updated_at",
+    )
+    .bind(feed_url)
+    .bind(&validators.etag)
+    .bind(&validators.last_modified)
+    .bind(&validators.body_hash)
+    .bind(&validators.discovered_url)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
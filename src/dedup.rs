@@ -0,0 +1,320 @@
+//! In-memory Bloom filter over archived GUIDs, consulted before the archive
+//! `EXISTS` check so that entries which are certainly new skip the query.
+//! A "maybe present" answer (including false positives) falls through to the database.
+//!
+//! Also near-duplicate detection: the same advisory republished by several feeds, under
+//! other GUIDs and links, has nearly the same text. Each entry gets a SimHash of its
+//! text, and a new entry within `near_duplicate_distance` bits of an earlier one is
+//! stored linked to it through `duplicate_of` rather than as an independent item. So is
+//! an entry whose link leads, through a URL shortener, to the page of an earlier one.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use futures::TryStreamExt;
+use once_cell::sync::Lazy;
+use sqlx::{PgExecutor, PgPool};
+use tracing::info;
+
+use crate::enrich;
+use crate::errors::IngestError;
+use crate::ingestor::FeedItem;
+
+/// Headroom added on top of the archived GUID count when sizing the filter.
+const GROWTH_HEADROOM: usize = 100_000;
+
+/// The process-wide GUID filter; `None` until `load` runs (or when disabled).
+static GUID_FILTER: Lazy<RwLock<Option<BloomFilter>>> = Lazy::new(|| RwLock::new(None));
+
+/// Fewest words a text needs for a SimHash; shorter ones are too alike to compare.
+const SIMHASH_MIN_WORDS: usize = 8;
+
+/// Near-duplicate matching: maximum distance in bits and how far back to look.
+/// `None` until `enable_near_duplicates` runs (or when disabled).
+static NEAR_DUPLICATES: Lazy<RwLock<Option<(u32, Duration)>>> = Lazy::new(|| RwLock::new(None));
+
+/// Fixed-size Bloom filter using double hashing over SipHash.
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// A filter sized for `capacity` items at the given false-positive rate.
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let n = capacity.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-n * false_positive_rate.ln() / (ln2 * ln2))
+            .ceil()
+            .max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().clamp(1.0, 16.0) as u32;
+        BloomFilter {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    pub fn insert(&mut self, key: &str) {
+        for bit in self.bit_positions(key) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    /// `false` means `key` was certainly never inserted.
+    pub fn might_contain(&self, key: &str) -> bool {
+        self.bit_positions(key)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    fn bit_positions(&self, key: &str) -> impl Iterator<Item = u64> {
+        let mut h = DefaultHasher::new();
+        key.hash(&mut h);
+        let h1 = h.finish();
+        0x9e37_79b9_7f4a_7c15_u64.hash(&mut h);
+        let h2 = h.finish() | 1;
+        let num_bits = self.num_bits;
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+/// Build the filter from every GUID in `archive` and install it.
+pub async fn load(pool: &PgPool, false_positive_rate: f64) -> Result<(), IngestError> {
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM archive")
+        .fetch_one(pool)
+        .await?;
+    let mut filter = BloomFilter::new(count as usize + GROWTH_HEADROOM, false_positive_rate);
+
+    let mut guids = sqlx::query_as::<_, (String,)>("SELECT guid FROM archive").fetch(pool);
+    while let Some((guid,)) = guids.try_next().await? {
+        filter.insert(&guid);
+    }
+    info!(
+        guids = count,
+        bytes = filter.bits.len() * 8,
+        "Loaded archive GUID Bloom filter"
+    );
+    *GUID_FILTER.write().expect("GUID filter poisoned") = Some(filter);
+    Ok(())
+}
+
+/// Whether a filter has been loaded.
+pub fn is_loaded() -> bool {
+    GUID_FILTER.read().expect("GUID filter poisoned").is_some()
+}
+
+/// Whether `guid` may already be archived. Always `true` when no filter is loaded.
+pub fn might_contain(guid: &str) -> bool {
+    GUID_FILTER
+        .read()
+        .expect("GUID filter poisoned")
+        .as_ref()
+        .is_none_or(|f| f.might_contain(guid))
+}
+
+/// Record a newly archived `guid`.
+pub fn insert(guid: &str) {
+    if let Some(filter) = GUID_FILTER.write().expect("GUID filter poisoned").as_mut() {
+        filter.insert(guid);
+    }
+}
+
+/// SQL for the number of bits in which two `BIGINT` expressions differ (`bit_count`
+/// needs Postgres 14).
+fn hamming(a: &str, b: &str) -> String {
+    format!("length(replace((({a} # {b})::bit(64))::text, '0', ''))")
+}
+
+/// 64-bit SimHash of `text` (plain or HTML) over its lower-cased three-word shingles,
+/// or `None` below `SIMHASH_MIN_WORDS` words. Texts differing in a few words get hashes
+/// differing in a few bits.
+pub fn simhash(text: &str) -> Option<u64> {
+    let text = enrich::plain_text(text).to_lowercase();
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.len() < SIMHASH_MIN_WORDS {
+        return None;
+    }
+    let mut weights = [0i32; 64];
+    for shingle in words.windows(3) {
+        let h = stable_hash(shingle);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if h >> bit & 1 == 1 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+    Some(
+        weights
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| **w > 0)
+            .fold(0, |h, (bit, _)| h | 1 << bit),
+    )
+}
+
+/// FNV-1a of the space-joined `words`, finished with the SplitMix64 mixer so every
+/// bit is well spread. Unlike `DefaultHasher` it is the same across builds, which
+/// stored hashes need.
+fn stable_hash(words: &[&str]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for (i, word) in words.iter().enumerate() {
+        let sep = if i > 0 { " " } else { "" };
+        for b in sep.bytes().chain(word.bytes()) {
+            h = (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+/// `item` with `simhash` set from its title and the richer of its content or summary.
+pub fn with_simhash(item: FeedItem) -> FeedItem {
+    let body = item.content.as_deref().or(item.summary.as_deref());
+    let text = format!("{}\n{}", item.title, body.unwrap_or_default());
+    FeedItem {
+        simhash: simhash(&text).map(|h| h as i64),
+        ..item
+    }
+}
+
+/// Link new entries to earlier ones within `max_distance` bits, stored in the last
+/// `window`.
+pub fn enable_near_duplicates(max_distance: u32, window: Duration) {
+    *NEAR_DUPLICATES
+        .write()
+        .expect("near-duplicate settings poisoned") = Some((max_distance, window));
+}
+
+/// Stop linking near duplicates, as before `enable_near_duplicates`.
+pub fn disable_near_duplicates() {
+    *NEAR_DUPLICATES
+        .write()
+        .expect("near-duplicate settings poisoned") = None;
+}
+
+/// Distance limit and earliest `inserted_at` compared, if detection is on.
+fn near_duplicate_scope() -> Option<(i32, NaiveDateTime)> {
+    let settings = *NEAR_DUPLICATES
+        .read()
+        .expect("near-duplicate settings poisoned");
+    settings.map(|(max_distance, window)| {
+        let window = chrono::Duration::from_std(window).unwrap_or(chrono::Duration::MAX);
+        let since = Utc::now()
+            .naive_utc()
+            .checked_sub_signed(window)
+            .unwrap_or(NaiveDateTime::MIN);
+        (max_distance as i32, since)
+    })
+}
+
+/// GUID of the earliest archived entry `item` nearly duplicates, or leads to the same
+/// page as through a URL shortener, among recent entries that are not duplicates
+/// themselves.
+pub async fn near_duplicate_of(
+    conn: impl PgExecutor<'_>,
+    item: &FeedItem,
+) -> Result<Option<String>, IngestError> {
+    let Some((max_distance, since)) = near_duplicate_scope() else {
+        return Ok(None);
+    };
+    let original = sqlx::query_scalar(&format!(
+        "SELECT guid FROM (
+            SELECT guid, inserted_at FROM archive
+            WHERE simhash IS NOT NULL AND duplicate_of IS NULL
+                AND inserted_at > $2 AND guid <> $3 AND {} <= $4
+            UNION ALL
+            SELECT guid, inserted_at FROM archive
+            WHERE duplicate_of IS NULL AND inserted_at > $2 AND guid <> $3
+                AND resolved_link = $5
+            UNION ALL
+            SELECT guid, inserted_at FROM archive
+            WHERE $6 AND duplicate_of IS NULL AND inserted_at > $2 AND guid <> $3
+                AND link = $5
+        ) AS candidates
+        ORDER BY inserted_at, guid LIMIT 1",
+        hamming("simhash", "$1")
+    ))
+    .bind(item.simhash)
+    .bind(since)
+    .bind(&item.guid)
+    .bind(max_distance)
+    .bind(destination(item))
+    .bind(item.resolved_link.is_some())
+    .fetch_optional(conn)
+    .await?;
+    Ok(original)
+}
+
+/// `near_duplicate_of` for many items, in one query: the original's GUID per
+/// duplicate's. Items are not compared with each other.
+pub async fn near_duplicates_of(
+    conn: impl PgExecutor<'_>,
+    items: &[&FeedItem],
+) -> Result<HashMap<String, String>, IngestError> {
+    let Some((max_distance, since)) = near_duplicate_scope() else {
+        return Ok(HashMap::new());
+    };
+    if items.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let guids: Vec<&str> = items.iter().map(|item| item.guid.as_str()).collect();
+    let hashes: Vec<Option<i64>> = items.iter().map(|item| item.simhash).collect();
+    let destinations: Vec<&str> = items.iter().map(|item| destination(item)).collect();
+    let resolved: Vec<bool> = items
+        .iter()
+        .map(|item| item.resolved_link.is_some())
+        .collect();
+    let pairs: Vec<(String, String)> = sqlx::query_as(&format!(
+        "WITH new AS (
+            SELECT * FROM UNNEST($1::text[], $2::bigint[], $5::text[], $6::bool[])
+                AS new (guid, hash, destination, resolved)
+        )
+        SELECT DISTINCT ON (guid) guid, original FROM (
+            SELECT new.guid, archive.guid AS original, archive.inserted_at
+            FROM new
+            JOIN archive ON archive.simhash IS NOT NULL AND archive.duplicate_of IS NULL
+                AND archive.inserted_at > $3 AND archive.guid <> new.guid
+                AND {} <= $4
+            UNION ALL
+            SELECT new.guid, archive.guid, archive.inserted_at
+            FROM new
+            JOIN archive ON archive.resolved_link = new.destination
+                AND archive.duplicate_of IS NULL
+                AND archive.inserted_at > $3 AND archive.guid <> new.guid
+            UNION ALL
+            SELECT new.guid, archive.guid, archive.inserted_at
+            FROM new
+            JOIN archive ON new.resolved AND archive.link = new.destination
+                AND archive.duplicate_of IS NULL
+                AND archive.inserted_at > $3 AND archive.guid <> new.guid
+        ) AS matches
+        ORDER BY guid, inserted_at, original",
+        hamming("archive.simhash", "new.hash")
+    ))
+    .bind(&guids)
+    .bind(&hashes)
+    .bind(since)
+    .bind(max_distance)
+    .bind(&destinations)
+    .bind(&resolved)
+    .fetch_all(conn)
+    .await?;
+    Ok(pairs.into_iter().collect())
+}
+
+/// The page `item` leads to: its resolved link, else its link.
+fn destination(item: &FeedItem) -> &str {
+    item.resolved_link.as_deref().unwrap_or(&item.link)
+}
@@ -0,0 +1,62 @@
+//! Feed autodiscovery: a feed configured with a web site's URL is fetched from the
+//! first `<link rel="alternate">` on the page pointing to an RSS, Atom or JSON feed.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use url::Url;
+
+/// Feed types linked for autodiscovery.
+const FEED_TYPES: [&str; 4] = [
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+    "application/json",
+];
+
+static LINK_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<link\b[^>]*>").unwrap());
+
+static ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"([A-Za-z-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap());
+
+/// Whether `body` is an HTML page rather than a feed.
+pub fn is_html(body: &[u8]) -> bool {
+    let body = body.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(body);
+    let start = body
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(body.len());
+    let head = &body[start..body.len().min(start + 15)];
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+    head.starts_with("<!doctype html") || head.starts_with("<html")
+}
+
+/// The absolute URL of the first feed `html` (fetched from `base`) links to.
+pub fn feed_link(html: &[u8], base: &str) -> Option<String> {
+    let html = String::from_utf8_lossy(html);
+    let base = Url::parse(base).ok()?;
+    LINK_TAG.find_iter(&html).find_map(|tag| {
+        let mut rel = None;
+        let mut kind = None;
+        let mut href = None;
+        for attribute in ATTRIBUTE.captures_iter(tag.as_str()) {
+            let value = attribute
+                .get(2)
+                .or_else(|| attribute.get(3))
+                .or_else(|| attribute.get(4))
+                .map_or("", |m| m.as_str());
+            match attribute[1].to_ascii_lowercase().as_str() {
+                "rel" => rel = Some(value.to_ascii_lowercase()),
+                "type" => kind = Some(value.trim().to_ascii_lowercase()),
+                "href" => href = Some(value),
+                _ => {}
+            }
+        }
+        let alternate = rel?.split_ascii_whitespace().any(|r| r == "alternate");
+        if !alternate || !FEED_TYPES.contains(&kind?.as_str()) {
+            return None;
+        }
+        let href = htmlescape::decode_html(href?).ok()?;
+        let url = base.join(href.trim()).ok()?;
+        matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+    })
+}
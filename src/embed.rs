@@ -0,0 +1,221 @@
+//! Vector embeddings of entries, for finding related reporting across feeds with
+//! `db_utils::similar_entries`. Each entry new to the archive is queued for an
+//! `Embedder` (an OpenAI-compatible embeddings API unless a library user supplies
+//! another), and the vector it returns is stored in `entry_embeddings`, a pgvector
+//! table that only exists where the extension is installed. One background worker
+//! works through the queue at `requests_per_minute`. With `languages` set, entries in
+//! other languages are not embedded.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, Response};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::config::EmbeddingSettings;
+use crate::enrich;
+use crate::errors::IngestError;
+use crate::ingestor::FeedItem;
+use crate::metrics::EMBEDDINGS;
+use crate::tlp::{self, Tlp};
+
+/// An async enrichment turning an entry into a vector.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Name stored with each vector; only vectors of the same model are compared.
+    fn model(&self) -> &str;
+
+    async fn embed(&self, item: &FeedItem) -> Result<Vec<f32>, IngestError>;
+}
+
+/// Embeddings from `POST <endpoint>/embeddings`.
+pub struct OpenAiEmbedder {
+    client: Client,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+    max_input_chars: usize,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a str,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+}
+
+impl OpenAiEmbedder {
+    pub fn new(settings: &EmbeddingSettings) -> Result<Self, IngestError> {
+        let client = Client::builder()
+            .timeout(settings.timeout)
+            .build()
+            .map_err(|e| IngestError::Embedding(e.to_string()))?;
+        Ok(OpenAiEmbedder {
+            client,
+            url: format!("{}/embeddings", settings.endpoint.trim_end_matches('/')),
+            model: settings.model.clone(),
+            api_key: settings.api_key.clone(),
+            max_input_chars: settings.max_input_chars,
+        })
+    }
+}
+
+#[async_trait]
+impl Embedder for OpenAiEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed(&self, item: &FeedItem) -> Result<Vec<f32>, IngestError> {
+        let text = enrich::model_input(item, self.max_input_chars);
+        let body = EmbeddingRequest {
+            model: &self.model,
+            input: &text,
+        };
+        let mut request = self.client.post(&self.url).json(&body);
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+        let response: EmbeddingResponse = request
+            .send()
+            .await
+            .and_then(Response::error_for_status)
+            .map_err(|e| IngestError::Embedding(e.without_url().to_string()))?
+            .json()
+            .await
+            .map_err(|e| IngestError::Embedding(e.without_url().to_string()))?;
+        response
+            .data
+            .into_iter()
+            .next()
+            .map(|d| d.embedding)
+            .filter(|v| !v.is_empty() && v.iter().all(|x| x.is_finite()))
+            .ok_or_else(|| IngestError::Embedding("no usable embedding in response".into()))
+    }
+}
+
+/// Hands new entries to the embedder's worker; disabled by default.
+#[derive(Clone, Default)]
+pub struct EmbeddingQueue {
+    tx: Option<mpsc::Sender<FeedItem>>,
+    max_tlp: Option<Tlp>,
+    languages: Option<Vec<String>>,
+}
+
+impl EmbeddingQueue {
+    /// Start the worker for the configured embedder, if any. Fails if the database has
+    /// no `entry_embeddings` table, i.e. pgvector was missing when it was migrated.
+    pub async fn from_settings(
+        pool: &PgPool,
+        settings: Option<&EmbeddingSettings>,
+    ) -> Result<Self, IngestError> {
+        let Some(settings) = settings else {
+            return Ok(Self::default());
+        };
+        let (exists,): (bool,) =
+            sqlx::query_as("SELECT to_regclass('entry_embeddings') IS NOT NULL")
+                .fetch_one(pool)
+                .await?;
+        if !exists {
+            return Err(IngestError::Embedding(
+                "no entry_embeddings table: install the pgvector extension, then run \
+                migrations/20250620_create_entry_embeddings.sql by hand"
+                    .into(),
+            ));
+        }
+        let embedder = Arc::new(OpenAiEmbedder::new(settings)?);
+        Ok(Self::spawn(pool.clone(), embedder, settings))
+    }
+
+    /// Start a worker storing `embedder`'s vectors, paced by `settings`.
+    pub fn spawn(pool: PgPool, embedder: Arc<dyn Embedder>, settings: &EmbeddingSettings) -> Self {
+        let (tx, rx) = mpsc::channel(settings.queue);
+        let spacing = Duration::from_secs(60) / settings.requests_per_minute;
+        tokio::spawn(work(pool, embedder, spacing, rx));
+        EmbeddingQueue {
+            tx: Some(tx),
+            max_tlp: settings.max_tlp,
+            languages: settings.languages.clone(),
+        }
+    }
+
+    /// Queue `item` for an embedding, unless it is above `max_tlp`, not in `languages`
+    /// or the queue is full.
+    pub fn submit(&self, item: &FeedItem) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        if !tlp::permits(self.max_tlp, item.tlp) {
+            EMBEDDINGS.with_label_values(&["withheld"]).inc();
+            return;
+        }
+        if !enrich::language_in(self.languages.as_deref(), item) {
+            EMBEDDINGS.with_label_values(&["other_language"]).inc();
+            return;
+        }
+        if tx.try_send(item.clone()).is_err() {
+            EMBEDDINGS.with_label_values(&["dropped"]).inc();
+        }
+    }
+}
+
+async fn work(
+    pool: PgPool,
+    embedder: Arc<dyn Embedder>,
+    spacing: Duration,
+    mut rx: mpsc::Receiver<FeedItem>,
+) {
+    let mut next = Instant::now();
+    while let Some(item) = rx.recv().await {
+        tokio::time::sleep_until(next).await;
+        next = Instant::now() + spacing;
+        let stored = async {
+            let vector = embedder.embed(&item).await?;
+            store(&pool, &item.guid, embedder.model(), &vector).await
+        };
+        match stored.await {
+            Ok(()) => EMBEDDINGS.with_label_values(&["stored"]).inc(),
+            Err(e) => {
+                EMBEDDINGS.with_label_values(&["failed"]).inc();
+                warn!(guid = %item.guid, error = %e, "Failed to embed entry");
+            }
+        }
+    }
+}
+
+async fn store(pool: &PgPool, guid: &str, model: &str, vector: &[f32]) -> Result<(), IngestError> {
+    sqlx::query(
+        "INSERT INTO entry_embeddings (guid, model, embedding)
+        VALUES ($1, $2, $3::vector)
+        ON CONFLICT (guid) DO UPDATE SET
+            model = EXCLUDED.model,
+            embedding = EXCLUDED.embedding,
+            embedded_at = NOW()",
+    )
+    .bind(guid)
+    .bind(model)
+    .bind(vector_literal(vector))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// `vector` in pgvector's text form, `[1,2.5,-3]`.
+fn vector_literal(vector: &[f32]) -> String {
+    let parts: Vec<String> = vector.iter().map(f32::to_string).collect();
+    format!("[{}]", parts.join(","))
+}
@@ -0,0 +1,288 @@
+//! Enclosures: the files an entry carries (PDF advisories, podcast audio, IOC CSVs),
+//! from RSS `<enclosure>` and Media RSS `<media:content>` (which `feed_rs` models
+//! alike), Atom `rel="enclosure"` links and JSON Feed attachments. They are stored with
+//! the entry in `enclosures`. With `[enclosures]` configured, those of entries new to
+//! the archive are also downloaded into a local directory, each once, by one
+//! background worker; files over `max_bytes` are skipped.
+
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+
+use feed_rs::model::Entry;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::config::EnclosureSettings;
+use crate::errors::IngestError;
+use crate::extensions::EntryElements;
+use crate::http::{Body, HttpClients};
+use crate::ingestor::FeedItem;
+use crate::metrics::ENCLOSURE_DOWNLOADS;
+use crate::urlguard;
+
+/// A file attached to an entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Enclosure {
+    pub url: String,
+    /// Media type, as the feed states it
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Size in bytes, as the feed states it
+    #[serde(default)]
+    pub length: Option<u64>,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+/// The enclosures of `entry`, with relative URLs resolved against `link`. `feed_rs`
+/// drops relative RSS enclosure URLs, so the entry's first `<enclosure>` is also read
+/// from its raw `elements`.
+pub fn for_entry(
+    entry: &Entry,
+    elements: Option<&EntryElements>,
+    link: &str,
+) -> Option<Vec<Enclosure>> {
+    let base = Url::parse(link).ok();
+    let resolve = |href: &str| {
+        let url = match &base {
+            Some(base) => base.join(href.trim()).ok()?,
+            None => Url::parse(href.trim()).ok()?,
+        };
+        matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+    };
+
+    let links = entry
+        .links
+        .iter()
+        .filter(|l| l.rel.as_deref() == Some("enclosure"))
+        .filter_map(|l| {
+            Some(Enclosure {
+                url: resolve(&l.href)?,
+                content_type: l.media_type.clone(),
+                length: l.length,
+                title: l.title.clone(),
+            })
+        });
+    // An object's title only names its content when there is just one
+    let media = entry.media.iter().flat_map(|m| {
+        let title = m
+            .title
+            .as_ref()
+            .filter(|_| m.content.len() == 1)
+            .map(|t| t.content.clone());
+        m.content.iter().filter_map(move |c| {
+            Some(Enclosure {
+                url: resolve(c.url.as_ref()?.as_str())?,
+                content_type: c.content_type.as_ref().map(|ct| ct.to_string()),
+                length: c.size,
+                title: title.clone(),
+            })
+        })
+    });
+    let raw = elements.and_then(|e| {
+        Some(Enclosure {
+            url: resolve(e.get("enclosure@url")?)?,
+            content_type: e.get("enclosure@type").cloned(),
+            length: e
+                .get("enclosure@length")
+                .and_then(|l| l.trim().parse().ok()),
+            title: None,
+        })
+    });
+
+    let mut seen = HashSet::new();
+    let enclosures: Vec<Enclosure> = links
+        .chain(media)
+        .chain(raw)
+        .filter(|e| seen.insert(e.url.clone()))
+        .collect();
+    (!enclosures.is_empty()).then_some(enclosures)
+}
+
+/// Hands the enclosures of new entries to the download worker; disabled by default.
+#[derive(Clone, Default)]
+pub struct EnclosureDownloads {
+    tx: Option<mpsc::Sender<FeedItem>>,
+}
+
+impl EnclosureDownloads {
+    /// Start the worker if downloads are configured, creating their directory. Files are
+    /// fetched with the default feed client.
+    pub fn from_settings(
+        pool: &PgPool,
+        clients: &HttpClients,
+        settings: Option<&EnclosureSettings>,
+    ) -> Result<Self, IngestError> {
+        let Some(settings) = settings else {
+            return Ok(Self::default());
+        };
+        std::fs::create_dir_all(&settings.dir)
+            .map_err(|e| IngestError::Enclosure(format!("{}: {}", settings.dir.display(), e)))?;
+        let (tx, rx) = mpsc::channel(settings.queue);
+        tokio::spawn(work(pool.clone(), clients.clone(), settings.clone(), rx));
+        Ok(EnclosureDownloads { tx: Some(tx) })
+    }
+
+    /// Queue `item`'s enclosures for download, unless it has none or the queue is full.
+    pub fn submit(&self, item: &FeedItem) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        if item.enclosures.as_ref().is_none_or(Vec::is_empty) {
+            return;
+        }
+        if tx.try_send(item.clone()).is_err() {
+            ENCLOSURE_DOWNLOADS.with_label_values(&["dropped"]).inc();
+        }
+    }
+}
+
+async fn work(
+    pool: PgPool,
+    clients: HttpClients,
+    settings: EnclosureSettings,
+    mut rx: mpsc::Receiver<FeedItem>,
+) {
+    while let Some(item) = rx.recv().await {
+        let client = clients.default_client();
+        for enclosure in item.enclosures.iter().flatten() {
+            let outcome = match download(&pool, &client, &settings, &item.guid, enclosure).await {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    warn!(guid = %item.guid, url = %enclosure.url, error = %e, "Failed to download enclosure");
+                    "failed"
+                }
+            };
+            ENCLOSURE_DOWNLOADS.with_label_values(&[outcome]).inc();
+        }
+    }
+}
+
+/// Download `enclosure` into `settings.dir` unless it is filtered out, too large or
+/// stored already. Returns the outcome counted in `enclosure_downloads_total`.
+async fn download(
+    pool: &PgPool,
+    client: &Client,
+    settings: &EnclosureSettings,
+    guid: &str,
+    enclosure: &Enclosure,
+) -> Result<&'static str, IngestError> {
+    if !wanted(settings, enclosure.content_type.as_deref()) {
+        return Ok("filtered");
+    }
+    if enclosure.length.is_some_and(|len| len > settings.max_bytes) {
+        return Ok("too_large");
+    }
+    let (stored,): (bool,) =
+        sqlx::query_as("SELECT EXISTS(SELECT 1 FROM enclosure_files WHERE url = $1)")
+            .bind(&enclosure.url)
+            .fetch_one(pool)
+            .await?;
+    if stored {
+        return Ok("known");
+    }
+    if let Err(e) = urlguard::check(&enclosure.url) {
+        debug!(url = %enclosure.url, error = %e, "Enclosure URL refused");
+        return Ok("refused");
+    }
+
+    let url = enclosure.url.as_str();
+    let fetch_error = |e: reqwest::Error| IngestError::Fetch(url.to_string(), e.without_url());
+    let response = client
+        .get(url)
+        .timeout(settings.timeout)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(fetch_error)?;
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(';').next().unwrap_or(v).trim().to_ascii_lowercase())
+        .or_else(|| enclosure.content_type.clone());
+    if enclosure.content_type.is_none() && !wanted(settings, content_type.as_deref()) {
+        return Ok("filtered");
+    }
+    if response
+        .content_length()
+        .is_some_and(|len| len > settings.max_bytes)
+    {
+        return Ok("too_large");
+    }
+    let Some(body) = Body::read_limited(response, settings.max_bytes)
+        .await
+        .map_err(fetch_error)?
+    else {
+        return Ok("too_large");
+    };
+    let data = match body.decode(settings.max_bytes) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::FileTooLarge => return Ok("too_large"),
+        Err(e) => return Err(IngestError::Decode(url.to_string(), e)),
+    };
+
+    let sha256 = format!("{:x}", Sha256::digest(&data));
+    let name = match extension(&enclosure.url) {
+        Some(ext) => format!("{}.{}", sha256, ext),
+        None => sha256.clone(),
+    };
+    let path = settings.dir.join(&name);
+    write(&path, &data)
+        .await
+        .map_err(|e| IngestError::Enclosure(format!("{}: {}", path.display(), e)))?;
+    sqlx::query(
+        "INSERT INTO enclosure_files (url, guid, path, content_type, bytes, sha256)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (url) DO NOTHING",
+    )
+    .bind(&enclosure.url)
+    .bind(guid)
+    .bind(path.to_string_lossy().as_ref())
+    .bind(&content_type)
+    .bind(data.len() as i64)
+    .bind(&sha256)
+    .execute(pool)
+    .await?;
+    info!(guid = %guid, url = %enclosure.url, path = %path.display(), "Downloaded enclosure");
+    Ok("stored")
+}
+
+/// Whether `content_type` matches one of the configured prefixes (any does when none
+/// are configured; an unknown type does not otherwise).
+fn wanted(settings: &EnclosureSettings, content_type: Option<&str>) -> bool {
+    if settings.content_types.is_empty() {
+        return true;
+    }
+    let Some(content_type) = content_type else {
+        return false;
+    };
+    let content_type = content_type.to_ascii_lowercase();
+    settings
+        .content_types
+        .iter()
+        .any(|prefix| content_type.starts_with(&prefix.to_ascii_lowercase()))
+}
+
+/// The file extension of `url`'s path, if it has a short alphanumeric one.
+fn extension(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let name = url.path_segments()?.next_back()?;
+    let (_, ext) = name.rsplit_once('.')?;
+    (!ext.is_empty() && ext.len() <= 8 && ext.bytes().all(|b| b.is_ascii_alphanumeric()))
+        .then(|| ext.to_ascii_lowercase())
+}
+
+/// Write `data` to `path` through a temporary file, so a partial file never appears.
+async fn write(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let partial = path.with_extension("part");
+    tokio::fs::write(&partial, data).await?;
+    tokio::fs::rename(&partial, path).await
+}
@@ -0,0 +1,401 @@
+//! Post-processing enrichments derived from stored entries.
+//!
+//! Currently: products and version ranges named in advisory-style text
+//! ("Apache Struts 2.0.0 through 2.5.25", "OpenSSL versions prior to 3.0.7"),
+//! kept in `affected_products` so they can be queried per product; and indicators
+//! (CVE IDs, IPv4 addresses, file hashes), extracted on demand by the STIX export.
+//! Outputs can also render entries defanged (`hxxp://`, `198.51.100[.]7`) so nothing
+//! downstream turns an indicator into a live link; storage keeps the canonical form.
+//! Entries are also tagged with the language of their text (`detected_language`),
+//! since many feeds declare none or declare it for the whole feed only.
+
+use std::collections::BTreeSet;
+use std::net::Ipv4Addr;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use sqlx::PgExecutor;
+
+use crate::errors::IngestError;
+use crate::ingestor::FeedItem;
+use crate::sanitize::LengthUnit;
+
+/// A product mention with the versions it applies to, e.g. `("OpenSSL", "< 3.0.7")`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AffectedProduct {
+    pub product: String,
+    /// `= V`, `< V`, `<= V`, `>= V` or `>= A, <= B`
+    pub versions: String,
+}
+
+/// Dotted version number, optionally with a letter or pre-release suffix (1.1.1k, 3.0.0-rc1)
+const VERSION: &str = r"\d+(?:\.\d+)+[a-z]?(?:[-.]?(?:alpha|beta|rc|pre)\.?\d*)?\b";
+
+/// Up to four capitalised words on one line followed by a version or version range.
+static MENTION: Lazy<Regex> = Lazy::new(|| {
+    let pattern = format!(
+        r"(?x)
+        (?P<product>[A-Z][\w+/\-]*(?:[\ \t]+[A-Z][\w+./\-]*){{0,3}})
+        \s+(?:(?:versions?|releases?|v\.?)\s*)?
+        (?:
+            (?P<op>prior\s+to|before|earlier\s+than|older\s+than|below
+                  |up\s+to(?:\s+and\s+including)?|through|<=?|≤)
+                \s*(?:versions?\s+)?(?P<bound>{VERSION})
+          | (?P<lo>{VERSION})\s*(?:through|to|until|-|–|—)\s*(?:versions?\s+)?(?P<hi>{VERSION})
+          | (?P<max>{VERSION})\s+(?:and|or)\s+(?:earlier|prior|older|below|lower)\b
+          | (?P<min>{VERSION})\s+(?:and|or)\s+(?:later|above|newer|higher)\b
+          | (?P<exact>{VERSION})
+        )"
+    );
+    Regex::new(&pattern).expect("valid product regex")
+});
+
+/// Text right before a mention naming the fixed release rather than an affected one.
+static FIX_CONTEXT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(?:(?:fixed|patched|resolved|addressed)\s+in|(?:upgrade|update)\s+to)\s+$")
+        .expect("valid fix context regex")
+});
+
+/// A CVE ID, dotted-quad address or MD5/SHA-1/SHA-256 digest, as a whole word.
+static IOC: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?xi)
+        \b(?:
+            (?P<cve>CVE-\d{4}-\d{4,})
+          | (?P<ipv4>(?:\d{1,3}\.){3}\d{1,3})
+          | (?P<hash>[0-9a-f]{64}|[0-9a-f]{40}|[0-9a-f]{32})
+        )\b",
+    )
+    .expect("valid indicator regex")
+});
+
+/// An http(s) URL, or a dotted-quad address outside one.
+static FANGED: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\b(?P<url>https?://[^\s<>"']+)|\b(?P<ipv4>(?:\d{1,3}\.){3}\d{1,3})\b"#)
+        .expect("valid defang regex")
+});
+
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").expect("valid tag regex"));
+
+/// What an extracted indicator is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IocKind {
+    Cve,
+    Ipv4,
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+/// An indicator named in entry text; `value` is normalised (upper-case CVE IDs,
+/// lower-case hashes).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Ioc {
+    pub kind: IocKind,
+    pub value: String,
+}
+
+/// Capitalised words that start sentences rather than product names.
+const LEADING_NOISE: &[&str] = &[
+    "a",
+    "affected",
+    "affects",
+    "all",
+    "an",
+    "and",
+    "before",
+    "critical",
+    "customers",
+    "fixed",
+    "for",
+    "high",
+    "in",
+    "install",
+    "low",
+    "medium",
+    "of",
+    "on",
+    "patch",
+    "patched",
+    "prior",
+    "release",
+    "releases",
+    "security",
+    "the",
+    "these",
+    "this",
+    "update",
+    "updated",
+    "upgrade",
+    "users",
+    "version",
+    "versions",
+    "vulnerable",
+    "with",
+];
+
+/// Capitalised words followed by numbers that are not products.
+const NOT_PRODUCTS: &[&str] = &[
+    "cvss",
+    "cvssv2",
+    "cvssv3",
+    "cvssv3.1",
+    "cvssv4",
+    "cwe",
+    "epss",
+    "score",
+    "base score",
+    "section",
+    "step",
+    "table",
+    "figure",
+    "page",
+    "chapter",
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+impl Ioc {
+    /// `value` for display: an address with its dots bracketed, anything else as is.
+    pub fn defanged(&self) -> String {
+        match self.kind {
+            IocKind::Ipv4 => self.value.replace('.', "[.]"),
+            _ => self.value.clone(),
+        }
+    }
+}
+
+/// Extract product/version mentions from plain text or an HTML fragment.
+pub fn affected_products(text: &str) -> Vec<AffectedProduct> {
+    let text = TAG.replace_all(text, "\n").replace("&nbsp;", " ");
+    let mut found = BTreeSet::new();
+    for caps in MENTION.captures_iter(&text) {
+        let Some(product) = product_name(&caps["product"]) else {
+            continue;
+        };
+        let whole = caps.get(0).expect("match");
+        if caps.name("exact").is_some() && FIX_CONTEXT.is_match(&text[..whole.start()]) {
+            continue;
+        }
+        found.insert(AffectedProduct {
+            product,
+            versions: version_range(&caps),
+        });
+    }
+    found.into_iter().collect()
+}
+
+/// Extract indicators from plain text or an HTML fragment. Defanged notation
+/// (`198.51.100[.]7`) is accepted; loopback, unspecified and broadcast addresses are not.
+pub fn indicators(text: &str) -> Vec<Ioc> {
+    let text = TAG
+        .replace_all(text, "\n")
+        .replace("[.]", ".")
+        .replace("(.)", ".")
+        .replace("[dot]", ".");
+    let mut found = BTreeSet::new();
+    for caps in IOC.captures_iter(&text) {
+        let ioc = if let Some(cve) = caps.name("cve") {
+            Ioc {
+                kind: IocKind::Cve,
+                value: cve.as_str().to_uppercase(),
+            }
+        } else if let Some(ip) = caps.name("ipv4") {
+            match ip.as_str().parse::<Ipv4Addr>() {
+                Ok(addr)
+                    if !(addr.is_loopback() || addr.is_unspecified() || addr.is_broadcast()) =>
+                {
+                    Ioc {
+                        kind: IocKind::Ipv4,
+                        value: addr.to_string(),
+                    }
+                }
+                _ => continue,
+            }
+        } else {
+            let hash = caps["hash"].to_lowercase();
+            let kind = match hash.len() {
+                32 => IocKind::Md5,
+                40 => IocKind::Sha1,
+                _ => IocKind::Sha256,
+            };
+            Ioc { kind, value: hash }
+        };
+        found.insert(ioc);
+    }
+    found.into_iter().collect()
+}
+
+/// `item`'s title, summary and content, one per line.
+pub fn item_text(item: &FeedItem) -> String {
+    [
+        Some(item.title.as_str()),
+        item.summary.as_deref(),
+        item.content.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// `item`'s title and the richer of its content or summary (the content usually
+/// repeats the summary), as plain text cut to `max_chars`: what language models get.
+pub fn model_input(item: &FeedItem, max_chars: usize) -> String {
+    let body = item.content.as_deref().or(item.summary.as_deref());
+    let text = plain_text(&format!("{}\n{}", item.title, body.unwrap_or_default()));
+    LengthUnit::Chars
+        .truncate(text.trim(), max_chars)
+        .to_string()
+}
+
+/// `text` (plain or HTML) with its tags removed.
+pub fn plain_text(text: &str) -> String {
+    TAG.replace_all(text, " ").replace("&nbsp;", " ")
+}
+
+/// ISO 639-3 code of the language `text` (plain or HTML) is written in, if it can be
+/// told reliably.
+pub fn language(text: &str) -> Option<String> {
+    let text = plain_text(text);
+    whatlang::detect(&text)
+        .filter(|info| info.is_reliable())
+        .map(|info| info.lang().code().to_string())
+}
+
+/// Whether `item` is in one of `languages` (`und` standing for undetected); `None`
+/// admits every language.
+pub fn language_in(languages: Option<&[String]>, item: &FeedItem) -> bool {
+    let language = item.detected_language.as_deref().unwrap_or("und");
+    languages.is_none_or(|languages| languages.iter().any(|l| l == language))
+}
+
+/// `item` with `detected_language` set from its title, summary and content.
+pub fn with_language(item: FeedItem) -> FeedItem {
+    FeedItem {
+        detected_language: language(&item_text(&item)),
+        ..item
+    }
+}
+
+/// Defang plain text or an HTML fragment: `http(s)://` becomes `hxxp(s)://`, and the
+/// dots of URL hosts and of the addresses `indicators` would extract become `[.]`.
+pub fn defang(text: &str) -> String {
+    FANGED
+        .replace_all(text, |caps: &Captures| {
+            if let Some(url) = caps.name("url") {
+                let (scheme, rest) = url.as_str().split_once("://").expect("matched scheme");
+                let host_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+                return format!(
+                    "hxxp{}://{}{}",
+                    &scheme[4..],
+                    rest[..host_end].replace('.', "[.]"),
+                    &rest[host_end..]
+                );
+            }
+            let ip = &caps["ipv4"];
+            match ip.parse::<Ipv4Addr>() {
+                Ok(addr)
+                    if !(addr.is_loopback() || addr.is_unspecified() || addr.is_broadcast()) =>
+                {
+                    ip.replace('.', "[.]")
+                }
+                _ => ip.to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// `item` with its title, summary and content defanged. `link`, the entry's own
+/// page, stays usable.
+pub fn defanged(item: &FeedItem) -> FeedItem {
+    FeedItem {
+        title: defang(&item.title),
+        summary: item.summary.as_deref().map(defang),
+        content: item.content.as_deref().map(defang),
+        ..item.clone()
+    }
+}
+
+fn product_name(raw: &str) -> Option<String> {
+    let words: Vec<&str> = raw.split_whitespace().collect();
+    let start = words
+        .iter()
+        .position(|w| !LEADING_NOISE.contains(&w.to_lowercase().as_str()))?;
+    let name = words[start..].join(" ");
+    let lower = name.to_lowercase();
+    // "See CVE-2025-1234 4.2": a CVE ID is never part of a product name
+    if NOT_PRODUCTS.contains(&lower.as_str()) || lower.split(' ').any(|w| w.starts_with("cve-")) {
+        return None;
+    }
+    Some(name)
+}
+
+fn version_range(caps: &Captures) -> String {
+    if let Some(bound) = caps.name("bound") {
+        let op = caps["op"].split_whitespace().collect::<Vec<_>>().join(" ");
+        let op = match op.as_str() {
+            "up to" | "up to and including" | "through" | "<=" | "≤" => "<=",
+            _ => "<",
+        };
+        format!("{op} {}", bound.as_str())
+    } else if let (Some(lo), Some(hi)) = (caps.name("lo"), caps.name("hi")) {
+        format!(">= {}, <= {}", lo.as_str(), hi.as_str())
+    } else if let Some(max) = caps.name("max") {
+        format!("<= {}", max.as_str())
+    } else if let Some(min) = caps.name("min") {
+        format!(">= {}", min.as_str())
+    } else {
+        format!("= {}", &caps["exact"])
+    }
+}
+
+/// Record the products mentioned in `item`'s title, summary and content. Callers
+/// pass only new or revised entries; an unchanged one names nothing new.
+pub async fn store_affected_products(
+    conn: impl PgExecutor<'_>,
+    item: &FeedItem,
+) -> Result<(), IngestError> {
+    store_all_affected_products(conn, &[item]).await
+}
+
+/// `store_affected_products` for many items, in one statement.
+pub async fn store_all_affected_products(
+    conn: impl PgExecutor<'_>,
+    items: &[&FeedItem],
+) -> Result<(), IngestError> {
+    let mut guids = Vec::new();
+    let mut products = Vec::new();
+    let mut versions = Vec::new();
+    for item in items {
+        for found in affected_products(&item_text(item)) {
+            guids.push(item.guid.clone());
+            products.push(found.product);
+            versions.push(found.versions);
+        }
+    }
+    if guids.is_empty() {
+        return Ok(());
+    }
+    sqlx::query(
+        "INSERT INTO affected_products (guid, product, versions)
+        SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[])
+        ON CONFLICT DO NOTHING",
+    )
+    .bind(&guids)
+    .bind(&products)
+    .bind(&versions)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
@@ -7,12 +7,82 @@ pub enum IngestError {
     #[error("HTTP error fetching {0}: {1}")]
     Fetch(String, #[source] reqwest::Error),
 
+    #[error("Timed out fetching {0}: {1}")]
+    Timeout(String, #[source] reqwest::Error),
+
+    #[error("Refused to fetch {0}: {1}")]
+    UnsafeUrl(String, #[source] crate::urlguard::Blocked),
+
+    #[error("Could not decode body of {0}: {1}")]
+    Decode(String, #[source] std::io::Error),
+
     #[error("Parse error for {0}: {1}")]
     Parse(String, #[source] feed_rs::parser::ParseFeedError),
 
     #[error("Database error: {0}")]
     Db(#[from] sqlx::Error),
 
+    #[error("Database writes timed out after {0:?}")]
+    WriteTimeout(std::time::Duration),
+
     #[error("Configuration error: {0}")]
     Config(#[from] config::ConfigError),
+
+    #[error("Sink {0} error: {1}")]
+    Sink(String, String),
+
+    #[error("Summarizer error: {0}")]
+    Summarizer(String),
+
+    #[error("Embedding error: {0}")]
+    Embedding(String),
+
+    #[error("State snapshot error: {0}")]
+    State(String),
+
+    #[error("Incompatible database schema: {0}")]
+    Schema(String),
+
+    #[error("Export error: {0}")]
+    Export(String),
+
+    #[error("Invalid tag rule: {0}")]
+    TagRule(String),
+
+    #[error("Template error: {0}")]
+    Template(#[from] minijinja::Error),
+
+    #[error("Spool error: {0}")]
+    Spool(String),
+
+    #[error("Enclosure error: {0}")]
+    Enclosure(String),
+
+    #[error("{0} needs a PostgreSQL database")]
+    Unsupported(&'static str),
+}
+
+impl IngestError {
+    /// HTTP status of the response behind a fetch error, if the server answered at all.
+    pub fn http_status(&self) -> Option<u16> {
+        match self {
+            IngestError::Fetch(_, e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Whether this is Postgres being unreachable (rather than rejecting the statement),
+    /// so the write is worth spooling and retrying once it is back.
+    pub fn is_db_unavailable(&self) -> bool {
+        match self {
+            IngestError::Db(
+                sqlx::Error::Io(_) | sqlx::Error::Tls(_) | sqlx::Error::PoolTimedOut,
+            ) => true,
+            // connection_exception (08) and operator_intervention (57P: shutdown, restart)
+            IngestError::Db(sqlx::Error::Database(e)) => e
+                .code()
+                .is_some_and(|code| code.starts_with("08") || code.starts_with("57P")),
+            _ => false,
+        }
+    }
 }
@@ -0,0 +1,105 @@
+//! Raw per-entry XML elements, for fields `feed_rs` does not model
+//! (nonstandard dates, bylines, vendor namespaces, Atom threading, Dublin Core), and
+//! the per-feed `extensions` picked out of them.
+
+use std::collections::{BTreeMap, HashMap};
+
+use tracing::debug;
+
+/// Elements of one entry, keyed by their path below it: `prefix:name` for a child (or
+/// bare `name` without a prefix), `prefix:name/prefix:child` for its children and so on,
+/// plus their attributes keyed by `path@attribute`. Repeated elements keep their first
+/// value.
+pub type EntryElements = HashMap<String, String>;
+
+/// Namespaces keyed by a fixed prefix whatever prefix a feed uses: Atom Threading
+/// Extensions (RFC 4685), Dublin Core elements and terms, and the Creative Commons
+/// RSS 1.0 and RSS 2.0 license modules.
+const FIXED_PREFIXES: &[(&str, &str)] = &[
+    ("http://purl.org/syndication/thread/1.0", "thr"),
+    ("http://purl.org/dc/elements/1.1/", "dc"),
+    ("http://purl.org/dc/terms/", "dcterms"),
+    ("http://web.resource.org/cc/", "cc"),
+    ("http://creativecommons.org/ns#", "cc"),
+    (
+        "http://backend.userland.com/creativeCommonsRssModule",
+        "creativeCommons",
+    ),
+];
+
+/// Collect the elements of every `<item>`/`<entry>` in document order.
+/// Returns an empty list for bodies that are not well-formed XML (e.g. JSON Feed).
+pub fn entry_elements(body: &[u8]) -> Vec<EntryElements> {
+    let Ok(text) = std::str::from_utf8(body) else {
+        return Vec::new();
+    };
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let doc = match roxmltree::Document::parse_with_options(text, options) {
+        Ok(doc) => doc,
+        Err(e) => {
+            debug!(error = %e, "Raw element extraction skipped");
+            return Vec::new();
+        }
+    };
+
+    doc.descendants()
+        .filter(|n| n.is_element() && matches!(n.tag_name().name(), "item" | "entry"))
+        .map(|item| {
+            let mut elements = EntryElements::new();
+            for child in item.descendants().skip(1).filter(|c| c.is_element()) {
+                let key = path(item, child);
+                for attr in child.attributes() {
+                    elements
+                        .entry(format!("{}@{}", key, attr.name()))
+                        .or_insert_with(|| attr.value().to_string());
+                }
+                let value = child.text().map(str::trim).unwrap_or_default();
+                elements.entry(key).or_insert_with(|| value.to_string());
+            }
+            elements
+        })
+        .collect()
+}
+
+/// The configured `extensions` found in an entry's `elements`, by name.
+pub fn custom(
+    extensions: &HashMap<String, String>,
+    elements: Option<&EntryElements>,
+) -> Option<BTreeMap<String, String>> {
+    let elements = elements?;
+    let found: BTreeMap<String, String> = extensions
+        .iter()
+        .filter_map(|(name, path)| {
+            let value = elements.get(path.trim())?.trim();
+            (!value.is_empty()).then(|| (name.clone(), value.to_string()))
+        })
+        .collect();
+    (!found.is_empty()).then_some(found)
+}
+
+/// `node`'s path below `item`, its ancestors' names and its own joined by `/`.
+fn path(item: roxmltree::Node, node: roxmltree::Node) -> String {
+    let mut names: Vec<String> = node
+        .ancestors()
+        .take_while(|a| *a != item)
+        .map(|a| qualified_name(&a))
+        .collect();
+    names.reverse();
+    names.join("/")
+}
+
+/// `prefix:name` for a namespaced element, using the prefix declared in the document.
+fn qualified_name(node: &roxmltree::Node) -> String {
+    let name = node.tag_name().name();
+    let namespace = node.tag_name().namespace();
+    if let Some((_, prefix)) = FIXED_PREFIXES.iter().find(|(ns, _)| Some(*ns) == namespace) {
+        return format!("{}:{}", prefix, name);
+    }
+    match namespace.and_then(|ns| node.lookup_prefix(ns)) {
+        Some(prefix) if !prefix.is_empty() => format!("{}:{}", prefix, name),
+        _ => name.to_string(),
+    }
+}
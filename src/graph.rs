@@ -0,0 +1,173 @@
+//! Entity co-occurrence graph for link-analysis tools. The entities of an entry are
+//! the CVE IDs and products it mentions and the tags `tag_rules` gave it (threat
+//! actors and malware families are best written as tag rules). Every two entities
+//! named by the same entry are joined by an edge weighted by the number of such
+//! entries. `rust_feed_ingestor graph` rebuilds the `entity_edges` table from stored
+//! entries and can write the graph as GraphML for Gephi, Maltego and the like.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::enrich::{self, IocKind};
+use crate::errors::IngestError;
+use crate::ingestor::FeedItem;
+
+/// Entries naming more entities than this (bulk patch roundups) are left out, as
+/// they would join everything to everything.
+pub const MAX_ENTITIES_PER_ENTRY: usize = 50;
+
+/// Edges written per statement.
+const INSERT_CHUNK: usize = 5000;
+
+/// Something an entry names.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Entity {
+    /// "cve", "product" or "tag"
+    pub kind: &'static str,
+    pub name: String,
+}
+
+/// Two entities named together, `source` ordered before `target`.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Edge {
+    pub source_kind: String,
+    pub source: String,
+    pub target_kind: String,
+    pub target: String,
+    /// Entries naming both
+    pub weight: i64,
+    /// Earliest and latest of those entries, by publication (else storage) time
+    pub first_seen: NaiveDateTime,
+    pub last_seen: NaiveDateTime,
+}
+
+/// The entities `item` names.
+pub fn entities(item: &FeedItem) -> BTreeSet<Entity> {
+    let text = enrich::item_text(item);
+    let cves = enrich::indicators(&text)
+        .into_iter()
+        .filter(|ioc| ioc.kind == IocKind::Cve)
+        .map(|ioc| Entity {
+            kind: "cve",
+            name: ioc.value,
+        });
+    let products = enrich::affected_products(&text)
+        .into_iter()
+        .map(|found| Entity {
+            kind: "product",
+            name: found.product,
+        });
+    let tags = item.tags.iter().flatten().map(|tag| Entity {
+        kind: "tag",
+        name: tag.clone(),
+    });
+    cves.chain(products).chain(tags).collect()
+}
+
+/// The co-occurrence edges among `items`, heaviest first.
+pub fn edges(items: &[FeedItem]) -> Vec<Edge> {
+    let mut found: BTreeMap<(Entity, Entity), (i64, NaiveDateTime, NaiveDateTime)> =
+        BTreeMap::new();
+    for item in items {
+        let named: Vec<Entity> = entities(item).into_iter().collect();
+        if named.len() > MAX_ENTITIES_PER_ENTRY {
+            continue;
+        }
+        let seen = item.published.unwrap_or(item.inserted_at);
+        for (i, source) in named.iter().enumerate() {
+            for target in &named[i + 1..] {
+                let edge = found
+                    .entry((source.clone(), target.clone()))
+                    .or_insert((0, seen, seen));
+                edge.0 += 1;
+                edge.1 = edge.1.min(seen);
+                edge.2 = edge.2.max(seen);
+            }
+        }
+    }
+    let mut edges: Vec<Edge> = found
+        .into_iter()
+        .map(|((source, target), (weight, first_seen, last_seen))| Edge {
+            source_kind: source.kind.to_string(),
+            source: source.name,
+            target_kind: target.kind.to_string(),
+            target: target.name,
+            weight,
+            first_seen,
+            last_seen,
+        })
+        .collect();
+    edges.sort_by_key(|e| std::cmp::Reverse(e.weight));
+    edges
+}
+
+/// Replace the contents of `entity_edges` with `edges`.
+pub async fn store(pool: &PgPool, edges: &[Edge]) -> Result<(), IngestError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM entity_edges")
+        .execute(&mut *tx)
+        .await?;
+    for chunk in edges.chunks(INSERT_CHUNK) {
+        let column = |f: fn(&Edge) -> String| chunk.iter().map(f).collect::<Vec<_>>();
+        sqlx::query(
+            "INSERT INTO entity_edges
+                (source_kind, source, target_kind, target, weight, first_seen, last_seen)
+            SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[],
+                $5::bigint[], $6::timestamp[], $7::timestamp[])",
+        )
+        .bind(column(|e| e.source_kind.clone()))
+        .bind(column(|e| e.source.clone()))
+        .bind(column(|e| e.target_kind.clone()))
+        .bind(column(|e| e.target.clone()))
+        .bind(chunk.iter().map(|e| e.weight).collect::<Vec<_>>())
+        .bind(chunk.iter().map(|e| e.first_seen).collect::<Vec<_>>())
+        .bind(chunk.iter().map(|e| e.last_seen).collect::<Vec<_>>())
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// `edges` as an undirected GraphML graph; each node carries its `kind`, each edge its
+/// `weight`.
+pub fn graphml(edges: &[Edge]) -> String {
+    let id = |kind: &str, name: &str| htmlescape::encode_minimal(&format!("{kind}:{name}"));
+    let mut nodes = BTreeSet::new();
+    for edge in edges {
+        nodes.insert((edge.source_kind.as_str(), edge.source.as_str()));
+        nodes.insert((edge.target_kind.as_str(), edge.target.as_str()));
+    }
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n\
+        \x20 <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n\
+        \x20 <key id=\"kind\" for=\"node\" attr.name=\"kind\" attr.type=\"string\"/>\n\
+        \x20 <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"long\"/>\n\
+        \x20 <graph id=\"entities\" edgedefault=\"undirected\">\n",
+    );
+    for (kind, name) in nodes {
+        let _ = writeln!(
+            out,
+            "    <node id=\"{}\"><data key=\"label\">{}</data><data key=\"kind\">{}</data></node>",
+            id(kind, name),
+            htmlescape::encode_minimal(name),
+            kind
+        );
+    }
+    for edge in edges {
+        let _ = writeln!(
+            out,
+            "    <edge source=\"{}\" target=\"{}\"><data key=\"weight\">{}</data></edge>",
+            id(&edge.source_kind, &edge.source),
+            id(&edge.target_kind, &edge.target),
+            edge.weight
+        );
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
@@ -0,0 +1,656 @@
+//! GraphQL query API at `/graphql` (enable with `--features graphql`): entries from
+//! `current` or `archive` with their enrichment (affected products, indicators,
+//! revisions), and the configured feeds with their schedule and fetch history, so
+//! clients ask for the fields and filters they need instead of a REST route each.
+//! Read-only; queries are bounded in depth and complexity.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::sync::Arc;
+
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Enum, Error, InputObject, Json, Object, Request,
+    Response, Result, Schema, SimpleObject,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use sqlx::PgPool;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::db_utils::{self, Cursor, EntryQuery, FEED_ITEM_COLUMNS};
+use crate::enclosures::Enclosure;
+use crate::enrich::{self, IocKind};
+use crate::errors::IngestError;
+use crate::history::{self, HistoryRow};
+use crate::ingestor::FeedItem;
+use crate::media::MediaItem;
+use crate::scheduler::{FeedSchedule, Schedule};
+
+/// Deepest nesting of fields a query may use.
+const MAX_DEPTH: usize = 8;
+
+/// Highest complexity a query may have: each field costs 1, lists their length times
+/// the cost of their items.
+const MAX_COMPLEXITY: usize = 20_000;
+
+pub type QuerySchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+static SCHEMA: Lazy<QuerySchema> = Lazy::new(|| {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+});
+
+/// Run `request` against the entries in `pool` and the feeds in `schedule`.
+pub async fn execute(pool: &PgPool, schedule: &Schedule, request: Request) -> Response {
+    let request = request
+        .data(pool.clone())
+        .data(schedule.clone())
+        .data(DataLoader::new(ProductLoader(pool.clone()), tokio::spawn))
+        .data(DataLoader::new(RevisionLoader(pool.clone()), tokio::spawn))
+        .data(DataLoader::new(HistoryLoader(pool.clone()), tokio::spawn));
+    SCHEMA.execute(request).await
+}
+
+/// The GraphiQL page, an in-browser IDE sending its queries to `endpoint`.
+pub fn graphiql(endpoint: &str) -> String {
+    GraphiQLSource::build().endpoint(endpoint).finish()
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// Entries matching `filter`, newest first, `first` at a time; pass a page's
+    /// `nextCursor` as `after` for the next one.
+    #[graphql(
+        complexity = "first.clamp(1, EntryQuery::MAX_LIMIT as i32) as usize * child_complexity"
+    )]
+    async fn entries(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<EntryFilter>,
+        #[graphql(default = 50)] first: i32,
+        after: Option<String>,
+        #[graphql(default)] source: Source,
+        #[graphql(default)] defang: bool,
+    ) -> Result<EntryPage> {
+        let mut query = filter.unwrap_or_default().into_query()?;
+        query.limit = i64::from(first).clamp(1, EntryQuery::MAX_LIMIT);
+        query.archive = source == Source::Archive;
+        if let Some(after) = after {
+            query.cursor =
+                Some(Cursor::decode(&after).ok_or_else(|| Error::new("invalid cursor"))?);
+        }
+        let items = db_utils::list_entries(ctx.data()?, &query)
+            .await
+            .map_err(|e| database_error("list entries", e))?;
+        let next_cursor = match items.last() {
+            Some(last) if items.len() as i64 == query.limit => Some(Cursor::after(last).encode()),
+            _ => None,
+        };
+        Ok(EntryPage {
+            nodes: items
+                .into_iter()
+                .map(|item| Entry { item, defang })
+                .collect(),
+            next_cursor,
+        })
+    }
+
+    /// The entry with `guid`, if stored.
+    async fn entry(
+        &self,
+        ctx: &Context<'_>,
+        guid: String,
+        #[graphql(default)] source: Source,
+        #[graphql(default)] defang: bool,
+    ) -> Result<Option<Entry>> {
+        let table = match source {
+            Source::Current => "current",
+            Source::Archive => "archive",
+        };
+        let sql = format!("SELECT {FEED_ITEM_COLUMNS} FROM {table} WHERE guid = $1");
+        let item: Option<FeedItem> = sqlx::query_as(&sql)
+            .bind(guid)
+            .fetch_optional(ctx.data::<PgPool>()?)
+            .await
+            .map_err(|e| database_error("load entry", e))?;
+        Ok(item.map(|item| Entry { item, defang }))
+    }
+
+    /// The configured feeds, next due first.
+    async fn feeds(&self, ctx: &Context<'_>) -> Result<Vec<Feed>> {
+        let schedule = ctx.data::<Schedule>()?;
+        Ok(schedule.snapshot().into_iter().map(Feed).collect())
+    }
+
+    /// The configured feed called `name`.
+    async fn feed(&self, ctx: &Context<'_>, name: String) -> Result<Option<Feed>> {
+        let schedule = ctx.data::<Schedule>()?;
+        Ok(schedule
+            .snapshot()
+            .into_iter()
+            .find(|f| f.feed == name)
+            .map(Feed))
+    }
+}
+
+/// Which version of entries to read.
+#[derive(Enum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Source {
+    /// As last seen, from `current`
+    #[default]
+    Current,
+    /// As first seen, from `archive`
+    Archive,
+}
+
+/// Filters on `entries`, as on `GET /api/entries`.
+#[derive(InputObject, Debug, Default)]
+pub struct EntryFilter {
+    feed_url: Option<String>,
+    feed_type: Option<String>,
+    /// Only entries from feeds with this tag
+    tag: Option<String>,
+    /// Only replies to the entry with this GUID
+    parent_guid: Option<String>,
+    /// Only entries naming this product, any case
+    product: Option<String>,
+    /// Only entries published at or after this time
+    since: Option<DateTime<Utc>>,
+    /// Only entries published before this time
+    until: Option<DateTime<Utc>>,
+    /// Case-insensitive text match over title, summary and content
+    q: Option<String>,
+    /// Only entries marked at most this TLP (`CLEAR`, `GREEN`, `AMBER`,
+    /// `AMBER+STRICT`, `RED`); unmarked entries count as CLEAR
+    max_tlp: Option<String>,
+}
+
+impl EntryFilter {
+    fn into_query(self) -> Result<EntryQuery> {
+        Ok(EntryQuery {
+            feed_url: self.feed_url,
+            feed_type: self.feed_type,
+            tag: self.tag,
+            parent_guid: self.parent_guid,
+            product: self.product,
+            since: self.since.map(|t| t.naive_utc()),
+            until: self.until.map(|t| t.naive_utc()),
+            q: self.q,
+            max_tlp: self.max_tlp.map(|t| t.parse()).transpose()?,
+            ..Default::default()
+        })
+    }
+}
+
+/// A page of `entries`.
+#[derive(SimpleObject)]
+pub struct EntryPage {
+    nodes: Vec<Entry>,
+    /// Pass as `after` for the next page; null on the last page
+    next_cursor: Option<String>,
+}
+
+/// A stored entry.
+pub struct Entry {
+    item: FeedItem,
+    /// Render title, summary, content and indicators defanged (see `enrich::defang`)
+    defang: bool,
+}
+
+impl Entry {
+    fn text(&self, text: &str) -> String {
+        if self.defang {
+            enrich::defang(text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[Object]
+impl Entry {
+    async fn id(&self) -> Uuid {
+        self.item.id
+    }
+
+    async fn guid(&self) -> &str {
+        &self.item.guid
+    }
+
+    /// GUID of the entry this one replies to
+    async fn parent_guid(&self) -> Option<&str> {
+        self.item.parent_guid.as_deref()
+    }
+
+    async fn title(&self) -> String {
+        self.text(&self.item.title)
+    }
+
+    async fn link(&self) -> &str {
+        &self.item.link
+    }
+
+    /// Where `link` leads when it goes through a URL shortener
+    async fn resolved_link(&self) -> Option<&str> {
+        self.item.resolved_link.as_deref()
+    }
+
+    async fn published(&self) -> Option<NaiveDateTime> {
+        self.item.published
+    }
+
+    async fn updated(&self) -> Option<NaiveDateTime> {
+        self.item.entry_updated
+    }
+
+    async fn summary(&self) -> Option<String> {
+        self.item.summary.as_deref().map(|s| self.text(s))
+    }
+
+    /// Analyst summary written by the configured summarizer
+    async fn summary_ai(&self) -> Option<&str> {
+        self.item.summary_ai.as_deref()
+    }
+
+    async fn content(&self) -> Option<String> {
+        self.item.content.as_deref().map(|c| self.text(c))
+    }
+
+    async fn author(&self) -> Option<&str> {
+        self.item.author.as_deref()
+    }
+
+    async fn categories(&self) -> &[String] {
+        self.item.categories.as_deref().unwrap_or_default()
+    }
+
+    async fn image_url(&self) -> Option<&str> {
+        self.item.image_url.as_deref()
+    }
+
+    async fn thumbnail_url(&self) -> Option<&str> {
+        self.item.thumbnail_url.as_deref()
+    }
+
+    /// Files the entry carries, as in `GET /api/entries`
+    async fn enclosures(&self) -> Option<Json<&Vec<Enclosure>>> {
+        self.item.enclosures.as_ref().map(Json)
+    }
+
+    /// Media RSS content, as in `GET /api/entries`
+    async fn media(&self) -> Option<Json<&Vec<MediaItem>>> {
+        self.item.media.as_ref().map(Json)
+    }
+
+    /// Values of the feed's configured `extensions`, by name
+    async fn extensions(&self) -> Option<Json<&BTreeMap<String, String>>> {
+        self.item.extensions.as_ref().map(Json)
+    }
+
+    /// ISO 639-3 code of the language detected in the entry's text
+    async fn language(&self) -> Option<&str> {
+        self.item.detected_language.as_deref()
+    }
+
+    async fn tlp(&self) -> Option<&str> {
+        self.item.tlp.map(|t| t.as_str())
+    }
+
+    async fn license(&self) -> Option<&str> {
+        self.item.license.as_deref()
+    }
+
+    /// Tags of the configured rules the entry matches
+    async fn tags(&self) -> &[String] {
+        self.item.tags.as_deref().unwrap_or_default()
+    }
+
+    /// GUID of the earlier entry this one nearly duplicates
+    async fn duplicate_of(&self) -> Option<&str> {
+        self.item.duplicate_of.as_deref()
+    }
+
+    async fn inserted_at(&self) -> NaiveDateTime {
+        self.item.inserted_at
+    }
+
+    /// The feed the entry came from, as it described itself
+    async fn feed(&self) -> EntryFeed<'_> {
+        EntryFeed(&self.item)
+    }
+
+    /// Products and version ranges named in the entry
+    async fn affected_products(&self, ctx: &Context<'_>) -> Result<Vec<AffectedProduct>> {
+        let loader = ctx.data::<DataLoader<ProductLoader>>()?;
+        let found = loader
+            .load_one(self.item.guid.clone())
+            .await
+            .map_err(|e| database_error("load affected products", e))?;
+        Ok(found.unwrap_or_default())
+    }
+
+    /// CVE IDs, IPv4 addresses and file hashes named in the entry
+    async fn indicators(&self) -> Vec<Indicator> {
+        enrich::indicators(&enrich::item_text(&self.item))
+            .into_iter()
+            .map(|ioc| Indicator {
+                kind: ioc.kind.into(),
+                value: if self.defang {
+                    ioc.defanged()
+                } else {
+                    ioc.value.clone()
+                },
+            })
+            .collect()
+    }
+
+    /// Earlier edits of the entry, oldest first
+    async fn revisions(&self, ctx: &Context<'_>) -> Result<Vec<Revision>> {
+        let loader = ctx.data::<DataLoader<RevisionLoader>>()?;
+        let found = loader
+            .load_one(self.item.guid.clone())
+            .await
+            .map_err(|e| database_error("load revisions", e))?;
+        Ok(found.unwrap_or_default())
+    }
+}
+
+/// Feed metadata stored with an entry.
+pub struct EntryFeed<'a>(&'a FeedItem);
+
+#[Object]
+impl EntryFeed<'_> {
+    async fn url(&self) -> &str {
+        &self.0.feed_url
+    }
+
+    async fn title(&self) -> Option<&str> {
+        self.0.feed_title.as_deref()
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.0.feed_description.as_deref()
+    }
+
+    async fn language(&self) -> Option<&str> {
+        self.0.feed_language.as_deref()
+    }
+
+    async fn icon(&self) -> Option<&str> {
+        self.0.feed_icon.as_deref()
+    }
+
+    async fn updated(&self) -> Option<NaiveDateTime> {
+        self.0.feed_updated
+    }
+
+    /// The feed's configured `type`
+    #[graphql(name = "type")]
+    async fn feed_type(&self) -> Option<&str> {
+        self.0.feed_type.as_deref()
+    }
+
+    /// The feed's configured `tags`
+    async fn tags(&self) -> &[String] {
+        self.0.feed_tags.as_deref().unwrap_or_default()
+    }
+}
+
+/// A product mention with the versions it applies to.
+#[derive(SimpleObject, Clone, sqlx::FromRow)]
+pub struct AffectedProduct {
+    product: String,
+    /// `= V`, `< V`, `<= V`, `>= V` or `>= A, <= B`
+    versions: String,
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+#[graphql(remote = "IocKind")]
+pub enum IndicatorKind {
+    Cve,
+    Ipv4,
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+/// An indicator named in an entry.
+#[derive(SimpleObject)]
+pub struct Indicator {
+    kind: IndicatorKind,
+    /// Normalised: upper-case CVE IDs, lower-case hashes
+    value: String,
+}
+
+/// An edit of an entry, as recorded in `revisions`.
+#[derive(SimpleObject, Clone, sqlx::FromRow)]
+pub struct Revision {
+    title: String,
+    link: String,
+    summary: Option<String>,
+    content: Option<String>,
+    /// The entry's own update time, if it has one
+    entry_updated: Option<NaiveDateTime>,
+    /// Bytes changed from the version before
+    diff_bytes: i64,
+    recorded_at: NaiveDateTime,
+}
+
+/// A configured feed and its schedule.
+pub struct Feed(FeedSchedule);
+
+#[Object]
+impl Feed {
+    async fn name(&self) -> &str {
+        &self.0.feed
+    }
+
+    async fn url(&self) -> &str {
+        &self.0.url
+    }
+
+    async fn interval_seconds(&self) -> u64 {
+        self.0.interval.as_secs()
+    }
+
+    async fn last_run(&self) -> Option<DateTime<Utc>> {
+        self.0.last_run
+    }
+
+    async fn next_run(&self) -> DateTime<Utc> {
+        self.0.next_run
+    }
+
+    /// Failed runs in a row (0 after any success)
+    async fn consecutive_failures(&self) -> u32 {
+        self.0.consecutive_failures
+    }
+
+    async fn last_error(&self) -> Option<&str> {
+        self.0.last_error.as_deref()
+    }
+
+    /// Success rate of recent runs, the latest weighing most (1.0 = all succeeded)
+    async fn health(&self) -> f64 {
+        self.0.health
+    }
+
+    /// Only probed until a run succeeds
+    async fn disabled(&self) -> bool {
+        self.0.disabled
+    }
+
+    /// The newest `last` runs, newest first, as in `GET /feeds/{name}/history`
+    #[graphql(complexity = "last.clamp(1, 1000) as usize * child_complexity")]
+    async fn history(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 20)] last: i32,
+    ) -> Result<Vec<FetchRun>> {
+        let loader = ctx.data::<DataLoader<HistoryLoader>>()?;
+        let rows = loader
+            .load_one((self.0.feed.clone(), i64::from(last).clamp(1, 1000)))
+            .await
+            .map_err(|e| database_error("load fetch history", e))?;
+        Ok(rows.unwrap_or_default().into_iter().map(FetchRun).collect())
+    }
+}
+
+/// One run of a feed.
+pub struct FetchRun(HistoryRow);
+
+#[Object]
+impl FetchRun {
+    async fn started_at(&self) -> NaiveDateTime {
+        self.0.started_at
+    }
+
+    /// `fetched`, `not_modified`, `unchanged`, `failed` or `quota_skipped`
+    async fn status(&self) -> &str {
+        &self.0.status
+    }
+
+    async fn http_status(&self) -> Option<i32> {
+        self.0.http_status
+    }
+
+    async fn entries_seen(&self) -> i32 {
+        self.0.entries_seen
+    }
+
+    async fn entries_new(&self) -> i32 {
+        self.0.entries_new
+    }
+
+    async fn errors(&self) -> i32 {
+        self.0.errors
+    }
+
+    async fn duration_ms(&self) -> i64 {
+        self.0.duration_ms
+    }
+
+    async fn error(&self) -> Option<&str> {
+        self.0.error.as_deref()
+    }
+
+    async fn bytes_compressed(&self) -> i64 {
+        self.0.bytes_compressed
+    }
+
+    async fn bytes_decompressed(&self) -> i64 {
+        self.0.bytes_decompressed
+    }
+
+    /// The captured `history.headers`, by lower-case name
+    async fn response_headers(&self) -> Option<Json<&BTreeMap<String, String>>> {
+        self.0.response_headers.as_ref().map(|h| Json(&h.0))
+    }
+}
+
+/// Loads the affected products of the entries on a page in one query.
+pub struct ProductLoader(PgPool);
+
+impl Loader<String> for ProductLoader {
+    type Value = Vec<AffectedProduct>;
+    type Error = Arc<IngestError>;
+
+    async fn load(&self, guids: &[String]) -> Result<HashMap<String, Self::Value>, Self::Error> {
+        let rows: Vec<(String, String, String)> = sqlx::query_as(
+            "SELECT guid, product, versions FROM affected_products
+            WHERE guid = ANY($1::text[])
+            ORDER BY guid, product, versions",
+        )
+        .bind(guids)
+        .fetch_all(&self.0)
+        .await
+        .map_err(|e| Arc::new(IngestError::from(e)))?;
+        let mut products: HashMap<String, Self::Value> = HashMap::new();
+        for (guid, product, versions) in rows {
+            products
+                .entry(guid)
+                .or_default()
+                .push(AffectedProduct { product, versions });
+        }
+        Ok(products)
+    }
+}
+
+/// Loads the revisions of the entries on a page in one query.
+pub struct RevisionLoader(PgPool);
+
+impl Loader<String> for RevisionLoader {
+    type Value = Vec<Revision>;
+    type Error = Arc<IngestError>;
+
+    async fn load(&self, guids: &[String]) -> Result<HashMap<String, Self::Value>, Self::Error> {
+        let rows: Vec<(String, Revision)> = sqlx::query_as::<_, RevisionRow>(
+            "SELECT guid, title, link, summary, content, entry_updated, diff_bytes, recorded_at
+            FROM revisions
+            WHERE guid = ANY($1::text[])
+            ORDER BY guid, recorded_at, id",
+        )
+        .bind(guids)
+        .fetch_all(&self.0)
+        .await
+        .map_err(|e| Arc::new(IngestError::from(e)))?
+        .into_iter()
+        .map(|row| (row.guid, row.revision))
+        .collect();
+        let mut revisions: HashMap<String, Self::Value> = HashMap::new();
+        for (guid, revision) in rows {
+            revisions.entry(guid).or_default().push(revision);
+        }
+        Ok(revisions)
+    }
+}
+
+/// Loads the newest runs of the feeds on a page in one query per `last`, keyed by
+/// feed name and `last`.
+pub struct HistoryLoader(PgPool);
+
+impl Loader<(String, i64)> for HistoryLoader {
+    type Value = Vec<HistoryRow>;
+    type Error = Arc<IngestError>;
+
+    async fn load(
+        &self,
+        keys: &[(String, i64)],
+    ) -> Result<HashMap<(String, i64), Self::Value>, Self::Error> {
+        let mut by_limit: HashMap<i64, Vec<String>> = HashMap::new();
+        for (feed, last) in keys {
+            by_limit.entry(*last).or_default().push(feed.clone());
+        }
+        let mut runs = HashMap::new();
+        for (last, feeds) in by_limit {
+            let mut loaded = history::recent_by_feed(&self.0, &feeds, last)
+                .await
+                .map_err(Arc::new)?;
+            for feed in feeds {
+                let rows = loaded.remove(&feed).unwrap_or_default();
+                runs.insert((feed, last), rows);
+            }
+        }
+        Ok(runs)
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct RevisionRow {
+    guid: String,
+    #[sqlx(flatten)]
+    revision: Revision,
+}
+
+/// Log `e` and report a database error without its details, as the REST routes do.
+fn database_error(action: &str, e: impl Display) -> Error {
+    error!(error = %e, "Failed to {} for GraphQL", action);
+    Error::new("database error")
+}
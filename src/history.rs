@@ -0,0 +1,227 @@
+//! Per-feed ingestion history (`fetch_history`): one row per feed run, capped per
+//! feed, served by `GET /feeds/{name}/history`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::NaiveDateTime;
+use reqwest::header::HeaderMap;
+use serde::Serialize;
+use sqlx::types::Json;
+use sqlx::PgPool;
+
+use crate::errors::IngestError;
+
+/// How a feed run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchStatus {
+    /// A fresh body was fetched and its entries processed
+    Fetched,
+    /// The server answered `304 Not Modified`
+    NotModified,
+    /// The body hashed the same as last time
+    Unchanged,
+    /// The fetch or parse failed
+    Failed,
+    /// A daily quota was reached, so nothing was fetched
+    QuotaSkipped,
+}
+
+impl FetchStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FetchStatus::Fetched => "fetched",
+            FetchStatus::NotModified => "not_modified",
+            FetchStatus::Unchanged => "unchanged",
+            FetchStatus::Failed => "failed",
+            FetchStatus::QuotaSkipped => "quota_skipped",
+        }
+    }
+}
+
+/// What one feed run did, as recorded in `fetch_history`.
+#[derive(Debug, Clone)]
+pub struct FetchRecord {
+    pub status: FetchStatus,
+    pub http_status: Option<u16>,
+    pub entries_seen: usize,
+    /// Entries not previously in the archive
+    pub entries_new: usize,
+    pub errors: usize,
+    pub error: Option<String>,
+    /// Body size as transferred, and after undoing its `Content-Encoding`
+    pub bytes_compressed: usize,
+    pub bytes_decompressed: usize,
+    /// The captured response headers, by lowercase name; `None` if no response arrived
+    pub response_headers: Option<BTreeMap<String, String>>,
+}
+
+impl FetchRecord {
+    pub fn new(status: FetchStatus, http_status: Option<u16>) -> Self {
+        FetchRecord {
+            status,
+            http_status,
+            entries_seen: 0,
+            entries_new: 0,
+            errors: 0,
+            error: None,
+            bytes_compressed: 0,
+            bytes_decompressed: 0,
+            response_headers: None,
+        }
+    }
+
+    /// This record for a run that downloaded a body of the given sizes.
+    pub fn with_bytes(self, compressed: usize, decompressed: usize) -> Self {
+        FetchRecord {
+            bytes_compressed: compressed,
+            bytes_decompressed: decompressed,
+            ..self
+        }
+    }
+
+    /// This record with the response headers kept by `capture`.
+    pub fn with_headers(self, headers: Option<BTreeMap<String, String>>) -> Self {
+        FetchRecord {
+            response_headers: headers,
+            ..self
+        }
+    }
+}
+
+/// The headers among `names` present in `headers`, by lowercase name, repeated ones
+/// joined with ", ". `None` if nothing is captured or no response arrived.
+pub fn capture(headers: &HeaderMap, names: &[String]) -> Option<BTreeMap<String, String>> {
+    if names.is_empty() || headers.is_empty() {
+        return None;
+    }
+    let mut captured = BTreeMap::new();
+    for name in names {
+        let values: Vec<&str> = headers
+            .get_all(name.as_str())
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .collect();
+        if !values.is_empty() {
+            captured.insert(name.to_ascii_lowercase(), values.join(", "));
+        }
+    }
+    Some(captured)
+}
+
+/// One row of `GET /feeds/{name}/history`.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct HistoryRow {
+    pub started_at: NaiveDateTime,
+    pub status: String,
+    pub http_status: Option<i32>,
+    pub entries_seen: i32,
+    pub entries_new: i32,
+    pub errors: i32,
+    pub duration_ms: i64,
+    pub error: Option<String>,
+    pub bytes_compressed: i64,
+    pub bytes_decompressed: i64,
+    pub response_headers: Option<Json<BTreeMap<String, String>>>,
+}
+
+/// Append a run to `feed_name`'s history, keeping only its newest `keep` rows.
+pub async fn record(
+    pool: &PgPool,
+    feed_name: &str,
+    started_at: NaiveDateTime,
+    duration_ms: i64,
+    run: &FetchRecord,
+    keep: u32,
+) -> Result<(), IngestError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO fetch_history (
+            feed_name, started_at, status, http_status, entries_seen, entries_new, errors,
+            duration_ms, error, bytes_compressed, bytes_decompressed, response_headers
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+    )
+    .bind(feed_name)
+    .bind(started_at)
+    .bind(run.status.as_str())
+    .bind(run.http_status.map(i32::from))
+    .bind(run.entries_seen as i32)
+    .bind(run.entries_new as i32)
+    .bind(run.errors as i32)
+    .bind(duration_ms)
+    .bind(&run.error)
+    .bind(run.bytes_compressed as i64)
+    .bind(run.bytes_decompressed as i64)
+    .bind(run.response_headers.as_ref().map(Json))
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "DELETE FROM fetch_history
+        WHERE feed_name = $1 AND id <= (
+            SELECT id FROM fetch_history WHERE feed_name = $1
+            ORDER BY id DESC OFFSET $2 LIMIT 1
+        )",
+    )
+    .bind(feed_name)
+    .bind(i64::from(keep))
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// The newest `limit` runs of each of `feed_names`, newest first, in one query.
+pub async fn recent_by_feed(
+    pool: &PgPool,
+    feed_names: &[String],
+    limit: i64,
+) -> Result<HashMap<String, Vec<HistoryRow>>, IngestError> {
+    let rows: Vec<FeedHistoryRow> = sqlx::query_as(
+        "SELECT feed_name, started_at, status, http_status, entries_seen, entries_new, errors,
+                duration_ms, error, bytes_compressed, bytes_decompressed, response_headers
+        FROM (
+            SELECT *, row_number() OVER (PARTITION BY feed_name ORDER BY id DESC) AS n
+            FROM fetch_history
+            WHERE feed_name = ANY($1::text[])
+        ) AS ranked
+        WHERE n <= $2
+        ORDER BY feed_name, id DESC",
+    )
+    .bind(feed_names)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    let mut runs: HashMap<String, Vec<HistoryRow>> = HashMap::new();
+    for row in rows {
+        runs.entry(row.feed_name).or_default().push(row.run);
+    }
+    Ok(runs)
+}
+
+#[derive(sqlx::FromRow)]
+struct FeedHistoryRow {
+    feed_name: String,
+    #[sqlx(flatten)]
+    run: HistoryRow,
+}
+
+/// The newest `limit` runs of `feed_name`, newest first.
+pub async fn recent(
+    pool: &PgPool,
+    feed_name: &str,
+    limit: i64,
+) -> Result<Vec<HistoryRow>, IngestError> {
+    let rows = sqlx::query_as(
+        "SELECT started_at, status, http_status, entries_seen, entries_new, errors,
+                duration_ms, error, bytes_compressed, bytes_decompressed, response_headers
+        FROM fetch_history
+        WHERE feed_name = $1
+        ORDER BY id DESC
+        LIMIT $2",
+    )
+    .bind(feed_name)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
@@ -0,0 +1,230 @@
+//! Shared HTTP clients for feed fetches, one per distinct proxy, so connections are
+//! pooled across runs and each feed goes out through its configured proxy. All of
+//! them send the configured User-Agent and default headers, and unless `url_safety`
+//! is disabled they refuse internal addresses (see `urlguard`).
+//!
+//! Bodies are not decompressed by reqwest but by `Body::decode`, so both the size on
+//! the wire and the decoded size are known; zstd is offered ahead of gzip.
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::sync::{Arc, RwLock};
+
+use config::ConfigError;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING};
+use reqwest::{Client, Proxy, Response};
+
+use crate::config::{Feed, HttpSettings, Settings};
+use crate::errors::IngestError;
+use crate::urlguard;
+
+/// `proxy` value that bypasses the global default (and any `*_PROXY` environment variables).
+pub const DIRECT: &str = "direct";
+
+/// Content codings `Body::decode` handles, sent as `Accept-Encoding` unless the
+/// configured headers say otherwise.
+const ACCEPTED_ENCODINGS: &str = "zstd, gzip";
+
+/// Feed-fetching clients keyed by proxy URL; clones share (and see reloads of) the same set.
+#[derive(Debug, Clone, Default)]
+pub struct HttpClients {
+    inner: Arc<RwLock<ClientSet>>,
+}
+
+#[derive(Debug)]
+struct ClientSet {
+    default: Client,
+    by_proxy: HashMap<String, Client>,
+}
+
+impl HttpClients {
+    /// Build the clients for `settings`. Invalid proxy URLs are rejected here.
+    pub fn from_settings(settings: &Settings) -> Result<Self, IngestError> {
+        Ok(HttpClients {
+            inner: Arc::new(RwLock::new(ClientSet::from_settings(settings)?)),
+        })
+    }
+
+    /// Replace the clients with ones built for `settings`, e.g. after a config reload.
+    pub fn reload(&self, settings: &Settings) -> Result<(), IngestError> {
+        let clients = ClientSet::from_settings(settings)?;
+        *self.inner.write().expect("client set poisoned") = clients;
+        Ok(())
+    }
+
+    /// The client `feed` should be fetched with.
+    pub fn for_feed(&self, feed: &Feed) -> Client {
+        let set = self.inner.read().expect("client set poisoned");
+        feed.proxy
+            .as_ref()
+            .and_then(|p| set.by_proxy.get(p))
+            .unwrap_or(&set.default)
+            .clone()
+    }
+
+    /// The client for requests not tied to a feed, going through the global proxy.
+    pub fn default_client(&self) -> Client {
+        self.inner
+            .read()
+            .expect("client set poisoned")
+            .default
+            .clone()
+    }
+}
+
+impl ClientSet {
+    /// The default client (through `settings.proxy`, if set) and one client per other
+    /// proxy named by a feed.
+    fn from_settings(settings: &Settings) -> Result<Self, IngestError> {
+        let default = build(&settings.http, settings.proxy.as_deref())?;
+        let mut by_proxy = HashMap::new();
+        for feed in &settings.feeds {
+            if let Some(proxy) = &feed.proxy {
+                if !by_proxy.contains_key(proxy) {
+                    by_proxy.insert(
+                        proxy.clone(),
+                        build(&settings.http, Some(proxy)).map_err(|e| {
+                            IngestError::Config(ConfigError::Message(format!(
+                                "feed '{}': {}",
+                                feed.name, e
+                            )))
+                        })?,
+                    );
+                }
+            }
+        }
+        Ok(ClientSet { default, by_proxy })
+    }
+}
+
+impl Default for ClientSet {
+    fn default() -> Self {
+        ClientSet {
+            default: Client::new(),
+            by_proxy: HashMap::new(),
+        }
+    }
+}
+
+/// A client going through `proxy` (`http://`, `https://`, `socks5://` or `socks5h://`),
+/// `DIRECT`, or, when `None`, the environment's proxy settings.
+fn build(http: &HttpSettings, proxy: Option<&str>) -> Result<Client, IngestError> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &http.headers {
+        let name = HeaderName::from_bytes(name.as_bytes());
+        let value = HeaderValue::from_str(value);
+        let (Ok(name), Ok(value)) = (name, value) else {
+            return Err(IngestError::Config(ConfigError::Message(
+                "http: invalid header".into(),
+            )));
+        };
+        headers.insert(name, value);
+    }
+    headers
+        .entry(ACCEPT_ENCODING)
+        .or_insert(HeaderValue::from_static(ACCEPTED_ENCODINGS));
+    // TLS details let `certs` track each host's certificate
+    let builder = Client::builder()
+        .tls_info(true)
+        .no_gzip()
+        .timeout(http.timeout)
+        .user_agent(&http.user_agent)
+        .default_headers(headers);
+    let builder = if urlguard::is_enabled() {
+        builder
+            .dns_resolver(urlguard::resolver(proxy))
+            .redirect(urlguard::redirect_policy())
+    } else {
+        builder
+    };
+    let builder = match proxy {
+        None => builder,
+        Some(DIRECT) => builder.no_proxy(),
+        Some(url) => builder.proxy(Proxy::all(url).map_err(|e| {
+            IngestError::Config(ConfigError::Message(format!(
+                "invalid proxy {}: {}",
+                url, e
+            )))
+        })?),
+    };
+    builder
+        .build()
+        .map_err(|e| IngestError::Config(ConfigError::Message(format!("HTTP client: {}", e))))
+}
+
+/// A response body as received, still in its `Content-Encoding`.
+#[derive(Debug, Clone)]
+pub struct Body {
+    /// The bytes transferred
+    pub raw: Vec<u8>,
+    encoding: Option<String>,
+}
+
+impl Body {
+    /// Read the whole body of `response`.
+    pub async fn read(response: Response) -> Result<Self, reqwest::Error> {
+        let encoding = encoding(&response);
+        let raw = response.bytes().await?.into();
+        Ok(Body { raw, encoding })
+    }
+
+    /// Read the body of `response` unless more than `limit` bytes are transferred.
+    pub async fn read_limited(
+        mut response: Response,
+        limit: u64,
+    ) -> Result<Option<Self>, reqwest::Error> {
+        let encoding = encoding(&response);
+        let mut raw = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if (raw.len() + chunk.len()) as u64 > limit {
+                return Ok(None);
+            }
+            raw.extend_from_slice(&chunk);
+        }
+        Ok(Some(Body { raw, encoding }))
+    }
+
+    /// The body with its `zstd`, `gzip` or `x-gzip` coding undone; more than `limit`
+    /// decoded bytes (a compression bomb, say) is a `FileTooLarge` error.
+    pub fn decode(&self, limit: u64) -> io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        // One byte past the limit tells a body of exactly `limit` bytes from a longer one
+        let cap = limit.saturating_add(1);
+        match self.encoding.as_deref() {
+            None => decoded.extend_from_slice(&self.raw),
+            Some("zstd") => {
+                zstd::stream::read::Decoder::new(&self.raw[..])?
+                    .take(cap)
+                    .read_to_end(&mut decoded)?;
+            }
+            Some("gzip" | "x-gzip") => {
+                flate2::read::MultiGzDecoder::new(&self.raw[..])
+                    .take(cap)
+                    .read_to_end(&mut decoded)?;
+            }
+            Some(other) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unsupported Content-Encoding '{}'", other),
+                ))
+            }
+        }
+        if decoded.len() as u64 > limit {
+            return Err(io::Error::new(
+                io::ErrorKind::FileTooLarge,
+                format!("body exceeds {} bytes once decoded", limit),
+            ));
+        }
+        Ok(decoded)
+    }
+}
+
+/// The `Content-Encoding` of `response`, unless none or `identity`.
+fn encoding(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_ascii_lowercase())
+        .filter(|v| !v.is_empty() && v != "identity")
+}
@@ -0,0 +1,182 @@
+//! Feed icon caching: a feed's icon (or its site's favicon) is downloaded once, when
+//! the feed is first fetched, and served from `/icons/{feed}` so it outlives the
+//! publisher's URL. A feed without a usable icon is looked at again after
+//! `MISS_RETRY`, not on every fetch.
+
+use std::time::Duration;
+
+use feed_rs::model::Feed;
+use reqwest::Client;
+use sqlx::PgPool;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::config;
+use crate::errors::IngestError;
+use crate::http::Body;
+use crate::urlguard;
+
+/// Icons larger than this are not cached.
+const MAX_ICON_BYTES: usize = 1024 * 1024;
+
+const ICON_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a feed none of whose candidates yielded an icon is left alone.
+const MISS_RETRY: Duration = Duration::from_secs(24 * 3600);
+
+/// A cached icon as served over HTTP.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct CachedIcon {
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Cache an icon for `source` in the background unless one is stored already, using
+/// the feed's `client`. Tries the feed's own icon or logo first, then `/favicon.ico`
+/// on the site host and on the feed host.
+pub fn cache_in_background(pool: PgPool, client: Client, source: &config::Feed, feed: &Feed) {
+    let feed_name = source.name.clone();
+    let urls = candidate_urls(source, feed);
+    tokio::spawn(async move {
+        if let Err(e) = ensure_cached(&pool, &client, &feed_name, urls).await {
+            warn!(feed = %feed_name, error = %e, "Failed to cache feed icon");
+        }
+    });
+}
+
+async fn ensure_cached(
+    pool: &PgPool,
+    client: &Client,
+    feed_name: &str,
+    urls: Vec<String>,
+) -> Result<(), IngestError> {
+    let (cached,): (bool,) = sqlx::query_as(
+        "SELECT EXISTS(SELECT 1 FROM feed_icons WHERE feed_name = $1)
+            OR EXISTS(SELECT 1 FROM feed_icon_misses
+                WHERE feed_name = $1 AND retry_after > NOW())",
+    )
+    .bind(feed_name)
+    .fetch_one(pool)
+    .await?;
+    if cached {
+        return Ok(());
+    }
+
+    for url in urls {
+        if let Err(e) = urlguard::check(&url) {
+            debug!(feed = %feed_name, url = %url, error = %e, "Icon URL refused");
+            continue;
+        }
+        match download(client, &url).await {
+            Ok(Some(icon)) => {
+                sqlx::query(
+                    "INSERT INTO feed_icons (feed_name, source_url, content_type, data)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT (feed_name) DO NOTHING",
+                )
+                .bind(feed_name)
+                .bind(&url)
+                .bind(&icon.content_type)
+                .bind(&icon.data)
+                .execute(pool)
+                .await?;
+                sqlx::query("DELETE FROM feed_icon_misses WHERE feed_name = $1")
+                    .bind(feed_name)
+                    .execute(pool)
+                    .await?;
+                info!(feed = %feed_name, url = %url, "Cached feed icon");
+                return Ok(());
+            }
+            Ok(None) => debug!(feed = %feed_name, url = %url, "Not a usable icon"),
+            Err(e) => debug!(feed = %feed_name, url = %url, error = %e, "Icon fetch failed"),
+        }
+    }
+    sqlx::query(
+        "INSERT INTO feed_icon_misses (feed_name, retry_after)
+        VALUES ($1, NOW() + make_interval(secs => $2))
+        ON CONFLICT (feed_name) DO UPDATE SET retry_after = EXCLUDED.retry_after",
+    )
+    .bind(feed_name)
+    .bind(MISS_RETRY.as_secs_f64())
+    .execute(pool)
+    .await?;
+    debug!(feed = %feed_name, retry_s = MISS_RETRY.as_secs(), "No usable feed icon");
+    Ok(())
+}
+
+/// The cached icon for the feed named `feed_name`, if any.
+pub async fn load(pool: &PgPool, feed_name: &str) -> Result<Option<CachedIcon>, IngestError> {
+    let icon = sqlx::query_as("SELECT content_type, data FROM feed_icons WHERE feed_name = $1")
+        .bind(feed_name)
+        .fetch_optional(pool)
+        .await?;
+    Ok(icon)
+}
+
+fn candidate_urls(source: &config::Feed, feed: &Feed) -> Vec<String> {
+    let base = Url::parse(&source.url).ok();
+    let resolve = |href: &str| match &base {
+        Some(base) => base.join(href).ok().map(|u| u.to_string()),
+        None => Url::parse(href).ok().map(|u| u.to_string()),
+    };
+
+    let mut urls: Vec<String> = [&feed.icon, &feed.logo]
+        .into_iter()
+        .flatten()
+        .filter_map(|image| resolve(&image.uri))
+        .collect();
+    let site = feed
+        .links
+        .iter()
+        .find(|l| l.rel.as_deref().is_none_or(|rel| rel == "alternate"))
+        .and_then(|l| resolve(&l.href))
+        .and_then(|s| Url::parse(&s).ok());
+    urls.extend(
+        [site, base]
+            .into_iter()
+            .flatten()
+            .filter_map(|host| host.join("/favicon.ico").ok())
+            .map(|u| u.to_string()),
+    );
+    let mut seen = std::collections::HashSet::new();
+    urls.retain(|u| seen.insert(u.clone()));
+    urls
+}
+
+/// Download `url`, returning `None` when the response is not a reasonably sized image.
+async fn download(client: &Client, url: &str) -> Result<Option<CachedIcon>, reqwest::Error> {
+    let response = client
+        .get(url)
+        .timeout(ICON_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(';').next().unwrap_or(v).trim().to_ascii_lowercase());
+    let content_type = match content_type {
+        Some(ct) if ct.starts_with("image/") => ct,
+        // Plenty of servers send favicons untyped or as octet-stream
+        Some(ct) if ct == "application/octet-stream" && url.ends_with(".ico") => {
+            "image/x-icon".into()
+        }
+        None if url.ends_with(".ico") => "image/x-icon".into(),
+        _ => return Ok(None),
+    };
+    if response
+        .content_length()
+        .is_some_and(|len| len as usize > MAX_ICON_BYTES)
+    {
+        return Ok(None);
+    }
+    // Feed clients leave Content-Encoding (used for SVG and ICO icons) to `Body`
+    let Ok(data) = Body::read(response).await?.decode(MAX_ICON_BYTES as u64) else {
+        return Ok(None);
+    };
+    if data.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(CachedIcon { content_type, data }))
+}
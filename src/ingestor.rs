@@ -1,47 +1,141 @@
 //! Core ingestion logic: fetch, parse, dedupe, sanitize, and upsert.
 
+use crate::certs;
+use crate::charset;
+use crate::config::{self, RetrySettings};
+use crate::dedup;
+use crate::discovery;
+use crate::enclosures::{self, Enclosure};
+use crate::enrich;
 use crate::errors::IngestError;
-use crate::metrics::{ENTRIES_PROCESSED, FETCH_COUNTER, FETCH_HISTOGRAM, SANITIZATION_FAILURES};
-use ammonia::clean;
-use chrono::{NaiveDateTime, Utc};
+use crate::extensions::{self, EntryElements};
+use crate::http::Body;
+use crate::license;
+use crate::links;
+use crate::mbox;
+use crate::media::{self, MediaItem};
+use crate::metrics::{
+    DB_WRITE_HISTOGRAM, DEDUP_BLOOM_FALSE_POSITIVES, DEDUP_BLOOM_SKIPS, FEED_BODY_UNCHANGED,
+    FEED_BYTES, FEED_CACHE_HITS, FEED_TRANSCODED, FETCH_COUNTER, FETCH_FAILURES, FETCH_HISTOGRAM,
+    FETCH_RETRIES, NEAR_DUPLICATES, SYNTHETIC_GUIDS,
+};
+use crate::parsing;
+use crate::revisions;
+use crate::sanitize::{LengthLimits, Sanitizer};
+use crate::tagging;
+use crate::tlp::{self, Tlp};
+use crate::urlguard;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use feed_rs::model::{Entry, Feed};
-use feed_rs::parser;
-use sqlx::PgPool;
-use std::time::Instant;
-use tracing::{debug, info, warn};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use regex::Regex;
+use reqwest::header::{
+    HeaderMap, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use siphasher::sip128::{Hasher128, SipHasher};
+use sqlx::{PgPool, Postgres, QueryBuilder, Transaction};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hasher;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, instrument, warn};
 use url::Url;
 use uuid::Uuid;
 
 /// Represents all unified fields we store for each RSS/Atom article.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct FeedItem {
     // Core/primary fields
     pub id: Uuid,
     pub guid: String,
+    /// GUID of the entry this one replies to (Atom `thr:in-reply-to`), for threaded sources
+    #[serde(default)]
+    pub parent_guid: Option<String>,
     pub title: String,
     pub link: String,
+    /// Where `link` leads when it goes through a URL shortener (see `links::Unshortener`)
+    #[serde(default)]
+    pub resolved_link: Option<String>,
     pub published: Option<NaiveDateTime>,
     pub content: Option<String>,
     pub summary: Option<String>,
+    /// Analyst summary written by the configured summarizer, some time after storing
+    #[serde(default)]
+    pub summary_ai: Option<String>,
     pub author: Option<String>,
     pub categories: Option<Vec<String>>,
     pub entry_updated: Option<NaiveDateTime>,
+    pub image_url: Option<String>,
+    /// The entry's first Media RSS thumbnail
+    #[serde(default)]
+    pub thumbnail_url: Option<String>,
+    /// Media RSS content: images, video and audio with their dimensions and duration
+    #[serde(default)]
+    #[sqlx(json(nullable))]
+    pub media: Option<Vec<MediaItem>>,
+    /// Values of the feed's configured `extensions`, by name
+    #[serde(default)]
+    #[sqlx(json(nullable))]
+    pub extensions: Option<BTreeMap<String, String>>,
+    /// Files the entry carries: RSS enclosures, Atom enclosure links, JSON Feed attachments
+    #[serde(default)]
+    #[sqlx(json(nullable))]
+    pub enclosures: Option<Vec<Enclosure>>,
     // Feed/source metadata
     pub feed_url: String,
     pub feed_title: Option<String>,
     pub feed_description: Option<String>,
     pub feed_language: Option<String>,
+    /// ISO 639-3 code of the language detected in the entry's text (see `enrich::language`)
+    #[serde(default)]
+    pub detected_language: Option<String>,
     pub feed_icon: Option<String>,
     pub feed_updated: Option<NaiveDateTime>,
+    pub feed_type: Option<String>,
+    pub feed_tags: Option<Vec<String>>,
+    /// TLP marking from the feed's config and/or markers in the entry
+    #[serde(default)]
+    pub tlp: Option<Tlp>,
+    /// License or rights statement (the feed's configured one, else the entry's or feed's)
+    #[serde(default)]
+    pub license: Option<String>,
+    /// SimHash of the entry's text, for near-duplicate detection (see `dedup::simhash`)
+    #[serde(default)]
+    pub simhash: Option<i64>,
+    /// GUID of the earlier entry this one nearly duplicates, when stored as a duplicate
+    #[serde(default)]
+    pub duplicate_of: Option<String>,
+    /// Tags of the configured rules the entry matches (see `tagging`)
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
     pub inserted_at: NaiveDateTime,
 }
 
+/// Dublin Core elements standing in for core fields a feed leaves out, most specific first.
+const DC_PUBLISHED: &[&str] = &["dc:date", "dcterms:issued", "dcterms:created"];
+const DC_UPDATED: &[&str] = &["dcterms:modified"];
+const DC_CREATOR: &[&str] = &["dc:creator", "dcterms:creator"];
+const DC_SUBJECT: &[&str] = &["dc:subject", "dcterms:subject"];
+
 /// Given an entry and its feed metadata, map all fields, always preferring the most content-rich field available.
 /// - If `entry.content` exists, use that (most feeds with `<content:encoded>` or `<content>`).
 /// - Else, use `entry.summary` (maps to `<description>` or `<summary>`).
 /// - Clean HTML for both, as per best practice.
-pub fn entry_to_feed_item(entry: &Entry, feed: &Feed, feed_url: &str) -> FeedItem {
-    // Compute the "best" link (resolve relative URLs if needed)
+/// - Apply the source's `mapping` overrides using the entry's raw `elements`.
+/// - Fall back on Dublin Core elements (`dc:date`, `dc:creator`, ...) for dates, author
+///   and categories the entry itself lacks.
+pub fn entry_to_feed_item(
+    entry: &Entry,
+    feed: &Feed,
+    source: &config::Feed,
+    elements: Option<&EntryElements>,
+) -> FeedItem {
+    let feed_url = source.url.as_str();
+    // Compute the "best" link (resolve relative URLs if needed, then canonicalize)
     let link_raw = entry
         .links
         .first()
@@ -54,6 +148,7 @@ pub fn entry_to_feed_item(entry: &Entry, feed: &Feed, feed_url: &str) -> FeedIte
             .map(|u| u.to_string())
             .unwrap_or(link_raw.clone()),
     };
+    let link = links::canonical(&link, source.prefer_https);
 
     // Prefer entry.content (usually from <content:encoded> or <content>), else summary/description
     let content = entry
@@ -65,6 +160,11 @@ pub fn entry_to_feed_item(entry: &Entry, feed: &Feed, feed_url: &str) -> FeedIte
     // Keep summary as original summary field (for metadata/teaser purposes)
     let summary = entry.summary.as_ref().map(|s| s.content.clone());
 
+    let image_url = lead_image(entry, content.as_deref().or(summary.as_deref()), &link);
+    let thumbnail_url = media::thumbnail_url(entry, &link);
+    let media = media::for_entry(entry, elements, &link);
+    let enclosures = enclosures::for_entry(entry, elements, &link);
+
     // Log if both are None for visibility
     if content.is_none() && summary.is_none() {
         warn!(
@@ -78,121 +178,825 @@ pub fn entry_to_feed_item(entry: &Entry, feed: &Feed, feed_url: &str) -> FeedIte
         );
     }
 
+    let mapped = |field: &Option<String>| {
+        field
+            .as_ref()
+            .and_then(|name| elements.and_then(|e| e.get(name)))
+            .filter(|v| !v.is_empty())
+    };
+    // The first non-empty of `names`: `<dc:date/>` falls through to `dcterms:issued`
+    let dublin_core = |names: &[&str]| {
+        names.iter().find_map(|name| {
+            elements
+                .and_then(|e| e.get(*name))
+                .filter(|v| !v.is_empty())
+        })
+    };
+    let published = mapped(&source.mapping.published_from)
+        .and_then(|v| parse_date(v))
+        .or_else(|| entry.published.map(|dt| dt.naive_utc()))
+        .or_else(|| dublin_core(DC_PUBLISHED).and_then(|v| parse_date(v)));
+    let entry_updated = mapped(&source.mapping.updated_from)
+        .and_then(|v| parse_date(v))
+        .or_else(|| entry.updated.map(|dt| dt.naive_utc()))
+        .or_else(|| dublin_core(DC_UPDATED).and_then(|v| parse_date(v)));
+    let author = mapped(&source.mapping.author_from)
+        .cloned()
+        .or_else(|| {
+            entry
+                .authors
+                .first()
+                .map(|a| a.name.trim().to_string())
+                .filter(|name| !name.is_empty())
+        })
+        .or_else(|| dublin_core(DC_CREATOR).cloned());
+    let mut categories: Vec<String> = entry.categories.iter().map(|c| c.term.clone()).collect();
+    if categories.is_empty() {
+        categories.extend(dublin_core(DC_SUBJECT).cloned());
+    }
+
+    let title = entry
+        .title
+        .as_ref()
+        .map(|t| t.content.clone())
+        .unwrap_or_default();
+    let tlp = tlp::classify(
+        source,
+        [Some(title.as_str()), summary.as_deref(), content.as_deref()]
+            .into_iter()
+            .flatten()
+            .chain(entry.categories.iter().map(|c| c.term.as_str())),
+    );
+
     FeedItem {
         id: Uuid::new_v4(),
         guid: entry.id.clone(),
-        title: entry
-            .title
-            .as_ref()
-            .map(|t| t.content.clone())
-            .unwrap_or_default(),
+        parent_guid: elements
+            .and_then(|e| e.get("thr:in-reply-to@ref"))
+            .filter(|r| !r.is_empty())
+            .cloned(),
+        title,
         link,
-        published: entry.published.map(|dt| dt.naive_utc()),
+        resolved_link: None,
+        published,
         content,
         summary,
-        author: entry.authors.first().map(|a| a.name.clone()),
-        categories: if entry.categories.is_empty() {
-            None
-        } else {
-            Some(entry.categories.iter().map(|c| c.term.clone()).collect())
-        },
-        entry_updated: entry.updated.map(|dt| dt.naive_utc()),
+        summary_ai: None,
+        author,
+        categories: (!categories.is_empty()).then_some(categories),
+        entry_updated,
+        image_url,
+        thumbnail_url,
+        media,
+        enclosures,
         feed_url: feed_url.to_string(),
         feed_title: feed.title.as_ref().map(|t| t.content.clone()),
         feed_description: feed.description.as_ref().map(|d| d.content.clone()),
         feed_language: feed.language.clone(),
+        detected_language: None,
+        simhash: None,
+        duplicate_of: None,
+        tags: None,
         feed_icon: feed.icon.as_ref().map(|i| i.uri.clone()),
         feed_updated: feed.updated.map(|dt| dt.naive_utc()),
+        feed_type: source.feed_type.clone(),
+        feed_tags: if source.tags.is_empty() {
+            None
+        } else {
+            Some(source.tags.clone())
+        },
+        tlp,
+        license: license::for_entry(source, entry, feed, elements),
+        extensions: extensions::custom(&source.extensions, elements),
         inserted_at: Utc::now().naive_utc(),
     }
 }
 
+/// Parse a loosely formatted date from a raw element (RFC 3339, RFC 2822, or a bare date).
+pub(crate) fn parse_date(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_rfc2822(value))
+        .map(|dt| dt.naive_utc())
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+}
+
+/// First `src` of an `<img>` tag in an HTML fragment
+static IMG_SRC: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)<img\b[^>]*?\bsrc\s*=\s*["']([^"']+)["']"#).expect("valid img regex")
+});
+
+/// Pick a lead image for an entry, in order of preference:
+/// a Media RSS thumbnail, an image `media:content`/enclosure, then the first `<img>` in the HTML.
+/// Relative URLs are resolved against the entry link.
+fn lead_image(entry: &Entry, html: Option<&str>, link: &str) -> Option<String> {
+    let thumbnail = entry
+        .media
+        .iter()
+        .flat_map(|m| m.thumbnails.iter())
+        .map(|t| t.image.uri.clone())
+        .next();
+    let media_image = || {
+        entry
+            .media
+            .iter()
+            .flat_map(|m| m.content.iter())
+            .find(|c| {
+                c.content_type
+                    .as_ref()
+                    .is_some_and(|ct| ct.type_().as_str() == "image")
+            })
+            .and_then(|c| c.url.as_ref().map(|u| u.to_string()))
+    };
+    let inline_image = || {
+        html.and_then(|h| IMG_SRC.captures(h))
+            .map(|c| htmlescape::decode_html(&c[1]).unwrap_or_else(|_| c[1].to_string()))
+    };
+
+    let raw = thumbnail.or_else(media_image).or_else(inline_image)?;
+    match Url::parse(&raw) {
+        Ok(u) => Some(u.to_string()),
+        Err(_) => Url::parse(link)
+            .and_then(|base| base.join(&raw))
+            .map(|u| u.to_string())
+            .ok(),
+    }
+}
+
+/// Why `sanitize_and_validate` rejected an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum Rejection {
+    #[error("title missing or too long")]
+    Title,
+    #[error("summary too long")]
+    Summary,
+    #[error("content too long")]
+    Content,
+    #[error("invalid link")]
+    Link,
+}
+
+/// Which field `entry_to_feed_item` takes an entry's content from: "content" (usually
+/// `<content:encoded>` or `<content>`), "summary" or "none".
+pub fn content_source(entry: &Entry) -> &'static str {
+    if entry.content.as_ref().is_some_and(|c| c.body.is_some()) {
+        "content"
+    } else if entry.summary.is_some() {
+        "summary"
+    } else {
+        "none"
+    }
+}
+
 /// Sanitize, validate, and log why an entry is skipped if it fails.
-/// - Ensures title, summary, and content are within length limits and required fields are present.
-/// - Sanitizes HTML for title, summary, and content.
-pub fn sanitize_and_validate(item: &FeedItem) -> Option<FeedItem> {
+/// - Ensures title, summary, and content are within the feed's `limits` (truncating them
+///   if so configured) and required fields are present.
+/// - Sanitizes HTML for title, summary, and content with `sanitizer` (normally the
+///   feed's profile, `config::Feed::sanitize`).
+pub fn sanitize_and_validate(
+    item: &FeedItem,
+    sanitizer: &dyn Sanitizer,
+    limits: LengthLimits,
+) -> Result<FeedItem, Rejection> {
     let title = item.title.trim();
-    if title.is_empty() || title.len() > 1024 {
-        SANITIZATION_FAILURES.inc();
+    let Some(title) = limits.fit(title, limits.title).filter(|t| !t.is_empty()) else {
         warn!("Sanitization failed: title missing/too long: {:?}", item);
-        return None;
-    }
+        return Err(Rejection::Title);
+    };
 
     // Limit summary size
-    let summary = item.summary.as_deref().map(str::trim);
-    if let Some(s) = summary {
-        if s.len() > 200_000 {
-            SANITIZATION_FAILURES.inc();
-            warn!("Sanitization failed: summary too long: {:?}", item);
-            return None;
-        }
+    let summary = item
+        .summary
+        .as_deref()
+        .map(|s| limits.fit(s.trim(), limits.summary));
+    if summary == Some(None) {
+        warn!("Sanitization failed: summary too long: {:?}", item);
+        return Err(Rejection::Summary);
     }
+    let summary = summary.flatten();
 
     // Limit content size
-    let content = item.content.as_deref().map(str::trim);
-    if let Some(c) = content {
-        if c.len() > 500_000 {
-            SANITIZATION_FAILURES.inc();
-            warn!("Sanitization failed: content too long: {:?}", item);
-            return None;
-        }
+    let content = item
+        .content
+        .as_deref()
+        .map(|c| limits.fit(c.trim(), limits.content));
+    if content == Some(None) {
+        warn!("Sanitization failed: content too long: {:?}", item);
+        return Err(Rejection::Content);
     }
+    let content = content.flatten();
 
     // Validate link
     if Url::parse(&item.link).is_err() {
-        SANITIZATION_FAILURES.inc();
         warn!("Sanitization failed: invalid link: {:?}", item.link);
-        return None;
+        return Err(Rejection::Link);
     }
 
-    let sanitized_title = clean(title).to_string();
-    let sanitized_summary = summary.map(|s| clean(s).to_string());
-    let sanitized_content = content.map(|c| clean(c).to_string());
-
-    ENTRIES_PROCESSED.inc();
+    let sanitized_title = sanitizer.clean(title);
+    let sanitized_summary = summary.map(|s| sanitizer.clean(s));
+    let sanitized_content = content.map(|c| sanitizer.clean(c));
 
-    Some(FeedItem {
+    Ok(FeedItem {
         title: sanitized_title,
         summary: sanitized_summary,
         content: sanitized_content,
+        image_url: item.image_url.clone().filter(|_| sanitizer.allows_images()),
+        thumbnail_url: item
+            .thumbnail_url
+            .clone()
+            .filter(|_| sanitizer.allows_images()),
         ..item.clone()
     })
 }
 
-/// Download and parse the feed.
+/// HTTP cache validators remembered between fetches of the same feed.
+#[derive(Debug, Clone, Default, sqlx::FromRow)]
+pub struct CacheValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Hex SHA-256 of the last response body
+    pub body_hash: Option<String>,
+    /// Feed found on the web page configured as the feed's URL, fetched instead of it
+    pub discovered_url: Option<String>,
+}
+
+/// Result of a (conditional) feed fetch.
+#[derive(Debug)]
+pub enum FetchOutcome {
+    /// The server returned a fresh body, which was parsed.
+    Fetched {
+        /// HTTP status of the response
+        status: u16,
+        feed: Box<Feed>,
+        /// Raw child elements of each entry, aligned with `feed.entries` (empty if unavailable)
+        elements: Vec<EntryElements>,
+        validators: CacheValidators,
+        /// Size of the downloaded body, decoded
+        bytes: usize,
+        /// Size of the body as transferred, before undoing its `Content-Encoding`
+        compressed_bytes: usize,
+    },
+    /// The server answered `304 Not Modified`; nothing to parse.
+    NotModified,
+    /// The body is byte-identical to the previous fetch; parsing was skipped.
+    Unchanged {
+        status: u16,
+        validators: CacheValidators,
+        bytes: usize,
+        compressed_bytes: usize,
+    },
+}
+
+/// Parse `body` and map + sanitize its entries exactly as a fetch of `source` would,
+/// without touching the network or database. Entries failing sanitization are dropped.
+///
+/// `id` and `inserted_at` are assigned fresh on every call; everything else is a
+/// pure function of the inputs, which makes this suitable for fixture-driven tests.
+pub fn process_feed_bytes(
+    body: &[u8],
+    source: &config::Feed,
+) -> Result<Vec<FeedItem>, IngestError> {
+    let mut feed =
+        parsing::parse_feed(source, body).map_err(|e| IngestError::Parse(source.url.clone(), e))?;
+    let elements = raw_elements(source, body, &feed);
+    synthesize_guids(source, &mut feed, &elements);
+    Ok(feed_items(&feed, &elements, source))
+}
+
+/// Map and sanitize the entries of a parsed `feed`, dropping those failing validation.
+pub fn feed_items(feed: &Feed, elements: &[EntryElements], source: &config::Feed) -> Vec<FeedItem> {
+    feed.entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| entry_to_feed_item(entry, feed, source, elements.get(i)))
+        .filter_map(|item| {
+            sanitize_and_validate(&item, source.sanitize.sanitizer(), source.limits).ok()
+        })
+        .map(enrich::with_language)
+        .map(dedup::with_simhash)
+        .map(tagging::with_tags)
+        .collect()
+}
+
+/// Raw child elements of each entry in `body`, or none if they don't line up with `feed.entries`.
+fn raw_elements(source: &config::Feed, body: &[u8], feed: &Feed) -> Vec<EntryElements> {
+    let body = parsing::preprocess(&source.parser, body);
+    let mut elements = if mbox::is_mbox(&body) {
+        mbox::entry_elements(&body)
+    } else {
+        extensions::entry_elements(&body)
+    };
+    if elements.len() != feed.entries.len() {
+        elements.clear();
+    }
+    elements
+}
+
+/// Replace the IDs of entries that have none, or share theirs with another entry of the
+/// feed, by one derived from the entry's link, title and publication date. `feed_rs`
+/// already derives a stable ID from the link and title of an entry without one, which
+/// is kept but recomputed from the canonical link, so spellings of one link (`http` and
+/// `https` with `prefer_https`, a fragment, an uppercase host) get one ID; unchanged
+/// links keep the ID `feed_rs` gave them. Lacking a link, `feed_rs` makes up a random
+/// ID, which would store the entry anew on every fetch, so that counts as none.
+fn synthesize_guids(source: &config::Feed, feed: &mut Feed, elements: &[EntryElements]) {
+    for entry in &mut feed.entries {
+        let Some(link) = entry.links.first() else {
+            continue;
+        };
+        let title = entry.title.as_ref().map(|t| t.content.as_str());
+        if entry.id == link_id(&link.href, title) {
+            let link = links::canonical(&link.href, source.prefer_https);
+            entry.id = link_id(&link, title);
+        }
+    }
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for entry in &feed.entries {
+        *seen.entry(entry.id.clone()).or_default() += 1;
+    }
+    for (i, entry) in feed.entries.iter_mut().enumerate() {
+        let made_up = entry.links.is_empty()
+            && elements
+                .get(i)
+                .is_some_and(|e| !e.contains_key("guid") && !e.contains_key("id"));
+        let reason = if entry.id.trim().is_empty() || made_up {
+            "missing"
+        } else if seen[&entry.id] > 1 {
+            "duplicate"
+        } else {
+            continue;
+        };
+        let link = entry
+            .links
+            .first()
+            .map(|l| links::canonical(&l.href, source.prefer_https));
+        let mut hasher = Sha256::new();
+        for part in [
+            link.as_deref(),
+            entry.title.as_ref().map(|t| t.content.as_str()),
+        ] {
+            hasher.update(part.unwrap_or_default().trim().as_bytes());
+            hasher.update(b"\n");
+        }
+        if let Some(published) = entry.published {
+            hasher.update(published.to_rfc3339().as_bytes());
+        }
+        let guid = format!("synthetic:{:x}", hasher.finalize());
+        debug!(feed = %source.name, id = %entry.id, guid = %guid, reason, "Synthesized GUID");
+        SYNTHETIC_GUIDS
+            .with_label_values(&[source.name.as_str(), reason])
+            .inc();
+        entry.id = guid;
+    }
+}
+
+/// The ID `feed_rs` gives an entry without one, from its first link and its title.
+fn link_id(link: &str, title: Option<&str>) -> String {
+    // feed_rs' keys, so an unchanged link keeps the ID stored before
+    let mut hasher = SipHasher::new_with_keys(0x5d78_4074_2887_2d60, 0x90ee_ca4c_90a5_e228);
+    hasher.write(link.as_bytes());
+    if let Some(title) = title {
+        hasher.write(title.as_bytes());
+    }
+    let hash = hasher.finish128();
+    format!("{:x}{:x}", hash.h1, hash.h2)
+}
+
+/// Attach the feed's own headers and configured credentials to `request`.
+fn authorize(mut request: RequestBuilder, source: &config::Feed) -> RequestBuilder {
+    for (name, value) in &source.headers {
+        request = request.header(name, value);
+    }
+    let Some(auth) = &source.auth else {
+        return request;
+    };
+    if let Some(username) = &auth.username {
+        request = request.basic_auth(username, auth.password.as_ref());
+    }
+    if let Some(token) = &auth.bearer_token {
+        request = request.bearer_auth(token);
+    }
+    for (name, value) in &auth.headers {
+        request = request.header(name, value);
+    }
+    request
+}
+
+/// Send `request`, retrying connection errors, timeouts, `429` and `5xx`
+/// responses with exponential backoff. Any other error status fails immediately.
+/// The headers of the last response received, error or not, are left in `headers`.
+async fn send_with_retry(
+    url: &str,
+    request: RequestBuilder,
+    retry: &RetrySettings,
+    headers: &mut HeaderMap,
+) -> Result<Response, IngestError> {
+    let mut attempt = 1;
+    loop {
+        let attempt_request = request
+            .try_clone()
+            .expect("GET requests have no streaming body");
+        let sent = attempt_request.send().await;
+        if let Ok(response) = &sent {
+            headers.clone_from(response.headers());
+        }
+        let err = match sent.and_then(Response::error_for_status) {
+            Ok(response) => return Ok(response),
+            Err(e) => e,
+        };
+        if attempt >= retry.max_attempts || !is_transient(&err) {
+            FETCH_FAILURES.inc();
+            return Err(fetch_error(url, err));
+        }
+        let delay = backoff_delay(retry, attempt);
+        FETCH_RETRIES.inc();
+        warn!(
+            url = %url,
+            attempt,
+            delay_ms = delay.as_millis() as u64,
+            error = %err,
+            "Transient fetch failure; retrying"
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// `Timeout` for a request that ran out of time, else `Fetch`.
+fn fetch_error(url: &str, err: reqwest::Error) -> IngestError {
+    if err.is_timeout() {
+        IngestError::Timeout(url.to_string(), err)
+    } else {
+        IngestError::Fetch(url.to_string(), err)
+    }
+}
+
+/// Whether a retry may succeed: a 5xx or 429 response, a timeout or a failure to
+/// connect. Other request errors (a malformed URL or header, a redirect loop, a
+/// refused address) fail the same way every time.
+pub(crate) fn is_transient(err: &reqwest::Error) -> bool {
+    if urlguard::is_blocked(err) {
+        return false;
+    }
+    match err.status() {
+        Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+        None => err.is_timeout() || err.is_connect(),
+    }
+}
+
+/// Delay before retrying after failed attempt number `attempt` (1-based); jitter is
+/// applied before the `max_delay` cap, so the cap always holds.
+pub(crate) fn backoff_delay(retry: &RetrySettings, attempt: u32) -> Duration {
+    let exp = retry
+        .base_delay
+        .saturating_mul(2u32.saturating_pow(attempt - 1));
+    if retry.jitter <= 0.0 {
+        return exp.min(retry.max_delay);
+    }
+    let factor = 1.0 + rand::thread_rng().gen_range(-retry.jitter..=retry.jitter);
+    // `exp` saturates at `Duration::MAX` for late attempts, where `mul_f64` would panic
+    Duration::try_from_secs_f64(exp.as_secs_f64() * factor)
+        .unwrap_or(Duration::MAX)
+        .min(retry.max_delay)
+}
+
+/// Largest feed body accepted once decompressed, so a small compressed response
+/// cannot exhaust memory.
+const MAX_FEED_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Download and parse the feed, sending `If-None-Match`/`If-Modified-Since`
+/// when validators from a previous fetch are available, and skipping the parse
+/// when the body hashes the same as last time.
+/// - Monthly mailing list archives are fetched per `mbox::archive_urls` and
+///   concatenated; a month without an archive yet is skipped.
+/// - A web page instead of a feed is searched for the feed it links to, which is
+///   then fetched, and remembered in the validators; if that feed later disappears
+///   (404 or 410), the page is searched again.
+/// - Transient failures are retried according to `retry`.
+/// - The headers of the last response, including a failed one, are left in
+///   `response_headers`.
 /// - Tracks metrics and logs timing.
-pub async fn fetch_feed(url: &str) -> Result<Feed, IngestError> {
-    FETCH_COUNTER.inc();
+#[instrument(skip_all, fields(url = %source.url))]
+pub async fn fetch_feed(
+    client: &reqwest::Client,
+    source: &config::Feed,
+    validators: &CacheValidators,
+    retry: &RetrySettings,
+    response_headers: &mut HeaderMap,
+) -> Result<FetchOutcome, IngestError> {
+    FETCH_COUNTER.with_label_values(&[&source.name]).inc();
+    let fetch_histogram = FETCH_HISTOGRAM.with_label_values(&[&source.name]);
     let start = Instant::now();
-    let bytes = reqwest::get(url)
-        .await
-        .map_err(|e| IngestError::Fetch(url.to_string(), e))?
-        .bytes()
-        .await
-        .map_err(|e| IngestError::Fetch(url.to_string(), e))?;
-    let feed = parser::parse(&bytes[..]).map_err(|e| IngestError::Parse(url.to_string(), e))?;
+    let mut discovered = validators.discovered_url.clone();
+    let urls = match &discovered {
+        Some(feed_url) => vec![feed_url.clone()],
+        None => mbox::archive_urls(&source.url, Utc::now()),
+    };
+
+    let mut fetched = fetch_body(client, source, &urls, validators, retry, response_headers).await;
+    if discovered.is_some()
+        && matches!(&fetched, Err(e) if matches!(e.http_status(), Some(404 | 410)))
+    {
+        info!(feed = %source.name, url = %urls[0], "Discovered feed is gone; searching the site again");
+        discovered = None;
+        let site = [source.url.clone()];
+        let none = CacheValidators::default();
+        fetched = fetch_body(client, source, &site, &none, retry, response_headers).await;
+    }
+    let Some(mut body) = fetched? else {
+        fetch_histogram.observe(start.elapsed().as_secs_f64());
+        debug!("Feed {} not modified since last fetch", urls[0]);
+        return Ok(FetchOutcome::NotModified);
+    };
+    if discovered.is_none() && urls.len() == 1 && discovery::is_html(&body.bytes) {
+        if let Some(feed_url) = discovery::feed_link(&body.bytes, &source.url) {
+            info!(feed = %source.name, site = %source.url, url = %feed_url, "Discovered feed URL");
+            let found = [feed_url.clone()];
+            let none = CacheValidators::default();
+            if let Some(feed_body) =
+                fetch_body(client, source, &found, &none, retry, response_headers).await?
+            {
+                body = feed_body;
+                discovered = Some(feed_url);
+            }
+        }
+    }
+    let url = discovered
+        .clone()
+        .unwrap_or_else(|| urls.last().expect("at least one archive URL").clone());
+
+    let FetchedBody {
+        status,
+        etag,
+        last_modified,
+        bytes,
+        compressed_bytes,
+    } = body;
+    let body_hash = format!("{:x}", Sha256::digest(&bytes));
+    let fresh_validators = CacheValidators {
+        etag,
+        last_modified,
+        body_hash: Some(body_hash),
+        discovered_url: discovered,
+    };
+
+    if fresh_validators.body_hash == validators.body_hash {
+        FEED_BODY_UNCHANGED.inc();
+        fetch_histogram.observe(start.elapsed().as_secs_f64());
+        debug!("Feed {} body unchanged since last fetch", url);
+        return Ok(FetchOutcome::Unchanged {
+            status,
+            validators: fresh_validators,
+            bytes: bytes.len(),
+            compressed_bytes,
+        });
+    }
+    let mut feed =
+        parsing::parse_feed(source, &bytes).map_err(|e| IngestError::Parse(url.clone(), e))?;
     let elapsed = start.elapsed().as_secs_f64();
-    FETCH_HISTOGRAM.observe(elapsed);
+    fetch_histogram.observe(elapsed);
     debug!("Fetched and parsed feed {} in {:.2}s", url, elapsed);
-    Ok(feed)
+    let elements = raw_elements(source, &bytes, &feed);
+    synthesize_guids(source, &mut feed, &elements);
+    Ok(FetchOutcome::Fetched {
+        status,
+        feed: Box::new(feed),
+        elements,
+        bytes: bytes.len(),
+        compressed_bytes,
+        validators: fresh_validators,
+    })
+}
+
+/// A downloaded body, transcoded to UTF-8, with what `CacheValidators` keeps of it.
+struct FetchedBody {
+    status: u16,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    bytes: Vec<u8>,
+    compressed_bytes: usize,
+}
+
+/// Download and concatenate `urls`, or `None` if the server answered `304 Not Modified`.
+async fn fetch_body(
+    client: &reqwest::Client,
+    source: &config::Feed,
+    urls: &[String],
+    validators: &CacheValidators,
+    retry: &RetrySettings,
+    response_headers: &mut HeaderMap,
+) -> Result<Option<FetchedBody>, IngestError> {
+    // Validators describe a single response, so several archives rely on the body hash
+    let conditional = urls.len() == 1;
+
+    let mut status = 0;
+    let mut etag = None;
+    let mut last_modified = None;
+    let mut bytes = Vec::new();
+    let mut compressed_bytes = 0;
+    let mut missing = None;
+    for archive in urls {
+        urlguard::check(archive).map_err(|e| IngestError::UnsafeUrl(archive.clone(), e))?;
+        let mut request = authorize(client.get(archive), source);
+        if let Some(timeout) = source.timeout {
+            request = request.timeout(timeout);
+        }
+        if conditional {
+            if let Some(etag) = &validators.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response = match send_with_retry(archive, request, retry, response_headers).await {
+            Ok(response) => response,
+            Err(e) if mbox::is_monthly(&source.url) && e.http_status() == Some(404) => {
+                debug!("Archive {} does not exist (yet)", archive);
+                missing = Some(e);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        certs::observe(&response);
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            FEED_CACHE_HITS.inc();
+            return Ok(None);
+        }
+        let header_value = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        status = response.status().as_u16();
+        if conditional {
+            etag = header_value(ETAG);
+            last_modified = header_value(LAST_MODIFIED);
+        }
+        let content_type = header_value(CONTENT_TYPE);
+        let body = Body::read(response)
+            .await
+            .map_err(|e| fetch_error(archive, e))?;
+        compressed_bytes += body.raw.len();
+        FEED_BYTES
+            .with_label_values(&[&source.name, "compressed"])
+            .inc_by(body.raw.len() as u64);
+        let body = body
+            .decode(MAX_FEED_BYTES)
+            .map_err(|e| IngestError::Decode(archive.to_string(), e))?;
+        FEED_BYTES
+            .with_label_values(&[&source.name, "decompressed"])
+            .inc_by(body.len() as u64);
+        let (body, transcoded) = charset::to_utf8(body, content_type.as_deref(), archive);
+        if let Some(encoding) = transcoded {
+            debug!("Transcoded {} from {} to UTF-8", archive, encoding.name());
+            FEED_TRANSCODED
+                .with_label_values(&[&source.name, encoding.name()])
+                .inc();
+        }
+        if !bytes.is_empty() {
+            // Messages only start after a blank line
+            bytes.extend_from_slice(b"\n\n");
+        }
+        bytes.extend_from_slice(&body);
+    }
+    if status == 0 {
+        return Err(missing.expect("every archive was missing"));
+    }
+    Ok(Some(FetchedBody {
+        status,
+        etag,
+        last_modified,
+        bytes,
+        compressed_bytes,
+    }))
+}
+
+/// Columns of `archive` and `current` written for each entry, in binding order.
+pub(crate) const ENTRY_COLUMNS: &str =
+    "id, guid, title, link, published, content, summary, author, categories, \
+    entry_updated, feed_url, feed_title, feed_description, feed_language, feed_icon, feed_updated, \
+    inserted_at, image_url, feed_type, feed_tags, tlp, parent_guid, license, detected_language, simhash, duplicate_of, tags, \
+    enclosures, thumbnail_url, media, extensions, resolved_link";
+
+/// Conflict clause refreshing a known entry in `current` with the new values.
+pub(crate) const CURRENT_UPSERT: &str = "ON CONFLICT (guid) DO UPDATE SET
+    title = EXCLUDED.title,
+    link = EXCLUDED.link,
+    published = EXCLUDED.published,
+    content = EXCLUDED.content,
+    summary = EXCLUDED.summary,
+    author = EXCLUDED.author,
+    categories = EXCLUDED.categories,
+    entry_updated = EXCLUDED.entry_updated,
+    feed_url = EXCLUDED.feed_url,
+    feed_title = EXCLUDED.feed_title,
+    feed_description = EXCLUDED.feed_description,
+    feed_language = EXCLUDED.feed_language,
+    feed_icon = EXCLUDED.feed_icon,
+    feed_updated = EXCLUDED.feed_updated,
+    inserted_at = EXCLUDED.inserted_at,
+    image_url = EXCLUDED.image_url,
+    feed_type = EXCLUDED.feed_type,
+    feed_tags = EXCLUDED.feed_tags,
+    tlp = EXCLUDED.tlp,
+    parent_guid = EXCLUDED.parent_guid,
+    license = EXCLUDED.license,
+    detected_language = EXCLUDED.detected_language,
+    simhash = EXCLUDED.simhash,
+    tags = EXCLUDED.tags,
+    enclosures = EXCLUDED.enclosures,
+    thumbnail_url = EXCLUDED.thumbnail_url,
+    media = EXCLUDED.media,
+    extensions = EXCLUDED.extensions,
+    resolved_link = EXCLUDED.resolved_link";
+
+/// Rows per multi-row statement in `process_batch`, keeping the 32 binds per row well
+/// under Postgres' limit of 65535 parameters.
+const BATCH_ROWS: usize = 500;
+
+/// What storing an entry changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// New to the archive
+    New,
+    /// New to the archive, but nearly duplicating an earlier entry, and stored linked
+    /// to it (`duplicate_of`)
+    Duplicate,
+    /// Known, and its title, summary or content differs from the stored version (a
+    /// revision was recorded)
+    Updated,
+    /// Known and unchanged
+    Unchanged,
+}
+
+impl Change {
+    pub fn is_new(self) -> bool {
+        self == Change::New
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Change::New => "new",
+            Change::Duplicate => "duplicate",
+            Change::Updated => "updated",
+            Change::Unchanged => "unchanged",
+        }
+    }
 }
 
 /// Write a FeedItem to the database, with dedupe logic.
 /// - Logs when an insert or upsert occurs.
-pub async fn process_entry(pool: &PgPool, item: &FeedItem) -> Result<(), IngestError> {
-    // Dedupe in archive by GUID
-    let exists: (bool,) = sqlx::query_as("SELECT EXISTS(SELECT 1 FROM archive WHERE guid = $1)")
-        .bind(&item.guid)
-        .fetch_one(pool)
-        .await?;
-    if !exists.0 {
-        sqlx::query(
-            "INSERT INTO archive (
-                id, guid, title, link, published, content, summary, author, categories, entry_updated,
-                feed_url, feed_title, feed_description, feed_language, feed_icon, feed_updated, inserted_at
-            )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)",
-        )
+/// - Returns whether the entry was new to the archive (or a near duplicate), updated or
+///   unchanged.
+#[instrument(skip_all, fields(guid = %item.guid))]
+pub async fn process_entry(pool: &PgPool, item: &FeedItem) -> Result<Change, IngestError> {
+    let _timer = DB_WRITE_HISTOGRAM
+        .with_label_values(&["entry"])
+        .start_timer();
+    // Archive and `current` change together or not at all
+    let mut tx = pool.begin().await?;
+    // Dedupe in archive by GUID, skipping the query when the Bloom filter rules it out
+    let exists = if dedup::might_contain(&item.guid) {
+        let (exists,): (bool,) =
+            sqlx::query_as("SELECT EXISTS(SELECT 1 FROM archive WHERE guid = $1)")
+                .bind(&item.guid)
+                .fetch_one(&mut *tx)
+                .await?;
+        if !exists && dedup::is_loaded() {
+            DEDUP_BLOOM_FALSE_POSITIVES.inc();
+        }
+        exists
+    } else {
+        DEDUP_BLOOM_SKIPS.inc();
+        false
+    };
+    let mut inserted = false;
+    let flagged;
+    let mut item = item;
+    if !exists {
+        // A near duplicate is stored linked to the earlier entry, here and in `current`
+        if let Some(original) = dedup::near_duplicate_of(&mut *tx, item).await? {
+            flagged = FeedItem {
+                duplicate_of: Some(original),
+                ..item.clone()
+            };
+            item = &flagged;
+        }
+        let result = sqlx::query(&format!(
+            "INSERT INTO archive ({ENTRY_COLUMNS})
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32)
+            ON CONFLICT (guid) DO NOTHING"
+        ))
         .bind(item.id)
         .bind(&item.guid)
         .bind(&item.title)
@@ -210,35 +1014,48 @@ pub async fn process_entry(pool: &PgPool, item: &FeedItem) -> Result<(), IngestE
         .bind(&item.feed_icon)
         .bind(item.feed_updated)
         .bind(item.inserted_at)
-        .execute(pool)
+        .bind(&item.image_url)
+        .bind(&item.feed_type)
+        .bind(&item.feed_tags)
+        .bind(item.tlp)
+        .bind(&item.parent_guid)
+        .bind(&item.license)
+        .bind(&item.detected_language)
+        .bind(item.simhash)
+        .bind(&item.duplicate_of)
+        .bind(&item.tags)
+        .bind(item.enclosures.as_ref().map(sqlx::types::Json))
+        .bind(&item.thumbnail_url)
+        .bind(item.media.as_ref().map(sqlx::types::Json))
+        .bind(item.extensions.as_ref().map(sqlx::types::Json))
+        .bind(&item.resolved_link)
+        .execute(&mut *tx)
         .await?;
-        info!("Inserted new archive entry for GUID: {}", item.guid);
+        // A concurrent run may have archived the same GUID since the EXISTS check
+        inserted = result.rows_affected() == 1;
+        if inserted {
+            info!("Inserted new archive entry for GUID: {}", item.guid);
+            if let Some(original) = &item.duplicate_of {
+                NEAR_DUPLICATES.inc();
+                info!(original = %original, "Entry nearly duplicates an earlier one");
+            }
+        }
+    }
+    let revisions = revisions::record(&mut tx, std::slice::from_ref(item)).await?;
+    let revised = revisions.iter().any(Option::is_some);
+    if revised {
+        info!("Recorded a revision of GUID: {}", item.guid);
+    }
+    if inserted || revised {
+        enrich::store_affected_products(&mut *tx, item).await?;
     }
 
     // Always upsert into current
-    sqlx::query(
-        "INSERT INTO current (
-            id, guid, title, link, published, content, summary, author, categories, entry_updated,
-            feed_url, feed_title, feed_description, feed_language, feed_icon, feed_updated, inserted_at
-        )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
-        ON CONFLICT (guid) DO UPDATE SET
-            title = EXCLUDED.title,
-            link = EXCLUDED.link,
-            published = EXCLUDED.published,
-            content = EXCLUDED.content,
-            summary = EXCLUDED.summary,
-            author = EXCLUDED.author,
-            categories = EXCLUDED.categories,
-            entry_updated = EXCLUDED.entry_updated,
-            feed_url = EXCLUDED.feed_url,
-            feed_title = EXCLUDED.feed_title,
-            feed_description = EXCLUDED.feed_description,
-            feed_language = EXCLUDED.feed_language,
-            feed_icon = EXCLUDED.feed_icon,
-            feed_updated = EXCLUDED.feed_updated,
-            inserted_at = EXCLUDED.inserted_at",
-    )
+    sqlx::query(&format!(
+        "INSERT INTO current ({ENTRY_COLUMNS})
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32)
+        {CURRENT_UPSERT}"
+    ))
     .bind(item.id)
     .bind(&item.guid)
     .bind(&item.title)
@@ -256,8 +1073,222 @@ pub async fn process_entry(pool: &PgPool, item: &FeedItem) -> Result<(), IngestE
     .bind(&item.feed_icon)
     .bind(item.feed_updated)
     .bind(item.inserted_at)
-    .execute(pool)
+    .bind(&item.image_url)
+    .bind(&item.feed_type)
+    .bind(&item.feed_tags)
+    .bind(item.tlp)
+    .bind(&item.parent_guid)
+    .bind(&item.license)
+    .bind(&item.detected_language)
+    .bind(item.simhash)
+    .bind(&item.duplicate_of)
+    .bind(&item.tags)
+    .bind(item.enclosures.as_ref().map(sqlx::types::Json))
+    .bind(&item.thumbnail_url)
+    .bind(item.media.as_ref().map(sqlx::types::Json))
+    .bind(item.extensions.as_ref().map(sqlx::types::Json))
+    .bind(&item.resolved_link)
+    .execute(&mut *tx)
     .await?;
+    tx.commit().await?;
+    revisions::observe(&revisions);
+    if !exists {
+        dedup::insert(&item.guid);
+    }
     debug!("Upserted current entry for GUID: {}", item.guid);
-    Ok(())
+    Ok(if inserted && item.duplicate_of.is_some() {
+        Change::Duplicate
+    } else if inserted {
+        Change::New
+    } else if revised {
+        Change::Updated
+    } else {
+        Change::Unchanged
+    })
+}
+
+/// Store `items` like `process_entry` does one at a time, but with multi-row
+/// statements (`BATCH_ROWS` rows each) in a single transaction. Of several items with
+/// the same GUID the first is archived and the last kept in `current`, as when stored
+/// in order. Writes not done within `deadline` are rolled back (`WriteTimeout`); the
+/// commit itself is not cut short, so on any error nothing was stored. Returns, aligned
+/// with `items`, whether each was new to the archive (or a near duplicate), updated or
+/// unchanged.
+#[instrument(skip_all, fields(items = items.len()))]
+pub async fn process_batch(
+    pool: &PgPool,
+    items: &[FeedItem],
+    deadline: Duration,
+) -> Result<Vec<Change>, IngestError> {
+    let _timer = DB_WRITE_HISTOGRAM
+        .with_label_values(&["batch"])
+        .start_timer();
+    let BatchWrite {
+        tx,
+        items,
+        mut archived,
+        revised,
+    } = tokio::time::timeout(deadline, write_batch(pool, items))
+        .await
+        .map_err(|_| IngestError::WriteTimeout(deadline))??;
+    tx.commit().await?;
+    revisions::observe(&revised);
+
+    let mut changes = Vec::with_capacity(items.len());
+    for (item, revised) in items.iter().zip(revised) {
+        dedup::insert(&item.guid);
+        // Only the first item with a newly archived GUID counts as new
+        let change = if archived.remove(&item.guid) {
+            if item.duplicate_of.is_some() {
+                NEAR_DUPLICATES.inc();
+                Change::Duplicate
+            } else {
+                Change::New
+            }
+        } else if revised.is_some() {
+            Change::Updated
+        } else {
+            Change::Unchanged
+        };
+        changes.push(change);
+    }
+    let count = |c: Change| changes.iter().filter(|change| **change == c).count();
+    let inserted = count(Change::New) + count(Change::Duplicate);
+    let revised = count(Change::Updated);
+    if inserted > 0 {
+        info!(inserted, "Inserted new archive entries");
+    }
+    if revised > 0 {
+        info!(revised, "Recorded entry revisions");
+    }
+    Ok(changes)
+}
+
+/// The uncommitted writes of `process_batch`.
+struct BatchWrite<'a> {
+    tx: Transaction<'static, Postgres>,
+    /// `items`, with near duplicates linked
+    items: Cow<'a, [FeedItem]>,
+    /// GUIDs the transaction newly archived
+    archived: HashSet<String>,
+    /// The size of the revision each item added, if it did
+    revised: Vec<Option<usize>>,
+}
+
+async fn write_batch<'a>(
+    pool: &PgPool,
+    items: &'a [FeedItem],
+) -> Result<BatchWrite<'a>, IngestError> {
+    let mut tx = pool.begin().await?;
+
+    // Link near duplicates among GUIDs not yet archived, as `process_entry` does; only
+    // new GUIDs are archived, and `current` keeps the link
+    let guids: Vec<&str> = items.iter().map(|item| item.guid.as_str()).collect();
+    let known: Vec<(String,)> = sqlx::query_as("SELECT guid FROM archive WHERE guid = ANY($1)")
+        .bind(&guids)
+        .fetch_all(&mut *tx)
+        .await?;
+    let known: HashSet<String> = known.into_iter().map(|(guid,)| guid).collect();
+    let unarchived: Vec<&FeedItem> = items
+        .iter()
+        .filter(|item| !known.contains(&item.guid))
+        .collect();
+    let originals = dedup::near_duplicates_of(&mut *tx, &unarchived).await?;
+    let items: Cow<[FeedItem]> = if originals.is_empty() {
+        Cow::Borrowed(items)
+    } else {
+        Cow::Owned(
+            items
+                .iter()
+                .map(|item| FeedItem {
+                    duplicate_of: originals.get(&item.guid).cloned(),
+                    ..item.clone()
+                })
+                .collect(),
+        )
+    };
+
+    let mut archived: HashSet<String> = HashSet::new();
+    for chunk in items.chunks(BATCH_ROWS) {
+        let mut qb: QueryBuilder<Postgres> =
+            QueryBuilder::new(format!("INSERT INTO archive ({ENTRY_COLUMNS}) "));
+        qb.push_values(chunk, push_entry);
+        qb.push(" ON CONFLICT (guid) DO NOTHING RETURNING guid");
+        let guids: Vec<(String,)> = qb.build_query_as().fetch_all(&mut *tx).await?;
+        archived.extend(guids.into_iter().map(|(guid,)| guid));
+    }
+    let revised = revisions::record(&mut tx, &items).await?;
+    let changed: Vec<&FeedItem> = items
+        .iter()
+        .zip(&revised)
+        .filter(|(item, revised)| revised.is_some() || archived.contains(&item.guid))
+        .map(|(item, _)| item)
+        .collect();
+    enrich::store_all_affected_products(&mut *tx, &changed).await?;
+
+    // One row per GUID: a statement may not update the same row twice
+    let last: HashMap<&str, usize> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| (item.guid.as_str(), i))
+        .collect();
+    let latest: Vec<&FeedItem> = items
+        .iter()
+        .enumerate()
+        .filter(|(i, item)| last[item.guid.as_str()] == *i)
+        .map(|(_, item)| item)
+        .collect();
+    for chunk in latest.chunks(BATCH_ROWS) {
+        let mut qb: QueryBuilder<Postgres> =
+            QueryBuilder::new(format!("INSERT INTO current ({ENTRY_COLUMNS}) "));
+        qb.push_values(chunk, |row, item| push_entry(row, item));
+        qb.push(" ").push(CURRENT_UPSERT);
+        qb.build().execute(&mut *tx).await?;
+    }
+    debug!("Upserted {} current entries", latest.len());
+    Ok(BatchWrite {
+        tx,
+        items,
+        archived,
+        revised,
+    })
+}
+
+/// Bind one entry's values in `ENTRY_COLUMNS` order.
+fn push_entry(
+    mut row: sqlx::query_builder::Separated<'_, '_, Postgres, &'static str>,
+    item: &FeedItem,
+) {
+    row.push_bind(item.id)
+        .push_bind(item.guid.clone())
+        .push_bind(item.title.clone())
+        .push_bind(item.link.clone())
+        .push_bind(item.published)
+        .push_bind(item.content.clone())
+        .push_bind(item.summary.clone())
+        .push_bind(item.author.clone())
+        .push_bind(item.categories.clone())
+        .push_bind(item.entry_updated)
+        .push_bind(item.feed_url.clone())
+        .push_bind(item.feed_title.clone())
+        .push_bind(item.feed_description.clone())
+        .push_bind(item.feed_language.clone())
+        .push_bind(item.feed_icon.clone())
+        .push_bind(item.feed_updated)
+        .push_bind(item.inserted_at)
+        .push_bind(item.image_url.clone())
+        .push_bind(item.feed_type.clone())
+        .push_bind(item.feed_tags.clone())
+        .push_bind(item.tlp)
+        .push_bind(item.parent_guid.clone())
+        .push_bind(item.license.clone())
+        .push_bind(item.detected_language.clone())
+        .push_bind(item.simhash)
+        .push_bind(item.duplicate_of.clone())
+        .push_bind(item.tags.clone())
+        .push_bind(item.enclosures.clone().map(sqlx::types::Json))
+        .push_bind(item.thumbnail_url.clone())
+        .push_bind(item.media.clone().map(sqlx::types::Json))
+        .push_bind(item.extensions.clone().map(sqlx::types::Json))
+        .push_bind(item.resolved_link.clone());
 }
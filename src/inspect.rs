@@ -0,0 +1,61 @@
+//! The most recent run of each feed, entry by entry, served by
+//! `GET /debug/feeds/{name}/last`: where each entry's content came from, whether it
+//! passed sanitization and what storing it did. Answers "why wasn't this article
+//! stored?" without turning on debug logging. Kept in memory only.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// One entry of a feed run.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryTrace {
+    pub guid: String,
+    pub title: String,
+    /// Field the content was taken from: "content", "summary" or "none"
+    pub content_source: &'static str,
+    /// "passed", or why the entry was rejected
+    pub sanitization: String,
+    /// What storing did: "new", "known" (already archived), "staged" (canary),
+    /// "deferred" (spillover), "spooled", "failed: …" or "skipped: …"; absent for
+    /// entries rejected by sanitization
+    pub dedup: Option<String>,
+}
+
+/// A feed's latest run.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSnapshot {
+    pub started_at: DateTime<Utc>,
+    /// As in `fetch_history`: fetched, not_modified, unchanged, failed or quota_skipped
+    pub status: &'static str,
+    pub http_status: Option<u16>,
+    pub error: Option<String>,
+    /// Every entry of a fetched body, in feed order; empty otherwise
+    pub entries: Vec<EntryTrace>,
+}
+
+/// Latest `RunSnapshot` per feed name, shared by the pipeline and the HTTP server.
+#[derive(Debug, Clone, Default)]
+pub struct LastRuns {
+    inner: Arc<RwLock<HashMap<String, RunSnapshot>>>,
+}
+
+impl LastRuns {
+    /// Replace `feed_name`'s snapshot.
+    pub fn record(&self, feed_name: &str, snapshot: RunSnapshot) {
+        self.inner
+            .write()
+            .expect("last runs poisoned")
+            .insert(feed_name.to_string(), snapshot);
+    }
+
+    pub fn get(&self, feed_name: &str) -> Option<RunSnapshot> {
+        self.inner
+            .read()
+            .expect("last runs poisoned")
+            .get(feed_name)
+            .cloned()
+    }
+}
@@ -0,0 +1,232 @@
+//! JSON Feed (jsonfeed.org, versions 1.0 and 1.1) parser producing `feed_rs` models,
+//! so JSON-only sources flow through the same mapping and sanitization as RSS/Atom.
+
+use chrono::{DateTime, Utc};
+use feed_rs::model::{
+    Category, Content, Entry, Feed, FeedType, Image, Link, MediaObject, MediaThumbnail, Person,
+    Text,
+};
+use feed_rs::parser::ParseFeedError;
+use serde::Deserialize;
+
+/// Whether `body` looks like a JSON document rather than XML.
+pub fn is_json(body: &[u8]) -> bool {
+    let body = body.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(body);
+    body.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{')
+}
+
+/// Parse a JSON Feed document.
+pub fn parse(body: &[u8]) -> Result<Feed, ParseFeedError> {
+    let body = body.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(body);
+    let jf: JsonFeed = serde_json::from_slice(body).map_err(ParseFeedError::JsonSerde)?;
+    Ok(convert(jf))
+}
+
+fn convert(jf: JsonFeed) -> Feed {
+    let links = [jf.home_page_url, jf.feed_url]
+        .into_iter()
+        .flatten()
+        .map(|href| link(href, None))
+        .collect();
+    Feed {
+        feed_type: FeedType::JSON,
+        id: String::new(),
+        title: Some(text(jf.title, mime::TEXT_PLAIN)),
+        updated: None,
+        authors: people(jf.author, jf.authors),
+        description: jf.description.map(|d| text(d, mime::TEXT_PLAIN)),
+        links,
+        categories: Vec::new(),
+        contributors: Vec::new(),
+        generator: None,
+        icon: jf.favicon.map(image),
+        language: jf.language.clone(),
+        logo: jf.icon.map(image),
+        published: None,
+        rating: None,
+        rights: None,
+        ttl: None,
+        entries: jf.items.into_iter().map(convert_item).collect(),
+    }
+}
+
+fn convert_item(ji: JsonItem) -> Entry {
+    let mut entry = Entry {
+        id: ji.id.into_string(),
+        ..Default::default()
+    };
+
+    entry.links = [ji.url, ji.external_url]
+        .into_iter()
+        .flatten()
+        .map(|href| link(href, None))
+        .collect();
+    entry.title = ji.title.map(|t| text(t, mime::TEXT_PLAIN));
+
+    // Prefer HTML content; plain text becomes content only when there is no HTML,
+    // otherwise it backfills a missing summary.
+    entry.content = ji.content_html.map(|body| content(body, mime::TEXT_HTML));
+    entry.summary = ji.summary.map(|s| text(s, mime::TEXT_PLAIN));
+    if let Some(plain) = ji.content_text {
+        if entry.content.is_none() {
+            entry.content = Some(content(plain, mime::TEXT_PLAIN));
+        } else if entry.summary.is_none() {
+            entry.summary = Some(text(plain, mime::TEXT_PLAIN));
+        }
+    }
+
+    entry.published = ji.date_published.as_deref().and_then(timestamp);
+    entry.updated = ji.date_modified.as_deref().and_then(timestamp);
+    entry.authors = people(ji.author, ji.authors);
+    entry.categories = ji
+        .tags
+        .unwrap_or_default()
+        .iter()
+        .map(|t| Category::new(t))
+        .collect();
+
+    for attachment in ji.attachments.unwrap_or_default() {
+        let mut l = link(attachment.url, Some(attachment.mime_type));
+        l.rel = Some("enclosure".into());
+        l.title = attachment.title;
+        l.length = attachment.size_in_bytes;
+        entry.links.push(l);
+    }
+
+    if let Some(uri) = ji.image.or(ji.banner_image) {
+        let mut media = MediaObject::default();
+        media.thumbnails.push(MediaThumbnail {
+            image: image(uri),
+            time: None,
+        });
+        entry.media.push(media);
+    }
+
+    entry
+}
+
+/// JSON Feed 1.1 replaced `author` with an `authors` array; accept both.
+fn people(author: Option<JsonAuthor>, authors: Option<Vec<JsonAuthor>>) -> Vec<Person> {
+    authors
+        .unwrap_or_default()
+        .into_iter()
+        .chain(author)
+        .filter_map(|a| {
+            a.name.map(|name| Person {
+                name: name.trim().to_string(),
+                uri: a.url,
+                email: None,
+            })
+        })
+        .collect()
+}
+
+fn text(content: String, content_type: mime::Mime) -> Text {
+    Text {
+        content_type,
+        src: None,
+        content,
+    }
+}
+
+fn content(body: String, content_type: mime::Mime) -> Content {
+    Content {
+        length: Some(body.len() as u64),
+        body: Some(body.trim().to_string()),
+        content_type,
+        ..Default::default()
+    }
+}
+
+fn link(href: String, media_type: Option<String>) -> Link {
+    Link {
+        href,
+        rel: None,
+        media_type,
+        href_lang: None,
+        title: None,
+        length: None,
+    }
+}
+
+fn image(uri: String) -> Image {
+    Image {
+        uri,
+        title: None,
+        link: None,
+        width: None,
+        height: None,
+        description: None,
+    }
+}
+
+fn timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonFeed {
+    title: String,
+    home_page_url: Option<String>,
+    feed_url: Option<String>,
+    description: Option<String>,
+    icon: Option<String>,
+    favicon: Option<String>,
+    language: Option<String>,
+    author: Option<JsonAuthor>,
+    authors: Option<Vec<JsonAuthor>>,
+    #[serde(default)]
+    items: Vec<JsonItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonItem {
+    id: JsonId,
+    url: Option<String>,
+    external_url: Option<String>,
+    title: Option<String>,
+    content_html: Option<String>,
+    content_text: Option<String>,
+    summary: Option<String>,
+    image: Option<String>,
+    banner_image: Option<String>,
+    date_published: Option<String>,
+    date_modified: Option<String>,
+    author: Option<JsonAuthor>,
+    authors: Option<Vec<JsonAuthor>>,
+    tags: Option<Vec<String>>,
+    attachments: Option<Vec<JsonAttachment>>,
+}
+
+/// Item ids are strings per the spec, but numeric ids are common in the wild.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonId {
+    Text(String),
+    Number(serde_json::Number),
+}
+
+impl JsonId {
+    fn into_string(self) -> String {
+        match self {
+            JsonId::Text(s) => s,
+            JsonId::Number(n) => n.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonAuthor {
+    name: Option<String>,
+    url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonAttachment {
+    url: String,
+    mime_type: String,
+    title: Option<String>,
+    size_in_bytes: Option<u64>,
+}
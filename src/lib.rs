@@ -1,6 +1,55 @@
 //! Library entrypoint: re‑export modules
 
+pub mod canary;
+pub mod certs;
+pub mod charset;
 pub mod config;
+pub mod costs;
+pub mod db_utils;
+pub mod dedup;
+pub mod discovery;
+pub mod embed;
+pub mod enclosures;
+pub mod enrich;
 pub mod errors;
+pub mod extensions;
+pub mod graph;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod history;
+pub mod http;
+pub mod icons;
 pub mod ingestor;
+pub mod inspect;
+pub mod jsonfeed;
+pub mod license;
+pub mod links;
+pub mod mbox;
+pub mod media;
 pub mod metrics;
+pub mod parsing;
+pub mod ping;
+pub mod pipeline;
+pub mod reload;
+pub mod revisions;
+pub mod sanitize;
+pub mod scheduler;
+pub mod schema;
+pub mod server;
+pub mod shutdown;
+pub mod sinks;
+pub mod spillover;
+pub mod spool;
+pub mod state;
+pub mod stix;
+pub mod storage;
+pub mod summarize;
+pub mod systemd;
+pub mod tagging;
+pub mod taxii;
+pub mod telemetry;
+pub mod templates;
+#[cfg(feature = "integration")]
+pub mod testkit;
+pub mod tlp;
+pub mod urlguard;
@@ -0,0 +1,68 @@
+//! Content licensing: each item carries the license or rights statement that applies
+//! to it, so sinks re-publishing entries can leave out those that may not be
+//! redistributed.
+
+use feed_rs::model::{Entry, Feed};
+
+use crate::config::{self, LicensingSettings};
+use crate::extensions::EntryElements;
+
+/// Raw elements naming an entry's license, most specific first.
+const LICENSE_ELEMENTS: &[&str] = &[
+    "dc:rights",
+    "dcterms:license",
+    "dcterms:rights",
+    "cc:license@resource",
+    "cc:license",
+    "creativeCommons:license",
+];
+
+/// The license for `entry`: the feed's configured `license`, else the entry's own
+/// rights, license element or `rel="license"` link, else the feed-level rights.
+pub fn for_entry(
+    source: &config::Feed,
+    entry: &Entry,
+    feed: &Feed,
+    elements: Option<&EntryElements>,
+) -> Option<String> {
+    // Empty elements (`<dc:rights/>`) fall through to the next source
+    let present = |l: &String| -> Option<String> {
+        let l = l.trim();
+        (!l.is_empty()).then(|| l.to_string())
+    };
+    let from_elements = || {
+        LICENSE_ELEMENTS
+            .iter()
+            .find_map(|name| elements.and_then(|e| e.get(*name)).and_then(present))
+    };
+    let license_link = || {
+        entry
+            .links
+            .iter()
+            .filter(|l| l.rel.as_deref() == Some("license"))
+            .find_map(|l| present(&l.href))
+    };
+    source
+        .license
+        .as_ref()
+        .and_then(present)
+        .or_else(|| entry.rights.as_ref().and_then(|t| present(&t.content)))
+        .or_else(from_elements)
+        .or_else(license_link)
+        .or_else(|| feed.rights.as_ref().and_then(|t| present(&t.content)))
+}
+
+/// Whether an item under `license` may be passed on, i.e. the license names none of
+/// the `restricted` phrases (and, when absent, unlicensed items are allowed).
+pub fn permits_redistribution(policy: &LicensingSettings, license: Option<&str>) -> bool {
+    match license {
+        None => policy.allow_unlicensed,
+        Some(license) => {
+            let license = license.to_lowercase();
+            !policy
+                .restricted
+                .iter()
+                .any(|phrase| license.contains(&phrase.to_lowercase()))
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Entry links in canonical form, so one article is stored under one URL however a
+//! feed spells it: lowercase scheme and host, no default port, no fragment and no empty
+//! query; the path is kept as published. Feeds alternating between `http` and `https`
+//! can have `http` links upgraded (`prefer_https`). Links through URL shorteners can
+//! also be followed to the page they lead to (see `Unshortener`).
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use futures::stream::{self, StreamExt};
+use reqwest::{Client, Method, StatusCode};
+use tracing::debug;
+use url::Url;
+
+use crate::config::UnshortenSettings;
+use crate::ingestor::FeedItem;
+use crate::metrics::LINK_RESOLUTIONS;
+use crate::urlguard;
+
+/// `link` in canonical form, or unchanged if it is not an absolute `http(s)` URL. With
+/// `prefer_https`, an `http` link on the default port becomes `https`.
+pub fn canonical(link: &str, prefer_https: bool) -> String {
+    let mut url = match Url::parse(link) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+        _ => return link.to_string(),
+    };
+    // Parsing lowercases the scheme and host and drops the scheme's default port
+    url.set_fragment(None);
+    if url.query() == Some("") {
+        url.set_query(None);
+    }
+    if prefer_https && url.scheme() == "http" && url.port().is_none() {
+        url.set_scheme("https")
+            .expect("http and https are both special schemes");
+    }
+    url.to_string()
+}
+
+/// Resolves entry links through URL shorteners and feed proxies (`feedproxy`, `t.co`,
+/// `bit.ly`, ...) to the page they redirect to, stored in `resolved_link` next to the
+/// link as published; disabled by default. Each link is resolved once per process,
+/// with a `HEAD` request (`GET` where `HEAD` is refused) under the feed client's
+/// redirect limit and URL safety checks.
+#[derive(Clone, Default)]
+pub struct Unshortener {
+    inner: Option<Arc<Resolver>>,
+}
+
+struct Resolver {
+    settings: UnshortenSettings,
+    /// Link -> where it leads, if elsewhere
+    cache: Mutex<HashMap<String, Option<String>>>,
+}
+
+impl Unshortener {
+    pub fn from_settings(settings: Option<&UnshortenSettings>) -> Self {
+        Unshortener {
+            inner: settings.map(|settings| {
+                Arc::new(Resolver {
+                    settings: settings.clone(),
+                    cache: Mutex::new(HashMap::new()),
+                })
+            }),
+        }
+    }
+
+    /// Set `resolved_link` on those of `items` whose link goes through a configured
+    /// host and redirects elsewhere, fetching with `client`.
+    pub async fn resolve(&self, client: &Client, items: &mut [FeedItem]) {
+        let Some(resolver) = &self.inner else {
+            return;
+        };
+        stream::iter(items.iter_mut().filter(|i| resolver.shortened(&i.link)))
+            .for_each_concurrent(resolver.settings.concurrency, |item| async move {
+                item.resolved_link = resolver.lookup(client, &item.link).await;
+            })
+            .await;
+    }
+}
+
+impl Resolver {
+    /// Whether `link`'s host is one of the configured hosts or a subdomain of one.
+    fn shortened(&self, link: &str) -> bool {
+        let Some(host) = Url::parse(link)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_ascii_lowercase))
+        else {
+            return false;
+        };
+        self.settings.hosts.iter().any(|h| {
+            let h = h.to_ascii_lowercase();
+            host == h || host.strip_suffix(&h).is_some_and(|s| s.ends_with('.'))
+        })
+    }
+
+    async fn lookup(&self, client: &Client, link: &str) -> Option<String> {
+        let cached = self
+            .cache
+            .lock()
+            .expect("link cache poisoned")
+            .get(link)
+            .cloned();
+        if let Some(resolved) = cached {
+            LINK_RESOLUTIONS.with_label_values(&["cached"]).inc();
+            return resolved;
+        }
+        let followed = match urlguard::check(link) {
+            Ok(()) => Some(self.follow(client, link).await),
+            Err(_) => None,
+        };
+        let (outcome, resolved) = match followed {
+            Some(Ok(target)) if target != link => ("resolved", Some(target)),
+            Some(Ok(_)) => ("unchanged", None),
+            None => ("refused", None),
+            Some(Err(e)) if urlguard::is_blocked(&e) => ("refused", None),
+            Some(Err(e)) => {
+                debug!(link = %link, error = %e, "Failed to resolve link");
+                ("failed", None)
+            }
+        };
+        LINK_RESOLUTIONS.with_label_values(&[outcome]).inc();
+        // A failed link is not retried either, so a dead shortener costs one timeout
+        let mut cache = self.cache.lock().expect("link cache poisoned");
+        if cache.len() >= self.settings.cache {
+            cache.clear();
+        }
+        cache.insert(link.to_string(), resolved.clone());
+        resolved
+    }
+
+    /// The canonical form of the URL `link` finally redirects to.
+    async fn follow(&self, client: &Client, link: &str) -> Result<String, reqwest::Error> {
+        let send = |method: Method| {
+            client
+                .request(method, link)
+                .timeout(self.settings.timeout)
+                .send()
+        };
+        let mut response = send(Method::HEAD).await?;
+        if matches!(
+            response.status(),
+            StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+        ) {
+            response = send(Method::GET).await?;
+        }
+        Ok(canonical(response.url().as_str(), false))
+    }
+}
@@ -0,0 +1,536 @@
+//! Mailing list archives (mbox, and the monthly `.txt` archives pipermail publishes)
+//! parsed into `feed_rs` models, so lists like oss-security and full-disclosure flow
+//! through the same mapping and sanitization as RSS/Atom.
+//!
+//! Each message becomes an entry whose ID is its `Message-ID`; `In-Reply-To` (or the
+//! last `References` ID) becomes the parent for threading, and every header is exposed
+//! to field mappings as a raw element keyed by its lowercase name.
+//!
+//! Feed URLs may name the current month's archive with `{year}`, `{month}` (`06`) and
+//! `{month_name}` (`June`). Early in a month the previous month's archive is fetched
+//! too, so messages posted just before the rollover are not missed.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, TimeZone, Utc};
+use feed_rs::model::{Content, Entry, Feed, FeedType, Person, Text};
+use feed_rs::parser::{ParseErrorKind, ParseFeedError};
+use sha2::{Digest, Sha256};
+
+use crate::extensions::EntryElements;
+
+/// Days into a month during which the previous month's archive is still fetched.
+const PREVIOUS_MONTH_OVERLAP_DAYS: u32 = 3;
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Whether `body` looks like an mbox: it starts with a `From ` separator line.
+pub fn is_mbox(body: &[u8]) -> bool {
+    let body = body.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(body);
+    body.starts_with(b"From ")
+}
+
+/// Whether `url` contains month placeholders.
+pub fn is_monthly(url: &str) -> bool {
+    ["{year}", "{month}", "{month_name}"]
+        .iter()
+        .any(|p| url.contains(p))
+}
+
+/// The archive URLs to fetch at `now`, oldest first: `url` itself unless it is
+/// monthly, else this month's archive, preceded by last month's early in the month.
+pub fn archive_urls(url: &str, now: DateTime<Utc>) -> Vec<String> {
+    if !is_monthly(url) {
+        return vec![url.to_string()];
+    }
+    let mut months = Vec::with_capacity(2);
+    if now.day() <= PREVIOUS_MONTH_OVERLAP_DAYS {
+        months.push(now - Duration::days(i64::from(now.day())));
+    }
+    months.push(now);
+    months
+        .into_iter()
+        .map(|month| {
+            url.replace("{year}", &month.year().to_string())
+                .replace("{month}", &format!("{:02}", month.month()))
+                .replace("{month_name}", MONTH_NAMES[month.month0() as usize])
+        })
+        .collect()
+}
+
+/// Parse an mbox into a feed with one entry per message.
+pub fn parse(body: &[u8]) -> Result<Feed, ParseFeedError> {
+    let messages = messages(body);
+    if messages.is_empty() {
+        return Err(ParseFeedError::ParseError(ParseErrorKind::NoFeedRoot));
+    }
+    let title = messages
+        .iter()
+        .find_map(|m| m.header("list-id"))
+        .map(list_name)
+        .filter(|t| !t.is_empty());
+    Ok(Feed {
+        feed_type: FeedType::RSS2,
+        id: String::new(),
+        title: title.map(|t| text(t, mime::TEXT_PLAIN)),
+        updated: None,
+        authors: Vec::new(),
+        description: None,
+        links: Vec::new(),
+        categories: Vec::new(),
+        contributors: Vec::new(),
+        generator: None,
+        icon: None,
+        language: None,
+        logo: None,
+        published: None,
+        rating: None,
+        rights: None,
+        ttl: None,
+        entries: messages.iter().map(Message::to_entry).collect(),
+    })
+}
+
+/// Every message's headers, aligned with the entries of `parse`, plus the parent
+/// message ID under `thr:in-reply-to@ref`.
+pub fn entry_elements(body: &[u8]) -> Vec<EntryElements> {
+    messages(body)
+        .iter()
+        .map(|m| {
+            let mut elements = EntryElements::new();
+            for (name, value) in &m.headers {
+                elements
+                    .entry(name.to_ascii_lowercase())
+                    .or_insert_with(|| decode_words(value));
+            }
+            if let Some(parent) = m.parent() {
+                elements.insert("thr:in-reply-to@ref".to_string(), parent);
+            }
+            elements
+        })
+        .collect()
+}
+
+/// One message: its `From ` separator line, unfolded headers and raw body.
+struct Message {
+    separator: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+/// Split `body` into messages at `From ` lines that start the body or follow a blank line.
+fn messages(body: &[u8]) -> Vec<Message> {
+    let body = body.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(body);
+    let text = String::from_utf8_lossy(body).replace("\r\n", "\n");
+    let mut messages = Vec::new();
+    let mut current: Option<Vec<&str>> = None;
+    let mut previous_blank = true;
+    for line in text.split('\n') {
+        if previous_blank && line.starts_with("From ") {
+            if let Some(lines) = current.take() {
+                messages.push(Message::from_lines(&lines));
+            }
+            current = Some(vec![line]);
+        } else if let Some(lines) = current.as_mut() {
+            lines.push(line);
+        }
+        previous_blank = line.trim().is_empty();
+    }
+    if let Some(lines) = current {
+        messages.push(Message::from_lines(&lines));
+    }
+    messages
+}
+
+impl Message {
+    fn from_lines(lines: &[&str]) -> Self {
+        let (separator, rest) = lines.split_first().expect("separator line");
+        let end = rest.iter().position(|l| l.is_empty()).unwrap_or(rest.len());
+        let body = rest
+            .get(end + 1..)
+            .unwrap_or_default()
+            .iter()
+            .map(|l| unescape_from(l))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Message {
+            separator: separator.to_string(),
+            headers: unfold(&rest[..end]),
+            body: body.trim_end().to_string(),
+        }
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        header(&self.headers, name)
+    }
+
+    /// Message ID without angle brackets, or a digest of the separator and headers.
+    fn id(&self) -> String {
+        if let Some(id) = self
+            .header("message-id")
+            .and_then(|v| message_ids(v).next())
+        {
+            return id;
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(self.separator.as_bytes());
+        for (name, value) in &self.headers {
+            hasher.update(format!("\n{}: {}", name, value).as_bytes());
+        }
+        format!("mbox-{:x}", hasher.finalize())
+    }
+
+    fn parent(&self) -> Option<String> {
+        self.header("in-reply-to")
+            .and_then(|v| message_ids(v).next())
+            .or_else(|| {
+                self.header("references")
+                    .and_then(|v| message_ids(v).last())
+            })
+    }
+
+    fn date(&self) -> Option<DateTime<Utc>> {
+        self.header("date").and_then(parse_date).or_else(|| {
+            // `From sender  Mon Jun  2 10:00:00 2025`, in UTC by convention
+            // (pipermail writes the sender as `user at host`, so count from the end)
+            let words: Vec<&str> = self.separator.split_whitespace().collect();
+            let stamp = words[words.len().saturating_sub(5)..].join(" ");
+            NaiveDateTime::parse_from_str(&stamp, "%a %b %e %H:%M:%S %Y")
+                .ok()
+                .map(|dt| Utc.from_utc_datetime(&dt))
+        })
+    }
+
+    fn to_entry(&self) -> Entry {
+        let id = self.id();
+        let subject = self
+            .header("subject")
+            .map(decode_words)
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "(no subject)".to_string());
+        let mut entry = Entry {
+            id: id.clone(),
+            ..Default::default()
+        };
+        entry.title = Some(text(subject, mime::TEXT_PLAIN));
+        entry.links = vec![feed_rs::model::Link {
+            href: format!("mid:{}", percent_encoding::utf8_percent_encode(&id, MID)),
+            rel: None,
+            media_type: None,
+            href_lang: None,
+            title: None,
+            length: None,
+        }];
+        entry.published = self.date();
+        entry.authors = self
+            .header("from")
+            .map(|from| {
+                vec![Person {
+                    name: sender_name(&decode_words(from)),
+                    uri: None,
+                    email: None,
+                }]
+            })
+            .unwrap_or_default();
+        if let Some(body) = plain_text(&self.headers, &self.body) {
+            let html = format!("<pre>{}</pre>", htmlescape::encode_minimal(&body));
+            entry.content = Some(Content {
+                length: Some(html.len() as u64),
+                body: Some(html),
+                content_type: mime::TEXT_HTML,
+                ..Default::default()
+            });
+        }
+        entry
+    }
+}
+
+/// Characters escaped in a `mid:` URL (RFC 2392), besides controls.
+const MID: &percent_encoding::AsciiSet = &percent_encoding::CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'/')
+    .add(b'?');
+
+/// Undo mboxrd `>From ` quoting of body lines.
+fn unescape_from(line: &str) -> &str {
+    match line.strip_prefix('>') {
+        Some(rest) if rest.trim_start_matches('>').starts_with("From ") => rest,
+        _ => line,
+    }
+}
+
+/// Headers as `(name, value)`, with continuation lines joined.
+fn unfold(lines: &[&str]) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in lines {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    headers
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// The `<...>` IDs in a header value, without brackets.
+fn message_ids(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split('<')
+        .skip(1)
+        .filter_map(|s| s.split_once('>'))
+        .map(|(id, _)| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+}
+
+/// RFC 2822 dates, tolerating a trailing `(UTC)`-style comment.
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = match value.find('(') {
+        Some(i) => &value[..i],
+        None => value,
+    };
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// `Jane Doe <jane@example.org>`, `jane at example.org (Jane Doe)` (pipermail) or
+/// a bare address, reduced to the display name when there is one.
+fn sender_name(from: &str) -> String {
+    let from = from.trim();
+    if let Some((name, _)) = from.split_once('<') {
+        let name = name.trim().trim_matches('"').trim();
+        if !name.is_empty() {
+            return name.to_string();
+        }
+    }
+    if let (Some(open), true) = (from.find('('), from.ends_with(')')) {
+        let name = from[open + 1..from.len() - 1].trim();
+        if !name.is_empty() {
+            return name.to_string();
+        }
+    }
+    from.trim_matches(['<', '>']).to_string()
+}
+
+/// `"oss-security" <oss-security.lists.openwall.com>` → `oss-security`.
+fn list_name(list_id: &str) -> String {
+    let name = decode_words(list_id);
+    match name.split_once('<') {
+        Some((name, id)) if name.trim().is_empty() => id.trim_end_matches('>').trim().to_string(),
+        Some((name, _)) => name.trim().trim_matches('"').trim().to_string(),
+        None => name.trim().to_string(),
+    }
+}
+
+/// The message's text: the body itself, or for multipart messages the first
+/// `text/plain` part, after undoing any transfer encoding.
+fn plain_text(headers: &[(String, String)], body: &str) -> Option<String> {
+    let content_type = header(headers, "content-type").unwrap_or("text/plain");
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if media_type.starts_with("multipart/") {
+        let boundary = parameter(content_type, "boundary")?;
+        return parts(body, &boundary)
+            .find_map(|(part_headers, part_body)| plain_text(&part_headers, &part_body));
+    }
+    if media_type != "text/plain" {
+        return None;
+    }
+    let encoding = header(headers, "content-transfer-encoding").unwrap_or_default();
+    let bytes = if encoding.eq_ignore_ascii_case("quoted-printable") {
+        quoted_printable(body.as_bytes(), false)
+    } else if encoding.eq_ignore_ascii_case("base64") {
+        base64(body.as_bytes())?
+    } else {
+        return Some(body.to_string());
+    };
+    Some(decode_charset(
+        &bytes,
+        parameter(content_type, "charset").as_deref(),
+    ))
+}
+
+/// A `name=value` parameter of a structured header, unquoted.
+pub(crate) fn parameter(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|p| {
+        let (key, value) = p.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// The parts of a multipart body, each as headers and body.
+fn parts<'a>(
+    body: &'a str,
+    boundary: &str,
+) -> impl Iterator<Item = (Vec<(String, String)>, String)> + 'a {
+    let delimiter = format!("--{}", boundary);
+    let mut sections: Vec<Vec<&str>> = Vec::new();
+    let mut inside = false;
+    for line in body.split('\n') {
+        let line_trimmed = line.trim_end();
+        if line_trimmed == delimiter {
+            sections.push(Vec::new());
+            inside = true;
+        } else if line_trimmed == format!("{}--", delimiter) {
+            inside = false;
+        } else if inside {
+            if let Some(section) = sections.last_mut() {
+                section.push(line);
+            }
+        }
+    }
+    sections.into_iter().map(|lines| {
+        let end = lines
+            .iter()
+            .position(|l| l.trim().is_empty())
+            .unwrap_or(lines.len());
+        let body = lines.get(end + 1..).unwrap_or_default().join("\n");
+        (unfold(&lines[..end]), body)
+    })
+}
+
+/// Decode RFC 2047 encoded words (`=?UTF-8?Q?...?=`, `=?ISO-8859-1?B?...?=`).
+fn decode_words(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let decoded = rest[start + 2..].splitn(3, '?').collect::<Vec<_>>();
+        let word = match decoded.as_slice() {
+            [charset, encoding, tail] => tail.find("?=").and_then(|end| {
+                let data = &tail.as_bytes()[..end];
+                let bytes = match encoding.to_ascii_uppercase().as_str() {
+                    "Q" => Some(quoted_printable(data, true)),
+                    "B" => base64(data),
+                    _ => None,
+                }?;
+                let consumed = start + 2 + charset.len() + encoding.len() + 2 + end + 2;
+                Some((decode_charset(&bytes, Some(charset)), consumed))
+            }),
+            _ => None,
+        };
+        let Some((word, consumed)) = word else {
+            out.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            after_word = false;
+            continue;
+        };
+        // Whitespace between adjacent encoded words is not part of the text
+        let between = &rest[..start];
+        if !(after_word && between.trim().is_empty()) {
+            out.push_str(between);
+        }
+        out.push_str(&word);
+        rest = &rest[consumed..];
+        after_word = true;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Quoted-printable (or, with `header`, the Q encoding where `_` is a space).
+fn quoted_printable(data: &[u8], header: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            b'=' if data[i + 1..].starts_with(b"\n") => i += 1,
+            b'=' if data[i + 1..].starts_with(b"\r\n") => i += 2,
+            b'=' if i + 2 < data.len() => {
+                match std::str::from_utf8(&data[i + 1..i + 3])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'='),
+                }
+            }
+            b'_' if header => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Standard base64, ignoring whitespace; `None` on any other invalid character.
+fn base64(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &c in data {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// UTF-8 and ASCII as such, Latin-1 byte for byte; anything else lossily as UTF-8.
+fn decode_charset(bytes: &[u8], charset: Option<&str>) -> String {
+    let charset = charset.unwrap_or("utf-8").to_ascii_lowercase();
+    if matches!(
+        charset.as_str(),
+        "iso-8859-1" | "latin1" | "iso-8859-15" | "windows-1252"
+    ) && std::str::from_utf8(bytes).is_err()
+    {
+        return bytes.iter().map(|&b| b as char).collect();
+    }
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+fn text(content: String, content_type: mime::Mime) -> Text {
+    Text {
+        content_type,
+        src: None,
+        content,
+    }
+}
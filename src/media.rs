@@ -0,0 +1,98 @@
+//! Media RSS (`media:content`, `media:group`, `media:thumbnail`): an entry's images,
+//! video and audio, stored in `media` with their dimensions, duration and thumbnail,
+//! and its first thumbnail in `thumbnail_url`. `feed_rs` models RSS `<enclosure>` as
+//! Media RSS content, so enclosures are listed here too (see `enclosures`).
+
+use feed_rs::model::Entry;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::extensions::EntryElements;
+
+/// One `media:content` of an entry, with its title, description and thumbnail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaItem {
+    pub url: String,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    /// Play time in seconds
+    #[serde(default)]
+    pub duration_s: Option<u64>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub thumbnail_url: Option<String>,
+}
+
+/// The URL of `entry`'s first `media:thumbnail`, resolved against `link`.
+pub fn thumbnail_url(entry: &Entry, link: &str) -> Option<String> {
+    entry
+        .media
+        .iter()
+        .flat_map(|m| m.thumbnails.iter())
+        .find_map(|t| resolve(&t.image.uri, link))
+}
+
+/// The media content of `entry`, with relative URLs resolved against `link`. `feed_rs`
+/// drops relative `media:content` URLs, so the entry's first one is also read from its
+/// raw `elements`.
+pub fn for_entry(
+    entry: &Entry,
+    elements: Option<&EntryElements>,
+    link: &str,
+) -> Option<Vec<MediaItem>> {
+    let mut items: Vec<MediaItem> = entry
+        .media
+        .iter()
+        .flat_map(|m| {
+            let thumbnail = m
+                .thumbnails
+                .iter()
+                .find_map(|t| resolve(&t.image.uri, link));
+            m.content.iter().filter_map(move |c| {
+                Some(MediaItem {
+                    url: resolve(c.url.as_ref()?.as_str(), link)?,
+                    content_type: c.content_type.as_ref().map(|ct| ct.to_string()),
+                    width: c.width,
+                    height: c.height,
+                    duration_s: c.duration.or(m.duration).map(|d| d.as_secs()),
+                    title: m.title.as_ref().map(|t| t.content.clone()),
+                    description: m.description.as_ref().map(|d| d.content.clone()),
+                    thumbnail_url: thumbnail.clone(),
+                })
+            })
+        })
+        .collect();
+    let raw = elements.and_then(|e| {
+        let number = |key: &str| e.get(key).and_then(|v| v.trim().parse().ok());
+        Some(MediaItem {
+            url: resolve(e.get("media:content@url")?, link)?,
+            content_type: e.get("media:content@type").cloned(),
+            width: number("media:content@width"),
+            height: number("media:content@height"),
+            duration_s: number("media:content@duration").map(u64::from),
+            title: e.get("media:title").cloned(),
+            description: e.get("media:description").cloned(),
+            thumbnail_url: thumbnail_url(entry, link),
+        })
+    });
+    if let Some(raw) = raw.filter(|r| items.iter().all(|i| i.url != r.url)) {
+        items.push(raw);
+    }
+    (!items.is_empty()).then_some(items)
+}
+
+fn resolve(href: &str, link: &str) -> Option<String> {
+    let href = href.trim();
+    let url = match Url::parse(href) {
+        Ok(url) => url,
+        Err(_) => Url::parse(link).ok()?.join(href).ok()?,
+    };
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
@@ -1,6 +1,9 @@
 //! Prometheus metrics registry and metric definitions.
 use once_cell::sync::Lazy;
-use prometheus::{Encoder, Histogram, HistogramOpts, IntCounter, Opts, Registry, TextEncoder};
+use prometheus::{
+    Encoder, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
 
 /// Global registry under crate namespace
 pub static REGISTRY: Lazy<Registry> = Lazy::new(|| {
@@ -8,43 +11,522 @@ pub static REGISTRY: Lazy<Registry> = Lazy::new(|| {
         .expect("failed to create Prometheus registry")
 });
 
-/// Total fetch attempts
-pub static FETCH_COUNTER: Lazy<IntCounter> = Lazy::new(|| {
+/// Recent success rate of each feed's runs (see `FeedSchedule::health`)
+pub static FEED_HEALTH: Lazy<GaugeVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "feed_health",
+        "Recent success rate of feed runs, the latest weighing most",
+    );
+    let g = GaugeVec::new(opts, &["feed"]).expect("gauge opts");
+    REGISTRY.register(Box::new(g.clone())).unwrap();
+    g
+});
+
+/// 1 while a feed is disabled after consecutive failures (`feed_health.disable_after`)
+pub static FEED_DISABLED: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "feed_disabled",
+        "Whether a feed is disabled after consecutive failures",
+    );
+    let g = IntGaugeVec::new(opts, &["feed"]).expect("gauge opts");
+    REGISTRY.register(Box::new(g.clone())).unwrap();
+    g
+});
+
+/// Total fetch attempts, by feed
+pub static FETCH_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
     let opts = Opts::new("feeds_fetched_total", "Total number of feed fetch attempts");
+    let c = IntCounterVec::new(opts, &["feed"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Fetch attempts retried after a transient failure
+pub static FETCH_RETRIES: Lazy<IntCounter> = Lazy::new(|| {
+    let opts = Opts::new(
+        "feed_fetch_retries_total",
+        "Total number of feed fetch attempts retried after a transient failure",
+    );
     let c = IntCounter::with_opts(opts).expect("counter opts");
     REGISTRY.register(Box::new(c.clone())).unwrap();
     c
 });
 
-/// Histogram of fetch+parse durations
-pub static FETCH_HISTOGRAM: Lazy<Histogram> = Lazy::new(|| {
+/// Fetches that failed for good (retries exhausted or a permanent error)
+pub static FETCH_FAILURES: Lazy<IntCounter> = Lazy::new(|| {
+    let opts = Opts::new(
+        "feed_fetch_failures_total",
+        "Total number of feed fetches that failed after all retries",
+    );
+    let c = IntCounter::with_opts(opts).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Histogram of fetch+parse durations, by feed
+pub static FETCH_HISTOGRAM: Lazy<HistogramVec> = Lazy::new(|| {
     let opts = HistogramOpts::new(
         "fetch_duration_seconds",
         "Duration of feed fetch+parse in seconds",
     );
+    let h = HistogramVec::new(opts, &["feed"]).expect("histogram opts");
+    REGISTRY.register(Box::new(h.clone())).unwrap();
+    h
+});
+
+/// Time spent storing entries, by write path (`entry`: one at a time, `batch`: a
+/// feed's entries in multi-row statements)
+pub static DB_WRITE_HISTOGRAM: Lazy<HistogramVec> = Lazy::new(|| {
+    let opts = HistogramOpts::new(
+        "db_write_duration_seconds",
+        "Duration of archive/current writes in seconds, per call of each write path",
+    );
+    let h = HistogramVec::new(opts, &["path"]).expect("histogram opts");
+    REGISTRY.register(Box::new(h.clone())).unwrap();
+    h
+});
+
+/// Size of the change each entry revision makes, in bytes (see `revisions`)
+pub static REVISION_DIFF_BYTES: Lazy<Histogram> = Lazy::new(|| {
+    let opts = HistogramOpts::new(
+        "entry_revision_diff_bytes",
+        "Bytes of title, summary and content changed by each stored entry revision",
+    )
+    .buckets(prometheus::exponential_buckets(16.0, 4.0, 8).expect("histogram buckets"));
     let h = Histogram::with_opts(opts).expect("histogram opts");
     REGISTRY.register(Box::new(h.clone())).unwrap();
     h
 });
 
-/// Total number of feed entries that failed sanitization/validation
-pub static SANITIZATION_FAILURES: Lazy<IntCounter> = Lazy::new(|| {
+/// Total number of conditional fetches answered with `304 Not Modified`
+pub static FEED_CACHE_HITS: Lazy<IntCounter> = Lazy::new(|| {
+    let opts = Opts::new(
+        "feed_cache_hits_total",
+        "Total number of feed fetches answered with 304 Not Modified",
+    );
+    let c = IntCounter::with_opts(opts).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Total number of fetches whose body matched the previous fetch byte-for-byte
+pub static FEED_BODY_UNCHANGED: Lazy<IntCounter> = Lazy::new(|| {
+    let opts = Opts::new(
+        "feed_body_unchanged_total",
+        "Total number of feed fetches whose body hash matched the previous fetch",
+    );
+    let c = IntCounter::with_opts(opts).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Repair-and-retry attempts after a parse failure, by feed and outcome ("success"/"failure")
+pub static XML_REPAIRS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "feed_xml_repairs_total",
+        "Total number of malformed-XML repair attempts, by feed and outcome",
+    );
+    let c = IntCounterVec::new(opts, &["feed", "outcome"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Feed runs skipped because a daily quota was reached, by feed
+pub static QUOTA_SKIPS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "feed_quota_skips_total",
+        "Total number of feed runs skipped because a daily quota was reached",
+    );
+    let c = IntCounterVec::new(opts, &["feed"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Items held back in `canary_entries` because their feed is in canary, by feed
+pub static CANARY_STAGED: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "canary_entries_staged_total",
+        "Total number of entries staged while their feed was in canary",
+    );
+    let c = IntCounterVec::new(opts, &["feed"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Entries abandoned because processing exceeded `entry_timeout`, by feed
+pub static ENTRY_TIMEOUTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "entry_timeouts_total",
+        "Total number of entries abandoned after exceeding the processing deadline",
+    );
+    let c = IntCounterVec::new(opts, &["feed"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Write transactions rolled back on an error or timeout, by feed: a feed's batch (its
+/// entries are then stored one at a time) or a single entry's
+pub static FEED_ROLLBACKS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "feed_rollbacks_total",
+        "Total number of write transactions rolled back, by feed",
+    );
+    let c = IntCounterVec::new(opts, &["feed"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Feed body bytes downloaded, by feed and form: "compressed" as transferred (the same
+/// as decompressed without a `Content-Encoding`) and "decompressed"
+pub static FEED_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "feed_bytes_total",
+        "Total number of feed body bytes downloaded, by feed and form",
+    );
+    let c = IntCounterVec::new(opts, &["feed", "form"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Feed bodies transcoded to UTF-8 before parsing, by feed and source charset
+pub static FEED_TRANSCODED: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "feed_transcoded_total",
+        "Total number of feed bodies transcoded to UTF-8, by feed and source charset",
+    );
+    let c = IntCounterVec::new(opts, &["feed", "charset"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Entry GUIDs derived from link, title and date, by feed and reason: the entry had no
+/// ID (missing) or shared it with another entry of the feed (duplicate)
+pub static SYNTHETIC_GUIDS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "synthetic_guids_total",
+        "Total number of entry GUIDs synthesized for missing or non-unique IDs",
+    );
+    let c = IntCounterVec::new(opts, &["feed", "reason"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// New entries stored as near duplicates of earlier ones (`duplicate_of`)
+pub static NEAR_DUPLICATES: Lazy<IntCounter> = Lazy::new(|| {
+    let opts = Opts::new(
+        "near_duplicates_total",
+        "Total number of new entries linked to an earlier entry as near duplicates",
+    );
+    let c = IntCounter::with_opts(opts).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Archive `EXISTS` queries skipped because the GUID Bloom filter ruled the entry out
+pub static DEDUP_BLOOM_SKIPS: Lazy<IntCounter> = Lazy::new(|| {
+    let opts = Opts::new(
+        "dedup_bloom_skips_total",
+        "Total number of archive dedup queries avoided by the GUID Bloom filter",
+    );
+    let c = IntCounter::with_opts(opts).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// GUIDs the Bloom filter reported as present that the archive did not contain
+pub static DEDUP_BLOOM_FALSE_POSITIVES: Lazy<IntCounter> = Lazy::new(|| {
+    let opts = Opts::new(
+        "dedup_bloom_false_positives_total",
+        "Total number of GUID Bloom filter hits not found in the archive",
+    );
+    let c = IntCounter::with_opts(opts).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Deliveries handed to each sink, by sink and outcome (`attempted`, then `succeeded`
+/// or `failed`)
+pub static SINK_DELIVERIES: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "sink_deliveries_total",
+        "Total number of entry deliveries to sinks, by sink and outcome",
+    );
+    let c = IntCounterVec::new(opts, &["sink", "outcome"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Delivery attempts a sink retried after a transient failure, by sink
+pub static SINK_RETRIES: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "sink_delivery_retries_total",
+        "Total number of sink delivery attempts retried after a transient failure",
+    );
+    let c = IntCounterVec::new(opts, &["sink"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Time each delivery took, retries included, by sink
+pub static SINK_HISTOGRAM: Lazy<HistogramVec> = Lazy::new(|| {
+    let opts = HistogramOpts::new(
+        "sink_delivery_duration_seconds",
+        "Duration of entry deliveries to sinks in seconds, retries included",
+    );
+    let h = HistogramVec::new(opts, &["sink"]).expect("histogram opts");
+    REGISTRY.register(Box::new(h.clone())).unwrap();
+    h
+});
+
+/// Unix time of each sink's latest successful delivery
+pub static SINK_LAST_SUCCESS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "sink_last_success_timestamp_seconds",
+        "Unix time of the latest successful delivery to each sink",
+    );
+    let g = IntGaugeVec::new(opts, &["sink"]).expect("gauge opts");
+    REGISTRY.register(Box::new(g.clone())).unwrap();
+    g
+});
+
+/// Sink deliveries skipped as repeats within the suppression window, by feed
+pub static ALERTS_SUPPRESSED: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "alerts_suppressed_total",
+        "Total number of sink deliveries suppressed as repeats of a recent story",
+    );
+    let c = IntCounterVec::new(opts, &["feed"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Sink deliveries withheld because the item's TLP exceeds the sink's `max_tlp`, by sink
+pub static TLP_WITHHELD: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "tlp_withheld_total",
+        "Total number of sink deliveries withheld by TLP dissemination rules",
+    );
+    let c = IntCounterVec::new(opts, &["sink"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Sink deliveries withheld because the item's license forbids redistribution, by sink
+pub static LICENSE_WITHHELD: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "license_withheld_total",
+        "Total number of sink deliveries withheld because the license forbids redistribution",
+    );
+    let c = IntCounterVec::new(opts, &["sink"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Cycles that stored more items than `cycle_limits.soft_items`
+pub static CYCLE_SOFT_LIMIT_EXCEEDED: Lazy<IntCounter> = Lazy::new(|| {
+    let opts = Opts::new(
+        "cycle_soft_limit_exceeded_total",
+        "Total number of ingestion cycles exceeding the soft item limit",
+    );
+    let c = IntCounter::with_opts(opts).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Items deferred to the spillover queue by the hard per-cycle limit, by feed
+pub static SPILLOVER_DEFERRED: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "spillover_deferred_total",
+        "Total number of items deferred to the spillover queue",
+    );
+    let c = IntCounterVec::new(opts, &["feed"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Items waiting in the spillover queue at the end of the last cycle
+pub static SPILLOVER_BACKLOG: Lazy<IntGauge> = Lazy::new(|| {
+    let opts = Opts::new(
+        "spillover_backlog",
+        "Number of items waiting in the spillover queue",
+    );
+    let g = IntGauge::with_opts(opts).expect("gauge opts");
+    REGISTRY.register(Box::new(g.clone())).unwrap();
+    g
+});
+
+/// Feed hosts presenting a different TLS certificate than last recorded, by host and
+/// whether the old one was due for renewal (`expected`)
+pub static TLS_CERT_CHANGES: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "tls_certificate_changes_total",
+        "Total number of TLS certificate changes seen on feed hosts",
+    );
+    let c = IntCounterVec::new(opts, &["host", "expected"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Entries written to the disk spool while the database was unreachable, by feed
+pub static SPOOL_WRITTEN: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "spool_written_total",
+        "Total number of entries spooled to disk during a database outage",
+    );
+    let c = IntCounterVec::new(opts, &["feed"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Entries that could not be spooled because the spool was at its limits, by feed
+pub static SPOOL_REJECTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "spool_rejected_total",
+        "Total number of entries not spooled because the spool was full",
+    );
+    let c = IntCounterVec::new(opts, &["feed"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Spooled entries written to the database once it was back
+pub static SPOOL_DRAINED: Lazy<IntCounter> = Lazy::new(|| {
+    let opts = Opts::new(
+        "spool_drained_total",
+        "Total number of spooled entries stored after the database recovered",
+    );
+    let c = IntCounter::with_opts(opts).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Entries currently held in the disk spool
+pub static SPOOL_ITEMS: Lazy<IntGauge> = Lazy::new(|| {
+    let opts = Opts::new("spool_items", "Number of entries held in the disk spool");
+    let g = IntGauge::with_opts(opts).expect("gauge opts");
+    REGISTRY.register(Box::new(g.clone())).unwrap();
+    g
+});
+
+/// Bytes currently held in the disk spool
+pub static SPOOL_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    let opts = Opts::new("spool_bytes", "Size of the entries held in the disk spool");
+    let g = IntGauge::with_opts(opts).expect("gauge opts");
+    REGISTRY.register(Box::new(g.clone())).unwrap();
+    g
+});
+
+/// Total number of feed entries that failed sanitization/validation, by feed
+pub static SANITIZATION_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
     let opts = Opts::new(
         "sanitization_failures_total",
         "Total number of feed entries that failed sanitization/validation",
     );
-    let c = IntCounter::with_opts(opts).expect("counter opts");
+    let c = IntCounterVec::new(opts, &["feed"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Entries new to the archive, by detected language ("und" when none was detected)
+pub static ENTRIES_BY_LANGUAGE: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "entries_ingested_by_language_total",
+        "Total number of new entries ingested, by detected language",
+    );
+    let c = IntCounterVec::new(opts, &["language"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Entries new to the archive, by tag from `tag_rules`
+pub static ENTRIES_BY_TAG: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "entries_tagged_total",
+        "Total number of new entries ingested, by rule-assigned tag",
+    );
+    let c = IntCounterVec::new(opts, &["tag"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Summarizer outcomes per new entry: stored, failed, dropped (queue full), withheld
+/// (above `max_tlp`) or over_budget
+pub static SUMMARIES: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "summaries_total",
+        "Total number of entries handed to the summarizer, by outcome",
+    );
+    let c = IntCounterVec::new(opts, &["outcome"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Tokens used by the summarizer, by kind (prompt or completion)
+pub static SUMMARIZER_TOKENS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "summarizer_tokens_total",
+        "Total number of LLM tokens used for summaries",
+    );
+    let c = IntCounterVec::new(opts, &["kind"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Embedding outcomes per new entry: stored, failed, dropped (queue full) or withheld
+/// (above `max_tlp`)
+pub static EMBEDDINGS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "embeddings_total",
+        "Total number of entries handed to the embedder, by outcome",
+    );
+    let c = IntCounterVec::new(opts, &["outcome"]).expect("counter opts");
     REGISTRY.register(Box::new(c.clone())).unwrap();
     c
 });
 
-/// Total number of successfully processed entries
-pub static ENTRIES_PROCESSED: Lazy<IntCounter> = Lazy::new(|| {
+/// Enclosure download outcomes: stored, known (downloaded before), filtered (media
+/// type not configured), too_large, refused (internal address), failed or dropped
+/// (queue full)
+pub static ENCLOSURE_DOWNLOADS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "enclosure_downloads_total",
+        "Total number of enclosures handed to the downloader, by outcome",
+    );
+    let c = IntCounterVec::new(opts, &["outcome"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Links through URL shorteners, by outcome: resolved (to another page), unchanged,
+/// cached (resolved before), refused (internal address) or failed
+pub static LINK_RESOLUTIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "link_resolutions_total",
+        "Total number of shortened entry links resolved, by outcome",
+    );
+    let c = IntCounterVec::new(opts, &["outcome"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Total number of successfully processed entries, by feed
+pub static ENTRIES_PROCESSED: Lazy<IntCounterVec> = Lazy::new(|| {
     let opts = Opts::new(
         "entries_processed_total",
         "Total number of feed entries successfully sanitized and processed",
     );
-    let c = IntCounter::with_opts(opts).expect("counter opts");
+    let c = IntCounterVec::new(opts, &["feed"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Known entries arriving with a changed title, summary or content, by feed
+pub static ENTRIES_UPDATED: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "entries_updated_total",
+        "Total number of known entries whose title, summary or content changed",
+    );
+    let c = IntCounterVec::new(opts, &["feed"]).expect("counter opts");
     REGISTRY.register(Box::new(c.clone())).unwrap();
     c
 });
@@ -70,6 +552,28 @@ pub static CONTENT_FIELD_COUNT: Lazy<IntCounter> = Lazy::new(|| {
     c
 });
 
+/// Publisher pings received on `/ping` and `/websub/`, by outcome
+pub static PINGS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts = Opts::new(
+        "pings_total",
+        "Total number of feed pings from publishers, by outcome",
+    );
+    let c = IntCounterVec::new(opts, &["outcome"]).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
+/// Requests refused for targeting internal addresses (see `urlguard`)
+pub static UNSAFE_URLS_BLOCKED: Lazy<IntCounter> = Lazy::new(|| {
+    let opts = Opts::new(
+        "unsafe_urls_blocked_total",
+        "Total number of requests refused for targeting an internal address",
+    );
+    let c = IntCounter::with_opts(opts).expect("counter opts");
+    REGISTRY.register(Box::new(c.clone())).unwrap();
+    c
+});
+
 pub static SUMMARY_FALLBACK_COUNT: Lazy<IntCounter> = Lazy::new(|| {
     let opts = Opts::new(
         "feed_summary_fallback_entries_total",
@@ -0,0 +1,188 @@
+//! Feed body parsing: per-feed pre-parse fixups, user-registered custom parsers,
+//! and a single repair-and-retry pass for malformed XML.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use feed_rs::model::Feed;
+use feed_rs::parser::{self, ParseFeedError};
+use once_cell::sync::Lazy;
+use tracing::info;
+
+use crate::config::{self, ParserOptions};
+use crate::jsonfeed;
+use crate::mbox;
+use crate::metrics::XML_REPAIRS;
+
+/// A custom parser turning a (pre-processed) response body into a feed.
+pub type ParserFn = Arc<dyn Fn(&[u8]) -> Result<Feed, ParseFeedError> + Send + Sync>;
+
+/// Custom parsers keyed by feed name.
+static CUSTOM_PARSERS: Lazy<RwLock<HashMap<String, ParserFn>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Register `parser` to be used instead of `feed_rs` for the feed named `feed_name`.
+/// Replaces any parser previously registered for that feed.
+pub fn register_parser<F>(feed_name: &str, parser: F)
+where
+    F: Fn(&[u8]) -> Result<Feed, ParseFeedError> + Send + Sync + 'static,
+{
+    CUSTOM_PARSERS
+        .write()
+        .expect("parser registry poisoned")
+        .insert(feed_name.to_string(), Arc::new(parser));
+}
+
+/// Remove the custom parser registered for `feed_name`, if any.
+pub fn unregister_parser(feed_name: &str) {
+    CUSTOM_PARSERS
+        .write()
+        .expect("parser registry poisoned")
+        .remove(feed_name);
+}
+
+/// Apply the feed's parser options, then parse with its custom parser, the JSON Feed
+/// parser (for bodies starting with `{`), the mbox parser (for bodies starting with
+/// `From `), or `feed_rs`.
+/// If an XML body fails to parse, common defects are repaired and the parse retried once.
+pub fn parse_feed(source: &config::Feed, body: &[u8]) -> Result<Feed, ParseFeedError> {
+    let body = preprocess(&source.parser, body);
+    let err = match parse_raw(&source.name, &body) {
+        Ok(feed) => return Ok(feed),
+        Err(e) => e,
+    };
+
+    if jsonfeed::is_json(&body) || mbox::is_mbox(&body) {
+        return Err(err);
+    }
+    let repaired = repair_xml(&body);
+    if repaired[..] == body[..] {
+        return Err(err);
+    }
+    match parse_raw(&source.name, &repaired) {
+        Ok(feed) => {
+            XML_REPAIRS
+                .with_label_values(&[source.name.as_str(), "success"])
+                .inc();
+            info!(feed = %source.name, error = %err, "Parsed feed after repairing malformed XML");
+            Ok(feed)
+        }
+        Err(_) => {
+            XML_REPAIRS
+                .with_label_values(&[source.name.as_str(), "failure"])
+                .inc();
+            Err(err)
+        }
+    }
+}
+
+fn parse_raw(feed_name: &str, body: &[u8]) -> Result<Feed, ParseFeedError> {
+    let custom = CUSTOM_PARSERS
+        .read()
+        .expect("parser registry poisoned")
+        .get(feed_name)
+        .cloned();
+    match custom {
+        Some(parse) => parse(body),
+        None if jsonfeed::is_json(body) => jsonfeed::parse(body),
+        None if mbox::is_mbox(body) => mbox::parse(body),
+        None => parser::parse(body),
+    }
+}
+
+/// Fix the most common defects of hand-rolled XML feeds:
+/// a leading BOM or whitespace, stray `&` characters, and forbidden control characters.
+pub fn repair_xml(body: &[u8]) -> Vec<u8> {
+    let body = body.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(body);
+    let start = body
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(body.len());
+    let body = &body[start..];
+
+    let mut out = Vec::with_capacity(body.len());
+    for (i, b) in body.iter().enumerate() {
+        match *b {
+            b'&' if !is_entity_ref(&body[i + 1..]) => out.extend_from_slice(b"&amp;"),
+            b'\t' | b'\n' | b'\r' => out.push(*b),
+            c if c < 0x20 => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Whether `rest` (the bytes following an `&`) starts a well-formed entity reference.
+fn is_entity_ref(rest: &[u8]) -> bool {
+    let Some(end) = rest.iter().take(32).position(|b| *b == b';') else {
+        return false;
+    };
+    let name = &rest[..end];
+    match name {
+        [b'#', b'x' | b'X', hex @ ..] => !hex.is_empty() && hex.iter().all(u8::is_ascii_hexdigit),
+        [b'#', dec @ ..] => !dec.is_empty() && dec.iter().all(u8::is_ascii_digit),
+        [first, tail @ ..] => {
+            first.is_ascii_alphabetic()
+                && tail
+                    .iter()
+                    .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_'))
+        }
+        [] => false,
+    }
+}
+
+/// Rewrite the raw body according to `options`, borrowing when nothing changes.
+pub fn preprocess<'a>(options: &ParserOptions, body: &'a [u8]) -> Cow<'a, [u8]> {
+    let mut body = Cow::Borrowed(body);
+
+    if options.lenient {
+        body = Cow::Owned(lenient_cleanup(&body));
+    }
+
+    for (from, to) in &options.namespaces {
+        let from = format!("\"{}\"", from);
+        let to = format!("\"{}\"", to);
+        body = Cow::Owned(replace_all(&body, from.as_bytes(), to.as_bytes()));
+    }
+
+    for fixup in &options.fixups {
+        let replaced = fixup
+            .pattern
+            .regex()
+            .replace_all(&body, fixup.replacement.as_bytes());
+        if let Cow::Owned(out) = replaced {
+            body = Cow::Owned(out);
+        }
+    }
+
+    body
+}
+
+/// Drop anything before the document root and any control characters XML forbids.
+fn lenient_cleanup(body: &[u8]) -> Vec<u8> {
+    let start = body
+        .iter()
+        .position(|b| *b == b'<' || *b == b'{')
+        .unwrap_or(0);
+    body[start..]
+        .iter()
+        .copied()
+        .filter(|b| *b >= 0x20 || matches!(b, b'\t' | b'\n' | b'\r'))
+        .collect()
+}
+
+fn replace_all(haystack: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(haystack.len());
+    let mut i = 0;
+    while i < haystack.len() {
+        if !from.is_empty() && haystack[i..].starts_with(from) {
+            out.extend_from_slice(to);
+            i += from.len();
+        } else {
+            out.push(haystack[i]);
+            i += 1;
+        }
+    }
+    out
+}
@@ -0,0 +1,157 @@
+//! Publisher pings. A publisher announcing a new post, by XML-RPC `weblogUpdates.ping`
+//! or `weblogUpdates.extendedPing` to `/ping` or by WebSub content distribution to
+//! `/websub/{feed}`, gets the matching feed fetched at once rather than at the next
+//! cycle. Each feed is fetched for pings at most every `min_interval`; pings within it
+//! are acknowledged and ignored. Pinged runs happen between cycles, so they never
+//! overlap a cycle's run of the same feed. Outcomes are counted in `pings_total{outcome}`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+
+use crate::config::{Feed, PingSettings};
+use crate::metrics::PINGS;
+
+/// Pinged feeds waiting for the ingestion loop; more are dropped.
+const QUEUE: usize = 100;
+
+static METHOD_NAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<methodName>\s*([^<]*?)\s*</methodName>").unwrap());
+
+/// A `<value>` holding a string, with or without the `<string>` element.
+static STRING_VALUE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<value>\s*(?:<string>([^<]*)</string>|([^<]*))\s*</value>").unwrap());
+
+/// Accepts pings and hands the pinged feeds to the ingestion loop.
+#[derive(Clone)]
+pub struct Pings {
+    min_interval: Duration,
+    feeds: watch::Receiver<Arc<Vec<Feed>>>,
+    /// When each feed was last queued
+    queued: Arc<Mutex<HashMap<String, Instant>>>,
+    tx: mpsc::Sender<String>,
+}
+
+/// What became of a ping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The feed will be fetched shortly
+    Queued,
+    /// The feed was queued less than `min_interval` ago
+    TooSoon,
+    /// The queue is full
+    Dropped,
+}
+
+impl Outcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Queued => "queued",
+            Outcome::TooSoon => "too_soon",
+            Outcome::Dropped => "dropped",
+        }
+    }
+}
+
+impl Pings {
+    /// Pings for the current feed list; the receiver yields the names of feeds to fetch.
+    pub fn new(
+        settings: &PingSettings,
+        feeds: watch::Receiver<Arc<Vec<Feed>>>,
+    ) -> (Self, mpsc::Receiver<String>) {
+        let (tx, rx) = mpsc::channel(QUEUE);
+        let pings = Pings {
+            min_interval: settings.min_interval,
+            feeds,
+            queued: Arc::default(),
+            tx,
+        };
+        (pings, rx)
+    }
+
+    /// The configured feed called `name`.
+    pub fn feed(&self, name: &str) -> Option<Feed> {
+        self.feeds.borrow().iter().find(|f| f.name == name).cloned()
+    }
+
+    /// Names of the feeds at one of `urls` or below it (a site URL covers its feeds).
+    pub fn feeds_at(&self, urls: &[String]) -> Vec<String> {
+        let prefixes: Vec<&str> = urls
+            .iter()
+            .map(|u| u.trim().trim_end_matches('/'))
+            .filter(|u| u.starts_with("http://") || u.starts_with("https://"))
+            .collect();
+        self.feeds
+            .borrow()
+            .iter()
+            .filter(|feed| {
+                let url = feed.url.trim_end_matches('/');
+                prefixes
+                    .iter()
+                    .any(|p| url == *p || url.strip_prefix(p).is_some_and(|r| r.starts_with('/')))
+            })
+            .map(|feed| feed.name.clone())
+            .collect()
+    }
+
+    /// Queue the feed called `name` for a fetch, unless it was queued too recently.
+    pub fn ping(&self, name: &str) -> Outcome {
+        let outcome = {
+            let mut queued = self.queued.lock().expect("ping times poisoned");
+            let now = Instant::now();
+            match queued.get(name) {
+                Some(at) if now.duration_since(*at) < self.min_interval => Outcome::TooSoon,
+                _ if self.tx.try_send(name.to_string()).is_err() => Outcome::Dropped,
+                _ => {
+                    queued.insert(name.to_string(), now);
+                    Outcome::Queued
+                }
+            }
+        };
+        PINGS.with_label_values(&[outcome.as_str()]).inc();
+        outcome
+    }
+}
+
+/// The next pinged feed, or never if pings are disabled.
+pub async fn next(rx: &mut Option<mpsc::Receiver<String>>) -> String {
+    match rx {
+        Some(rx) => match rx.recv().await {
+            Some(name) => name,
+            None => std::future::pending().await,
+        },
+        None => std::future::pending().await,
+    }
+}
+
+/// The string parameters of a `weblogUpdates.ping` or `weblogUpdates.extendedPing`
+/// call: site name, site URL and, for an extended ping, the changed page and feed URL.
+pub fn weblog_ping_params(body: &str) -> Option<Vec<String>> {
+    let method = METHOD_NAME.captures(body)?.get(1)?.as_str();
+    if method != "weblogUpdates.ping" && method != "weblogUpdates.extendedPing" {
+        return None;
+    }
+    let params = STRING_VALUE
+        .captures_iter(body)
+        .filter_map(|c| c.get(1).or_else(|| c.get(2)))
+        .map(|m| htmlescape::decode_html(m.as_str()).unwrap_or_else(|_| m.as_str().to_string()))
+        .collect();
+    Some(params)
+}
+
+/// The XML-RPC reply to a weblogs ping.
+pub fn weblog_response(error: bool, message: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\"?>\n<methodResponse><params><param><value><struct>\
+        <member><name>flerror</name><value><boolean>{}</boolean></value></member>\
+        <member><name>message</name><value>{}</value></member>\
+        </struct></value></param></params></methodResponse>\n",
+        u8::from(error),
+        htmlescape::encode_minimal(message)
+    )
+}
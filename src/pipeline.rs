@@ -0,0 +1,921 @@
+//! One feed's ingestion run: quota and canary checks, conditional fetch, map, sanitize,
+//! store, and usage and history accounting. The binary drives this once per feed per cycle.
+//! While Postgres is unreachable, writes go to the disk spool and database bookkeeping
+//! is skipped; the next cycle that reaches it stores the spool first.
+
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use sqlx::postgres::PgConnection;
+use sqlx::{Connection, PgPool};
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::canary;
+use crate::certs;
+use crate::config::{Feed, Quota, RetrySettings};
+use crate::costs;
+use crate::dedup;
+use crate::embed::EmbeddingQueue;
+use crate::enclosures::EnclosureDownloads;
+use crate::enrich;
+use crate::errors::IngestError;
+use crate::history::{self, FetchRecord, FetchStatus};
+use crate::http::HttpClients;
+use crate::icons;
+use crate::ingestor::{
+    content_source, entry_to_feed_item, fetch_feed, sanitize_and_validate, CacheValidators, Change,
+    FeedItem, FetchOutcome,
+};
+use crate::inspect::{EntryTrace, LastRuns, RunSnapshot};
+use crate::links::Unshortener;
+use crate::metrics::{
+    CANARY_STAGED, ENTRIES_BY_LANGUAGE, ENTRIES_BY_TAG, ENTRIES_PROCESSED, ENTRIES_UPDATED,
+    ENTRY_TIMEOUTS, FEED_ROLLBACKS, QUOTA_SKIPS, SANITIZATION_FAILURES, SPILLOVER_BACKLOG,
+    SPILLOVER_DEFERRED, SPOOL_DRAINED, SPOOL_REJECTED,
+};
+use crate::scheduler::Schedule;
+use crate::shutdown::Shutdown;
+use crate::sinks::Sinks;
+use crate::spillover::{self, CycleBudget};
+use crate::spool::Spool;
+use crate::storage::Storage;
+use crate::summarize::SummaryQueue;
+use crate::tagging;
+
+/// How long a fresh connection may take before the database counts as unreachable
+/// (the pool itself would wait its whole acquire timeout)
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Spooled entries read from disk at a time while draining
+const SPOOL_DRAIN_BATCH: usize = 500;
+
+/// Everything a feed run needs besides the feed itself.
+#[derive(Clone)]
+pub struct Pipeline {
+    /// Where entries and per-feed state are stored
+    pub storage: Arc<dyn Storage>,
+    pub schedule: Schedule,
+    /// Daily limits across all feeds combined
+    pub global_quota: Quota,
+    /// Outputs receiving every stored entry
+    pub sinks: Sinks,
+    /// Runs a new or modified feed spends in canary (0 = disabled)
+    pub canary_cycles: u32,
+    /// Runs kept per feed in `fetch_history` (0 = disabled)
+    pub fetch_history_rows: u32,
+    /// Feed-fetching clients, one per proxy
+    pub clients: HttpClients,
+    /// Backoff policy for transient fetch failures
+    pub retry: RetrySettings,
+    /// Response headers kept in `fetch_history`
+    pub capture_headers: Vec<String>,
+    /// Deadline for storing and publishing a single entry
+    pub entry_timeout: Duration,
+    /// Once triggered, no new feed run starts and running ones stop between entries
+    pub shutdown: Shutdown,
+    /// Items the current cycle may still store before deferring to spillover
+    pub budget: CycleBudget,
+    /// Log each item instead of storing it, and skip every other database write
+    pub dry_run: bool,
+    /// Disk buffer for entries written while the database is unreachable
+    pub spool: Spool,
+    /// Entries new to the archive waiting for an LLM summary
+    pub summaries: SummaryQueue,
+    /// Entries new to the archive waiting for a vector embedding
+    pub embeddings: EmbeddingQueue,
+    /// Enclosures of entries new to the archive waiting for download
+    pub enclosures: EnclosureDownloads,
+    /// Resolves entry links through URL shorteners before storing
+    pub unshortener: Unshortener,
+    /// Each feed's latest run, entry by entry, for `/debug/feeds/{name}/last`
+    pub last_runs: LastRuns,
+}
+
+/// What `store` did with an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stored {
+    /// Archived for the first time
+    New,
+    /// Archived for the first time, linked to the earlier entry it nearly duplicates
+    Duplicate,
+    /// Already archived with another title, summary or content: `current` was
+    /// refreshed and a revision recorded
+    Updated,
+    /// Already archived; only `current` was refreshed
+    Known,
+    /// Held back while the feed is in canary
+    Staged,
+    /// Past the cycle's hard item limit, so queued in spillover
+    Deferred,
+    /// Written to the disk spool during a database outage
+    Spooled,
+}
+
+impl Stored {
+    fn written(change: Change) -> Self {
+        match change {
+            Change::New => Stored::New,
+            Change::Duplicate => Stored::Duplicate,
+            Change::Updated => Stored::Updated,
+            Change::Unchanged => Stored::Known,
+        }
+    }
+
+    pub fn is_new(self) -> bool {
+        self == Stored::New
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stored::New => "new",
+            Stored::Duplicate => "duplicate",
+            Stored::Updated => "updated",
+            Stored::Known => "known",
+            Stored::Staged => "staged",
+            Stored::Deferred => "deferred",
+            Stored::Spooled => "spooled",
+        }
+    }
+}
+
+/// Counts a write transaction of `feed` in `FEED_ROLLBACKS` unless marked committed:
+/// when it fails, and when a timeout abandons it midway (dropping it rolls it back).
+struct RollbackCount<'a> {
+    feed: &'a str,
+    committed: bool,
+}
+
+impl<'a> RollbackCount<'a> {
+    fn new(feed: &'a str) -> Self {
+        RollbackCount {
+            feed,
+            committed: false,
+        }
+    }
+
+    fn committed(mut self) {
+        self.committed = true;
+    }
+}
+
+impl Drop for RollbackCount<'_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            FEED_ROLLBACKS.with_label_values(&[self.feed]).inc();
+        }
+    }
+}
+
+/// Outcome of one feed run, aggregated into the cycle summary.
+#[derive(Debug, Clone)]
+pub struct FeedRun {
+    pub feed: String,
+    pub duration_s: f64,
+    pub entries: usize,
+    pub errors: usize,
+    /// The feed answered: fetched, or unchanged since the last run
+    pub reached: bool,
+}
+
+impl FeedRun {
+    /// A run that did not start.
+    fn skipped(feed: &Feed) -> Self {
+        FeedRun {
+            feed: feed.name.clone(),
+            duration_s: 0.0,
+            entries: 0,
+            errors: 0,
+            reached: false,
+        }
+    }
+}
+
+impl Pipeline {
+    /// Reset the cycle's item budget, store whatever was spooled during a database
+    /// outage, then store a batch of previously spilled items.
+    pub async fn start_cycle(&self) {
+        self.budget.reset();
+        if self.dry_run {
+            return;
+        }
+        if self.spool.in_outage() || !self.spool.is_empty() {
+            if !self.database_reachable().await {
+                self.spool.begin_outage();
+                warn!(
+                    spooled = self.spool.len(),
+                    "Database still unreachable; spooling this cycle's entries"
+                );
+                return;
+            }
+            self.drain_spool().await;
+        }
+        // Stored tag rules and spillover live in Postgres only
+        let Some(pool) = self.storage.postgres() else {
+            return;
+        };
+        if let Err(e) = tagging::refresh(pool).await {
+            warn!(error = %e, "Failed to reload stored tag rules");
+        }
+        let spilled = match spillover::oldest(pool, self.budget.drain_per_cycle()).await {
+            Ok(spilled) => spilled,
+            Err(e) => {
+                if self.spooling() && e.is_db_unavailable() {
+                    self.begin_outage(&e);
+                }
+                warn!(error = %e, "Failed to load spillover queue");
+                return;
+            }
+        };
+        if !spilled.is_empty() {
+            info!(
+                count = spilled.len(),
+                "Storing items from the spillover queue"
+            );
+        }
+        for s in spilled {
+            if self.shutdown.is_triggered() || !self.budget.try_take() {
+                break;
+            }
+            let stored = async {
+                let rollback = RollbackCount::new(&s.feed_name);
+                let change = self.storage.process_entry(&s.item).await?;
+                rollback.committed();
+                self.stored(&s.feed_name, &s.item, change);
+                spillover::remove(pool, s.id).await
+            };
+            if let Err(e) = stored.await {
+                error!(feed = %s.feed_name, guid = %s.item.guid, error = %e, "Failed to store spilled item");
+            }
+        }
+    }
+
+    /// Store spooled entries, oldest first, until the spool is empty or the database
+    /// drops again. Past the cycle budget they move on to spillover as usual.
+    async fn drain_spool(&self) {
+        if self.spool.end_outage() {
+            info!("Database reachable again; leaving outage mode");
+        }
+        let mut drained = 0;
+        while !self.shutdown.is_triggered() {
+            let batch = match self.spool.oldest(SPOOL_DRAIN_BATCH).await {
+                Ok(batch) if !batch.is_empty() => batch,
+                Ok(_) => break,
+                Err(e) => {
+                    warn!(error = %e, "Failed to read the spool");
+                    break;
+                }
+            };
+            for (path, entry) in batch {
+                if self.shutdown.is_triggered() {
+                    break;
+                }
+                let canary_left = u32::from(entry.staged);
+                let done = match self.write(&entry.feed_name, &entry.item, canary_left).await {
+                    Ok(_) => {
+                        drained += 1;
+                        SPOOL_DRAINED.inc();
+                        self.spool.remove(&path, &entry).await
+                    }
+                    Err(e) if e.is_db_unavailable() => {
+                        self.begin_outage(&e);
+                        info!(
+                            drained,
+                            left = self.spool.len(),
+                            "Stopped draining the spool"
+                        );
+                        return;
+                    }
+                    Err(e) => {
+                        error!(feed = %entry.feed_name, guid = %entry.item.guid, error = %e, "Failed to store spooled entry; setting it aside");
+                        self.spool.discard(&path, Some(&entry)).await
+                    }
+                };
+                if let Err(e) = done {
+                    warn!(error = %e, "Failed to update the spool");
+                    return;
+                }
+            }
+        }
+        if drained > 0 {
+            info!(drained, left = self.spool.len(), "Stored spooled entries");
+        }
+    }
+
+    /// Check the TLS certificates feed hosts presented, and publish the spillover
+    /// backlog left after this cycle.
+    pub async fn finish_cycle(&self) {
+        if self.spool.in_outage() {
+            return;
+        }
+        let Some(pool) = self.storage.postgres() else {
+            return;
+        };
+        if !self.dry_run {
+            if let Err(e) = certs::record_observed(pool).await {
+                warn!(error = %e, "Failed to record TLS certificates");
+            }
+        }
+        match spillover::backlog(pool).await {
+            Ok(count) => {
+                SPILLOVER_BACKLOG.set(count);
+                if count > 0 {
+                    warn!(backlog = count, "Spillover queue not empty after cycle");
+                }
+            }
+            Err(e) => warn!(error = %e, "Failed to count spillover queue"),
+        }
+    }
+
+    /// Fetch `feed` and store its entries, recording schedule, usage and history.
+    #[instrument(skip_all, fields(feed = %feed.name))]
+    pub async fn run_feed(&self, feed: &Feed) -> FeedRun {
+        let feed_start = Instant::now();
+        let run_started = Utc::now();
+
+        if self.shutdown.is_triggered() {
+            debug!(feed = %feed.name, "Shutting down; not starting feed");
+            return FeedRun::skipped(feed);
+        }
+        if !self.schedule.is_due(&feed.name, run_started) {
+            debug!(feed = %feed.name, "Backing off after failures; not due yet");
+            return FeedRun::skipped(feed);
+        }
+
+        let mut traces = Vec::new();
+        let record = self.fetch_and_store(feed, run_started, &mut traces).await;
+        let elapsed = feed_start.elapsed();
+        self.last_runs.record(
+            &feed.name,
+            RunSnapshot {
+                started_at: run_started,
+                status: record.status.as_str(),
+                http_status: record.http_status,
+                error: record.error.clone(),
+                entries: traces,
+            },
+        );
+        if self.fetch_history_rows > 0 && !self.dry_run && !self.spool.in_outage() {
+            if let Err(e) = self
+                .storage
+                .record_history(
+                    &feed.name,
+                    run_started.naive_utc(),
+                    elapsed.as_millis() as i64,
+                    &record,
+                    self.fetch_history_rows,
+                )
+                .await
+            {
+                warn!(feed = %feed.name, error = %e, "Failed to record fetch history");
+            }
+        }
+        if !self.dry_run && !self.spool.in_outage() {
+            if let Some(entry) = self.schedule.get(&feed.name) {
+                if let Err(e) = self.storage.save_schedule(&entry).await {
+                    warn!(feed = %feed.name, error = %e, "Failed to save feed schedule");
+                }
+            }
+        }
+        FeedRun {
+            feed: feed.name.clone(),
+            duration_s: elapsed.as_secs_f64(),
+            entries: record.entries_seen,
+            errors: record.errors,
+            reached: matches!(
+                record.status,
+                FetchStatus::Fetched | FetchStatus::NotModified | FetchStatus::Unchanged
+            ),
+        }
+    }
+
+    /// One run of `feed`, noting what became of each fetched entry in `traces`.
+    async fn fetch_and_store(
+        &self,
+        feed: &Feed,
+        run_started: DateTime<Utc>,
+        traces: &mut Vec<EntryTrace>,
+    ) -> FetchRecord {
+        let feed_start = Instant::now();
+        let feed_name = feed.name.as_str();
+        let feed_url = feed.url.as_str();
+        // During an outage every lookup would only wait out the pool's acquire timeout
+        let offline = self.spool.in_outage();
+
+        let quota = if offline {
+            Ok(None)
+        } else {
+            costs::quota_exceeded(&*self.storage, feed, &self.global_quota).await
+        };
+        match quota {
+            Ok(Some(reason)) => {
+                QUOTA_SKIPS.with_label_values(&[feed_name]).inc();
+                info!(feed = %feed_name, reason = %reason, "Quota reached; skipping feed");
+                return FetchRecord::new(FetchStatus::QuotaSkipped, None);
+            }
+            Ok(None) => {}
+            Err(e) => warn!(feed = %feed_name, error = %e, "Failed to check quota"),
+        }
+
+        // A dry run neither registers the feed for canary nor sends cache validators,
+        // so every run shows the whole feed as it would be stored
+        let canary_left = match self.storage.postgres() {
+            Some(pool) if !self.dry_run && !offline => {
+                canary::remaining_cycles(pool, feed, self.canary_cycles)
+                    .await
+                    .unwrap_or_else(|e| {
+                        warn!(feed = %feed_name, error = %e, "Failed to check canary state");
+                        0
+                    })
+            }
+            _ => 0,
+        };
+
+        let cached = if self.dry_run || offline {
+            CacheValidators::default()
+        } else {
+            self.storage
+                .load_validators(feed_url)
+                .await
+                .unwrap_or_else(|e| {
+                    warn!(feed = %feed_name, error = %e, "Failed to load cache validators");
+                    CacheValidators::default()
+                })
+        };
+
+        let mut headers = HeaderMap::new();
+        let fetched = fetch_feed(
+            &self.clients.for_feed(feed),
+            feed,
+            &cached,
+            &self.retry,
+            &mut headers,
+        )
+        .await;
+        let record = match fetched {
+            Ok(FetchOutcome::NotModified) => {
+                info!(
+                    feed = %feed_name,
+                    url = %feed_url,
+                    duration_s = feed_start.elapsed().as_secs_f64(),
+                    "Feed not modified; skipping"
+                );
+                self.record_usage(feed_name, 0, 0).await;
+                if canary_left > 0 {
+                    self.count_canary_cycle(feed_name).await;
+                }
+                self.schedule.record_run(feed_name, run_started, None);
+                FetchRecord::new(FetchStatus::NotModified, Some(304))
+            }
+            Ok(FetchOutcome::Unchanged {
+                status,
+                validators,
+                bytes,
+                compressed_bytes,
+            }) => {
+                info!(
+                    feed = %feed_name,
+                    url = %feed_url,
+                    duration_s = feed_start.elapsed().as_secs_f64(),
+                    "Feed body unchanged; skipping"
+                );
+                self.store_validators(feed, &validators).await;
+                self.record_usage(feed_name, bytes, 0).await;
+                if canary_left > 0 {
+                    self.count_canary_cycle(feed_name).await;
+                }
+                self.schedule.record_run(feed_name, run_started, None);
+                FetchRecord::new(FetchStatus::Unchanged, Some(status))
+                    .with_bytes(compressed_bytes, bytes)
+            }
+            Ok(FetchOutcome::Fetched {
+                status,
+                feed: feed_struct,
+                elements,
+                validators,
+                bytes,
+                compressed_bytes,
+            }) => {
+                let count = feed_struct.entries.len();
+                info!(
+                    feed = %feed_name,
+                    url = %feed_url,
+                    count = count,
+                    duration_s = feed_start.elapsed().as_secs_f64(),
+                    "Fetched feed"
+                );
+                if let Some(pool) = self
+                    .storage
+                    .postgres()
+                    .filter(|_| !self.dry_run && !offline)
+                {
+                    icons::cache_in_background(
+                        pool.clone(),
+                        self.clients.for_feed(feed),
+                        feed,
+                        &feed_struct,
+                    );
+                }
+                let mut record = FetchRecord::new(FetchStatus::Fetched, Some(status))
+                    .with_bytes(compressed_bytes, bytes);
+                record.entries_seen = count;
+                let mut rows: usize = 0;
+                let mut write_failed = false;
+                let mut items = Vec::with_capacity(count);
+                // Index into `traces` of each of `items`
+                let mut traced = Vec::with_capacity(count);
+                for (i, entry) in feed_struct.entries.iter().enumerate() {
+                    let feed_item = entry_to_feed_item(entry, &feed_struct, feed, elements.get(i));
+                    let mut trace = EntryTrace {
+                        guid: feed_item.guid.clone(),
+                        title: feed_item.title.clone(),
+                        content_source: content_source(entry),
+                        sanitization: "passed".to_string(),
+                        dedup: None,
+                    };
+                    let sanitizer = feed.sanitize.sanitizer();
+                    match sanitize_and_validate(&feed_item, sanitizer, feed.limits) {
+                        Ok(safe_item) => {
+                            items.push(tagging::with_tags(dedup::with_simhash(
+                                enrich::with_language(safe_item),
+                            )));
+                            traced.push(traces.len());
+                        }
+                        Err(reason) => {
+                            trace.sanitization = reason.to_string();
+                            record.errors += 1;
+                            SANITIZATION_FAILURES.with_label_values(&[feed_name]).inc();
+                            warn!(
+                                feed = %feed_name,
+                                entry_id = ?entry.id,
+                                "Entry failed sanitization/validation and was skipped"
+                            );
+                        }
+                    }
+                    traces.push(trace);
+                }
+                self.unshortener
+                    .resolve(&self.clients.for_feed(feed), &mut items)
+                    .await;
+                let mut trace = |i: usize, outcome: String| traces[traced[i]].dedup = Some(outcome);
+                // Store what the budget allows in one batch; the rest (or everything,
+                // if the batch fails) goes through `store` one entry at a time
+                let mut batched = 0;
+                if canary_left == 0
+                    && !self.dry_run
+                    && !self.spool.in_outage()
+                    && !self.shutdown.is_triggered()
+                {
+                    if let Some(changes) = self.store_batch(feed_name, &items).await {
+                        batched = changes.len();
+                        rows += batched;
+                        record.entries_new += changes.iter().filter(|c| c.is_new()).count();
+                        for (i, change) in changes.iter().enumerate() {
+                            trace(i, Stored::written(*change).as_str().to_string());
+                        }
+                        ENTRIES_PROCESSED
+                            .with_label_values(&[feed_name])
+                            .inc_by(batched as u64);
+                    }
+                }
+                for (i, item) in items.iter().enumerate().skip(batched) {
+                    if self.shutdown.is_triggered() {
+                        // Leave the rest (and the validators) for the next start
+                        warn!(feed = %feed_name, stored = i, "Shutting down mid-feed");
+                        write_failed = true;
+                        for rest in i..items.len() {
+                            trace(rest, "skipped: shutting down".to_string());
+                        }
+                        break;
+                    }
+                    let stored = match tokio::time::timeout(
+                        self.entry_timeout,
+                        self.store(feed_name, item, canary_left),
+                    )
+                    .await
+                    {
+                        // The pool may still be waiting for a connection: check directly
+                        Err(elapsed) if self.spooling() && !self.database_reachable().await => {
+                            self.begin_outage(&elapsed);
+                            Ok(self.spool_entry(feed_name, item, canary_left > 0).await)
+                        }
+                        stored => stored,
+                    };
+                    match stored {
+                        Ok(Ok(stored)) => {
+                            rows += 1;
+                            if stored.is_new() {
+                                record.entries_new += 1;
+                            }
+                            trace(i, stored.as_str().to_string());
+                            if !self.dry_run {
+                                ENTRIES_PROCESSED.with_label_values(&[feed_name]).inc();
+                            }
+                        }
+                        Err(_) => {
+                            record.errors += 1;
+                            write_failed = true;
+                            trace(i, "failed: timed out".to_string());
+                            ENTRY_TIMEOUTS.with_label_values(&[feed_name]).inc();
+                            error!(
+                                feed = %feed_name,
+                                guid = %item.guid,
+                                timeout_s = self.entry_timeout.as_secs_f64(),
+                                "Entry processing timed out"
+                            );
+                        }
+                        Ok(Err(e)) => {
+                            record.errors += 1;
+                            write_failed = true;
+                            trace(i, format!("failed: {}", e));
+                            error!(
+                                feed = %feed_name,
+                                guid = %item.guid,
+                                error = %e,
+                                "Failed to process entry"
+                            );
+                        }
+                    }
+                }
+                // Only remember validators once every entry is stored, so a failed
+                // or interrupted write is retried next cycle instead of cached away.
+                if self.dry_run {
+                    info!(feed = %feed_name, entries = rows, new = record.entries_new, "Dry run: nothing stored");
+                } else if !self.spool.in_outage() {
+                    if !write_failed {
+                        self.store_validators(feed, &validators).await;
+                    }
+                    self.record_usage(feed_name, bytes, rows).await;
+                }
+                if canary_left > 0 {
+                    self.count_canary_cycle(feed_name).await;
+                }
+                self.schedule.record_run(feed_name, run_started, None);
+                record
+            }
+            Err(e) => {
+                error!(
+                    feed = %feed_name,
+                    url = %feed_url,
+                    error = %e,
+                    duration_s = feed_start.elapsed().as_secs_f64(),
+                    "Failed to fetch feed"
+                );
+                self.schedule
+                    .record_run(feed_name, run_started, Some(e.to_string()));
+                let mut record = FetchRecord::new(FetchStatus::Failed, e.http_status());
+                record.errors = 1;
+                record.error = Some(e.to_string());
+                record
+            }
+        };
+        record.with_headers(history::capture(&headers, &self.capture_headers))
+    }
+
+    /// `write` the item, or spool it to disk if the database is unreachable.
+    async fn store(
+        &self,
+        feed_name: &str,
+        item: &FeedItem,
+        canary_left: u32,
+    ) -> Result<Stored, IngestError> {
+        if !self.spooling() {
+            return self.write(feed_name, item, canary_left).await;
+        }
+        if !self.spool.in_outage() {
+            match self.write(feed_name, item, canary_left).await {
+                Err(e) if e.is_db_unavailable() => self.begin_outage(&e),
+                written => return written,
+            }
+        }
+        self.spool_entry(feed_name, item, canary_left > 0).await
+    }
+
+    /// Store a sanitized item and hand it to the sinks, stage it while in canary, or
+    /// defer it once the cycle's hard item limit is reached.
+    /// In a dry run the item is only logged, and the result says what would be done.
+    async fn write(
+        &self,
+        feed_name: &str,
+        item: &FeedItem,
+        canary_left: u32,
+    ) -> Result<Stored, IngestError> {
+        if canary_left > 0 {
+            canary::stage(self.storage.require_postgres("canary")?, feed_name, item).await?;
+            CANARY_STAGED.with_label_values(&[feed_name]).inc();
+            return Ok(Stored::Staged);
+        }
+        if !self.budget.try_take() {
+            if self.dry_run {
+                info!(feed = %feed_name, guid = %item.guid, "Dry run: would defer to spillover");
+                return Ok(Stored::Deferred);
+            }
+            spillover::defer(self.storage.require_postgres("spillover")?, feed_name, item).await?;
+            SPILLOVER_DEFERRED.with_label_values(&[feed_name]).inc();
+            if self.budget.first_spill() {
+                warn!(feed = %feed_name, "Cycle hard item limit reached; deferring items to spillover");
+            }
+            return Ok(Stored::Deferred);
+        }
+        if self.dry_run {
+            return self.preview(feed_name, item).await.map(Stored::written);
+        }
+        let rollback = RollbackCount::new(feed_name);
+        let change = self.storage.process_entry(item).await?;
+        rollback.committed();
+        self.stored(feed_name, item, change);
+        Ok(Stored::written(change))
+    }
+
+    /// Count a stored item, queue it for a summary and its enclosures for download if
+    /// new, and hand it to the sinks. A near duplicate goes no further: the story it
+    /// repeats was already summarized and delivered.
+    fn stored(&self, feed_name: &str, item: &FeedItem, change: Change) {
+        if change == Change::Duplicate {
+            debug!(feed = %feed_name, guid = %item.guid, "Near duplicate not passed on");
+            return;
+        }
+        if change == Change::Updated {
+            ENTRIES_UPDATED.with_label_values(&[feed_name]).inc();
+            info!(feed = %feed_name, guid = %item.guid, title = %item.title, "Known entry was updated");
+        }
+        if change.is_new() {
+            let language = item.detected_language.as_deref().unwrap_or("und");
+            ENTRIES_BY_LANGUAGE.with_label_values(&[language]).inc();
+            for tag in item.tags.iter().flatten() {
+                ENTRIES_BY_TAG.with_label_values(&[tag]).inc();
+            }
+            self.summaries.submit(item);
+            self.embeddings.submit(item);
+            self.enclosures.submit(item);
+        }
+        self.sinks.publish(feed_name, item, change);
+    }
+
+    /// Whether unreachable-database writes go to the spool.
+    fn spooling(&self) -> bool {
+        self.spool.is_enabled() && !self.dry_run
+    }
+
+    /// Switch to spooling, warning once per outage.
+    fn begin_outage(&self, cause: &dyn Display) {
+        if self.spool.begin_outage() {
+            warn!(error = %cause, "Database unreachable; spooling entries to disk until it is back");
+        }
+    }
+
+    async fn spool_entry(
+        &self,
+        feed_name: &str,
+        item: &FeedItem,
+        staged: bool,
+    ) -> Result<Stored, IngestError> {
+        if !self.spool.push(feed_name, item, staged).await? {
+            SPOOL_REJECTED.with_label_values(&[feed_name]).inc();
+            return Err(IngestError::Spool(format!(
+                "spool full ({} entries)",
+                self.spool.len()
+            )));
+        }
+        Ok(Stored::Spooled)
+    }
+
+    /// Whether a fresh connection to the database succeeds within `PROBE_TIMEOUT`. A
+    /// SQLite file counts as always reachable.
+    async fn database_reachable(&self) -> bool {
+        let Some(pool) = self.storage.postgres() else {
+            return true;
+        };
+        let options = pool.connect_options();
+        match tokio::time::timeout(PROBE_TIMEOUT, PgConnection::connect_with(&options)).await {
+            Ok(Ok(conn)) => {
+                let _ = conn.close().await;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Log what `process_entry` would write for `item`, including its enrichment. Known
+    /// entries count as unchanged.
+    async fn preview(&self, feed_name: &str, item: &FeedItem) -> Result<Change, IngestError> {
+        let new = !self.storage.is_archived(&item.guid).await?;
+        let text = enrich::item_text(item);
+        let products: Vec<String> = enrich::affected_products(&text)
+            .into_iter()
+            .map(|p| format!("{} {}", p.product, p.versions))
+            .collect();
+        let indicators: Vec<String> = enrich::indicators(&text)
+            .into_iter()
+            .map(|i| i.value)
+            .collect();
+        info!(
+            feed = %feed_name,
+            guid = %item.guid,
+            title = %item.title,
+            link = %item.link,
+            published = ?item.published,
+            tlp = ?item.tlp,
+            language = ?item.detected_language,
+            new,
+            ?products,
+            ?indicators,
+            "Dry run: would store entry"
+        );
+        Ok(if new { Change::New } else { Change::Unchanged })
+    }
+
+    /// Store the leading `items` that fit in the cycle budget with one `process_batch`
+    /// (allowed `entry_timeout` per item), then hand them to the sinks. Returns what
+    /// storing each changed, or `None` if the batch was rolled back and nothing stored.
+    async fn store_batch(&self, feed_name: &str, items: &[FeedItem]) -> Option<Vec<Change>> {
+        let admitted = items.iter().take_while(|_| self.budget.try_take()).count();
+        if admitted == 0 {
+            return Some(Vec::new());
+        }
+        let batch = &items[..admitted];
+        let deadline = self.entry_timeout.saturating_mul(admitted as u32);
+        let rollback = RollbackCount::new(feed_name);
+        let changes = match self.storage.process_batch(batch, deadline).await {
+            Ok(changes) => {
+                rollback.committed();
+                changes
+            }
+            Err(e @ IngestError::WriteTimeout(_)) => {
+                if self.spooling() && !self.database_reachable().await {
+                    self.begin_outage(&e);
+                }
+                warn!(
+                    feed = %feed_name,
+                    timeout_s = deadline.as_secs_f64(),
+                    "Batch insert timed out; storing entries one at a time"
+                );
+                self.budget.give_back(admitted);
+                return None;
+            }
+            Err(e) => {
+                warn!(feed = %feed_name, error = %e, "Batch insert failed; storing entries one at a time");
+                if self.spooling() && e.is_db_unavailable() {
+                    self.begin_outage(&e);
+                }
+                self.budget.give_back(admitted);
+                return None;
+            }
+        };
+        for (item, change) in batch.iter().zip(&changes) {
+            self.stored(feed_name, item, *change);
+        }
+        Some(changes)
+    }
+
+    /// Count down a successful canary run and promote the feed once it reaches zero.
+    async fn count_canary_cycle(&self, feed_name: &str) {
+        let Some(pool) = self.storage.postgres() else {
+            return;
+        };
+        match canary::complete_cycle(pool, feed_name).await {
+            Ok(0) => self.promote(pool, feed_name).await,
+            Ok(left) => debug!(feed = %feed_name, left, "Canary run complete"),
+            Err(e) => warn!(feed = %feed_name, error = %e, "Failed to update canary state"),
+        }
+    }
+
+    /// Move a feed's staged items into the main tables and sinks.
+    async fn promote(&self, pool: &PgPool, feed_name: &str) {
+        let items = match canary::staged_items(pool, feed_name).await {
+            Ok(items) => items,
+            Err(e) => {
+                warn!(feed = %feed_name, error = %e, "Failed to load staged canary items");
+                return;
+            }
+        };
+        info!(feed = %feed_name, count = items.len(), "Canary complete; promoting staged items");
+        for item in items {
+            let promoted = async {
+                self.store(feed_name, &item, 0).await?;
+                canary::unstage(pool, feed_name, &item.guid).await
+            };
+            if let Err(e) = promoted.await {
+                error!(feed = %feed_name, guid = %item.guid, error = %e, "Failed to promote item");
+            }
+        }
+    }
+
+    async fn store_validators(&self, feed: &Feed, validators: &CacheValidators) {
+        if let Err(e) = self.storage.store_validators(&feed.url, validators).await {
+            warn!(feed = %feed.name, error = %e, "Failed to store cache validators");
+        }
+    }
+
+    async fn record_usage(&self, feed_name: &str, bytes: usize, rows: usize) {
+        if let Err(e) = self
+            .storage
+            .record_usage(feed_name, bytes as u64, rows as u64)
+            .await
+        {
+            warn!(feed = %feed_name, error = %e, "Failed to record feed usage");
+        }
+    }
+}
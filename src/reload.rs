@@ -0,0 +1,75 @@
+//! Hot reload of the feed list on SIGHUP: `Settings::new()` is re-run and the new
+//! feeds are swapped in for the next ingestion cycle, leaving in-flight runs alone.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+use crate::canary;
+use crate::config::{Feed, Settings};
+use crate::http::HttpClients;
+use crate::scheduler::Schedule;
+use crate::storage::Storage;
+
+/// Everything a reload updates besides the feed list itself.
+pub struct Reloader {
+    pub storage: Arc<dyn Storage>,
+    pub schedule: Schedule,
+    pub clients: HttpClients,
+    pub interval: Duration,
+    /// Leave `feed_registry` alone (see `Settings::dry_run`)
+    pub dry_run: bool,
+}
+
+impl Reloader {
+    /// Listen for SIGHUP and publish each successfully reloaded feed list on `feeds`.
+    /// A config that fails to load or validate is logged and the current one kept.
+    pub fn spawn(self, feeds: watch::Sender<Arc<Vec<Feed>>>) {
+        #[cfg(unix)]
+        tokio::spawn(async move {
+            use tokio::signal::unix::{signal, SignalKind};
+            let mut hangups = match signal(SignalKind::hangup()) {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!(error = %e, "Failed to install SIGHUP handler; config reload disabled");
+                    return;
+                }
+            };
+            while hangups.recv().await.is_some() {
+                info!("SIGHUP received; reloading configuration");
+                self.reload(&feeds).await;
+            }
+        });
+        #[cfg(not(unix))]
+        let _ = (self, feeds);
+    }
+
+    async fn reload(&self, feeds: &watch::Sender<Arc<Vec<Feed>>>) {
+        let settings = match Settings::new() {
+            Ok(s) => s,
+            Err(e) => {
+                error!(error = %e, "Config reload failed; keeping current feeds");
+                return;
+            }
+        };
+        if let Err(e) = self.clients.reload(&settings) {
+            error!(error = %e, "Config reload failed; keeping current feeds");
+            return;
+        }
+        // Canary registration lives in Postgres only
+        if let Some(pool) = self.storage.postgres().filter(|_| !self.dry_run) {
+            if let Err(e) = canary::bootstrap(pool, &settings.feeds).await {
+                warn!(error = %e, "Failed to register reloaded feeds");
+            }
+        }
+        self.schedule.sync(&settings.feeds, self.interval);
+        let count = settings.feeds.len();
+        feeds.send_replace(Arc::new(settings.feeds));
+        info!(
+            feeds = count,
+            "Configuration reloaded; new feed list applies from the next cycle (other settings need a restart)"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Revision history of edited entries. `archive` keeps an entry as first seen and
+//! `current` as last seen; every version in between whose title, summary or content
+//! differs from the one stored before it is added to `revisions`, with the size of
+//! the change, so an updated advisory can be followed edit by edit.
+
+use std::collections::HashMap;
+
+use sqlx::PgConnection;
+
+use crate::errors::IngestError;
+use crate::ingestor::FeedItem;
+use crate::metrics::REVISION_DIFF_BYTES;
+
+/// The fields of an entry whose changes make a revision.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Version {
+    title: String,
+    summary: Option<String>,
+    content: Option<String>,
+}
+
+impl Version {
+    fn of(item: &FeedItem) -> Self {
+        Version {
+            title: item.title.clone(),
+            summary: item.summary.clone(),
+            content: item.content.clone(),
+        }
+    }
+
+    /// Bytes changed from `previous`, field by field.
+    fn diff_size(&self, previous: &Version) -> usize {
+        let changed = |old: &Option<String>, new: &Option<String>| {
+            diff_size(
+                old.as_deref().unwrap_or_default(),
+                new.as_deref().unwrap_or_default(),
+            )
+        };
+        diff_size(&previous.title, &self.title)
+            + changed(&previous.summary, &self.summary)
+            + changed(&previous.content, &self.content)
+    }
+}
+
+/// Add a revision for each of `items` that changes the version of its entry stored in
+/// `current` (or the previous one of `items` with its GUID); run before `current` is
+/// updated. Returns, aligned with `items`, the size of each added revision (see
+/// `observe`).
+pub async fn record(
+    conn: &mut PgConnection,
+    items: &[FeedItem],
+) -> Result<Vec<Option<usize>>, IngestError> {
+    let guids: Vec<&str> = items.iter().map(|item| item.guid.as_str()).collect();
+    let stored: Vec<StoredVersion> = sqlx::query_as(
+        "SELECT guid, title, summary, content FROM current WHERE guid = ANY($1::text[])",
+    )
+    .bind(&guids)
+    .fetch_all(&mut *conn)
+    .await?;
+    let added = changes(stored, items);
+    let (revised, diff_bytes): (Vec<&FeedItem>, Vec<i64>) = items
+        .iter()
+        .zip(&added)
+        .filter_map(|(item, size)| Some((item, (*size)? as i64)))
+        .unzip();
+    if revised.is_empty() {
+        return Ok(added);
+    }
+    sqlx::query(
+        "INSERT INTO revisions (guid, title, link, summary, content, entry_updated, diff_bytes, recorded_at)
+        SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[], $5::text[],
+            $6::timestamp[], $7::bigint[], $8::timestamp[])",
+    )
+    .bind(revised.iter().map(|i| i.guid.as_str()).collect::<Vec<_>>())
+    .bind(revised.iter().map(|i| i.title.as_str()).collect::<Vec<_>>())
+    .bind(revised.iter().map(|i| i.link.as_str()).collect::<Vec<_>>())
+    .bind(revised.iter().map(|i| i.summary.as_deref()).collect::<Vec<_>>())
+    .bind(revised.iter().map(|i| i.content.as_deref()).collect::<Vec<_>>())
+    .bind(revised.iter().map(|i| i.entry_updated).collect::<Vec<_>>())
+    .bind(&diff_bytes)
+    .bind(revised.iter().map(|i| i.inserted_at).collect::<Vec<_>>())
+    .execute(&mut *conn)
+    .await?;
+    Ok(added)
+}
+
+/// The `guid, title, summary, content` of an entry in `current`.
+pub(crate) type StoredVersion = (String, String, Option<String>, Option<String>);
+
+/// The size of the revision each of `items` makes to the `stored` version of its
+/// entry (or to the previous one of `items` with its GUID), aligned with `items`;
+/// `None` for new entries and unchanged ones.
+pub(crate) fn changes(stored: Vec<StoredVersion>, items: &[FeedItem]) -> Vec<Option<usize>> {
+    let mut latest: HashMap<String, Version> = stored
+        .into_iter()
+        .map(|(guid, title, summary, content)| {
+            let version = Version {
+                title,
+                summary,
+                content,
+            };
+            (guid, version)
+        })
+        .collect();
+    items
+        .iter()
+        .map(|item| {
+            let version = Version::of(item);
+            let size = latest
+                .get(&item.guid)
+                .filter(|p| **p != version)
+                .map(|previous| version.diff_size(previous));
+            latest.insert(item.guid.clone(), version);
+            size
+        })
+        .collect()
+}
+
+/// Count the revisions `record` returned in `REVISION_DIFF_BYTES`, once their
+/// transaction has committed.
+pub fn observe(added: &[Option<usize>]) {
+    for size in added.iter().flatten() {
+        REVISION_DIFF_BYTES.observe(*size as f64);
+    }
+}
+
+/// Bytes changed between `old` and `new`: the longer of the two once their common
+/// beginning and end are cut off. Edits far apart count everything between them.
+pub fn diff_size(old: &str, new: &str) -> usize {
+    let (old, new) = (old.as_bytes(), new.as_bytes());
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    old.len().max(new.len()) - suffix
+}
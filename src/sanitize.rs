@@ -0,0 +1,334 @@
+//! Named HTML sanitization profiles, selectable per feed, each implemented by a
+//! `Sanitizer`. The ammonia ones are compiled once on first use and shared by every
+//! feed selecting them; the `custom` one is built from the configured
+//! `SanitizePolicy` by `configure`. Also the per-feed length limits
+//! applied before cleaning, measured and truncated without splitting a character.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+use ammonia::Builder;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How aggressively a feed's HTML is cleaned.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SanitizeProfile {
+    /// Paragraphs, emphasis, lists, quotes and http(s) links only; no images or tables
+    Strict,
+    /// ammonia's defaults: general article markup including images, tables and `<pre>`
+    #[default]
+    Article,
+    /// `Article` plus the `class` attributes syntax highlighters rely on
+    CodeFriendly,
+    /// No cleaning at all; only for trusted feeds whose HTML is already sanitized
+    None,
+    /// The configured `sanitize_policy`
+    Custom,
+}
+
+/// A sanitization policy from the config, used by feeds with the `custom` profile:
+/// a built-in profile with tags, attributes and URL schemes added or removed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SanitizePolicy {
+    /// Profile to start from: "strict", "article" or "code-friendly"
+    pub base: SanitizeProfile,
+    pub tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+    /// Attributes allowed per tag, e.g. `td = ["colspan", "rowspan"]`
+    pub tag_attributes: HashMap<String, Vec<String>>,
+    /// Attributes allowed on every tag
+    pub generic_attributes: Vec<String>,
+    /// Replaces the base's link and image URL schemes
+    pub url_schemes: Option<Vec<String>>,
+    /// Keep `<img>` and lead images; unset keeps what the base does
+    pub images: Option<bool>,
+}
+
+impl Default for SanitizePolicy {
+    fn default() -> Self {
+        SanitizePolicy {
+            base: SanitizeProfile::Article,
+            tags: Vec::new(),
+            remove_tags: Vec::new(),
+            tag_attributes: HashMap::new(),
+            generic_attributes: Vec::new(),
+            url_schemes: None,
+            images: None,
+        }
+    }
+}
+
+impl SanitizePolicy {
+    /// Reject policies ammonia cannot apply: a base that is not an ammonia profile,
+    /// allowing `script` or `style` (whose content is always removed), or `rel`, which
+    /// is always set on links.
+    pub fn validate(&self) -> Result<(), String> {
+        if matches!(self.base, SanitizeProfile::None | SanitizeProfile::Custom) {
+            return Err("sanitize_policy: base must be strict, article or code-friendly".into());
+        }
+        let tags = self.tags.iter().chain(self.tag_attributes.keys());
+        if let Some(tag) = tags
+            .into_iter()
+            .find(|t| t.eq_ignore_ascii_case("script") || t.eq_ignore_ascii_case("style"))
+        {
+            return Err(format!("sanitize_policy: '{}' cannot be allowed", tag));
+        }
+        let mut attributes = self
+            .tag_attributes
+            .values()
+            .flatten()
+            .chain(&self.generic_attributes);
+        if attributes.any(|a| a.eq_ignore_ascii_case("rel")) {
+            return Err("sanitize_policy: 'rel' cannot be allowed; links get their own".into());
+        }
+        Ok(())
+    }
+
+    fn ammonia(&'static self) -> Ammonia {
+        let (mut builder, base_images) = match self.base {
+            SanitizeProfile::Strict => (strict(), false),
+            SanitizeProfile::CodeFriendly => (code_friendly(), true),
+            _ => (Builder::default(), true),
+        };
+        builder
+            .add_tags(self.tags.iter().map(String::as_str))
+            .rm_tags(self.remove_tags.iter().map(String::as_str))
+            .add_generic_attributes(self.generic_attributes.iter().map(String::as_str));
+        for (tag, attributes) in &self.tag_attributes {
+            builder.add_tag_attributes(tag.as_str(), attributes.iter().map(String::as_str));
+        }
+        if let Some(schemes) = &self.url_schemes {
+            builder.url_schemes(schemes.iter().map(String::as_str).collect());
+        }
+        let images = self.images.unwrap_or(base_images);
+        match self.images {
+            Some(true) => {
+                builder
+                    .add_tags(["img"])
+                    .add_tag_attributes("img", ["src", "alt", "title", "width", "height"]);
+            }
+            Some(false) => {
+                builder.rm_tags(["img"]);
+            }
+            None => {}
+        }
+        Ammonia::new(builder, images)
+    }
+}
+
+static CUSTOM: RwLock<Option<&'static Ammonia>> = RwLock::new(None);
+
+/// Build the `custom` profile from `policy`, once at startup: each call keeps its
+/// policy for the life of the process.
+pub fn configure(policy: Option<&SanitizePolicy>) -> Result<(), String> {
+    let custom = match policy {
+        Some(policy) => {
+            policy.validate()?;
+            let policy: &'static SanitizePolicy = Box::leak(Box::new(policy.clone()));
+            Some(&*Box::leak(Box::new(policy.ammonia())))
+        }
+        None => None,
+    };
+    *CUSTOM.write().expect("sanitize policy poisoned") = custom;
+    Ok(())
+}
+
+/// Cleans untrusted HTML from feeds. The ammonia-based `Ammonia` backs the built-in
+/// profiles; embedders can supply their own policy engine to `sanitize_and_validate`.
+pub trait Sanitizer: Send + Sync {
+    /// Clean an HTML fragment.
+    fn clean(&self, html: &str) -> String;
+
+    /// Whether items keep a lead image (`image_url`) and thumbnail (`thumbnail_url`).
+    fn allows_images(&self) -> bool {
+        true
+    }
+}
+
+/// An ammonia policy.
+pub struct Ammonia {
+    builder: Builder<'static>,
+    images: bool,
+}
+
+impl Ammonia {
+    pub fn new(builder: Builder<'static>, images: bool) -> Self {
+        Ammonia { builder, images }
+    }
+}
+
+impl Sanitizer for Ammonia {
+    fn clean(&self, html: &str) -> String {
+        self.builder.clean(html).to_string()
+    }
+
+    fn allows_images(&self) -> bool {
+        self.images
+    }
+}
+
+/// Leaves HTML as it is, for feeds that are sanitized before they reach the ingestor.
+pub struct Passthrough;
+
+impl Sanitizer for Passthrough {
+    fn clean(&self, html: &str) -> String {
+        html.to_string()
+    }
+}
+
+fn strict() -> Builder<'static> {
+    let mut builder = Builder::empty();
+    builder
+        .add_tags([
+            "a",
+            "b",
+            "blockquote",
+            "br",
+            "em",
+            "i",
+            "li",
+            "ol",
+            "p",
+            "strong",
+            "ul",
+        ])
+        .add_tag_attributes("a", ["href"])
+        .url_schemes(HashSet::from(["http", "https"]))
+        .link_rel(Some("noopener noreferrer nofollow"));
+    builder
+}
+
+fn code_friendly() -> Builder<'static> {
+    let mut builder = Builder::default();
+    builder
+        .add_tag_attributes("pre", ["class"])
+        .add_tag_attributes("code", ["class"])
+        .add_tag_attributes("span", ["class"]);
+    builder
+}
+
+static STRICT: Lazy<Ammonia> = Lazy::new(|| Ammonia::new(strict(), false));
+
+static ARTICLE: Lazy<Ammonia> = Lazy::new(|| Ammonia::new(Builder::default(), true));
+
+static CODE_FRIENDLY: Lazy<Ammonia> = Lazy::new(|| Ammonia::new(code_friendly(), true));
+
+impl SanitizeProfile {
+    pub fn is_default(&self) -> bool {
+        *self == SanitizeProfile::default()
+    }
+
+    /// The sanitizer implementing this profile; `Custom` is `Article` until `configure`d.
+    pub fn sanitizer(self) -> &'static dyn Sanitizer {
+        match self {
+            SanitizeProfile::Strict => &*STRICT,
+            SanitizeProfile::Article => &*ARTICLE,
+            SanitizeProfile::CodeFriendly => &*CODE_FRIENDLY,
+            SanitizeProfile::None => &Passthrough,
+            SanitizeProfile::Custom => CUSTOM
+                .read()
+                .expect("sanitize policy poisoned")
+                .map_or(&*ARTICLE as &dyn Sanitizer, |custom| {
+                    custom as &dyn Sanitizer
+                }),
+        }
+    }
+}
+
+/// What `LengthLimits` counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LengthUnit {
+    /// UTF-8 bytes, i.e. storage size
+    Bytes,
+    /// Unicode scalar values
+    #[default]
+    Chars,
+    /// User-perceived characters (extended grapheme clusters), so an emoji sequence or a
+    /// letter with combining accents counts once
+    Graphemes,
+}
+
+impl LengthUnit {
+    /// Length of `text` in this unit.
+    pub fn measure(self, text: &str) -> usize {
+        match self {
+            LengthUnit::Bytes => text.len(),
+            LengthUnit::Chars => text.chars().count(),
+            LengthUnit::Graphemes => text.graphemes(true).count(),
+        }
+    }
+
+    /// The longest prefix of `text` at most `max` long, never ending inside a character
+    /// (or, for `Graphemes`, inside a cluster).
+    pub fn truncate(self, text: &str, max: usize) -> &str {
+        let end = match self {
+            LengthUnit::Bytes => (0..=max.min(text.len()))
+                .rev()
+                .find(|&i| text.is_char_boundary(i))
+                .unwrap_or(0),
+            LengthUnit::Chars => text.char_indices().nth(max).map_or(text.len(), |(i, _)| i),
+            LengthUnit::Graphemes => text
+                .grapheme_indices(true)
+                .nth(max)
+                .map_or(text.len(), |(i, _)| i),
+        };
+        &text[..end]
+    }
+}
+
+/// What happens to a field longer than its limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Overflow {
+    /// Skip the whole entry
+    #[default]
+    Reject,
+    /// Keep the entry with the field cut to the limit
+    Truncate,
+}
+
+/// Maximum lengths of an entry's text fields, checked on the raw (trimmed) text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LengthLimits {
+    pub unit: LengthUnit,
+    pub overflow: Overflow,
+    pub title: usize,
+    pub summary: usize,
+    pub content: usize,
+}
+
+impl Default for LengthLimits {
+    fn default() -> Self {
+        LengthLimits {
+            unit: LengthUnit::default(),
+            overflow: Overflow::default(),
+            title: 1024,
+            summary: 200_000,
+            content: 500_000,
+        }
+    }
+}
+
+impl LengthLimits {
+    pub fn is_default(&self) -> bool {
+        *self == LengthLimits::default()
+    }
+
+    /// `text` if it is within `max`, else its truncation or `None` (reject).
+    pub fn fit(self, text: &str, max: usize) -> Option<&str> {
+        // A string never has more chars or graphemes than bytes
+        if text.len() <= max || self.unit.measure(text) <= max {
+            return Some(text);
+        }
+        match self.overflow {
+            Overflow::Reject => None,
+            Overflow::Truncate => Some(self.unit.truncate(text, max)),
+        }
+    }
+}
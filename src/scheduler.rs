@@ -0,0 +1,311 @@
+//! Per-feed scheduling state, shared between the ingestion loop and the HTTP server.
+//! With `feed_health` configured, feeds that keep failing are backed off and
+//! eventually disabled (see `FeedHealthSettings`); others run every cycle.
+//!
+//! Library users can drive a `Schedule` from their own orchestration instead: run the
+//! feeds `due` now, report each with `record_run`, and sleep until `next_due`.
+//!
+//! Each feed's entry is saved to `feed_schedule` after its run (see `save`) and
+//! `restore`d from it at startup, so failure streaks, health and disabled feeds
+//! survive a restart.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::{Feed, FeedHealthSettings};
+use crate::errors::IngestError;
+use crate::metrics::{FEED_DISABLED, FEED_HEALTH};
+
+/// Weight of the latest run in `FeedSchedule::health`.
+const HEALTH_WEIGHT: f64 = 0.2;
+
+/// Where a feed's polling interval comes from.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IntervalSource {
+    /// The global `ingest_interval` from `Settings`.
+    Static,
+}
+
+/// Scheduling snapshot for a single feed, as reported by `GET /schedule`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedSchedule {
+    pub feed: String,
+    pub url: String,
+    pub interval_source: IntervalSource,
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+    pub last_run: Option<DateTime<Utc>>,
+    pub next_run: DateTime<Utc>,
+    /// Number of consecutive failed runs (0 after any success)
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    /// Success rate of recent runs, the latest weighing most (1.0 = all succeeded)
+    #[serde(default = "full_health")]
+    pub health: f64,
+    /// Failed `feed_health.disable_after` times in a row; only probed every
+    /// `probe_interval` until a run succeeds
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+fn full_health() -> f64 {
+    1.0
+}
+
+/// Thread-safe table of `FeedSchedule`s keyed by feed name.
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    inner: Arc<RwLock<HashMap<String, FeedSchedule>>>,
+    health: Option<FeedHealthSettings>,
+}
+
+impl Schedule {
+    /// Seed the schedule with every configured feed, all due immediately.
+    pub fn new(feeds: &[Feed], interval: Duration) -> Self {
+        let now = Utc::now();
+        let map = feeds
+            .iter()
+            .map(|f| (f.name.clone(), fresh_entry(f, interval, now)))
+            .collect();
+        Schedule {
+            inner: Arc::new(RwLock::new(map)),
+            health: None,
+        }
+    }
+
+    /// This schedule, backing off and disabling failing feeds per `health`.
+    pub fn with_health(self, health: Option<FeedHealthSettings>) -> Self {
+        Schedule { health, ..self }
+    }
+
+    /// Whether `feed` should run in a cycle starting at `now`. Feeds run every cycle
+    /// unless backed off after failures; half an interval of slack keeps a feed due
+    /// for the cycle its next run falls in.
+    pub fn is_due(&self, feed: &str, now: DateTime<Utc>) -> bool {
+        if self.health.is_none() {
+            return true;
+        }
+        let map = self.inner.read().expect("schedule lock poisoned");
+        map.get(feed).is_none_or(|entry| {
+            entry.consecutive_failures == 0
+                || now + chrono::Duration::from_std(entry.interval / 2).unwrap_or_default()
+                    >= entry.next_run
+        })
+    }
+
+    /// Feeds whose next run is at or before `now`, earliest first. Unlike `is_due`, this
+    /// holds every feed to its interval, as callers polling it have no cycle of their own.
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<String> {
+        let map = self.inner.read().expect("schedule lock poisoned");
+        let mut due: Vec<&FeedSchedule> = map.values().filter(|e| e.next_run <= now).collect();
+        due.sort_by(|a, b| a.next_run.cmp(&b.next_run).then(a.feed.cmp(&b.feed)));
+        due.into_iter().map(|e| e.feed.clone()).collect()
+    }
+
+    /// Bring the schedule in line with a reloaded feed list: feeds that are gone are
+    /// dropped, new ones are added due immediately, and kept ones retain their history.
+    pub fn sync(&self, feeds: &[Feed], interval: Duration) {
+        let now = Utc::now();
+        let mut map = self.inner.write().expect("schedule lock poisoned");
+        map.retain(|name, _| feeds.iter().any(|f| &f.name == name));
+        for feed in feeds {
+            map.entry(feed.name.clone())
+                .and_modify(|entry| entry.url = feed.url.clone())
+                .or_insert_with(|| fresh_entry(feed, interval, now));
+        }
+    }
+
+    /// Record the outcome of a feed run and compute its next due time.
+    pub fn record_run(&self, feed: &str, started: DateTime<Utc>, error: Option<String>) {
+        let mut map = self.inner.write().expect("schedule lock poisoned");
+        let Some(entry) = map.get_mut(feed) else {
+            return;
+        };
+        entry.last_run = Some(started);
+        let succeeded = error.is_none();
+        entry.health =
+            (1.0 - HEALTH_WEIGHT) * entry.health + HEALTH_WEIGHT * f64::from(u8::from(succeeded));
+        match error {
+            Some(e) => {
+                entry.consecutive_failures += 1;
+                entry.last_error = Some(e);
+            }
+            None => {
+                entry.consecutive_failures = 0;
+                entry.last_error = None;
+            }
+        }
+        let mut wait = entry.interval;
+        let was_disabled = entry.disabled;
+        entry.disabled = false;
+        if let (Some(health), false) = (&self.health, succeeded) {
+            let failures = entry.consecutive_failures;
+            if health.disable_after > 0 && failures >= health.disable_after {
+                entry.disabled = true;
+                wait = health.probe_interval;
+            } else {
+                wait = entry
+                    .interval
+                    .saturating_mul(2u32.saturating_pow(failures - 1))
+                    .min(health.max_backoff.max(entry.interval));
+            }
+        }
+        entry.next_run = started + chrono::Duration::from_std(wait).unwrap_or_default();
+        if entry.disabled && !was_disabled {
+            warn!(
+                feed = %feed,
+                failures = entry.consecutive_failures,
+                next_probe = %entry.next_run,
+                "Feed disabled after consecutive failures"
+            );
+        } else if was_disabled && !entry.disabled {
+            info!(feed = %feed, "Disabled feed recovered; re-enabled");
+        }
+        FEED_HEALTH.with_label_values(&[feed]).set(entry.health);
+        FEED_DISABLED
+            .with_label_values(&[feed])
+            .set(i64::from(entry.disabled));
+    }
+
+    /// Carry over run history and due times from another instance's schedule.
+    /// Entries are matched by feed name and URL; returns how many were applied.
+    pub fn restore(&self, entries: &[FeedSchedule]) -> usize {
+        let mut map = self.inner.write().expect("schedule lock poisoned");
+        let mut applied = 0;
+        for saved in entries {
+            if let Some(entry) = map.get_mut(&saved.feed) {
+                if entry.url == saved.url {
+                    entry.last_run = saved.last_run;
+                    entry.next_run = saved.next_run;
+                    entry.consecutive_failures = saved.consecutive_failures;
+                    entry.last_error = saved.last_error.clone();
+                    entry.health = saved.health;
+                    entry.disabled = saved.disabled;
+                    FEED_HEALTH
+                        .with_label_values(&[&entry.feed])
+                        .set(entry.health);
+                    FEED_DISABLED
+                        .with_label_values(&[&entry.feed])
+                        .set(i64::from(entry.disabled));
+                    applied += 1;
+                }
+            }
+        }
+        applied
+    }
+
+    /// Earliest due time across all feeds.
+    pub fn next_due(&self) -> Option<DateTime<Utc>> {
+        let map = self.inner.read().expect("schedule lock poisoned");
+        map.values().map(|e| e.next_run).min()
+    }
+
+    /// The schedule of the feed named `feed`, if it is configured.
+    pub fn get(&self, feed: &str) -> Option<FeedSchedule> {
+        let map = self.inner.read().expect("schedule lock poisoned");
+        map.get(feed).cloned()
+    }
+
+    /// All feed schedules, ordered by next due time.
+    pub fn snapshot(&self) -> Vec<FeedSchedule> {
+        let map = self.inner.read().expect("schedule lock poisoned");
+        let mut out: Vec<FeedSchedule> = map.values().cloned().collect();
+        out.sort_by(|a, b| a.next_run.cmp(&b.next_run).then(a.feed.cmp(&b.feed)));
+        out
+    }
+}
+
+/// A `feed_schedule` row.
+#[derive(sqlx::FromRow)]
+pub(crate) struct SavedSchedule {
+    feed_name: String,
+    url: String,
+    interval_ms: i64,
+    last_run: Option<NaiveDateTime>,
+    next_run: NaiveDateTime,
+    consecutive_failures: i32,
+    last_error: Option<String>,
+    health: f64,
+    disabled: bool,
+}
+
+/// Save `entry` to `feed_schedule`, replacing the feed's previous row.
+pub async fn save(pool: &PgPool, entry: &FeedSchedule) -> Result<(), IngestError> {
+    sqlx::query(
+        "INSERT INTO feed_schedule (feed_name, url, interval_ms, last_run, next_run,
+            consecutive_failures, last_error, health, disabled, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW())
+        ON CONFLICT (feed_name) DO UPDATE SET
+            url = EXCLUDED.url,
+            interval_ms = EXCLUDED.interval_ms,
+            last_run = EXCLUDED.last_run,
+            next_run = EXCLUDED.next_run,
+            consecutive_failures = EXCLUDED.consecutive_failures,
+            last_error = EXCLUDED.last_error,
+            health = EXCLUDED.health,
+            disabled = EXCLUDED.disabled,
+            updated_at = EXCLUDED.updated_at",
+    )
+    .bind(&entry.feed)
+    .bind(&entry.url)
+    .bind(entry.interval.as_millis() as i64)
+    .bind(entry.last_run.map(|t| t.naive_utc()))
+    .bind(entry.next_run.naive_utc())
+    .bind(entry.consecutive_failures as i32)
+    .bind(&entry.last_error)
+    .bind(entry.health)
+    .bind(entry.disabled)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Every saved schedule, for `Schedule::restore`.
+pub async fn load(pool: &PgPool) -> Result<Vec<FeedSchedule>, IngestError> {
+    let rows: Vec<SavedSchedule> = sqlx::query_as(
+        "SELECT feed_name, url, interval_ms, last_run, next_run, consecutive_failures,
+            last_error, health, disabled
+        FROM feed_schedule",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(FeedSchedule::from).collect())
+}
+
+impl From<SavedSchedule> for FeedSchedule {
+    fn from(row: SavedSchedule) -> Self {
+        FeedSchedule {
+            feed: row.feed_name,
+            url: row.url,
+            interval_source: IntervalSource::Static,
+            interval: Duration::from_millis(row.interval_ms.max(0) as u64),
+            last_run: row.last_run.map(|t| t.and_utc()),
+            next_run: row.next_run.and_utc(),
+            consecutive_failures: row.consecutive_failures.max(0) as u32,
+            last_error: row.last_error,
+            health: row.health,
+            disabled: row.disabled,
+        }
+    }
+}
+
+fn fresh_entry(feed: &Feed, interval: Duration, now: DateTime<Utc>) -> FeedSchedule {
+    FeedSchedule {
+        feed: feed.name.clone(),
+        url: feed.url.clone(),
+        interval_source: IntervalSource::Static,
+        interval,
+        last_run: None,
+        next_run: now,
+        consecutive_failures: 0,
+        last_error: None,
+        health: full_health(),
+        disabled: false,
+    }
+}
@@ -0,0 +1,411 @@
+//! Startup schema compatibility check: compares the live database with the
+//! migrations this binary ships and the tables and columns it queries, so a skipped
+//! or failed migration is reported up front (and by `/readyz`) instead of surfacing
+//! as sqlx errors mid-cycle. Also runs the migrations, one instance at a time.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use serde::Serialize;
+use sqlx::migrate::Migrator;
+use sqlx::{Connection, PgPool};
+use tracing::info;
+
+use crate::errors::IngestError;
+
+/// The migrations under `migrations/`, embedded at build time.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Session advisory lock held while migrating, so replicas starting together take
+/// turns: the first migrates, the others wait and then find nothing pending.
+const MIGRATION_LOCK: i64 = 0x7275_7374_6665_6564;
+
+/// Apply pending migrations, first waiting (up to `wait`) for any other instance that
+/// is migrating the same database.
+pub async fn migrate(pool: &PgPool, wait: Duration) -> Result<(), IngestError> {
+    // A connection of its own: closing it releases the lock on every path
+    let mut conn = pool.acquire().await?.detach();
+    let (acquired,): (bool,) = sqlx::query_as("SELECT pg_try_advisory_lock($1)")
+        .bind(MIGRATION_LOCK)
+        .fetch_one(&mut conn)
+        .await?;
+    if !acquired {
+        info!(
+            wait_s = wait.as_secs(),
+            "Another instance is migrating; waiting for it"
+        );
+        let lock = sqlx::query("SELECT pg_advisory_lock($1)")
+            .bind(MIGRATION_LOCK)
+            .execute(&mut conn);
+        match tokio::time::timeout(wait, lock).await {
+            Ok(result) => {
+                result?;
+            }
+            Err(_) => {
+                return Err(IngestError::Schema(format!(
+                    "gave up after {}s waiting for another instance's migrations",
+                    wait.as_secs()
+                )))
+            }
+        }
+        info!("Other instance finished migrating");
+    }
+    let result = MIGRATOR
+        .run_direct(&mut conn)
+        .await
+        .map_err(|e| IngestError::Schema(format!("migration failed: {}", e)));
+    conn.close().await?;
+    result
+}
+
+/// Columns of `archive`, and of `current` apart from its search vector.
+const ENTRY_COLUMNS: &[&str] = &[
+    "id",
+    "guid",
+    "title",
+    "link",
+    "published",
+    "content",
+    "summary",
+    "author",
+    "categories",
+    "entry_updated",
+    "image_url",
+    "feed_url",
+    "feed_title",
+    "feed_description",
+    "feed_language",
+    "feed_icon",
+    "feed_updated",
+    "feed_type",
+    "feed_tags",
+    "tlp",
+    "parent_guid",
+    "license",
+    "detected_language",
+    "summary_ai",
+    "simhash",
+    "duplicate_of",
+    "tags",
+    "enclosures",
+    "thumbnail_url",
+    "media",
+    "extensions",
+    "resolved_link",
+    "inserted_at",
+];
+
+/// Every table the ingestor reads or writes, with the columns it uses.
+const EXPECTED: &[(&str, &[&str])] = &[
+    ("archive", ENTRY_COLUMNS),
+    ("current", ENTRY_COLUMNS),
+    ("current", &["search"]),
+    (
+        "feed_cache",
+        &[
+            "feed_url",
+            "etag",
+            "last_modified",
+            "body_hash",
+            "discovered_url",
+            "updated_at",
+        ],
+    ),
+    (
+        "feed_registry",
+        &[
+            "feed_name",
+            "config_hash",
+            "canary_cycles_left",
+            "updated_at",
+        ],
+    ),
+    (
+        "canary_entries",
+        &["feed_name", "guid", "item", "inserted_at"],
+    ),
+    (
+        "feed_costs",
+        &[
+            "feed_name",
+            "day",
+            "fetches",
+            "bytes_fetched",
+            "rows_written",
+        ],
+    ),
+    (
+        "feed_icons",
+        &[
+            "feed_name",
+            "source_url",
+            "content_type",
+            "data",
+            "fetched_at",
+        ],
+    ),
+    ("feed_icon_misses", &["feed_name", "retry_after"]),
+    (
+        "feed_schedule",
+        &[
+            "feed_name",
+            "url",
+            "interval_ms",
+            "last_run",
+            "next_run",
+            "consecutive_failures",
+            "last_error",
+            "health",
+            "disabled",
+            "updated_at",
+        ],
+    ),
+    (
+        "enclosure_files",
+        &[
+            "url",
+            "guid",
+            "path",
+            "content_type",
+            "bytes",
+            "sha256",
+            "downloaded_at",
+        ],
+    ),
+    (
+        "revisions",
+        &[
+            "id",
+            "guid",
+            "title",
+            "link",
+            "summary",
+            "content",
+            "entry_updated",
+            "diff_bytes",
+            "recorded_at",
+        ],
+    ),
+    (
+        "affected_products",
+        &["guid", "product", "versions", "extracted_at"],
+    ),
+    (
+        "tag_rules",
+        &["id", "tag", "keywords", "pattern", "created_at"],
+    ),
+    (
+        "entity_edges",
+        &[
+            "source_kind",
+            "source",
+            "target_kind",
+            "target",
+            "weight",
+            "first_seen",
+            "last_seen",
+        ],
+    ),
+    (
+        "fetch_history",
+        &[
+            "id",
+            "feed_name",
+            "started_at",
+            "status",
+            "http_status",
+            "entries_seen",
+            "entries_new",
+            "errors",
+            "duration_ms",
+            "error",
+            "bytes_compressed",
+            "bytes_decompressed",
+            "response_headers",
+        ],
+    ),
+    (
+        "spillover_entries",
+        &["id", "feed_name", "guid", "item", "queued_at"],
+    ),
+    (
+        "tls_certificates",
+        &[
+            "host",
+            "fingerprint",
+            "issuer",
+            "not_after",
+            "previous_fingerprint",
+            "first_seen",
+            "last_seen",
+            "changed_at",
+        ],
+    ),
+    (
+        "summarizer_usage",
+        &["day", "requests", "prompt_tokens", "completion_tokens"],
+    ),
+];
+
+/// Differences between the live schema and what this binary expects.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SchemaReport {
+    /// Shipped migrations the database has not applied (`<version> <description>`)
+    pub pending_migrations: Vec<String>,
+    /// Applied migrations this binary does not know, i.e. the database is newer
+    pub unknown_migrations: Vec<String>,
+    /// Migrations recorded as failed, or applied from a different file than shipped
+    pub broken_migrations: Vec<String>,
+    pub missing_tables: Vec<String>,
+    /// `<table>.<column>`, for tables that exist
+    pub missing_columns: Vec<String>,
+}
+
+impl SchemaReport {
+    /// Whether ingestion can run. Unknown migrations alone are tolerated: a newer
+    /// schema that still has every expected column works.
+    pub fn is_compatible(&self) -> bool {
+        self.pending_migrations.is_empty()
+            && self.broken_migrations.is_empty()
+            && self.missing_tables.is_empty()
+            && self.missing_columns.is_empty()
+    }
+}
+
+impl fmt::Display for SchemaReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sections = [
+            ("pending migrations", &self.pending_migrations),
+            ("failed or modified migrations", &self.broken_migrations),
+            ("missing tables", &self.missing_tables),
+            ("missing columns", &self.missing_columns),
+            ("unknown migrations", &self.unknown_migrations),
+        ];
+        let mut first = true;
+        for (label, items) in sections.iter().filter(|(_, items)| !items.is_empty()) {
+            if !first {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}: {}", label, items.join(", "))?;
+            first = false;
+        }
+        if first {
+            f.write_str("schema up to date")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the database behind `pool` with `MIGRATOR` and `EXPECTED`.
+pub async fn check(pool: &PgPool) -> Result<SchemaReport, IngestError> {
+    let (tracked,): (bool,) = sqlx::query_as("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(pool)
+        .await?;
+    let applied: Vec<AppliedMigration> = if tracked {
+        sqlx::query_as("SELECT version, description, success, checksum FROM _sqlx_migrations")
+            .fetch_all(pool)
+            .await?
+    } else {
+        Vec::new()
+    };
+    let columns: Vec<(String, String)> = sqlx::query_as(
+        "SELECT table_name::text, column_name::text FROM information_schema.columns
+        WHERE table_schema = current_schema()",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(compare(&MIGRATOR, applied, EXPECTED, &columns))
+}
+
+/// A row of `_sqlx_migrations`: version, description, success and checksum.
+pub(crate) type AppliedMigration = (i64, String, bool, Vec<u8>);
+
+/// `EXPECTED`, for a schema holding only `tables` and no full-text `search` column.
+pub(crate) fn expected_in(tables: &[&str]) -> Vec<(&'static str, &'static [&'static str])> {
+    EXPECTED
+        .iter()
+        .filter(|(table, columns)| tables.contains(table) && !columns.contains(&"search"))
+        .copied()
+        .collect()
+}
+
+/// The report for a database with the `applied` migrations and the `(table, column)`
+/// pairs of `columns`, against the migrations of `migrator` and the `expected` columns.
+pub(crate) fn compare(
+    migrator: &Migrator,
+    applied: Vec<AppliedMigration>,
+    expected: &[(&str, &[&str])],
+    columns: &[(String, String)],
+) -> SchemaReport {
+    let mut report = SchemaReport::default();
+
+    let applied: HashMap<i64, (String, bool, Vec<u8>)> = applied
+        .into_iter()
+        .map(|(version, description, success, checksum)| {
+            (version, (description, success, checksum))
+        })
+        .collect();
+    for migration in migrator
+        .iter()
+        .filter(|m| m.migration_type.is_up_migration())
+    {
+        let name = format!("{} {}", migration.version, migration.description);
+        match applied.get(&migration.version) {
+            None => report.pending_migrations.push(name),
+            Some((_, false, _)) => report.broken_migrations.push(format!("{} (failed)", name)),
+            Some((_, true, checksum)) if *checksum != *migration.checksum => report
+                .broken_migrations
+                .push(format!("{} (checksum differs)", name)),
+            Some(_) => {}
+        }
+    }
+    let known: BTreeSet<i64> = migrator.iter().map(|m| m.version).collect();
+    let mut unknown: Vec<_> = applied
+        .iter()
+        .filter(|(version, _)| !known.contains(version))
+        .map(|(version, (description, _, _))| (*version, description))
+        .collect();
+    unknown.sort();
+    report.unknown_migrations = unknown
+        .into_iter()
+        .map(|(version, description)| format!("{} {}", version, description))
+        .collect();
+
+    let tables: BTreeSet<&str> = columns.iter().map(|(t, _)| t.as_str()).collect();
+    let columns: BTreeSet<(&str, &str)> = columns
+        .iter()
+        .map(|(t, c)| (t.as_str(), c.as_str()))
+        .collect();
+    for (table, expected) in expected {
+        if !tables.contains(table) {
+            if !report.missing_tables.iter().any(|t| t == table) {
+                report.missing_tables.push(table.to_string());
+            }
+            continue;
+        }
+        for column in *expected {
+            if !columns.contains(&(*table, *column)) {
+                report.missing_columns.push(format!("{}.{}", table, column));
+            }
+        }
+    }
+    report
+}
+
+/// The latest schema check, shared with `/readyz`; unset until the first check.
+#[derive(Debug, Clone, Default)]
+pub struct Readiness {
+    report: Arc<RwLock<Option<SchemaReport>>>,
+}
+
+impl Readiness {
+    pub fn set(&self, report: SchemaReport) {
+        *self.report.write().expect("readiness lock poisoned") = Some(report);
+    }
+
+    pub fn report(&self) -> Option<SchemaReport> {
+        self.report.read().expect("readiness lock poisoned").clone()
+    }
+}
@@ -0,0 +1,605 @@
+//! Embedded HTTP server: metrics, health, and operational endpoints.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use percent_encoding::percent_decode_str;
+use prometheus::{Encoder, TextEncoder};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+use url::form_urlencoded;
+
+use crate::config::{TagRule, TaxiiSettings};
+use crate::costs;
+use crate::db_utils::{self, Cursor, EntryQuery, SearchHit};
+use crate::enrich;
+use crate::errors::IngestError;
+#[cfg(feature = "graphql")]
+use crate::graphql;
+use crate::icons;
+use crate::ingestor::FeedItem;
+use crate::inspect::LastRuns;
+use crate::metrics;
+use crate::ping::{self, Outcome, Pings};
+use crate::scheduler::Schedule;
+use crate::schema::Readiness;
+use crate::shutdown::Shutdown;
+use crate::stix;
+use crate::storage::Storage;
+use crate::tagging;
+use crate::taxii;
+
+/// Largest request body read, authenticated or not
+const MAX_REQUEST_BODY: usize = 64 * 1024;
+
+/// Shared state handed to every request handler.
+#[derive(Clone)]
+pub struct AppState {
+    pub storage: Arc<dyn Storage>,
+    pub schedule: Schedule,
+    /// Result of the schema check, reported by `/readyz`
+    pub readiness: Readiness,
+    pub taxii: TaxiiSettings,
+    pub last_runs: LastRuns,
+    /// Bearer token for `/admin/`; the endpoints are disabled without one
+    pub admin_token: Option<String>,
+    /// Publisher pings; `/ping` and `/websub/` are disabled without them
+    pub pings: Option<Pings>,
+}
+
+/// Bind and serve the HTTP endpoints until `shutdown` fires, then finish open requests.
+pub async fn serve(
+    addr: SocketAddr,
+    state: AppState,
+    shutdown: Shutdown,
+) -> Result<(), hyper::Error> {
+    let make_svc = make_service_fn(move |_conn| {
+        let state = state.clone();
+        async move {
+            Ok::<_, IngestError>(service_fn(move |req: Request<Body>| {
+                let state = state.clone();
+                async move { route(req, state).await }
+            }))
+        }
+    });
+
+    info!(%addr, "Starting metrics & health server");
+    Server::bind(&addr)
+        .serve(make_svc)
+        .with_graceful_shutdown(async move { shutdown.wait().await })
+        .await
+}
+
+async fn route(req: Request<Body>, state: AppState) -> Result<Response<Body>, IngestError> {
+    match (req.method(), req.uri().path()) {
+        // ─── METRICS ENDPOINT ────────────────────────────────
+        (&Method::GET, "/metrics") => {
+            let metrics_text = metrics::gather_metrics();
+            let encoder = TextEncoder::new();
+            let mime = encoder.format_type();
+            let resp = Response::builder()
+                .header("Content-Type", mime)
+                .body(Body::from(metrics_text))
+                .expect("Failed to build /metrics response");
+            Ok(resp)
+        }
+        // ─── HEALTHCHECK ENDPOINT ───────────────────────────
+        (&Method::GET, "/healthz") => Ok(Response::new(Body::from("OK"))),
+        // ─── READINESS ENDPOINT ─────────────────────────────
+        (&Method::GET, "/readyz") => Ok(readiness(&state)),
+        // ─── SCHEDULE ENDPOINT ──────────────────────────────
+        (&Method::GET, "/schedule") => Ok(json_response(&state.schedule.snapshot())),
+        // ─── ENTRIES API ────────────────────────────────────
+        (&Method::GET, "/api/entries") => Ok(list_entries(&req, &state).await),
+        // ─── FULL-TEXT SEARCH ───────────────────────────────
+        (&Method::GET, "/api/search") => Ok(search_entries(&req, &state).await),
+        // ─── STIX 2.1 EXPORT ────────────────────────────────
+        (&Method::GET, "/api/stix") => Ok(stix_bundle(&req, &state).await),
+        // ─── TAXII 2.1 ──────────────────────────────────────
+        (&Method::GET, path) if path == "/taxii2" || path.starts_with("/taxii2/") => {
+            Ok(match state.storage.postgres() {
+                Some(pool) => taxii::handle(&req, query_pairs(&req), pool, &state.taxii).await,
+                None => needs_postgres(),
+            })
+        }
+        // ─── GRAPHQL ────────────────────────────────────────
+        #[cfg(feature = "graphql")]
+        (&Method::GET, "/graphql") => Ok(Response::builder()
+            .header("Content-Type", "text/html; charset=utf-8")
+            .body(Body::from(graphql::graphiql("/graphql")))
+            .expect("Failed to build GraphiQL response")),
+        #[cfg(feature = "graphql")]
+        (&Method::POST, "/graphql") => Ok(graphql_query(req, &state).await),
+        // ─── COST BREAKDOWN ─────────────────────────────────
+        (&Method::GET, "/costs") => Ok(cost_breakdown(&req, &state).await),
+        // ─── STATE EXPORT ───────────────────────────────────
+        (&Method::GET, "/state") => Ok(export_state(&state).await),
+        // ─── CACHED FEED ICONS ──────────────────────────────
+        (&Method::GET, path) if path.starts_with("/icons/") => {
+            Ok(feed_icon(&path["/icons/".len()..], &state).await)
+        }
+        // ─── PER-FEED FETCH HISTORY ─────────────────────────
+        (&Method::GET, path) if path.starts_with("/feeds/") && path.ends_with("/history") => {
+            let name = &path["/feeds/".len()..path.len() - "/history".len()];
+            Ok(feed_history(name, &req, &state).await)
+        }
+        // ─── LAST RUN, ENTRY BY ENTRY ───────────────────────
+        (&Method::GET, path) if path.starts_with("/debug/feeds/") && path.ends_with("/last") => {
+            let name = &path["/debug/feeds/".len()..path.len() - "/last".len()];
+            Ok(last_run(name, &state))
+        }
+        // ─── PUBLISHER PINGS ────────────────────────────────
+        (&Method::POST, "/ping") => Ok(weblog_ping(req, &state).await),
+        (_, path) if path.starts_with("/websub/") => Ok(websub(req, &state).await),
+        // ─── ADMIN ──────────────────────────────────────────
+        (_, path) if path.starts_with("/admin/") => Ok(admin(req, &state).await),
+        // ─── ANY OTHER ROUTE ────────────────────────────────
+        _ => Ok(Response::builder().status(404).body(Body::empty()).unwrap()),
+    }
+}
+
+/// `GET /readyz` — `OK` once the database schema matches this binary; otherwise 503
+/// with the schema report
+fn readiness(state: &AppState) -> Response<Body> {
+    match state.readiness.report() {
+        Some(report) if report.is_compatible() => Response::new(Body::from("OK")),
+        Some(report) => Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                serde_json::json!({
+                    "error": "database schema is incompatible",
+                    "schema": report,
+                })
+                .to_string(),
+            ))
+            .expect("Failed to build readiness response"),
+        None => error_response(StatusCode::SERVICE_UNAVAILABLE, "schema not checked yet"),
+    }
+}
+
+#[derive(Serialize)]
+struct EntriesPage {
+    entries: Vec<FeedItem>,
+    limit: i64,
+    offset: i64,
+    /// Pass back as `cursor` for the next page; absent on the last page
+    next_cursor: Option<String>,
+}
+
+/// `GET /api/entries?feed_url=&feed_type=&tag=&parent_guid=&product=&since=&until=&q=&max_tlp=&defang=&limit=&offset=&cursor=`
+async fn list_entries(req: &Request<Body>, state: &AppState) -> Response<Body> {
+    let query = match EntryQuery::from_pairs(query_pairs(req)) {
+        Ok(q) => q,
+        Err(msg) => return error_response(StatusCode::BAD_REQUEST, &msg),
+    };
+    match state.storage.list_entries(&query).await {
+        Ok(entries) => {
+            let next_cursor = match entries.last() {
+                Some(last) if entries.len() as i64 == query.limit => {
+                    Some(Cursor::after(last).encode())
+                }
+                _ => None,
+            };
+            let entries = if query.defang {
+                entries.iter().map(enrich::defanged).collect()
+            } else {
+                entries
+            };
+            json_response(&EntriesPage {
+                entries,
+                limit: query.limit,
+                offset: query.offset,
+                next_cursor,
+            })
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to list entries");
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SearchPage {
+    results: Vec<SearchHit>,
+    limit: i64,
+    offset: i64,
+}
+
+/// `GET /api/search?q=&feed_url=&feed_type=&tag=&parent_guid=&product=&since=&until=&max_tlp=&defang=&limit=&offset=`
+async fn search_entries(req: &Request<Body>, state: &AppState) -> Response<Body> {
+    let query = match EntryQuery::from_pairs(query_pairs(req)) {
+        Ok(q) => q,
+        Err(msg) => return error_response(StatusCode::BAD_REQUEST, &msg),
+    };
+    let Some(q) = query.q.as_deref().filter(|q| !q.trim().is_empty()) else {
+        return error_response(StatusCode::BAD_REQUEST, "q is required");
+    };
+    let Some(pool) = state.storage.postgres() else {
+        return needs_postgres();
+    };
+    match db_utils::search_entries(pool, q, &query).await {
+        Ok(mut results) => {
+            if query.defang {
+                for hit in &mut results {
+                    hit.item = enrich::defanged(&hit.item);
+                }
+            }
+            json_response(&SearchPage {
+                results,
+                limit: query.limit,
+                offset: query.offset,
+            })
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to search entries");
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+        }
+    }
+}
+
+/// `GET /api/stix?<as /api/entries>` — one page of entries as a STIX 2.1 bundle; the
+/// next page's cursor, if any, is in the `X-Next-Cursor` header
+async fn stix_bundle(req: &Request<Body>, state: &AppState) -> Response<Body> {
+    let query = match EntryQuery::from_pairs(query_pairs(req)) {
+        Ok(q) => q,
+        Err(msg) => return error_response(StatusCode::BAD_REQUEST, &msg),
+    };
+    let entries = match state.storage.list_entries(&query).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!(error = %e, "Failed to list entries for STIX export");
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "database error");
+        }
+    };
+    let mut resp = Response::builder().header("Content-Type", stix::CONTENT_TYPE);
+    match entries.last() {
+        Some(last) if entries.len() as i64 == query.limit => {
+            resp = resp.header("X-Next-Cursor", Cursor::after(last).encode());
+        }
+        _ => {}
+    }
+    resp.body(Body::from(stix::bundle(&entries).to_string()))
+        .expect("Failed to build STIX response")
+}
+
+/// `GET /costs?days=N` — per-feed usage over the last N days (default 7)
+async fn cost_breakdown(req: &Request<Body>, state: &AppState) -> Response<Body> {
+    let days = match query_pairs(req).into_iter().find(|(k, _)| k == "days") {
+        Some((_, v)) => match v.parse::<i32>() {
+            Ok(d) if d > 0 => d,
+            _ => return error_response(StatusCode::BAD_REQUEST, "days must be a positive integer"),
+        },
+        None => 7,
+    };
+    let Some(pool) = state.storage.postgres() else {
+        return needs_postgres();
+    };
+    match costs::cost_breakdown(pool, days).await {
+        Ok(rows) => json_response(&rows),
+        Err(e) => {
+            error!(error = %e, "Failed to load cost breakdown");
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+        }
+    }
+}
+
+/// `GET /state` — operational state including the live schedule, for `--restore-state`
+async fn export_state(state: &AppState) -> Response<Body> {
+    let Some(pool) = state.storage.postgres() else {
+        return needs_postgres();
+    };
+    match crate::state::dump(pool, Some(&state.schedule)).await {
+        Ok(snapshot) => json_response(&snapshot),
+        Err(e) => {
+            error!(error = %e, "Failed to export state");
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+        }
+    }
+}
+
+/// `GET /icons/{feed}` — the cached icon of the feed with that (URL-encoded) name
+async fn feed_icon(encoded_name: &str, state: &AppState) -> Response<Body> {
+    let Some(pool) = state.storage.postgres() else {
+        return needs_postgres();
+    };
+    let name = percent_decode_str(encoded_name).decode_utf8_lossy();
+    match icons::load(pool, &name).await {
+        Ok(Some(icon)) => Response::builder()
+            .header("Content-Type", icon.content_type)
+            .header("Cache-Control", "public, max-age=86400")
+            .body(Body::from(icon.data))
+            .expect("Failed to build icon response"),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "no cached icon for this feed"),
+        Err(e) => {
+            error!(error = %e, "Failed to load feed icon");
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+        }
+    }
+}
+
+/// `GET /feeds/{name}/history?limit=N` — the feed's latest runs, newest first (default 100)
+async fn feed_history(encoded_name: &str, req: &Request<Body>, state: &AppState) -> Response<Body> {
+    let name = percent_decode_str(encoded_name).decode_utf8_lossy();
+    let limit = match query_pairs(req).into_iter().find(|(k, _)| k == "limit") {
+        Some((_, v)) => match v.parse::<i64>() {
+            Ok(n) if n > 0 => n.min(1000),
+            _ => {
+                return error_response(StatusCode::BAD_REQUEST, "limit must be a positive integer")
+            }
+        },
+        None => 100,
+    };
+    match state.storage.recent_history(&name, limit).await {
+        Ok(rows) => json_response(&rows),
+        Err(e) => {
+            error!(error = %e, "Failed to load fetch history");
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+        }
+    }
+}
+
+/// `GET /debug/feeds/{name}/last` — the feed's most recent run since startup, with
+/// each entry's content source, sanitization outcome and dedup decision
+fn last_run(encoded_name: &str, state: &AppState) -> Response<Body> {
+    let name = percent_decode_str(encoded_name).decode_utf8_lossy();
+    match state.last_runs.get(&name) {
+        Some(snapshot) => json_response(&snapshot),
+        None => error_response(StatusCode::NOT_FOUND, "no run recorded for this feed"),
+    }
+}
+
+/// `POST /graphql` — a GraphQL request (`{"query": ..., "variables": ...}`); errors
+/// in the query are reported in the response's `errors`, with status 200.
+#[cfg(feature = "graphql")]
+async fn graphql_query(req: Request<Body>, state: &AppState) -> Response<Body> {
+    let body = match read_body(req.into_body()).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let request: async_graphql::Request = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("invalid GraphQL request: {}", e),
+            )
+        }
+    };
+    let Some(pool) = state.storage.postgres() else {
+        return needs_postgres();
+    };
+    json_response(&graphql::execute(pool, &state.schedule, request).await)
+}
+
+/// `POST /ping` — an XML-RPC weblogs ping (up to `MAX_REQUEST_BODY`); fetches the
+/// feeds at or below the pinged URLs.
+async fn weblog_ping(req: Request<Body>, state: &AppState) -> Response<Body> {
+    let Some(pings) = &state.pings else {
+        return Response::builder().status(404).body(Body::empty()).unwrap();
+    };
+    let reply = |error: bool, message: &str| {
+        Response::builder()
+            .header("Content-Type", "text/xml")
+            .body(Body::from(ping::weblog_response(error, message)))
+            .unwrap()
+    };
+    let body = match read_body(req.into_body()).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let Some(params) = ping::weblog_ping_params(&String::from_utf8_lossy(&body)) else {
+        return reply(
+            true,
+            "Expected weblogUpdates.ping or weblogUpdates.extendedPing",
+        );
+    };
+    let names = pings.feeds_at(&params);
+    if names.is_empty() {
+        metrics::PINGS.with_label_values(&["unknown"]).inc();
+        return reply(true, "No feed at the pinged URL");
+    }
+    for name in &names {
+        info!(feed = %name, outcome = ?pings.ping(name), "Weblogs ping");
+    }
+    reply(false, "Thanks for the ping")
+}
+
+/// `/websub/{feed}` — a WebSub subscriber callback. `GET` confirms the hub's
+/// (un)subscription request for the feed's own URL; `POST` is a content notification
+/// that fetches the feed, so the hub's copy of the content is never trusted (nor read).
+async fn websub(req: Request<Body>, state: &AppState) -> Response<Body> {
+    let not_found = || Response::builder().status(404).body(Body::empty()).unwrap();
+    let Some(pings) = &state.pings else {
+        return not_found();
+    };
+    let name = percent_decode_str(&req.uri().path()["/websub/".len()..])
+        .decode_utf8_lossy()
+        .into_owned();
+    let Some(feed) = pings.feed(&name) else {
+        metrics::PINGS.with_label_values(&["unknown"]).inc();
+        return not_found();
+    };
+    match *req.method() {
+        Method::GET => {
+            let params: std::collections::HashMap<String, String> =
+                query_pairs(&req).into_iter().collect();
+            let param = |key: &str| params.get(key).map(String::as_str).unwrap_or_default();
+            let mode_ok = matches!(param("hub.mode"), "subscribe" | "unsubscribe");
+            let topic_ok =
+                param("hub.topic").trim_end_matches('/') == feed.url.trim_end_matches('/');
+            if mode_ok && topic_ok && !param("hub.challenge").is_empty() {
+                info!(feed = %name, mode = param("hub.mode"), "Confirmed WebSub subscription");
+                Response::new(Body::from(param("hub.challenge").to_string()))
+            } else {
+                not_found()
+            }
+        }
+        Method::POST => {
+            let outcome = pings.ping(&name);
+            info!(feed = %name, ?outcome, "WebSub notification");
+            // Hubs retry anything but 2xx, so a ping that is too soon is acknowledged too
+            let status = match outcome {
+                Outcome::Dropped => StatusCode::SERVICE_UNAVAILABLE,
+                Outcome::Queued | Outcome::TooSoon => StatusCode::ACCEPTED,
+            };
+            Response::builder()
+                .status(status)
+                .body(Body::empty())
+                .unwrap()
+        }
+        _ => Response::builder().status(405).body(Body::empty()).unwrap(),
+    }
+}
+
+/// `/admin/` endpoints, for holders of `admin.token`:
+/// - `GET /admin/tag-rules` lists the stored tag rules
+/// - `POST /admin/tag-rules` stores the rule in the JSON body (`tag`, `keywords`, `pattern`)
+/// - `DELETE /admin/tag-rules/{id}` deletes one
+///
+/// Rule changes apply here at once, and on other instances from their next cycle.
+async fn admin(req: Request<Body>, state: &AppState) -> Response<Body> {
+    let Some(token) = &state.admin_token else {
+        return Response::builder().status(404).body(Body::empty()).unwrap();
+    };
+    if !authorized(&req, token) {
+        return error_response(StatusCode::UNAUTHORIZED, "invalid or missing admin token");
+    }
+    let Some(pool) = state.storage.postgres() else {
+        return needs_postgres();
+    };
+    let path = req.uri().path().to_string();
+    let changes_rules = req.method() != Method::GET;
+    let response = match (req.method(), path.as_str()) {
+        (&Method::GET, "/admin/tag-rules") => tagging::stored_rules(pool)
+            .await
+            .map(|rules| json_response(&rules)),
+        (&Method::POST, "/admin/tag-rules") => {
+            let body = match read_body(req.into_body()).await {
+                Ok(body) => body,
+                Err(response) => return response,
+            };
+            let rule: TagRule = match serde_json::from_slice(&body).ok() {
+                Some(rule) => rule,
+                None => {
+                    return error_response(
+                        StatusCode::BAD_REQUEST,
+                        "expected a JSON tag rule: tag, keywords and/or pattern",
+                    )
+                }
+            };
+            match tagging::add_rule(pool, &rule).await {
+                Ok(stored) => {
+                    info!(id = stored.id, tag = %stored.tag, "Added tag rule");
+                    let mut response = json_response(&stored);
+                    *response.status_mut() = StatusCode::CREATED;
+                    Ok(response)
+                }
+                Err(IngestError::TagRule(e)) => {
+                    return error_response(StatusCode::BAD_REQUEST, &e);
+                }
+                Err(e) => Err(e),
+            }
+        }
+        (&Method::DELETE, path) if path.starts_with("/admin/tag-rules/") => {
+            let Ok(id) = path["/admin/tag-rules/".len()..].parse::<i64>() else {
+                return error_response(StatusCode::BAD_REQUEST, "invalid rule id");
+            };
+            tagging::delete_rule(pool, id).await.map(|deleted| {
+                if deleted {
+                    info!(id, "Deleted tag rule");
+                    Response::builder().status(204).body(Body::empty()).unwrap()
+                } else {
+                    error_response(StatusCode::NOT_FOUND, "no such tag rule")
+                }
+            })
+        }
+        _ => return Response::builder().status(404).body(Body::empty()).unwrap(),
+    };
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            error!(error = %e, "Failed to manage tag rules");
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "database error");
+        }
+    };
+    if changes_rules && response.status().is_success() {
+        if let Err(e) = tagging::refresh(pool).await {
+            error!(error = %e, "Failed to reload tag rules");
+        }
+    }
+    response
+}
+
+/// Whether `req` carries `Authorization: Bearer <token>`. Digests are compared so
+/// the time taken does not reveal how much of the token matched.
+fn authorized(req: &Request<Body>, token: &str) -> bool {
+    let given = req
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    given.is_some_and(|given| Sha256::digest(given) == Sha256::digest(token))
+}
+
+/// Decoded query-string pairs of `req`.
+fn query_pairs(req: &Request<Body>) -> Vec<(String, String)> {
+    req.uri()
+        .query()
+        .map(|q| form_urlencoded::parse(q.as_bytes()).into_owned().collect())
+        .unwrap_or_default()
+}
+
+/// The whole of `body`, or the response to send instead: `413` past `MAX_REQUEST_BODY`,
+/// `400` if it cannot be read.
+async fn read_body(mut body: Body) -> Result<Vec<u8>, Response<Body>> {
+    let too_large = || error_response(StatusCode::PAYLOAD_TOO_LARGE, "request body too large");
+    if body.size_hint().lower() > MAX_REQUEST_BODY as u64 {
+        return Err(too_large());
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk =
+            chunk.map_err(|_| error_response(StatusCode::BAD_REQUEST, "unreadable body"))?;
+        if bytes.len() + chunk.len() > MAX_REQUEST_BODY {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// A JSON `{"error": ...}` response with the given status.
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            serde_json::json!({ "error": message }).to_string(),
+        ))
+        .expect("Failed to build error response")
+}
+
+/// `501 Not Implemented`, from an endpoint served only from PostgreSQL.
+fn needs_postgres() -> Response<Body> {
+    error_response(
+        StatusCode::NOT_IMPLEMENTED,
+        "this endpoint needs a PostgreSQL database",
+    )
+}
+
+/// Serialize `value` as a `200 OK` JSON response.
+fn json_response<T: Serialize>(value: &T) -> Response<Body> {
+    match serde_json::to_vec(value) {
+        Ok(body) => Response::builder()
+            .header("Content-Type", "application/json")
+            .body(Body::from(body))
+            .expect("Failed to build JSON response"),
+        Err(_) => Response::builder().status(500).body(Body::empty()).unwrap(),
+    }
+}
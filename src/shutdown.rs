@@ -0,0 +1,75 @@
+//! Process-wide shutdown signal shared by the ingestion loop, feed runs and the HTTP server.
+
+use std::sync::Arc;
+
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// Cloneable handle that flips once, either on SIGINT/SIGTERM or via `trigger`.
+#[derive(Clone)]
+pub struct Shutdown {
+    tx: Arc<watch::Sender<bool>>,
+    rx: watch::Receiver<bool>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        let (tx, rx) = watch::channel(false);
+        Shutdown {
+            tx: Arc::new(tx),
+            rx,
+        }
+    }
+}
+
+impl Shutdown {
+    /// A handle that is triggered when the process receives SIGINT or SIGTERM.
+    pub fn on_signals() -> Self {
+        let shutdown = Shutdown::default();
+        let handle = shutdown.clone();
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            info!("Shutdown signal received; finishing in-flight work");
+            handle.trigger();
+        });
+        shutdown
+    }
+
+    /// Ask every holder of this handle to stop.
+    pub fn trigger(&self) {
+        self.tx.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.rx.borrow()
+    }
+
+    /// Resolve once shutdown has been triggered.
+    pub async fn wait(&self) {
+        let mut rx = self.rx.clone();
+        // `self` holds the sender, so the channel cannot close under us
+        let _ = rx.wait_for(|triggered| *triggered).await;
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = sigterm.recv() => {}
+            }
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to install SIGTERM handler; only Ctrl-C will stop gracefully");
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,114 @@
+//! Kafka sink publishing each entry as JSON (or a rendered template) via `rdkafka`.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rdkafka::config::ClientConfig;
+use rdkafka::message::{Header, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+
+use super::Sink;
+use crate::config::{KafkaKey, KafkaSettings};
+use crate::errors::IngestError;
+use crate::ingestor::{Change, FeedItem};
+use crate::templates::Templates;
+use crate::tlp::Tlp;
+
+/// Message header saying whether the entry is `new`, `updated` or `unchanged`.
+pub const EVENT_HEADER: &str = "entry-event";
+
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+    key: KafkaKey,
+    timeout: Duration,
+    template: Option<String>,
+    templates: Templates,
+    max_tlp: Option<Tlp>,
+    defang: bool,
+    redistributable_only: bool,
+}
+
+impl KafkaSink {
+    pub fn new(settings: &KafkaSettings, templates: Templates) -> Result<Self, IngestError> {
+        if let Some(name) = &settings.template {
+            templates.check(name)?;
+        }
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", &settings.brokers);
+        for (k, v) in &settings.properties {
+            config.set(k, v);
+        }
+        let producer = config
+            .create()
+            .map_err(|e| IngestError::Sink("kafka".into(), e.to_string()))?;
+        Ok(KafkaSink {
+            producer,
+            topic: settings.topic.clone(),
+            key: settings.key,
+            timeout: settings.send_timeout,
+            template: settings.template.clone(),
+            templates,
+            max_tlp: settings.max_tlp,
+            defang: settings.defang,
+            redistributable_only: settings.redistributable_only,
+        })
+    }
+}
+
+#[async_trait]
+impl Sink for KafkaSink {
+    fn name(&self) -> &str {
+        "kafka"
+    }
+
+    fn max_tlp(&self) -> Option<Tlp> {
+        self.max_tlp
+    }
+
+    fn defang(&self) -> bool {
+        self.defang
+    }
+
+    fn redistributable_only(&self) -> bool {
+        self.redistributable_only
+    }
+
+    async fn publish(
+        &self,
+        feed_name: &str,
+        item: &FeedItem,
+        change: Change,
+    ) -> Result<(), IngestError> {
+        let payload = match &self.template {
+            Some(name) => self
+                .templates
+                .render_item(name, feed_name, item)?
+                .into_bytes(),
+            None => serde_json::to_vec(item)
+                .map_err(|e| IngestError::Sink("kafka".into(), e.to_string()))?,
+        };
+        let key = match self.key {
+            KafkaKey::Guid => Some(item.guid.as_str()),
+            KafkaKey::FeedUrl => Some(item.feed_url.as_str()),
+            KafkaKey::None => None,
+        };
+        // Entries are published every time they are stored; the header tells them apart
+        let mut record =
+            FutureRecord::to(&self.topic)
+                .payload(&payload)
+                .headers(OwnedHeaders::new().insert(Header {
+                    key: EVENT_HEADER,
+                    value: Some(change.as_str()),
+                }));
+        if let Some(key) = key {
+            record = record.key(key);
+        }
+        self.producer
+            .send(record, Timeout::After(self.timeout))
+            .await
+            .map_err(|(e, _)| IngestError::Sink("kafka".into(), e.to_string()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,255 @@
+//! Output sinks: every successfully processed `FeedItem` is handed to each configured sink.
+//! Each sink delivers from its own bounded queue in a background worker, so a slow or
+//! retrying receiver never holds up storing entries.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::config::{LicensingSettings, Settings};
+use crate::enrich;
+use crate::errors::IngestError;
+use crate::ingestor::{Change, FeedItem};
+use crate::license;
+use crate::metrics::{
+    ALERTS_SUPPRESSED, LICENSE_WITHHELD, SINK_DELIVERIES, SINK_HISTOGRAM, SINK_LAST_SUCCESS,
+    TLP_WITHHELD,
+};
+use crate::templates::Templates;
+use crate::tlp::{self, Tlp};
+use suppress::Suppressor;
+
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod suppress;
+pub mod webhook;
+
+/// A destination for processed entries.
+#[async_trait]
+pub trait Sink: Send + Sync {
+    /// Short name used in logs and metrics.
+    fn name(&self) -> &str;
+
+    /// Most restrictive TLP this sink may receive; `None` accepts everything.
+    fn max_tlp(&self) -> Option<Tlp> {
+        None
+    }
+
+    /// Whether this sink receives entries with their indicators defanged.
+    fn defang(&self) -> bool {
+        false
+    }
+
+    /// Whether this sink must not receive entries whose license forbids redistribution.
+    fn redistributable_only(&self) -> bool {
+        false
+    }
+
+    /// Whether this sink only wants entries new to the archive, not updates of known ones.
+    fn new_entries_only(&self) -> bool {
+        false
+    }
+
+    /// Whether a sink wanting only new entries also wants known ones whose title,
+    /// summary or content changed.
+    fn updated_entries(&self) -> bool {
+        false
+    }
+
+    /// Deliver one entry from the feed named `feed_name`, with what storing it changed.
+    /// Sinks retrying internally count each retry in `metrics::SINK_RETRIES`.
+    async fn publish(
+        &self,
+        feed_name: &str,
+        item: &FeedItem,
+        change: Change,
+    ) -> Result<(), IngestError>;
+}
+
+/// Deliveries waiting per sink; further ones are dropped (and counted) until it catches up
+const QUEUE: usize = 1024;
+
+/// One entry on its way to a sink.
+struct Delivery {
+    feed_name: String,
+    item: FeedItem,
+    change: Change,
+}
+
+/// A sink and the queue of its delivery worker.
+#[derive(Clone)]
+struct Queued {
+    sink: Arc<dyn Sink>,
+    tx: mpsc::Sender<Delivery>,
+}
+
+impl Queued {
+    fn start(sink: Arc<dyn Sink>) -> Self {
+        let (tx, rx) = mpsc::channel(QUEUE);
+        tokio::spawn(deliver(sink.clone(), rx));
+        Queued { sink, tx }
+    }
+}
+
+/// The set of sinks configured for this process.
+#[derive(Clone, Default)]
+pub struct Sinks {
+    sinks: Vec<Queued>,
+    /// Drops repeats of a recently delivered story, when a window is configured
+    suppressor: Option<Arc<Suppressor>>,
+    licensing: Arc<LicensingSettings>,
+}
+
+impl Sinks {
+    /// Build every sink enabled in `settings`, starting their delivery workers.
+    pub fn from_settings(settings: &Settings) -> Result<Self, IngestError> {
+        let mut sinks: Vec<Arc<dyn Sink>> = Vec::new();
+        let templates = Templates::from_settings(&settings.templates)?;
+
+        if let Some(kafka) = &settings.kafka {
+            #[cfg(feature = "kafka")]
+            sinks.push(Arc::new(kafka::KafkaSink::new(kafka, templates.clone())?));
+            #[cfg(not(feature = "kafka"))]
+            {
+                let _ = kafka;
+                return Err(IngestError::Sink(
+                    "kafka".into(),
+                    "configured but this build lacks the `kafka` feature".into(),
+                ));
+            }
+        }
+
+        for webhook in &settings.webhooks {
+            sinks.push(Arc::new(webhook::WebhookSink::new(
+                webhook,
+                templates.clone(),
+            )?));
+        }
+
+        Ok(Sinks {
+            sinks: sinks.into_iter().map(Queued::start).collect(),
+            suppressor: settings
+                .suppression_window
+                .map(|window| Arc::new(Suppressor::new(window))),
+            licensing: Arc::new(settings.licensing.clone()),
+        })
+    }
+
+    /// Add a sink (library users can plug in their own implementations), starting its
+    /// delivery worker.
+    pub fn push(&mut self, sink: Arc<dyn Sink>) {
+        self.sinks.push(Queued::start(sink));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Queue `item` (from the feed named `feed_name`) for every sink; a full queue drops
+    /// the delivery for that sink only. No sink receives an item above its `max_tlp` (or,
+    /// if `redistributable_only`, under a restricted license), and sinks wanting only new
+    /// entries are skipped unless `change` is new (or updated, for those also wanting
+    /// `updated_entries`). A new item going to at least one sink is skipped if it repeats
+    /// a story delivered within the suppression window. Sinks asking for it get the item
+    /// defanged.
+    pub fn publish(&self, feed_name: &str, item: &FeedItem, change: Change) {
+        if self.sinks.is_empty() {
+            return;
+        }
+        let mut recipients = Vec::with_capacity(self.sinks.len());
+        for queued in &self.sinks {
+            let sink = &queued.sink;
+            let wanted = match change {
+                Change::New => true,
+                Change::Updated => sink.updated_entries(),
+                Change::Duplicate | Change::Unchanged => false,
+            };
+            if sink.new_entries_only() && !wanted {
+                continue;
+            }
+            if !tlp::permits(sink.max_tlp(), item.tlp) {
+                TLP_WITHHELD.with_label_values(&[sink.name()]).inc();
+                debug!(sink = sink.name(), guid = %item.guid, tlp = ?item.tlp, "Withheld by TLP");
+                continue;
+            }
+            if sink.redistributable_only()
+                && !license::permits_redistribution(&self.licensing, item.license.as_deref())
+            {
+                LICENSE_WITHHELD.with_label_values(&[sink.name()]).inc();
+                debug!(sink = sink.name(), guid = %item.guid, license = ?item.license, "Withheld by license");
+                continue;
+            }
+            recipients.push(queued);
+        }
+        if recipients.is_empty() {
+            return;
+        }
+        // Only a delivered new story counts: an update is news about one already
+        // delivered, and an unchanged entry is no news at all
+        if let Some(suppressor) = self.suppressor.as_ref().filter(|_| change == Change::New) {
+            if suppressor.suppress(&item.title) {
+                ALERTS_SUPPRESSED.with_label_values(&[feed_name]).inc();
+                debug!(guid = %item.guid, title = %item.title, "Suppressed repeat alert");
+                return;
+            }
+        }
+        let mut defanged = None;
+        for queued in recipients {
+            let item = if queued.sink.defang() {
+                defanged.get_or_insert_with(|| enrich::defanged(item))
+            } else {
+                item
+            };
+            let delivery = Delivery {
+                feed_name: feed_name.to_string(),
+                item: item.clone(),
+                change,
+            };
+            if queued.tx.try_send(delivery).is_err() {
+                SINK_DELIVERIES
+                    .with_label_values(&[queued.sink.name(), "dropped"])
+                    .inc();
+                warn!(sink = queued.sink.name(), guid = %item.guid, "Sink queue full; delivery dropped");
+            }
+        }
+    }
+}
+
+/// Deliver `sink`'s queue in order; failures are logged and the next entry is tried.
+/// Every delivery is counted, timed and its last success recorded.
+async fn deliver(sink: Arc<dyn Sink>, mut rx: mpsc::Receiver<Delivery>) {
+    while let Some(Delivery {
+        feed_name,
+        item,
+        change,
+    }) = rx.recv().await
+    {
+        SINK_DELIVERIES
+            .with_label_values(&[sink.name(), "attempted"])
+            .inc();
+        let timer = SINK_HISTOGRAM
+            .with_label_values(&[sink.name()])
+            .start_timer();
+        let result = sink.publish(&feed_name, &item, change).await;
+        timer.observe_duration();
+        match result {
+            Ok(()) => {
+                SINK_DELIVERIES
+                    .with_label_values(&[sink.name(), "succeeded"])
+                    .inc();
+                SINK_LAST_SUCCESS
+                    .with_label_values(&[sink.name()])
+                    .set(Utc::now().timestamp());
+            }
+            Err(e) => {
+                SINK_DELIVERIES
+                    .with_label_values(&[sink.name(), "failed"])
+                    .inc();
+                warn!(sink = sink.name(), guid = %item.guid, error = %e, "Sink delivery failed");
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Time-window suppression of repeat alerts: the same story syndicated across many
+//! feeds is delivered to the sinks once per window.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Remembers recently delivered stories, keyed by normalized title.
+#[derive(Debug)]
+pub struct Suppressor {
+    window: Duration,
+    seen: Mutex<HashMap<String, Instant>>,
+}
+
+impl Suppressor {
+    pub fn new(window: Duration) -> Self {
+        Suppressor {
+            window,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether a story titled `title` was already delivered within the window.
+    /// If not, it is recorded as delivered now.
+    pub fn suppress(&self, title: &str) -> bool {
+        let key = normalize_title(title);
+        if key.is_empty() {
+            return false;
+        }
+        let now = Instant::now();
+        let mut seen = self.seen.lock().expect("suppression map poisoned");
+        if let Some(at) = seen.get(&key) {
+            if now.duration_since(*at) < self.window {
+                return true;
+            }
+        }
+        seen.retain(|_, at| now.duration_since(*at) < self.window);
+        seen.insert(key, now);
+        false
+    }
+}
+
+/// Lowercase alphanumeric words joined by single spaces, so punctuation, case and
+/// whitespace differences between outlets do not defeat suppression.
+pub fn normalize_title(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
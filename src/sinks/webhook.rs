@@ -0,0 +1,256 @@
+//! Webhook sink: POSTs each entry new to the archive (and, if configured, each update
+//! of a known one) as JSON or rendered from a template, optionally signed. With a
+//! `digest` period the entries are collected instead and POSTed together, rendered by
+//! the template, once per period.
+
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Response};
+use sha2::Sha256;
+use tracing::{debug, warn};
+
+use super::Sink;
+use crate::config::{RetrySettings, WebhookSettings};
+use crate::errors::IngestError;
+use crate::ingestor::{backoff_delay, is_transient, Change, FeedItem};
+use crate::metrics::{SINK_DELIVERIES, SINK_RETRIES};
+use crate::templates::Templates;
+use crate::tlp::Tlp;
+
+/// Header carrying `sha256=<hex HMAC of the body>` when a secret is configured.
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+
+/// Header saying whether the entry is `new` or `updated`, or that the body is a `digest`.
+pub const EVENT_HEADER: &str = "X-Entry-Event";
+
+/// Entries a digest holds; later ones in the same period are dropped (and counted).
+const MAX_DIGEST_ITEMS: usize = 1000;
+
+pub struct WebhookSink {
+    poster: Arc<Poster>,
+    updates: bool,
+    max_tlp: Option<Tlp>,
+    defang: bool,
+    redistributable_only: bool,
+    template: Option<String>,
+    templates: Templates,
+    /// Entries waiting for the next digest, when `digest` is set
+    pending: Option<Arc<Mutex<Vec<FeedItem>>>>,
+}
+
+/// Sends bodies to the webhook, retrying transient failures.
+struct Poster {
+    client: Client,
+    url: String,
+    /// `webhook:<host>`, so logs and metrics never show the path or query
+    name: String,
+    secret: Option<String>,
+    retry: RetrySettings,
+}
+
+impl WebhookSink {
+    /// Build the sink, starting its digest worker if `digest` is set.
+    pub fn new(settings: &WebhookSettings, templates: Templates) -> Result<Self, IngestError> {
+        if let Some(name) = &settings.template {
+            templates.check(name)?;
+        }
+        let client = Client::builder()
+            .timeout(settings.timeout)
+            .build()
+            .map_err(|e| IngestError::Sink("webhook".into(), e.to_string()))?;
+        let host = url::Url::parse(&settings.url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let poster = Arc::new(Poster {
+            client,
+            url: settings.url.clone(),
+            name: format!("webhook:{}", host),
+            secret: settings.secret.clone(),
+            retry: settings.retry.clone(),
+        });
+        let pending = match (settings.digest, &settings.template) {
+            (Some(period), Some(template)) => {
+                let pending = Arc::new(Mutex::new(Vec::new()));
+                tokio::spawn(send_digests(
+                    poster.clone(),
+                    templates.clone(),
+                    template.clone(),
+                    period,
+                    pending.clone(),
+                ));
+                Some(pending)
+            }
+            _ => None,
+        };
+        Ok(WebhookSink {
+            poster,
+            updates: settings.updates,
+            max_tlp: settings.max_tlp,
+            defang: settings.defang,
+            redistributable_only: settings.redistributable_only,
+            template: settings.template.clone(),
+            templates,
+            pending,
+        })
+    }
+}
+
+/// POST the entries collected in `pending` every `period`, rendered by `template`;
+/// a period without entries sends nothing.
+async fn send_digests(
+    poster: Arc<Poster>,
+    templates: Templates,
+    template: String,
+    period: Duration,
+    pending: Arc<Mutex<Vec<FeedItem>>>,
+) {
+    let mut ticks = tokio::time::interval(period);
+    // The first tick completes at once
+    ticks.tick().await;
+    loop {
+        ticks.tick().await;
+        let items = mem::take(&mut *pending.lock().expect("digest poisoned"));
+        if items.is_empty() {
+            continue;
+        }
+        let result = match templates.render_digest(&template, &items) {
+            Ok(body) => {
+                poster
+                    .post(body.into_bytes(), &[(EVENT_HEADER, "digest")])
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => debug!(sink = %poster.name, items = items.len(), "Sent digest"),
+            Err(e) => {
+                SINK_DELIVERIES
+                    .with_label_values(&[&poster.name, "failed"])
+                    .inc_by(items.len() as u64);
+                warn!(sink = %poster.name, items = items.len(), error = %e, "Digest delivery failed");
+            }
+        }
+    }
+}
+
+impl Poster {
+    fn signature(&self, body: &[u8]) -> Option<String> {
+        let secret = self.secret.as_ref()?;
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key");
+        mac.update(body);
+        Some(format!("sha256={:x}", mac.finalize().into_bytes()))
+    }
+
+    /// POST `body` with `headers`, retrying per `retry`.
+    async fn post(&self, body: Vec<u8>, headers: &[(&str, &str)]) -> Result<(), IngestError> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "application/json");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        if let Some(signature) = self.signature(&body) {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+        let request = request.body(body);
+
+        let mut attempt = 1;
+        loop {
+            let err = match request
+                .try_clone()
+                .expect("body is in memory")
+                .send()
+                .await
+                .and_then(Response::error_for_status)
+            {
+                Ok(_) => return Ok(()),
+                Err(e) => e,
+            };
+            if attempt >= self.retry.max_attempts || !is_transient(&err) {
+                return Err(IngestError::Sink(
+                    self.name.clone(),
+                    err.without_url().to_string(),
+                ));
+            }
+            let delay = backoff_delay(&self.retry, attempt);
+            warn!(
+                sink = %self.name,
+                attempt,
+                delay_ms = delay.as_millis() as u64,
+                error = %err.without_url(),
+                "Webhook delivery failed; retrying"
+            );
+            SINK_RETRIES.with_label_values(&[&self.name]).inc();
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for WebhookSink {
+    fn name(&self) -> &str {
+        &self.poster.name
+    }
+
+    fn max_tlp(&self) -> Option<Tlp> {
+        self.max_tlp
+    }
+
+    fn defang(&self) -> bool {
+        self.defang
+    }
+
+    fn redistributable_only(&self) -> bool {
+        self.redistributable_only
+    }
+
+    fn new_entries_only(&self) -> bool {
+        true
+    }
+
+    fn updated_entries(&self) -> bool {
+        self.updates
+    }
+
+    async fn publish(
+        &self,
+        feed_name: &str,
+        item: &FeedItem,
+        change: Change,
+    ) -> Result<(), IngestError> {
+        if let Some(pending) = &self.pending {
+            let mut pending = pending.lock().expect("digest poisoned");
+            if pending.len() >= MAX_DIGEST_ITEMS {
+                SINK_DELIVERIES
+                    .with_label_values(&[&self.poster.name, "dropped"])
+                    .inc();
+                warn!(sink = %self.poster.name, guid = %item.guid, "Digest full; entry dropped");
+            } else {
+                pending.push(item.clone());
+            }
+            return Ok(());
+        }
+        let body = match &self.template {
+            Some(name) => self
+                .templates
+                .render_item(name, feed_name, item)?
+                .into_bytes(),
+            None => serde_json::to_vec(item)
+                .map_err(|e| IngestError::Sink(self.poster.name.clone(), e.to_string()))?,
+        };
+        self.poster
+            .post(
+                body,
+                &[("X-Feed-Name", feed_name), (EVENT_HEADER, change.as_str())],
+            )
+            .await
+    }
+}
@@ -0,0 +1,145 @@
+//! Per-cycle item volume limits. Past the soft limit a cycle only warns; past the
+//! hard limit further items are deferred to `spillover_entries` and stored a batch
+//! at a time at the start of later cycles.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use sqlx::PgPool;
+use tracing::warn;
+
+use crate::config::CycleLimits;
+use crate::errors::IngestError;
+use crate::ingestor::FeedItem;
+use crate::metrics::CYCLE_SOFT_LIMIT_EXCEEDED;
+
+/// Items stored so far in the current cycle, shared by all of its feed runs.
+#[derive(Debug, Clone, Default)]
+pub struct CycleBudget {
+    limits: CycleLimits,
+    inner: Arc<BudgetState>,
+}
+
+#[derive(Debug, Default)]
+struct BudgetState {
+    used: AtomicUsize,
+    soft_warned: AtomicBool,
+    spill_warned: AtomicBool,
+}
+
+impl CycleBudget {
+    pub fn new(limits: CycleLimits) -> Self {
+        CycleBudget {
+            limits,
+            inner: Arc::default(),
+        }
+    }
+
+    /// Start counting a new cycle.
+    pub fn reset(&self) {
+        self.inner.used.store(0, Ordering::SeqCst);
+        self.inner.soft_warned.store(false, Ordering::SeqCst);
+        self.inner.spill_warned.store(false, Ordering::SeqCst);
+    }
+
+    /// Claim room for one item; `false` once the hard limit is reached.
+    pub fn try_take(&self) -> bool {
+        let hard = self.limits.hard_items.unwrap_or(usize::MAX);
+        let taken = self
+            .inner
+            .used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                (used < hard).then_some(used + 1)
+            });
+        match taken {
+            Ok(used) => {
+                if self.limits.soft_items.is_some_and(|soft| used + 1 > soft)
+                    && !self.inner.soft_warned.swap(true, Ordering::SeqCst)
+                {
+                    CYCLE_SOFT_LIMIT_EXCEEDED.inc();
+                    warn!(
+                        soft_items = self.limits.soft_items,
+                        "Cycle item volume passed the soft limit"
+                    );
+                }
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Return room claimed for `n` items that were not stored after all.
+    pub fn give_back(&self, n: usize) {
+        let _ = self
+            .inner
+            .used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                Some(used.saturating_sub(n))
+            });
+    }
+
+    /// Whether this is the cycle's first deferral (so the caller alerts once per cycle).
+    pub fn first_spill(&self) -> bool {
+        !self.inner.spill_warned.swap(true, Ordering::SeqCst)
+    }
+
+    /// Spilled items to store at the start of each cycle.
+    pub fn drain_per_cycle(&self) -> usize {
+        self.limits.drain_per_cycle
+    }
+}
+
+/// A deferred item awaiting storage.
+pub struct Spilled {
+    pub id: i64,
+    pub feed_name: String,
+    pub item: FeedItem,
+}
+
+/// Queue `item` for a later cycle.
+pub async fn defer(pool: &PgPool, feed_name: &str, item: &FeedItem) -> Result<(), IngestError> {
+    sqlx::query(
+        "INSERT INTO spillover_entries (feed_name, guid, item) VALUES ($1, $2, $3)
+        ON CONFLICT (guid) DO UPDATE SET item = EXCLUDED.item",
+    )
+    .bind(feed_name)
+    .bind(&item.guid)
+    .bind(sqlx::types::Json(item))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Up to `limit` deferred items, oldest first.
+pub async fn oldest(pool: &PgPool, limit: usize) -> Result<Vec<Spilled>, IngestError> {
+    let rows: Vec<(i64, String, sqlx::types::Json<FeedItem>)> =
+        sqlx::query_as("SELECT id, feed_name, item FROM spillover_entries ORDER BY id LIMIT $1")
+            .bind(limit as i64)
+            .fetch_all(pool)
+            .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, feed_name, item)| Spilled {
+            id,
+            feed_name,
+            item: item.0,
+        })
+        .collect())
+}
+
+/// Drop a stored item from the queue.
+pub async fn remove(pool: &PgPool, id: i64) -> Result<(), IngestError> {
+    sqlx::query("DELETE FROM spillover_entries WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Number of items waiting in the queue.
+pub async fn backlog(pool: &PgPool) -> Result<i64, IngestError> {
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM spillover_entries")
+        .fetch_one(pool)
+        .await?;
+    Ok(count)
+}
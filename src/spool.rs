@@ -0,0 +1,248 @@
+//! Disk-backed spool for entries that could not be written because Postgres was
+//! unreachable. Each entry is one JSON file in the spool directory, named so that
+//! lexical order is arrival order; the first cycle that finds the database answering
+//! again stores them. Bounded by entry count and total size, and holding each GUID once
+//! (feeds are refetched every cycle of an outage, since no cache validators are stored).
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::config::SpoolSettings;
+use crate::errors::IngestError;
+use crate::ingestor::FeedItem;
+use crate::metrics::{SPOOL_BYTES, SPOOL_ITEMS, SPOOL_WRITTEN};
+
+/// One write waiting for the database.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpooledEntry {
+    pub feed_name: String,
+    /// Staged for the feed's canary rather than stored
+    pub staged: bool,
+    pub item: FeedItem,
+}
+
+/// Handle on the spool directory, shared by all feed runs.
+#[derive(Debug, Clone, Default)]
+pub struct Spool {
+    settings: SpoolSettings,
+    inner: Arc<SpoolState>,
+}
+
+#[derive(Debug, Default)]
+struct SpoolState {
+    /// Set by the first unreachable write, cleared once a cycle finds the database back
+    outage: AtomicBool,
+    seq: AtomicU64,
+    held: Mutex<Held>,
+}
+
+/// What is on disk.
+#[derive(Debug, Default)]
+struct Held {
+    items: u64,
+    bytes: u64,
+    guids: HashSet<String>,
+}
+
+impl Held {
+    fn release(&mut self, guid: Option<&str>, size: u64) {
+        self.items = self.items.saturating_sub(1);
+        self.bytes = self.bytes.saturating_sub(size);
+        if let Some(guid) = guid {
+            self.guids.remove(guid);
+        }
+        self.publish();
+    }
+
+    fn publish(&self) {
+        SPOOL_ITEMS.set(self.items as i64);
+        SPOOL_BYTES.set(self.bytes as i64);
+    }
+}
+
+impl Spool {
+    /// Open the configured directory (creating it if needed) and count what an earlier
+    /// run left there. Without a directory the spool is disabled.
+    pub async fn open(settings: &SpoolSettings) -> Result<Self, IngestError> {
+        let spool = Spool {
+            settings: settings.clone(),
+            inner: Arc::default(),
+        };
+        let Some(dir) = &settings.dir else {
+            return Ok(spool);
+        };
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| spool_error(dir, e))?;
+        let mut held = Held::default();
+        for path in entry_files(dir).await? {
+            let bytes = tokio::fs::read(&path)
+                .await
+                .map_err(|e| spool_error(&path, e))?;
+            held.items += 1;
+            held.bytes += bytes.len() as u64;
+            if let Ok(entry) = serde_json::from_slice::<SpooledEntry>(&bytes) {
+                held.guids.insert(entry.item.guid);
+            }
+        }
+        held.publish();
+        *spool.inner.held.lock().unwrap() = held;
+        Ok(spool)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.settings.dir.is_some()
+    }
+
+    /// Entries waiting on disk.
+    pub fn len(&self) -> u64 {
+        self.inner.held.lock().unwrap().items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether writes currently go straight to the spool.
+    pub fn in_outage(&self) -> bool {
+        self.inner.outage.load(Ordering::SeqCst)
+    }
+
+    /// Send writes to the spool until `end_outage`; `true` if this started the outage.
+    pub fn begin_outage(&self) -> bool {
+        !self.inner.outage.swap(true, Ordering::SeqCst)
+    }
+
+    /// Write to the database again; `true` if an outage was in progress.
+    pub fn end_outage(&self) -> bool {
+        self.inner.outage.swap(false, Ordering::SeqCst)
+    }
+
+    /// Write `item` to disk, unless its GUID is already there. `Ok(false)` if the spool
+    /// is disabled or this would take it past `max_items` or `max_bytes`.
+    pub async fn push(
+        &self,
+        feed_name: &str,
+        item: &FeedItem,
+        staged: bool,
+    ) -> Result<bool, IngestError> {
+        let Some(dir) = &self.settings.dir else {
+            return Ok(false);
+        };
+        let entry = SpooledEntry {
+            feed_name: feed_name.to_string(),
+            staged,
+            item: item.clone(),
+        };
+        let json = serde_json::to_vec(&entry).map_err(|e| IngestError::Spool(e.to_string()))?;
+        let size = json.len() as u64;
+        {
+            let mut held = self.inner.held.lock().unwrap();
+            if held.guids.contains(&item.guid) {
+                return Ok(true);
+            }
+            if held.items >= self.settings.max_items || held.bytes + size > self.settings.max_bytes
+            {
+                return Ok(false);
+            }
+            held.items += 1;
+            held.bytes += size;
+            held.guids.insert(item.guid.clone());
+            held.publish();
+        }
+        let name = format!(
+            "{:020}-{:010}",
+            Utc::now().timestamp_micros(),
+            self.inner.seq.fetch_add(1, Ordering::SeqCst)
+        );
+        // Written under a temporary name so a crash never leaves half an entry
+        let tmp = dir.join(format!("{}.tmp", name));
+        let written = async {
+            tokio::fs::write(&tmp, &json).await?;
+            tokio::fs::rename(&tmp, dir.join(format!("{}.json", name))).await
+        };
+        if let Err(e) = written.await {
+            self.release(Some(&item.guid), size);
+            return Err(spool_error(&tmp, e));
+        }
+        SPOOL_WRITTEN.with_label_values(&[feed_name]).inc();
+        Ok(true)
+    }
+
+    /// Up to `limit` spooled entries, oldest first. Unreadable ones are set aside.
+    pub async fn oldest(&self, limit: usize) -> Result<Vec<(PathBuf, SpooledEntry)>, IngestError> {
+        let Some(dir) = &self.settings.dir else {
+            return Ok(Vec::new());
+        };
+        let mut entries = Vec::new();
+        for path in entry_files(dir).await? {
+            if entries.len() >= limit {
+                break;
+            }
+            let read = tokio::fs::read(&path).await.map_err(|e| e.to_string());
+            match read.and_then(|bytes| serde_json::from_slice(&bytes).map_err(|e| e.to_string())) {
+                Ok(entry) => entries.push((path, entry)),
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "Unreadable spool entry; setting it aside");
+                    self.discard(&path, None).await?;
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Delete a stored entry.
+    pub async fn remove(&self, path: &Path, entry: &SpooledEntry) -> Result<(), IngestError> {
+        let size = tokio::fs::metadata(path).await.map_or(0, |m| m.len());
+        tokio::fs::remove_file(path)
+            .await
+            .map_err(|e| spool_error(path, e))?;
+        self.release(Some(&entry.item.guid), size);
+        Ok(())
+    }
+
+    /// Rename an entry that cannot be stored to `*.bad`, out of the queue but kept for
+    /// the operator.
+    pub async fn discard(
+        &self,
+        path: &Path,
+        entry: Option<&SpooledEntry>,
+    ) -> Result<(), IngestError> {
+        let size = tokio::fs::metadata(path).await.map_or(0, |m| m.len());
+        tokio::fs::rename(path, path.with_extension("bad"))
+            .await
+            .map_err(|e| spool_error(path, e))?;
+        self.release(entry.map(|e| e.item.guid.as_str()), size);
+        Ok(())
+    }
+
+    fn release(&self, guid: Option<&str>, size: u64) {
+        self.inner.held.lock().unwrap().release(guid, size);
+    }
+}
+
+/// Spooled entry files in `dir`, oldest first.
+async fn entry_files(dir: &Path) -> Result<Vec<PathBuf>, IngestError> {
+    let mut files = Vec::new();
+    let mut read = tokio::fs::read_dir(dir)
+        .await
+        .map_err(|e| spool_error(dir, e))?;
+    while let Some(file) = read.next_entry().await.map_err(|e| spool_error(dir, e))? {
+        let path = file.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn spool_error(path: &Path, e: std::io::Error) -> IngestError {
+    IngestError::Spool(format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,184 @@
+//! Export and import of operational state as one JSON document, so a replacement
+//! instance (e.g. the other half of a blue/green deploy) can take over mid-interval
+//! without re-fetching every feed.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::errors::IngestError;
+use crate::scheduler::{self, FeedSchedule, Schedule};
+
+/// Bumped whenever the document layout changes incompatibly.
+pub const STATE_VERSION: u32 = 1;
+
+/// Everything an instance needs to resume where another left off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub feed_cache: Vec<FeedCacheRow>,
+    pub feed_registry: Vec<FeedRegistryRow>,
+    pub canary_entries: Vec<CanaryEntryRow>,
+    pub feed_costs: Vec<FeedCostRow>,
+    /// The running instance's schedule, or else the one saved in `feed_schedule`
+    #[serde(default)]
+    pub schedule: Vec<FeedSchedule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FeedCacheRow {
+    pub feed_url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body_hash: Option<String>,
+    #[serde(default)]
+    pub discovered_url: Option<String>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FeedRegistryRow {
+    pub feed_name: String,
+    pub config_hash: String,
+    pub canary_cycles_left: i32,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CanaryEntryRow {
+    pub feed_name: String,
+    pub guid: String,
+    pub item: serde_json::Value,
+    pub inserted_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FeedCostRow {
+    pub feed_name: String,
+    pub day: NaiveDate,
+    pub fetches: i64,
+    pub bytes_fetched: i64,
+    pub rows_written: i64,
+}
+
+/// Read all operational tables, plus `schedule` when called inside a running instance
+/// (otherwise the schedule saved in `feed_schedule`).
+pub async fn dump(
+    pool: &PgPool,
+    schedule: Option<&Schedule>,
+) -> Result<StateSnapshot, IngestError> {
+    Ok(StateSnapshot {
+        version: STATE_VERSION,
+        exported_at: Utc::now(),
+        feed_cache: sqlx::query_as(
+            "SELECT feed_url, etag, last_modified, body_hash, discovered_url, updated_at
+            FROM feed_cache",
+        )
+        .fetch_all(pool)
+        .await?,
+        feed_registry: sqlx::query_as(
+            "SELECT feed_name, config_hash, canary_cycles_left, updated_at FROM feed_registry",
+        )
+        .fetch_all(pool)
+        .await?,
+        canary_entries: sqlx::query_as(
+            "SELECT feed_name, guid, item, inserted_at FROM canary_entries",
+        )
+        .fetch_all(pool)
+        .await?,
+        feed_costs: sqlx::query_as(
+            "SELECT feed_name, day, fetches, bytes_fetched, rows_written FROM feed_costs",
+        )
+        .fetch_all(pool)
+        .await?,
+        schedule: match schedule {
+            Some(schedule) => schedule.snapshot(),
+            None => scheduler::load(pool).await?,
+        },
+    })
+}
+
+/// Write `snapshot`'s tables back in one transaction. Existing rows are overwritten,
+/// except usage counters, which keep whichever value is higher.
+pub async fn restore(pool: &PgPool, snapshot: &StateSnapshot) -> Result<(), IngestError> {
+    if snapshot.version != STATE_VERSION {
+        return Err(IngestError::State(format!(
+            "unsupported version {} (expected {})",
+            snapshot.version, STATE_VERSION
+        )));
+    }
+
+    let mut tx = pool.begin().await?;
+    for row in &snapshot.feed_cache {
+        sqlx::query(
+            "INSERT INTO feed_cache
+                (feed_url, etag, last_modified, body_hash, discovered_url, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (feed_url) DO UPDATE SET
+                etag = EXCLUDED.etag,
+                last_modified = EXCLUDED.last_modified,
+                body_hash = EXCLUDED.body_hash,
+                discovered_url = EXCLUDED.discovered_url,
+                updated_at = EXCLUDED.updated_at",
+        )
+        .bind(&row.feed_url)
+        .bind(&row.etag)
+        .bind(&row.last_modified)
+        .bind(&row.body_hash)
+        .bind(&row.discovered_url)
+        .bind(row.updated_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for row in &snapshot.feed_registry {
+        sqlx::query(
+            "INSERT INTO feed_registry (feed_name, config_hash, canary_cycles_left, updated_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (feed_name) DO UPDATE SET
+                config_hash = EXCLUDED.config_hash,
+                canary_cycles_left = EXCLUDED.canary_cycles_left,
+                updated_at = EXCLUDED.updated_at",
+        )
+        .bind(&row.feed_name)
+        .bind(&row.config_hash)
+        .bind(row.canary_cycles_left)
+        .bind(row.updated_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for row in &snapshot.canary_entries {
+        sqlx::query(
+            "INSERT INTO canary_entries (feed_name, guid, item, inserted_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (feed_name, guid) DO UPDATE SET
+                item = EXCLUDED.item,
+                inserted_at = EXCLUDED.inserted_at",
+        )
+        .bind(&row.feed_name)
+        .bind(&row.guid)
+        .bind(&row.item)
+        .bind(row.inserted_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for row in &snapshot.feed_costs {
+        sqlx::query(
+            "INSERT INTO feed_costs (feed_name, day, fetches, bytes_fetched, rows_written)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (feed_name, day) DO UPDATE SET
+                fetches = GREATEST(feed_costs.fetches, EXCLUDED.fetches),
+                bytes_fetched = GREATEST(feed_costs.bytes_fetched, EXCLUDED.bytes_fetched),
+                rows_written = GREATEST(feed_costs.rows_written, EXCLUDED.rows_written)",
+        )
+        .bind(&row.feed_name)
+        .bind(row.day)
+        .bind(row.fetches)
+        .bind(row.bytes_fetched)
+        .bind(row.rows_written)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
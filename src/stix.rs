@@ -0,0 +1,192 @@
+//! STIX 2.1 export: each entry becomes a `report` referencing the `indicator`s
+//! (IPv4 addresses, file hashes) and `vulnerability`s (CVE IDs) extracted from its
+//! text, so the archive can be loaded into a threat intelligence platform.
+//!
+//! IDs are UUIDv5s of the entry GUID or indicator value, so exporting the same
+//! entries twice yields the same objects and a platform can merge re-imports.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDateTime;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::enrich::{self, Ioc, IocKind};
+use crate::ingestor::FeedItem;
+use crate::tlp::Tlp;
+
+/// Media type of a STIX 2.1 bundle.
+pub const CONTENT_TYPE: &str = "application/stix+json;version=2.1";
+
+/// The namespace STIX 2.1 defines for deterministic identifiers.
+const NAMESPACE: Uuid = Uuid::from_u128(0x00abedb4_aa42_466c_9c01_fed23315a9b7);
+
+/// STIX 2.1's predefined TLP marking definitions (TLP 1.0: no AMBER+STRICT, WHITE for CLEAR).
+fn tlp_marking(tlp: Tlp) -> &'static str {
+    match tlp {
+        Tlp::Clear => "marking-definition--613f2e26-407d-48c7-9eca-b8e91df99dc9",
+        Tlp::Green => "marking-definition--34098fce-860f-48ae-8e50-ebd3cc5e41da",
+        Tlp::Amber | Tlp::AmberStrict => "marking-definition--f88d31f6-486f-44da-b317-01333bde0b82",
+        Tlp::Red => "marking-definition--5e57c739-391a-4eb3-b6be-7d15ca92d5ed",
+    }
+}
+
+fn stix_id(kind: &str, name: &str) -> String {
+    format!(
+        "{kind}--{}",
+        Uuid::new_v5(&NAMESPACE, format!("{kind}:{name}").as_bytes())
+    )
+}
+
+pub(crate) fn timestamp(t: NaiveDateTime) -> String {
+    t.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// An object shared between reports, dated by the earliest entry naming it and
+/// marked with the most restrictive TLP among them.
+struct Shared {
+    first_seen: NaiveDateTime,
+    tlp: Option<Tlp>,
+    body: Value,
+}
+
+impl Shared {
+    fn seen(&mut self, at: NaiveDateTime, tlp: Option<Tlp>) {
+        self.first_seen = self.first_seen.min(at);
+        self.tlp = self.tlp.max(tlp);
+    }
+
+    fn into_object(self) -> Value {
+        let mut object = self.body;
+        let created = timestamp(self.first_seen);
+        object["spec_version"] = json!("2.1");
+        object["created"] = json!(created);
+        object["modified"] = json!(created);
+        if object["type"] == "indicator" {
+            object["valid_from"] = json!(created);
+        }
+        if let Some(tlp) = self.tlp {
+            object["object_marking_refs"] = json!([tlp_marking(tlp)]);
+        }
+        object
+    }
+}
+
+fn indicator_body(ioc: &Ioc) -> (String, Value) {
+    if ioc.kind == IocKind::Cve {
+        let id = stix_id("vulnerability", &ioc.value);
+        let body = json!({
+            "type": "vulnerability",
+            "id": id,
+            "name": ioc.value,
+            "external_references": [{ "source_name": "cve", "external_id": ioc.value }],
+        });
+        return (id, body);
+    }
+    let pattern = match ioc.kind {
+        IocKind::Ipv4 => format!("[ipv4-addr:value = '{}']", ioc.value),
+        IocKind::Md5 => format!("[file:hashes.MD5 = '{}']", ioc.value),
+        IocKind::Sha1 => format!("[file:hashes.'SHA-1' = '{}']", ioc.value),
+        _ => format!("[file:hashes.'SHA-256' = '{}']", ioc.value),
+    };
+    let id = stix_id("indicator", &pattern);
+    let body = json!({
+        "type": "indicator",
+        "id": id,
+        "name": ioc.value,
+        "pattern": pattern,
+        "pattern_type": "stix",
+    });
+    (id, body)
+}
+
+/// Build a STIX 2.1 bundle from `items`: one `identity` per source feed, one
+/// `report` per entry, and the indicators and vulnerabilities they reference.
+pub fn bundle(items: &[FeedItem]) -> Value {
+    let mut sources: BTreeMap<String, Shared> = BTreeMap::new();
+    let mut shared: BTreeMap<String, Shared> = BTreeMap::new();
+    let mut reports = Vec::with_capacity(items.len());
+
+    for item in items {
+        // `inserted_at` moves whenever `current` is refreshed, so it dates the
+        // version; `created` must not change between versions
+        let created = item
+            .published
+            .map_or(item.inserted_at, |p| p.min(item.inserted_at));
+        let modified = item
+            .entry_updated
+            .map_or(item.inserted_at, |t| t.max(item.inserted_at));
+        let source_id = stix_id("identity", &item.feed_url);
+        let source_name = item.feed_title.as_deref().unwrap_or(&item.feed_url);
+        sources
+            .entry(source_id.clone())
+            .or_insert_with(|| Shared {
+                first_seen: created,
+                tlp: None,
+                body: json!({
+                    "type": "identity",
+                    "id": source_id,
+                    "name": source_name,
+                    "identity_class": "organization",
+                    "contact_information": item.feed_url,
+                }),
+            })
+            .seen(created, None);
+
+        let mut refs = Vec::new();
+        for ioc in enrich::indicators(&enrich::item_text(item)) {
+            let (id, body) = indicator_body(&ioc);
+            shared
+                .entry(id.clone())
+                .or_insert_with(|| Shared {
+                    first_seen: created,
+                    tlp: item.tlp,
+                    body,
+                })
+                .seen(created, item.tlp);
+            refs.push(id);
+        }
+        // A report must reference at least one object; fall back to its source
+        if refs.is_empty() {
+            refs.push(source_id.clone());
+        }
+
+        let mut report = json!({
+            "type": "report",
+            "spec_version": "2.1",
+            "id": stix_id("report", &item.guid),
+            "created": timestamp(created),
+            "modified": timestamp(modified),
+            "created_by_ref": source_id,
+            "name": item.title,
+            "published": timestamp(item.published.unwrap_or(item.inserted_at)),
+            "report_types": ["threat-report"],
+            "object_refs": refs,
+            "external_references": [{
+                "source_name": source_name,
+                "url": item.resolved_link.as_ref().unwrap_or(&item.link),
+                "external_id": item.guid,
+            }],
+        });
+        if let Some(summary) = &item.summary {
+            report["description"] = json!(summary);
+        }
+        if let Some(categories) = item.categories.as_ref().filter(|c| !c.is_empty()) {
+            report["labels"] = json!(categories);
+        }
+        if let Some(tlp) = item.tlp {
+            report["object_marking_refs"] = json!([tlp_marking(tlp)]);
+        }
+        reports.push(report);
+    }
+
+    let mut objects: Vec<Value> = sources.into_values().map(Shared::into_object).collect();
+    objects.extend(reports);
+    objects.extend(shared.into_values().map(Shared::into_object));
+    let ids: Vec<&str> = objects.iter().filter_map(|o| o["id"].as_str()).collect();
+    json!({
+        "type": "bundle",
+        "id": stix_id("bundle", &ids.join(",")),
+        "objects": objects,
+    })
+}
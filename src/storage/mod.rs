@@ -0,0 +1,233 @@
+//! Where entries and per-feed state are kept: PostgreSQL (a `PgPool`), or a SQLite file
+//! for small deployments (`SqliteStorage`), picked by the scheme of `database_url`.
+//! Both store and list entries and keep the feed cache, fetch history, feed schedule
+//! and daily usage. Everything else (canary, spillover, summaries, embeddings,
+//! enclosures, icons, TLS certificates, stored tag rules, TAXII, GraphQL, full-text
+//! search) reaches PostgreSQL through `postgres`, and is rejected in the
+//! configuration or answered with 501 on SQLite.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use tracing::info;
+
+use crate::costs::{self, Usage};
+use crate::db_utils::{self, Cursor, EntryQuery};
+use crate::errors::IngestError;
+use crate::history::{self, FetchRecord, HistoryRow};
+use crate::ingestor::{self, CacheValidators, Change, FeedItem};
+use crate::scheduler::{self, FeedSchedule};
+use crate::schema::{self, SchemaReport};
+
+mod sqlite;
+
+pub use sqlite::SqliteStorage;
+
+/// Whether `database_url` names a SQLite database (`sqlite://path`, `sqlite::memory:`).
+pub fn is_sqlite(database_url: &str) -> bool {
+    database_url.starts_with("sqlite:")
+}
+
+/// Open the database at `database_url`, without migrating it.
+pub async fn connect(database_url: &str) -> Result<Arc<dyn Storage>, IngestError> {
+    if is_sqlite(database_url) {
+        let storage = SqliteStorage::connect(database_url).await?;
+        info!("Opened SQLite database");
+        return Ok(Arc::new(storage));
+    }
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect(database_url)
+        .await?;
+    info!("Connected to Postgres");
+    Ok(Arc::new(pool))
+}
+
+/// A storage backend. Methods mirror the Postgres functions they stand for
+/// (`ingestor::process_entry`, `db_utils::list_entries`, `history::record`, ...).
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// The Postgres pool, for the features only implemented there; `None` on SQLite.
+    fn postgres(&self) -> Option<&PgPool>;
+
+    /// Apply pending migrations (waiting up to `wait` for another instance's).
+    async fn migrate(&self, wait: Duration) -> Result<(), IngestError>;
+
+    /// Compare the live schema with the migrations and columns this binary expects.
+    async fn check_schema(&self) -> Result<SchemaReport, IngestError>;
+
+    async fn close(&self);
+
+    /// Store an entry in `archive` and `current`.
+    async fn process_entry(&self, item: &FeedItem) -> Result<Change, IngestError>;
+
+    /// `process_entry` for many entries in one transaction, rolled back unless done
+    /// within `deadline`.
+    async fn process_batch(
+        &self,
+        items: &[FeedItem],
+        deadline: Duration,
+    ) -> Result<Vec<Change>, IngestError>;
+
+    async fn is_archived(&self, guid: &str) -> Result<bool, IngestError>;
+
+    /// Entries matching `query`, in rank order (see `db_utils::RANK_KEY`).
+    async fn list_entries(&self, query: &EntryQuery) -> Result<Vec<FeedItem>, IngestError>;
+
+    /// Every entry matching `query`, in rank order, paging through `list_entries` (its
+    /// `limit` and `offset` are ignored; a `cursor` sets the starting point).
+    async fn list_all_entries(&self, mut query: EntryQuery) -> Result<Vec<FeedItem>, IngestError> {
+        query.limit = EntryQuery::MAX_LIMIT;
+        query.offset = 0;
+        let mut items = Vec::new();
+        loop {
+            let page = self.list_entries(&query).await?;
+            let full = page.len() as i64 == query.limit;
+            query.cursor = page.last().map(Cursor::after);
+            items.extend(page);
+            if !full {
+                return Ok(items);
+            }
+        }
+    }
+
+    async fn load_validators(&self, feed_url: &str) -> Result<CacheValidators, IngestError>;
+
+    async fn store_validators(
+        &self,
+        feed_url: &str,
+        validators: &CacheValidators,
+    ) -> Result<(), IngestError>;
+
+    /// Add one fetch's usage to today's row for `feed_name`.
+    async fn record_usage(&self, feed_name: &str, bytes: u64, rows: u64)
+        -> Result<(), IngestError>;
+
+    /// Today's usage for one feed, or across all feeds when `feed_name` is `None`.
+    async fn usage_today(&self, feed_name: Option<&str>) -> Result<Usage, IngestError>;
+
+    /// Append a run to `feed_name`'s history, keeping only its newest `keep` rows.
+    async fn record_history(
+        &self,
+        feed_name: &str,
+        started_at: NaiveDateTime,
+        duration_ms: i64,
+        run: &FetchRecord,
+        keep: u32,
+    ) -> Result<(), IngestError>;
+
+    /// The newest `limit` runs of `feed_name`, newest first.
+    async fn recent_history(
+        &self,
+        feed_name: &str,
+        limit: i64,
+    ) -> Result<Vec<HistoryRow>, IngestError>;
+
+    /// Save a feed's schedule, replacing its previous one.
+    async fn save_schedule(&self, entry: &FeedSchedule) -> Result<(), IngestError>;
+
+    /// Every saved schedule, for `Schedule::restore`.
+    async fn load_schedules(&self) -> Result<Vec<FeedSchedule>, IngestError>;
+}
+
+impl dyn Storage {
+    /// The Postgres pool, or an `Unsupported` error naming the `feature` that needs it.
+    pub fn require_postgres(&self, feature: &'static str) -> Result<&PgPool, IngestError> {
+        self.postgres().ok_or(IngestError::Unsupported(feature))
+    }
+}
+
+#[async_trait]
+impl Storage for PgPool {
+    fn postgres(&self) -> Option<&PgPool> {
+        Some(self)
+    }
+
+    async fn migrate(&self, wait: Duration) -> Result<(), IngestError> {
+        schema::migrate(self, wait).await
+    }
+
+    async fn check_schema(&self) -> Result<SchemaReport, IngestError> {
+        schema::check(self).await
+    }
+
+    async fn close(&self) {
+        PgPool::close(self).await
+    }
+
+    async fn process_entry(&self, item: &FeedItem) -> Result<Change, IngestError> {
+        ingestor::process_entry(self, item).await
+    }
+
+    async fn process_batch(
+        &self,
+        items: &[FeedItem],
+        deadline: Duration,
+    ) -> Result<Vec<Change>, IngestError> {
+        ingestor::process_batch(self, items, deadline).await
+    }
+
+    async fn is_archived(&self, guid: &str) -> Result<bool, IngestError> {
+        db_utils::is_archived(self, guid).await
+    }
+
+    async fn list_entries(&self, query: &EntryQuery) -> Result<Vec<FeedItem>, IngestError> {
+        db_utils::list_entries(self, query).await
+    }
+
+    async fn load_validators(&self, feed_url: &str) -> Result<CacheValidators, IngestError> {
+        db_utils::load_validators(self, feed_url).await
+    }
+
+    async fn store_validators(
+        &self,
+        feed_url: &str,
+        validators: &CacheValidators,
+    ) -> Result<(), IngestError> {
+        db_utils::store_validators(self, feed_url, validators).await
+    }
+
+    async fn record_usage(
+        &self,
+        feed_name: &str,
+        bytes: u64,
+        rows: u64,
+    ) -> Result<(), IngestError> {
+        costs::record_usage(self, feed_name, bytes, rows).await
+    }
+
+    async fn usage_today(&self, feed_name: Option<&str>) -> Result<Usage, IngestError> {
+        costs::usage_today(self, feed_name).await
+    }
+
+    async fn record_history(
+        &self,
+        feed_name: &str,
+        started_at: NaiveDateTime,
+        duration_ms: i64,
+        run: &FetchRecord,
+        keep: u32,
+    ) -> Result<(), IngestError> {
+        history::record(self, feed_name, started_at, duration_ms, run, keep).await
+    }
+
+    async fn recent_history(
+        &self,
+        feed_name: &str,
+        limit: i64,
+    ) -> Result<Vec<HistoryRow>, IngestError> {
+        history::recent(self, feed_name, limit).await
+    }
+
+    async fn save_schedule(&self, entry: &FeedSchedule) -> Result<(), IngestError> {
+        scheduler::save(self, entry).await
+    }
+
+    async fn load_schedules(&self) -> Result<Vec<FeedSchedule>, IngestError> {
+        scheduler::load(self).await
+    }
+}
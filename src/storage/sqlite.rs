@@ -0,0 +1,667 @@
+//! SQLite backend: one database file, migrated by its own migration set under
+//! `migrations/sqlite/`. Entries are written as by the Postgres functions, with lists
+//! and structured values as JSON text; one connection does everything, so writes
+//! never contend for the file's lock.
+
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use sqlx::migrate::Migrator;
+use sqlx::query::Query;
+use sqlx::sqlite::{
+    SqliteArguments, SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions,
+};
+use sqlx::types::Json;
+use sqlx::{PgPool, QueryBuilder, Sqlite, Transaction};
+use tracing::{debug, info, instrument};
+use uuid::Uuid;
+
+use super::Storage;
+use crate::costs::Usage;
+use crate::db_utils::{EntryQuery, FEED_ITEM_COLUMNS};
+use crate::enclosures::Enclosure;
+use crate::enrich;
+use crate::errors::IngestError;
+use crate::history::{FetchRecord, HistoryRow};
+use crate::ingestor::{CacheValidators, Change, FeedItem, CURRENT_UPSERT, ENTRY_COLUMNS};
+use crate::media::MediaItem;
+use crate::metrics::DB_WRITE_HISTOGRAM;
+use crate::revisions::{self, StoredVersion};
+use crate::scheduler::{FeedSchedule, SavedSchedule};
+use crate::schema::{self, AppliedMigration, SchemaReport};
+use crate::tlp::Tlp;
+
+/// The migrations under `migrations/sqlite/`, embedded at build time.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// Tables of `schema::EXPECTED` the SQLite schema has.
+const TABLES: &[&str] = &[
+    "archive",
+    "current",
+    "feed_cache",
+    "revisions",
+    "affected_products",
+    "fetch_history",
+    "feed_costs",
+    "feed_schedule",
+];
+
+/// How long a statement waits for another process holding the database file.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A SQLite database (`sqlite://path/to/file.db`, created if missing).
+#[derive(Clone)]
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    /// Open (or create) the database at `database_url`.
+    pub async fn connect(database_url: &str) -> Result<Self, IngestError> {
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(BUSY_TIMEOUT);
+        // Kept open for good, which also keeps a `sqlite::memory:` database alive
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await?;
+        Ok(SqliteStorage { pool })
+    }
+
+    /// The connection pool, for queries beyond `Storage`.
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// Write `items` to `archive`, `revisions`, `affected_products` and `current` as
+    /// `ingestor::process_batch` does, leaving the transaction to commit. Returns the
+    /// GUIDs newly archived and, aligned with `items`, the size of each revision added.
+    async fn write(
+        &self,
+        items: &[FeedItem],
+    ) -> Result<
+        (
+            Transaction<'static, Sqlite>,
+            HashSet<String>,
+            Vec<Option<usize>>,
+        ),
+        IngestError,
+    > {
+        let mut tx = self.pool.begin().await?;
+        let mut archived = HashSet::new();
+        let insert = format!(
+            "INSERT INTO archive ({ENTRY_COLUMNS}) VALUES ({}) ON CONFLICT (guid) DO NOTHING",
+            placeholders()
+        );
+        for item in items {
+            let result = bind_entry(sqlx::query(&insert), item)
+                .execute(&mut *tx)
+                .await?;
+            if result.rows_affected() == 1 {
+                archived.insert(item.guid.clone());
+            }
+        }
+
+        let guids: Vec<&str> = items.iter().map(|item| item.guid.as_str()).collect();
+        let stored: Vec<StoredVersion> = sqlx::query_as(
+            "SELECT guid, title, summary, content FROM current
+            WHERE guid IN (SELECT value FROM json_each($1))",
+        )
+        .bind(Json(&guids))
+        .fetch_all(&mut *tx)
+        .await?;
+        let revised = revisions::changes(stored, items);
+        for (item, size) in items.iter().zip(&revised) {
+            if let Some(size) = size {
+                sqlx::query(
+                    "INSERT INTO revisions (guid, title, link, summary, content, entry_updated, diff_bytes, recorded_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                )
+                .bind(&item.guid)
+                .bind(&item.title)
+                .bind(&item.link)
+                .bind(&item.summary)
+                .bind(&item.content)
+                .bind(item.entry_updated)
+                .bind(*size as i64)
+                .bind(item.inserted_at)
+                .execute(&mut *tx)
+                .await?;
+            } else if !archived.contains(&item.guid) {
+                continue;
+            }
+            for found in enrich::affected_products(&enrich::item_text(item)) {
+                sqlx::query(
+                    "INSERT INTO affected_products (guid, product, versions) VALUES ($1, $2, $3)
+                    ON CONFLICT DO NOTHING",
+                )
+                .bind(&item.guid)
+                .bind(&found.product)
+                .bind(&found.versions)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        // In order, so of several items with one GUID the last is kept
+        let upsert = format!(
+            "INSERT INTO current ({ENTRY_COLUMNS}) VALUES ({}) {CURRENT_UPSERT}",
+            placeholders()
+        );
+        for item in items {
+            bind_entry(sqlx::query(&upsert), item)
+                .execute(&mut *tx)
+                .await?;
+        }
+        debug!("Upserted {} current entries", items.len());
+        Ok((tx, archived, revised))
+    }
+}
+
+/// `$1, ..., $n` for the columns of `ENTRY_COLUMNS`.
+fn placeholders() -> String {
+    let columns = ENTRY_COLUMNS.split(',').count();
+    (1..=columns)
+        .map(|i| format!("${i}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Bind one entry's values in `ENTRY_COLUMNS` order.
+fn bind_entry<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    item: &'q FeedItem,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    query
+        .bind(item.id)
+        .bind(&item.guid)
+        .bind(&item.title)
+        .bind(&item.link)
+        .bind(item.published)
+        .bind(&item.content)
+        .bind(&item.summary)
+        .bind(&item.author)
+        .bind(item.categories.as_ref().map(Json))
+        .bind(item.entry_updated)
+        .bind(&item.feed_url)
+        .bind(&item.feed_title)
+        .bind(&item.feed_description)
+        .bind(&item.feed_language)
+        .bind(&item.feed_icon)
+        .bind(item.feed_updated)
+        .bind(item.inserted_at)
+        .bind(&item.image_url)
+        .bind(&item.feed_type)
+        .bind(item.feed_tags.as_ref().map(Json))
+        .bind(item.tlp.map(Tlp::as_str))
+        .bind(&item.parent_guid)
+        .bind(&item.license)
+        .bind(&item.detected_language)
+        .bind(item.simhash)
+        .bind(&item.duplicate_of)
+        .bind(item.tags.as_ref().map(Json))
+        .bind(item.enclosures.as_ref().map(Json))
+        .bind(&item.thumbnail_url)
+        .bind(item.media.as_ref().map(Json))
+        .bind(item.extensions.as_ref().map(Json))
+        .bind(&item.resolved_link)
+}
+
+/// What storing each of `items` changed, given the GUIDs the write newly `archived`
+/// and the `revised` sizes it returned.
+fn changes(
+    items: &[FeedItem],
+    mut archived: HashSet<String>,
+    revised: &[Option<usize>],
+) -> Vec<Change> {
+    let changes: Vec<Change> = items
+        .iter()
+        .zip(revised)
+        .map(|(item, revised)| {
+            // Only the first item with a newly archived GUID counts as new
+            if archived.remove(&item.guid) {
+                Change::New
+            } else if revised.is_some() {
+                Change::Updated
+            } else {
+                Change::Unchanged
+            }
+        })
+        .collect();
+    let inserted = changes.iter().filter(|c| c.is_new()).count();
+    let updated = changes.iter().filter(|c| **c == Change::Updated).count();
+    if inserted > 0 {
+        info!(inserted, "Inserted new archive entries");
+    }
+    if updated > 0 {
+        info!(revised = updated, "Recorded entry revisions");
+    }
+    changes
+}
+
+/// A row of `archive` or `current`, with SQLite's column types.
+#[derive(sqlx::FromRow)]
+struct EntryRow {
+    id: Uuid,
+    guid: String,
+    parent_guid: Option<String>,
+    title: String,
+    link: String,
+    resolved_link: Option<String>,
+    published: Option<NaiveDateTime>,
+    content: Option<String>,
+    summary: Option<String>,
+    summary_ai: Option<String>,
+    author: Option<String>,
+    categories: Option<Json<Vec<String>>>,
+    entry_updated: Option<NaiveDateTime>,
+    image_url: Option<String>,
+    thumbnail_url: Option<String>,
+    media: Option<Json<Vec<MediaItem>>>,
+    extensions: Option<Json<BTreeMap<String, String>>>,
+    enclosures: Option<Json<Vec<Enclosure>>>,
+    feed_url: String,
+    feed_title: Option<String>,
+    feed_description: Option<String>,
+    feed_language: Option<String>,
+    detected_language: Option<String>,
+    feed_icon: Option<String>,
+    feed_updated: Option<NaiveDateTime>,
+    feed_type: Option<String>,
+    feed_tags: Option<Json<Vec<String>>>,
+    tlp: Option<String>,
+    license: Option<String>,
+    simhash: Option<i64>,
+    duplicate_of: Option<String>,
+    tags: Option<Json<Vec<String>>>,
+    inserted_at: NaiveDateTime,
+}
+
+impl From<EntryRow> for FeedItem {
+    fn from(row: EntryRow) -> Self {
+        FeedItem {
+            id: row.id,
+            guid: row.guid,
+            parent_guid: row.parent_guid,
+            title: row.title,
+            link: row.link,
+            resolved_link: row.resolved_link,
+            published: row.published,
+            content: row.content,
+            summary: row.summary,
+            summary_ai: row.summary_ai,
+            author: row.author,
+            categories: row.categories.map(|c| c.0),
+            entry_updated: row.entry_updated,
+            image_url: row.image_url,
+            thumbnail_url: row.thumbnail_url,
+            media: row.media.map(|m| m.0),
+            extensions: row.extensions.map(|e| e.0),
+            enclosures: row.enclosures.map(|e| e.0),
+            feed_url: row.feed_url,
+            feed_title: row.feed_title,
+            feed_description: row.feed_description,
+            feed_language: row.feed_language,
+            detected_language: row.detected_language,
+            feed_icon: row.feed_icon,
+            feed_updated: row.feed_updated,
+            feed_type: row.feed_type,
+            feed_tags: row.feed_tags.map(|t| t.0),
+            tlp: row.tlp.and_then(|t| t.parse().ok()),
+            license: row.license,
+            simhash: row.simhash,
+            duplicate_of: row.duplicate_of,
+            tags: row.tags.map(|t| t.0),
+            inserted_at: row.inserted_at,
+        }
+    }
+}
+
+/// `AND` clauses for the filters of `query`, as `db_utils::push_filters` adds them.
+fn push_filters<'a>(qb: &mut QueryBuilder<'a, Sqlite>, query: &'a EntryQuery) {
+    if let Some(feed_url) = &query.feed_url {
+        qb.push(" AND feed_url = ").push_bind(feed_url);
+    }
+    if let Some(feed_type) = &query.feed_type {
+        qb.push(" AND feed_type = ").push_bind(feed_type);
+    }
+    if let Some(tag) = &query.tag {
+        qb.push(" AND EXISTS (SELECT 1 FROM json_each(feed_tags) WHERE value = ")
+            .push_bind(tag)
+            .push(")");
+    }
+    if let Some(parent_guid) = &query.parent_guid {
+        qb.push(" AND parent_guid = ").push_bind(parent_guid);
+    }
+    if let Some(product) = &query.product {
+        qb.push(" AND guid IN (SELECT guid FROM affected_products WHERE lower(product) = lower(")
+            .push_bind(product)
+            .push("))");
+    }
+    if let Some(max) = query.max_tlp {
+        qb.push(" AND (tlp IS NULL OR tlp IN (");
+        let mut allowed = qb.separated(", ");
+        for tlp in Tlp::ALL.iter().filter(|t| **t <= max) {
+            allowed.push_bind(tlp.as_str());
+        }
+        qb.push("))");
+    }
+    if let Some(since) = query.since {
+        qb.push(" AND published >= ").push_bind(since);
+    }
+    if let Some(until) = query.until {
+        qb.push(" AND published < ").push_bind(until);
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    fn postgres(&self) -> Option<&PgPool> {
+        None
+    }
+
+    async fn migrate(&self, _wait: Duration) -> Result<(), IngestError> {
+        MIGRATOR
+            .run(&self.pool)
+            .await
+            .map_err(|e| IngestError::Schema(format!("migration failed: {}", e)))
+    }
+
+    async fn check_schema(&self) -> Result<SchemaReport, IngestError> {
+        let (tracked,): (bool,) = sqlx::query_as(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let applied: Vec<AppliedMigration> = if tracked {
+            sqlx::query_as("SELECT version, description, success, checksum FROM _sqlx_migrations")
+                .fetch_all(&self.pool)
+                .await?
+        } else {
+            Vec::new()
+        };
+        let columns: Vec<(String, String)> = sqlx::query_as(
+            "SELECT m.name, p.name FROM sqlite_master m, pragma_table_info(m.name) p
+            WHERE m.type = 'table'",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let expected = schema::expected_in(TABLES);
+        Ok(schema::compare(&MIGRATOR, applied, &expected, &columns))
+    }
+
+    async fn close(&self) {
+        self.pool.close().await
+    }
+
+    #[instrument(skip_all, fields(guid = %item.guid))]
+    async fn process_entry(&self, item: &FeedItem) -> Result<Change, IngestError> {
+        let _timer = DB_WRITE_HISTOGRAM
+            .with_label_values(&["entry"])
+            .start_timer();
+        let items = std::slice::from_ref(item);
+        let (tx, archived, revised) = self.write(items).await?;
+        tx.commit().await?;
+        revisions::observe(&revised);
+        Ok(changes(items, archived, &revised)[0])
+    }
+
+    #[instrument(skip_all, fields(items = items.len()))]
+    async fn process_batch(
+        &self,
+        items: &[FeedItem],
+        deadline: Duration,
+    ) -> Result<Vec<Change>, IngestError> {
+        let _timer = DB_WRITE_HISTOGRAM
+            .with_label_values(&["batch"])
+            .start_timer();
+        let (tx, archived, revised) = tokio::time::timeout(deadline, self.write(items))
+            .await
+            .map_err(|_| IngestError::WriteTimeout(deadline))??;
+        tx.commit().await?;
+        revisions::observe(&revised);
+        Ok(changes(items, archived, &revised))
+    }
+
+    async fn is_archived(&self, guid: &str) -> Result<bool, IngestError> {
+        let (exists,): (bool,) =
+            sqlx::query_as("SELECT EXISTS(SELECT 1 FROM archive WHERE guid = $1)")
+                .bind(guid)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(exists)
+    }
+
+    async fn list_entries(&self, query: &EntryQuery) -> Result<Vec<FeedItem>, IngestError> {
+        let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT ");
+        qb.push(FEED_ITEM_COLUMNS);
+        qb.push(if query.archive {
+            " FROM archive WHERE TRUE"
+        } else {
+            " FROM current WHERE TRUE"
+        });
+        push_filters(&mut qb, query);
+        if let Some(q) = &query.q {
+            // LIKE ignores case, though only for ASCII letters
+            let pattern = format!(
+                "%{}%",
+                q.replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_")
+            );
+            qb.push(" AND (title LIKE ")
+                .push_bind(pattern.clone())
+                .push(" ESCAPE '\\' OR summary LIKE ")
+                .push_bind(pattern.clone())
+                .push(" ESCAPE '\\' OR content LIKE ")
+                .push_bind(pattern)
+                .push(" ESCAPE '\\')");
+        }
+        // Undated entries rank last: '' sorts before any timestamp
+        if let Some(cursor) = &query.cursor {
+            qb.push(" AND (COALESCE(published, ''), inserted_at, id) < (COALESCE(")
+                .push_bind(cursor.published)
+                .push(", ''), ")
+                .push_bind(cursor.inserted_at)
+                .push(", ")
+                .push_bind(cursor.id)
+                .push(")");
+        }
+        qb.push(" ORDER BY COALESCE(published, '') DESC, inserted_at DESC, id DESC");
+        qb.push(" LIMIT ").push_bind(query.limit);
+        if query.cursor.is_none() {
+            qb.push(" OFFSET ").push_bind(query.offset);
+        }
+
+        let rows = qb
+            .build_query_as::<EntryRow>()
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(FeedItem::from).collect())
+    }
+
+    async fn load_validators(&self, feed_url: &str) -> Result<CacheValidators, IngestError> {
+        let row: Option<CacheValidators> = sqlx::query_as(
+            "SELECT etag, last_modified, body_hash, discovered_url FROM feed_cache
+                WHERE feed_url = $1",
+        )
+        .bind(feed_url)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.unwrap_or_default())
+    }
+
+    async fn store_validators(
+        &self,
+        feed_url: &str,
+        validators: &CacheValidators,
+    ) -> Result<(), IngestError> {
+        sqlx::query(
+            "INSERT INTO feed_cache (feed_url, etag, last_modified, body_hash, discovered_url, updated_at)
+            VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP)
+            ON CONFLICT (feed_url) DO UPDATE SET
+                etag = excluded.etag,
+                last_modified = excluded.last_modified,
+                body_hash = excluded.body_hash,
+                discovered_url = excluded.discovered_url,
+                updated_at = excluded.updated_at",
+        )
+        .bind(feed_url)
+        .bind(&validators.etag)
+        .bind(&validators.last_modified)
+        .bind(&validators.body_hash)
+        .bind(&validators.discovered_url)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_usage(
+        &self,
+        feed_name: &str,
+        bytes: u64,
+        rows: u64,
+    ) -> Result<(), IngestError> {
+        sqlx::query(
+            "INSERT INTO feed_costs (feed_name, day, fetches, bytes_fetched, rows_written)
+            VALUES ($1, CURRENT_DATE, 1, $2, $3)
+            ON CONFLICT (feed_name, day) DO UPDATE SET
+                fetches = fetches + 1,
+                bytes_fetched = bytes_fetched + excluded.bytes_fetched,
+                rows_written = rows_written + excluded.rows_written",
+        )
+        .bind(feed_name)
+        .bind(bytes as i64)
+        .bind(rows as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn usage_today(&self, feed_name: Option<&str>) -> Result<Usage, IngestError> {
+        let usage = sqlx::query_as(
+            "SELECT COALESCE(SUM(fetches), 0) AS fetches,
+                    COALESCE(SUM(bytes_fetched), 0) AS bytes_fetched,
+                    COALESCE(SUM(rows_written), 0) AS rows_written
+            FROM feed_costs
+            WHERE day = CURRENT_DATE AND ($1 IS NULL OR feed_name = $1)",
+        )
+        .bind(feed_name)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(usage)
+    }
+
+    async fn record_history(
+        &self,
+        feed_name: &str,
+        started_at: NaiveDateTime,
+        duration_ms: i64,
+        run: &FetchRecord,
+        keep: u32,
+    ) -> Result<(), IngestError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO fetch_history (
+                feed_name, started_at, status, http_status, entries_seen, entries_new, errors,
+                duration_ms, error, bytes_compressed, bytes_decompressed, response_headers
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+        )
+        .bind(feed_name)
+        .bind(started_at)
+        .bind(run.status.as_str())
+        .bind(run.http_status.map(i32::from))
+        .bind(run.entries_seen as i32)
+        .bind(run.entries_new as i32)
+        .bind(run.errors as i32)
+        .bind(duration_ms)
+        .bind(&run.error)
+        .bind(run.bytes_compressed as i64)
+        .bind(run.bytes_decompressed as i64)
+        .bind(run.response_headers.as_ref().map(Json))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "DELETE FROM fetch_history
+            WHERE feed_name = $1 AND id <= (
+                SELECT id FROM fetch_history WHERE feed_name = $1
+                ORDER BY id DESC LIMIT 1 OFFSET $2
+            )",
+        )
+        .bind(feed_name)
+        .bind(i64::from(keep))
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn recent_history(
+        &self,
+        feed_name: &str,
+        limit: i64,
+    ) -> Result<Vec<HistoryRow>, IngestError> {
+        let rows = sqlx::query_as(
+            "SELECT started_at, status, http_status, entries_seen, entries_new, errors,
+                    duration_ms, error, bytes_compressed, bytes_decompressed, response_headers
+            FROM fetch_history
+            WHERE feed_name = $1
+            ORDER BY id DESC
+            LIMIT $2",
+        )
+        .bind(feed_name)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
+
+    async fn save_schedule(&self, entry: &FeedSchedule) -> Result<(), IngestError> {
+        sqlx::query(
+            "INSERT INTO feed_schedule (feed_name, url, interval_ms, last_run, next_run,
+                consecutive_failures, last_error, health, disabled, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, CURRENT_TIMESTAMP)
+            ON CONFLICT (feed_name) DO UPDATE SET
+                url = excluded.url,
+                interval_ms = excluded.interval_ms,
+                last_run = excluded.last_run,
+                next_run = excluded.next_run,
+                consecutive_failures = excluded.consecutive_failures,
+                last_error = excluded.last_error,
+                health = excluded.health,
+                disabled = excluded.disabled,
+                updated_at = excluded.updated_at",
+        )
+        .bind(&entry.feed)
+        .bind(&entry.url)
+        .bind(entry.interval.as_millis() as i64)
+        .bind(entry.last_run.map(|t| t.naive_utc()))
+        .bind(entry.next_run.naive_utc())
+        .bind(entry.consecutive_failures as i32)
+        .bind(&entry.last_error)
+        .bind(entry.health)
+        .bind(entry.disabled)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn load_schedules(&self) -> Result<Vec<FeedSchedule>, IngestError> {
+        let rows: Vec<SavedSchedule> = sqlx::query_as(
+            "SELECT feed_name, url, interval_ms, last_run, next_run, consecutive_failures,
+                last_error, health, disabled
+            FROM feed_schedule",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(FeedSchedule::from).collect())
+    }
+}
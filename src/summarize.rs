@@ -0,0 +1,323 @@
+//! LLM analyst summaries. Each entry new to the archive is queued for a `Summarizer`
+//! (an OpenAI-compatible chat completions API unless a library user supplies another),
+//! and the short summary it returns is stored in `summary_ai`. One background worker
+//! works through the queue at `requests_per_minute`, and stops for the rest of the day
+//! once the tokens recorded in `summarizer_usage` reach `daily_token_budget`. Entries
+//! are routed by `detected_language`: only `languages` are summarized, each with its
+//! language's entry in `prompts` if there is one.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, Response};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::config::SummarizerSettings;
+use crate::enrich;
+use crate::errors::IngestError;
+use crate::ingestor::FeedItem;
+use crate::metrics::{SUMMARIES, SUMMARIZER_TOKENS};
+use crate::tlp::{self, Tlp};
+
+/// A summary and what it cost.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub text: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// An async enrichment writing a short analyst summary of an entry.
+#[async_trait]
+pub trait Summarizer: Send + Sync {
+    async fn summarize(&self, item: &FeedItem) -> Result<Summary, IngestError>;
+}
+
+/// Summaries from `POST <endpoint>/chat/completions`.
+pub struct OpenAiSummarizer {
+    client: Client,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+    prompt: String,
+    prompts: HashMap<String, String>,
+    max_input_chars: usize,
+    max_tokens: u32,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    messages: [ChatMessage<'a>; 2],
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+    #[serde(default)]
+    usage: ChatUsage,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatReply,
+}
+
+#[derive(Deserialize)]
+struct ChatReply {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct ChatUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+}
+
+impl OpenAiSummarizer {
+    pub fn new(settings: &SummarizerSettings) -> Result<Self, IngestError> {
+        let client = Client::builder()
+            .timeout(settings.timeout)
+            .build()
+            .map_err(|e| IngestError::Summarizer(e.to_string()))?;
+        Ok(OpenAiSummarizer {
+            client,
+            url: format!(
+                "{}/chat/completions",
+                settings.endpoint.trim_end_matches('/')
+            ),
+            model: settings.model.clone(),
+            api_key: settings.api_key.clone(),
+            prompt: settings.prompt.clone(),
+            prompts: settings.prompts.clone(),
+            max_input_chars: settings.max_input_chars,
+            max_tokens: settings.max_tokens,
+        })
+    }
+}
+
+#[async_trait]
+impl Summarizer for OpenAiSummarizer {
+    async fn summarize(&self, item: &FeedItem) -> Result<Summary, IngestError> {
+        let text = enrich::model_input(item, self.max_input_chars);
+        let prompt = item
+            .detected_language
+            .as_ref()
+            .and_then(|language| self.prompts.get(language))
+            .unwrap_or(&self.prompt);
+        let body = ChatRequest {
+            model: &self.model,
+            max_tokens: self.max_tokens,
+            messages: [
+                ChatMessage {
+                    role: "system",
+                    content: prompt,
+                },
+                ChatMessage {
+                    role: "user",
+                    content: &text,
+                },
+            ],
+        };
+        let mut request = self.client.post(&self.url).json(&body);
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+        let response: ChatResponse = request
+            .send()
+            .await
+            .and_then(Response::error_for_status)
+            .map_err(|e| IngestError::Summarizer(e.without_url().to_string()))?
+            .json()
+            .await
+            .map_err(|e| IngestError::Summarizer(e.without_url().to_string()))?;
+        let text = response
+            .choices
+            .into_iter()
+            .find_map(|c| c.message.content)
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| IngestError::Summarizer("empty completion".into()))?;
+        Ok(Summary {
+            text,
+            prompt_tokens: response.usage.prompt_tokens,
+            completion_tokens: response.usage.completion_tokens,
+        })
+    }
+}
+
+/// Hands new entries to the summarizer's worker; disabled by default.
+#[derive(Clone, Default)]
+pub struct SummaryQueue {
+    tx: Option<mpsc::Sender<FeedItem>>,
+    max_tlp: Option<Tlp>,
+    languages: Option<Vec<String>>,
+}
+
+impl SummaryQueue {
+    /// Start the worker for the configured summarizer, if any.
+    pub fn from_settings(
+        pool: &PgPool,
+        settings: Option<&SummarizerSettings>,
+    ) -> Result<Self, IngestError> {
+        match settings {
+            Some(settings) => {
+                let summarizer = Arc::new(OpenAiSummarizer::new(settings)?);
+                Ok(Self::spawn(pool.clone(), summarizer, settings))
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Start a worker storing `summarizer`'s summaries, paced and budgeted by `settings`.
+    pub fn spawn(
+        pool: PgPool,
+        summarizer: Arc<dyn Summarizer>,
+        settings: &SummarizerSettings,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel(settings.queue);
+        tokio::spawn(work(pool, summarizer, settings.clone(), rx));
+        SummaryQueue {
+            tx: Some(tx),
+            max_tlp: settings.max_tlp,
+            languages: settings.languages.clone(),
+        }
+    }
+
+    /// Queue `item` for a summary, unless it is above `max_tlp`, not in `languages` or
+    /// the queue is full.
+    pub fn submit(&self, item: &FeedItem) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        if !tlp::permits(self.max_tlp, item.tlp) {
+            SUMMARIES.with_label_values(&["withheld"]).inc();
+            return;
+        }
+        if !enrich::language_in(self.languages.as_deref(), item) {
+            SUMMARIES.with_label_values(&["other_language"]).inc();
+            return;
+        }
+        if tx.try_send(item.clone()).is_err() {
+            SUMMARIES.with_label_values(&["dropped"]).inc();
+        }
+    }
+}
+
+async fn work(
+    pool: PgPool,
+    summarizer: Arc<dyn Summarizer>,
+    settings: SummarizerSettings,
+    mut rx: mpsc::Receiver<FeedItem>,
+) {
+    let spacing = Duration::from_secs(60) / settings.requests_per_minute;
+    let mut next = Instant::now();
+    let mut over_budget = false;
+    while let Some(item) = rx.recv().await {
+        if let Some(budget) = settings.daily_token_budget {
+            // Without the day's usage the budget cannot be enforced: skip the entry
+            let used = match tokens_today(&pool).await {
+                Ok(used) => used,
+                Err(e) => {
+                    SUMMARIES.with_label_values(&["failed"]).inc();
+                    warn!(guid = %item.guid, error = %e, "Failed to read summarizer usage");
+                    continue;
+                }
+            };
+            if used >= budget {
+                if !over_budget {
+                    warn!(used, budget, "Summarizer token budget spent for today");
+                    over_budget = true;
+                }
+                SUMMARIES.with_label_values(&["over_budget"]).inc();
+                continue;
+            }
+            if over_budget {
+                info!("Summarizer token budget renewed");
+                over_budget = false;
+            }
+        }
+        tokio::time::sleep_until(next).await;
+        next = Instant::now() + spacing;
+        let summary = match summarizer.summarize(&item).await {
+            Ok(summary) => summary,
+            Err(e) => {
+                SUMMARIES.with_label_values(&["failed"]).inc();
+                warn!(guid = %item.guid, error = %e, "Failed to summarize entry");
+                continue;
+            }
+        };
+        SUMMARIZER_TOKENS
+            .with_label_values(&["prompt"])
+            .inc_by(summary.prompt_tokens);
+        SUMMARIZER_TOKENS
+            .with_label_values(&["completion"])
+            .inc_by(summary.completion_tokens);
+        let stored = async {
+            record_usage(&pool, &summary).await?;
+            store(&pool, &item.guid, &summary.text).await
+        };
+        match stored.await {
+            Ok(()) => SUMMARIES.with_label_values(&["stored"]).inc(),
+            Err(e) => {
+                SUMMARIES.with_label_values(&["failed"]).inc();
+                warn!(guid = %item.guid, error = %e, "Failed to store summary");
+            }
+        }
+    }
+}
+
+async fn store(pool: &PgPool, guid: &str, summary: &str) -> Result<(), IngestError> {
+    for table in ["archive", "current"] {
+        sqlx::query(&format!(
+            "UPDATE {table} SET summary_ai = $2 WHERE guid = $1"
+        ))
+        .bind(guid)
+        .bind(summary)
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+async fn record_usage(pool: &PgPool, summary: &Summary) -> Result<(), IngestError> {
+    sqlx::query(
+        "INSERT INTO summarizer_usage (day, requests, prompt_tokens, completion_tokens)
+        VALUES (CURRENT_DATE, 1, $1, $2)
+        ON CONFLICT (day) DO UPDATE SET
+            requests = summarizer_usage.requests + 1,
+            prompt_tokens = summarizer_usage.prompt_tokens + EXCLUDED.prompt_tokens,
+            completion_tokens = summarizer_usage.completion_tokens + EXCLUDED.completion_tokens",
+    )
+    .bind(summary.prompt_tokens as i64)
+    .bind(summary.completion_tokens as i64)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Prompt plus completion tokens used today.
+async fn tokens_today(pool: &PgPool) -> Result<u64, IngestError> {
+    let used: Option<i64> = sqlx::query_scalar(
+        "SELECT prompt_tokens + completion_tokens FROM summarizer_usage WHERE day = CURRENT_DATE",
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(used.unwrap_or(0) as u64)
+}
@@ -0,0 +1,59 @@
+//! systemd service notifications when built with the `systemd` feature: `READY=1`
+//! after the first cycle in which a feed could be fetched, `WATCHDOG=1` from the ingestion
+//! loop at half the unit's `WatchdogSec`, and `STOPPING=1` at graceful shutdown. Without
+//! the feature, or when not started by systemd (`Type=notify`), these do nothing.
+
+use std::future::Future;
+use std::time::Duration;
+
+/// Tell systemd the service is up.
+pub fn ready() {
+    #[cfg(feature = "systemd")]
+    notify(sd_notify::NotifyState::Ready);
+}
+
+/// Tell systemd the service is shutting down.
+pub fn stopping() {
+    #[cfg(feature = "systemd")]
+    notify(sd_notify::NotifyState::Stopping);
+}
+
+/// Keep-alive for the unit's watchdog.
+pub fn watchdog() {
+    #[cfg(feature = "systemd")]
+    notify(sd_notify::NotifyState::Watchdog);
+}
+
+/// How often to call `watchdog`, if systemd expects it.
+pub fn watchdog_interval() -> Option<Duration> {
+    #[cfg(feature = "systemd")]
+    {
+        let mut usec = 0;
+        if sd_notify::watchdog_enabled(false, &mut usec) {
+            return Some(Duration::from_micros(usec) / 2);
+        }
+    }
+    None
+}
+
+#[cfg(feature = "systemd")]
+fn notify(state: sd_notify::NotifyState) {
+    if let Err(e) = sd_notify::notify(false, &[state]) {
+        tracing::warn!(error = %e, "Failed to notify systemd");
+    }
+}
+
+/// Await `fut`, sending watchdog keep-alives meanwhile if systemd expects them.
+pub async fn kept_alive<F: Future>(fut: F) -> F::Output {
+    let Some(every) = watchdog_interval() else {
+        return fut.await;
+    };
+    tokio::pin!(fut);
+    let mut ticker = tokio::time::interval(every);
+    loop {
+        tokio::select! {
+            output = &mut fut => return output,
+            _ = ticker.tick() => watchdog(),
+        }
+    }
+}
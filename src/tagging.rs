@@ -0,0 +1,168 @@
+//! Rule-based tagging. Each rule maps keywords or a regular expression to a tag; an
+//! entry whose title, summary or content (as plain text) matches gets the tag in
+//! `tags`. Rules come from `[[tag_rules]]` in the config and from the `tag_rules`
+//! table, which analysts manage through `/admin/tag-rules` without a deploy; stored
+//! rules are reloaded at the start of every cycle. New entries are counted per tag in
+//! `entries_tagged_total{tag}`.
+
+use std::sync::RwLock;
+
+use chrono::NaiveDateTime;
+use once_cell::sync::Lazy;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use sqlx::PgPool;
+use tracing::warn;
+
+use crate::config::TagRule;
+use crate::enrich;
+use crate::errors::IngestError;
+use crate::ingestor::FeedItem;
+
+/// The configured rules and the tagger `with_tags` applies.
+static RULES: Lazy<RwLock<Rules>> = Lazy::new(RwLock::default);
+
+#[derive(Default)]
+struct Rules {
+    configured: Vec<TagRule>,
+    tagger: Tagger,
+}
+
+/// Compiled tag rules.
+#[derive(Debug, Clone, Default)]
+pub struct Tagger {
+    rules: Vec<(String, Vec<Regex>)>,
+}
+
+impl Tagger {
+    /// Compile `rules`: keywords match case-insensitively as whole words, patterns as
+    /// written.
+    pub fn new(rules: &[TagRule]) -> Result<Self, regex::Error> {
+        let mut compiled = Vec::with_capacity(rules.len());
+        for rule in rules {
+            let mut matchers = Vec::new();
+            if !rule.keywords.is_empty() {
+                let words: Vec<String> = rule.keywords.iter().map(|k| regex::escape(k)).collect();
+                matchers.push(
+                    RegexBuilder::new(&format!(r"\b(?:{})\b", words.join("|")))
+                        .case_insensitive(true)
+                        .build()?,
+                );
+            }
+            if let Some(pattern) = &rule.pattern {
+                matchers.push(Regex::new(pattern)?);
+            }
+            compiled.push((rule.tag.clone(), matchers));
+        }
+        Ok(Tagger { rules: compiled })
+    }
+
+    /// Tags of the rules `item` matches, in rule order and without repeats.
+    pub fn tags(&self, item: &FeedItem) -> Vec<String> {
+        if self.rules.is_empty() {
+            return Vec::new();
+        }
+        let text = enrich::plain_text(&enrich::item_text(item));
+        let mut tags: Vec<String> = Vec::new();
+        for (tag, matchers) in &self.rules {
+            if !tags.contains(tag) && matchers.iter().any(|m| m.is_match(&text)) {
+                tags.push(tag.clone());
+            }
+        }
+        tags
+    }
+}
+
+/// A rule in the `tag_rules` table.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct StoredRule {
+    pub id: i64,
+    pub tag: String,
+    pub keywords: Vec<String>,
+    pub pattern: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<StoredRule> for TagRule {
+    fn from(rule: StoredRule) -> Self {
+        TagRule {
+            tag: rule.tag,
+            keywords: rule.keywords,
+            pattern: rule.pattern,
+        }
+    }
+}
+
+/// Tag entries with the configured `rules` (and, after `refresh`, the stored ones).
+pub fn configure(rules: &[TagRule]) -> Result<(), regex::Error> {
+    let tagger = Tagger::new(rules)?;
+    *RULES.write().expect("tag rules poisoned") = Rules {
+        configured: rules.to_vec(),
+        tagger,
+    };
+    Ok(())
+}
+
+/// Reload the stored rules, applying them after the configured ones. A stored rule
+/// that no longer compiles is skipped.
+pub async fn refresh(pool: &PgPool) -> Result<(), IngestError> {
+    let stored = stored_rules(pool).await?;
+    let mut rules = RULES.write().expect("tag rules poisoned");
+    let mut all = rules.configured.clone();
+    for rule in stored {
+        let (id, rule) = (rule.id, TagRule::from(rule));
+        match Tagger::new(std::slice::from_ref(&rule)) {
+            Ok(_) => all.push(rule),
+            Err(e) => warn!(id, error = %e, "Skipping stored tag rule"),
+        }
+    }
+    rules.tagger = Tagger::new(&all).expect("every rule compiled on its own");
+    Ok(())
+}
+
+/// `item` with `tags` set by the current rules.
+pub fn with_tags(item: FeedItem) -> FeedItem {
+    let tags = RULES.read().expect("tag rules poisoned").tagger.tags(&item);
+    FeedItem {
+        tags: if tags.is_empty() { None } else { Some(tags) },
+        ..item
+    }
+}
+
+/// The stored rules, oldest first.
+pub async fn stored_rules(pool: &PgPool) -> Result<Vec<StoredRule>, IngestError> {
+    let rules =
+        sqlx::query_as("SELECT id, tag, keywords, pattern, created_at FROM tag_rules ORDER BY id")
+            .fetch_all(pool)
+            .await?;
+    Ok(rules)
+}
+
+/// Store `rule`, after checking it has a tag, something to match and a valid pattern.
+pub async fn add_rule(pool: &PgPool, rule: &TagRule) -> Result<StoredRule, IngestError> {
+    if rule.tag.trim().is_empty() || (rule.keywords.is_empty() && rule.pattern.is_none()) {
+        return Err(IngestError::TagRule(
+            "a rule needs a tag and keywords or a pattern".into(),
+        ));
+    }
+    Tagger::new(std::slice::from_ref(rule)).map_err(|e| IngestError::TagRule(e.to_string()))?;
+    let stored = sqlx::query_as(
+        "INSERT INTO tag_rules (tag, keywords, pattern) VALUES ($1, $2, $3)
+        RETURNING id, tag, keywords, pattern, created_at",
+    )
+    .bind(rule.tag.trim())
+    .bind(&rule.keywords)
+    .bind(&rule.pattern)
+    .fetch_one(pool)
+    .await?;
+    Ok(stored)
+}
+
+/// Delete the stored rule `id`; false if there was none.
+pub async fn delete_rule(pool: &PgPool, id: i64) -> Result<bool, IngestError> {
+    let result = sqlx::query("DELETE FROM tag_rules WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() == 1)
+}
@@ -0,0 +1,246 @@
+//! Read-only TAXII 2.1 server: the entries in `current` as one collection of STIX
+//! objects (see `stix.rs`), so MISP, OpenCTI and other TAXII clients can pull from
+//! the ingestor directly.
+//!
+//! Pages follow the order entries were written to `current`. `limit` counts entries:
+//! a page holds that many `report`s plus the identities, indicators and
+//! vulnerabilities they reference.
+
+use chrono::{DateTime, NaiveDateTime};
+use hyper::{Body, Request, Response, StatusCode};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::config::TaxiiSettings;
+use crate::db_utils;
+use crate::stix;
+
+/// Media type of every TAXII 2.1 response.
+pub const MEDIA_TYPE: &str = "application/taxii+json;version=2.1";
+
+/// The only API root.
+const API_ROOT: &str = "/taxii2/archive/";
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+/// Stable ID of the collection of archived entries.
+static COLLECTION_ID: Lazy<String> =
+    Lazy::new(|| Uuid::new_v5(&Uuid::NAMESPACE_URL, b"taxii:collection:archive").to_string());
+
+/// `GET /taxii2/...`: discovery, the API root, its collections and their objects.
+pub async fn handle(
+    req: &Request<Body>,
+    query: Vec<(String, String)>,
+    pool: &PgPool,
+    settings: &TaxiiSettings,
+) -> Response<Body> {
+    if !accepts_taxii(req) {
+        return taxii_error(
+            StatusCode::NOT_ACCEPTABLE,
+            &format!("responses are {}", MEDIA_TYPE),
+        );
+    }
+    let path = req.uri().path().trim_end_matches('/');
+    let Some(rest) = path.strip_prefix(API_ROOT.trim_end_matches('/')) else {
+        return match path {
+            "/taxii2" => taxii_response(json!({
+                "title": "rust-feed-ingestor",
+                "description": "Archived feed entries as STIX 2.1 reports and indicators",
+                "default": API_ROOT,
+                "api_roots": [API_ROOT],
+            })),
+            _ => taxii_error(StatusCode::NOT_FOUND, "unknown TAXII resource"),
+        };
+    };
+    let segments: Vec<&str> = rest.split('/').filter(|s| !s.is_empty()).collect();
+    match segments.as_slice() {
+        [] => taxii_response(json!({
+            "title": "Feed archive",
+            "versions": [MEDIA_TYPE],
+            // Read-only: no request bodies are accepted
+            "max_content_length": 0,
+        })),
+        ["collections"] => taxii_response(json!({ "collections": [collection()] })),
+        ["collections", id] if *id == COLLECTION_ID.as_str() => taxii_response(collection()),
+        ["collections", id, "objects"] if *id == COLLECTION_ID.as_str() => {
+            objects(query, pool, settings).await
+        }
+        ["collections", ..] => taxii_error(StatusCode::NOT_FOUND, "unknown collection"),
+        _ => taxii_error(StatusCode::NOT_FOUND, "unknown TAXII resource"),
+    }
+}
+
+fn collection() -> Value {
+    json!({
+        "id": COLLECTION_ID.as_str(),
+        "title": "Feed entries",
+        "description": "One report per stored entry, with the indicators extracted from it",
+        "can_read": true,
+        "can_write": false,
+        "media_types": [stix::CONTENT_TYPE],
+    })
+}
+
+/// Filters of a `GET .../objects/` request.
+struct ObjectsQuery {
+    added_after: Option<NaiveDateTime>,
+    next: Option<(NaiveDateTime, Uuid)>,
+    limit: i64,
+    types: Option<Vec<String>>,
+    ids: Option<Vec<String>>,
+}
+
+impl ObjectsQuery {
+    fn from_pairs(pairs: Vec<(String, String)>) -> Result<Self, String> {
+        let mut query = ObjectsQuery {
+            added_after: None,
+            next: None,
+            limit: DEFAULT_LIMIT,
+            types: None,
+            ids: None,
+        };
+        let list = |v: &str| v.split(',').map(|s| s.trim().to_string()).collect();
+        for (key, value) in pairs {
+            match key.as_str() {
+                "added_after" => {
+                    query.added_after = Some(
+                        DateTime::parse_from_rfc3339(&value)
+                            .map_err(|_| format!("invalid added_after: {}", value))?
+                            .naive_utc(),
+                    )
+                }
+                "next" => {
+                    query.next = Some(
+                        decode_next(&value).ok_or_else(|| format!("invalid next: {}", value))?,
+                    )
+                }
+                "limit" => match value.parse::<i64>() {
+                    Ok(n) if n > 0 => query.limit = n.min(MAX_LIMIT),
+                    _ => return Err(format!("invalid limit: {}", value)),
+                },
+                "match[type]" => query.types = Some(list(&value)),
+                "match[id]" => query.ids = Some(list(&value)),
+                _ => {}
+            }
+        }
+        Ok(query)
+    }
+}
+
+/// `<inserted_at µs>~<id>` of the last entry on a page.
+fn encode_next(inserted_at: NaiveDateTime, id: Uuid) -> String {
+    format!(
+        "{}~{}",
+        inserted_at.and_utc().timestamp_micros(),
+        id.simple()
+    )
+}
+
+fn decode_next(s: &str) -> Option<(NaiveDateTime, Uuid)> {
+    let (micros, id) = s.split_once('~')?;
+    let inserted_at = DateTime::from_timestamp_micros(micros.parse().ok()?)?.naive_utc();
+    Some((inserted_at, Uuid::parse_str(id).ok()?))
+}
+
+/// The objects envelope for one page of entries.
+async fn objects(
+    pairs: Vec<(String, String)>,
+    pool: &PgPool,
+    settings: &TaxiiSettings,
+) -> Response<Body> {
+    let query = match ObjectsQuery::from_pairs(pairs) {
+        Ok(q) => q,
+        Err(msg) => return taxii_error(StatusCode::BAD_REQUEST, &msg),
+    };
+    let entries = match db_utils::list_added(
+        pool,
+        query.added_after,
+        query.next,
+        settings.max_tlp,
+        query.limit,
+    )
+    .await
+    {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!(error = %e, "Failed to list entries for TAXII");
+            return taxii_error(StatusCode::INTERNAL_SERVER_ERROR, "database error");
+        }
+    };
+
+    let mut bundle = stix::bundle(&entries);
+    let mut objects = match bundle["objects"].take() {
+        Value::Array(objects) => objects,
+        _ => Vec::new(),
+    };
+    objects.retain(|o| {
+        let matches = |filter: &Option<Vec<String>>, field: &str| {
+            filter
+                .as_ref()
+                .is_none_or(|f| f.iter().any(|v| o[field] == v.as_str()))
+        };
+        matches(&query.types, "type") && matches(&query.ids, "id")
+    });
+
+    let more = entries.len() as i64 == query.limit;
+    let mut envelope = json!({ "more": more, "objects": objects });
+    if let (true, Some(last)) = (more, entries.last()) {
+        envelope["next"] = json!(encode_next(last.inserted_at, last.id));
+    }
+    let mut resp = Response::builder().header("Content-Type", MEDIA_TYPE);
+    if let (Some(first), Some(last)) = (entries.first(), entries.last()) {
+        resp = resp
+            .header(
+                "X-TAXII-Date-Added-First",
+                stix::timestamp(first.inserted_at),
+            )
+            .header("X-TAXII-Date-Added-Last", stix::timestamp(last.inserted_at));
+    }
+    resp.body(Body::from(envelope.to_string()))
+        .expect("Failed to build TAXII response")
+}
+
+/// Whether the client's `Accept` header (if any) allows a TAXII response.
+fn accepts_taxii(req: &Request<Body>) -> bool {
+    let Some(accept) = req
+        .headers()
+        .get(hyper::header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return true;
+    };
+    accept.split(',').any(|range| {
+        let range = range.split(';').next().unwrap_or_default().trim();
+        matches!(
+            range,
+            "application/taxii+json" | "application/json" | "application/*" | "*/*"
+        )
+    })
+}
+
+fn taxii_response(value: Value) -> Response<Body> {
+    Response::builder()
+        .header("Content-Type", MEDIA_TYPE)
+        .body(Body::from(value.to_string()))
+        .expect("Failed to build TAXII response")
+}
+
+/// A TAXII error message with the given status.
+fn taxii_error(status: StatusCode, description: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", MEDIA_TYPE)
+        .body(Body::from(
+            json!({
+                "title": status.canonical_reason().unwrap_or("Error"),
+                "description": description,
+                "http_status": status.as_str(),
+            })
+            .to_string(),
+        ))
+        .expect("Failed to build TAXII error response")
+}
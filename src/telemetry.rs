@@ -0,0 +1,85 @@
+//! Tracing subscriber setup: stdout (or stderr) logs, plus OTLP span export when built with the
+//! `otel` feature and `OTEL_EXPORTER_OTLP_ENDPOINT` is set.
+
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+/// Keeps the span exporter alive; call `shutdown` before exit to flush pending spans.
+#[derive(Default)]
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::TracerProvider>,
+}
+
+impl Telemetry {
+    /// Flush and stop the span exporter, if one is running.
+    pub fn shutdown(self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                tracing::warn!(error = %e, "Failed to flush OTLP spans");
+            }
+        }
+    }
+}
+
+/// Install the global subscriber, logging to stdout or (for commands whose output
+/// goes to stdout) stderr. Must be called from within the Tokio runtime.
+pub fn init(log_to_stdout: bool) -> Telemetry {
+    let writer = if log_to_stdout {
+        BoxMakeWriter::new(std::io::stdout)
+    } else {
+        BoxMakeWriter::new(std::io::stderr)
+    };
+    let registry = tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env())
+        .with(fmt::layer().with_writer(writer));
+
+    #[cfg(feature = "otel")]
+    if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_some() {
+        match otlp_provider() {
+            Ok(provider) => {
+                use opentelemetry::trace::TracerProvider as _;
+                let tracer = provider.tracer("rust_feed_ingestor");
+                registry
+                    .with(tracing_opentelemetry::layer().with_tracer(tracer))
+                    .init();
+                tracing::info!("Exporting spans over OTLP");
+                return Telemetry {
+                    provider: Some(provider),
+                };
+            }
+            Err(e) => {
+                registry.init();
+                tracing::warn!(error = %e, "Failed to set up OTLP exporter; spans not exported");
+                return Telemetry::default();
+            }
+        }
+    }
+
+    registry.init();
+    Telemetry::default()
+}
+
+#[cfg(feature = "otel")]
+fn otlp_provider(
+) -> Result<opentelemetry_sdk::trace::TracerProvider, opentelemetry::trace::TraceError> {
+    use opentelemetry::KeyValue;
+    use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .build()?;
+    let mut builder = TracerProvider::builder().with_batch_exporter(exporter, runtime::Tokio);
+    // The default resource already honours OTEL_SERVICE_NAME / OTEL_RESOURCE_ATTRIBUTES
+    if std::env::var_os("OTEL_SERVICE_NAME").is_none() {
+        builder =
+            builder.with_resource(Resource::default().merge(&Resource::new([KeyValue::new(
+                "service.name",
+                "rust-feed-ingestor",
+            )])));
+    }
+    Ok(builder.build())
+}
@@ -0,0 +1,100 @@
+//! User-provided minijinja templates for outgoing notifications (sink payloads,
+//! digests), with a locale-aware `date` filter so messages can be branded and
+//! translated without code changes, and a `defang` filter for indicator-laden text.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Locale, NaiveDateTime, Utc};
+use minijinja::{context, path_loader, Environment, ErrorKind};
+use serde::Serialize;
+
+use crate::config::TemplateSettings;
+use crate::enrich;
+use crate::errors::IngestError;
+use crate::ingestor::FeedItem;
+
+/// Format used by `date` when a template gives none: day, month name, year and time.
+const DEFAULT_DATE_FORMAT: &str = "%e %B %Y %H:%M";
+
+/// The template environment shared by every sink.
+#[derive(Clone)]
+pub struct Templates {
+    env: Arc<Environment<'static>>,
+}
+
+impl Templates {
+    /// Load templates from `settings.dir` (if set); names are file paths relative to it.
+    pub fn from_settings(settings: &TemplateSettings) -> Result<Self, IngestError> {
+        let default_locale = parse_locale(&settings.locale).map_err(|e| {
+            IngestError::Config(config::ConfigError::Message(format!("templates: {}", e)))
+        })?;
+
+        let mut env = Environment::new();
+        if let Some(dir) = &settings.dir {
+            env.set_loader(path_loader(dir));
+        }
+        // {{ item.published | date }}, {{ item.published | date("%A %d %B", "fr_FR") }}
+        env.add_filter(
+            "date",
+            move |value: String, format: Option<String>, locale: Option<String>| {
+                let locale = match locale {
+                    Some(l) => parse_locale(&l)
+                        .map_err(|e| minijinja::Error::new(ErrorKind::InvalidOperation, e))?,
+                    None => default_locale,
+                };
+                let when = parse_timestamp(&value).ok_or_else(|| {
+                    minijinja::Error::new(
+                        ErrorKind::InvalidOperation,
+                        format!("not a timestamp: {}", value),
+                    )
+                })?;
+                let format = format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT);
+                Ok(when.format_localized(format, locale).to_string())
+            },
+        );
+        // {{ item.content | defang }}: hxxp:// and [.] so indicators are not clickable
+        env.add_filter("defang", |value: String| enrich::defang(&value));
+        Ok(Templates { env: Arc::new(env) })
+    }
+
+    /// Fail early if the template `name` is missing or does not compile.
+    pub fn check(&self, name: &str) -> Result<(), IngestError> {
+        self.env.get_template(name)?;
+        Ok(())
+    }
+
+    /// Render `name` for one entry, with `item` and `feed` (the feed's name) in scope.
+    pub fn render_item(
+        &self,
+        name: &str,
+        feed_name: &str,
+        item: &FeedItem,
+    ) -> Result<String, IngestError> {
+        self.render(name, context! { item => item, feed => feed_name })
+    }
+
+    /// Render `name` for a batch of entries, with `items` in scope.
+    pub fn render_digest(&self, name: &str, items: &[FeedItem]) -> Result<String, IngestError> {
+        self.render(name, context! { items => items })
+    }
+
+    fn render<S: Serialize>(&self, name: &str, ctx: S) -> Result<String, IngestError> {
+        Ok(self.env.get_template(name)?.render(ctx)?)
+    }
+}
+
+fn parse_locale(name: &str) -> Result<Locale, String> {
+    Locale::try_from(name).map_err(|_| format!("unknown locale '{}'", name))
+}
+
+/// Stored timestamps are naive UTC; RFC 3339 strings are accepted too.
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|d| d.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+                .ok()
+                .map(|n| n.and_utc())
+        })
+}
@@ -0,0 +1,333 @@
+//! End-to-end test harness (`--features integration`): a throwaway Postgres with the
+//! schema migrated, a local server for feed documents, and the `Pipeline` the service
+//! runs, so a test can ingest real cycles and assert on the stored rows. Postgres is a
+//! testcontainers container, or, with `TESTKIT_DATABASE_URL` set (CI without Docker),
+//! a fresh database created on that server and dropped by `close`. `SqliteKit` runs
+//! the same pipeline on an in-memory SQLite database, needing neither.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::Context;
+use config::{Config, File, FileFormat};
+use futures::future::join_all;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{Connection, PgConnection, PgPool};
+use testcontainers_modules::postgres::Postgres;
+use testcontainers_modules::testcontainers::runners::AsyncRunner;
+use testcontainers_modules::testcontainers::ContainerAsync;
+
+use crate::config::{Feed, Settings};
+use crate::embed::EmbeddingQueue;
+use crate::enclosures::EnclosureDownloads;
+use crate::http::HttpClients;
+use crate::inspect::LastRuns;
+use crate::links::Unshortener;
+use crate::pipeline::{FeedRun, Pipeline};
+use crate::scheduler::Schedule;
+use crate::schema;
+use crate::shutdown::Shutdown;
+use crate::sinks::Sinks;
+use crate::spillover::CycleBudget;
+use crate::spool::Spool;
+use crate::storage::{SqliteStorage, Storage};
+use crate::summarize::SummaryQueue;
+
+/// Server to create the test database on instead of starting a container.
+pub const DATABASE_URL_VAR: &str = "TESTKIT_DATABASE_URL";
+
+/// A migrated test database and a feed server.
+pub struct TestKit {
+    pub pool: PgPool,
+    pub feeds: FeedServer,
+    database_url: String,
+    database: Database,
+}
+
+enum Database {
+    /// Stopped when dropped
+    Container {
+        _container: Box<ContainerAsync<Postgres>>,
+    },
+    Created {
+        server_url: String,
+        name: String,
+    },
+}
+
+impl TestKit {
+    /// Start Postgres and the feed server, and apply every migration.
+    pub async fn start() -> anyhow::Result<Self> {
+        let (database_url, database) = match std::env::var(DATABASE_URL_VAR) {
+            Ok(server_url) => {
+                let name = format!("testkit_{}", uuid::Uuid::new_v4().simple());
+                let mut conn = PgConnection::connect(&server_url)
+                    .await
+                    .with_context(|| format!("connecting to {}", DATABASE_URL_VAR))?;
+                sqlx::query(&format!("CREATE DATABASE {name}"))
+                    .execute(&mut conn)
+                    .await?;
+                conn.close().await?;
+                let mut url = url::Url::parse(&server_url)?;
+                url.set_path(&name);
+                (url.to_string(), Database::Created { server_url, name })
+            }
+            Err(_) => {
+                let container = Postgres::default()
+                    .start()
+                    .await
+                    .context("starting the Postgres container")?;
+                let url = format!(
+                    "postgres://postgres:postgres@{}:{}/postgres",
+                    container.get_host().await?,
+                    container.get_host_port_ipv4(5432).await?
+                );
+                (
+                    url,
+                    Database::Container {
+                        _container: Box::new(container),
+                    },
+                )
+            }
+        };
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await?;
+        schema::migrate(&pool, Duration::from_secs(60)).await?;
+        Ok(TestKit {
+            pool,
+            feeds: FeedServer::start().await?,
+            database_url,
+            database,
+        })
+    }
+
+    /// Default settings for the test database and `feeds`, with canary off so entries
+    /// are stored on the first run.
+    pub fn settings(&self, feeds: Vec<Feed>) -> anyhow::Result<Settings> {
+        settings(&self.database_url, feeds)
+    }
+
+    /// The service's pipeline for `settings`, without summaries, embeddings, enclosure downloads or a
+    /// spool.
+    pub fn pipeline(&self, settings: &Settings) -> anyhow::Result<Pipeline> {
+        pipeline(Arc::new(self.pool.clone()), settings)
+    }
+
+    /// One ingestion cycle over `feeds`, as the service runs it.
+    pub async fn run_cycle(&self, pipeline: &Pipeline, feeds: &[Feed]) -> Vec<FeedRun> {
+        run_cycle(pipeline, feeds).await
+    }
+
+    /// Rows in `table`.
+    pub async fn count(&self, table: &str) -> anyhow::Result<i64> {
+        let (count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {table}"))
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
+    }
+
+    /// Close the pool and remove the database (or container).
+    pub async fn close(self) -> anyhow::Result<()> {
+        self.pool.close().await;
+        if let Database::Created { server_url, name } = self.database {
+            let mut conn = PgConnection::connect(&server_url).await?;
+            sqlx::query(&format!("DROP DATABASE {name} WITH (FORCE)"))
+                .execute(&mut conn)
+                .await?;
+            conn.close().await?;
+        }
+        Ok(())
+    }
+}
+
+/// `TestKit` on an in-memory SQLite database.
+pub struct SqliteKit {
+    pub storage: SqliteStorage,
+    pub feeds: FeedServer,
+}
+
+impl SqliteKit {
+    const DATABASE_URL: &'static str = "sqlite::memory:";
+
+    /// Open the database and start the feed server, and apply every migration.
+    pub async fn start() -> anyhow::Result<Self> {
+        let storage = SqliteStorage::connect(Self::DATABASE_URL).await?;
+        storage.migrate(Duration::ZERO).await?;
+        Ok(SqliteKit {
+            storage,
+            feeds: FeedServer::start().await?,
+        })
+    }
+
+    /// As `TestKit::settings`.
+    pub fn settings(&self, feeds: Vec<Feed>) -> anyhow::Result<Settings> {
+        settings(Self::DATABASE_URL, feeds)
+    }
+
+    /// As `TestKit::pipeline`.
+    pub fn pipeline(&self, settings: &Settings) -> anyhow::Result<Pipeline> {
+        pipeline(Arc::new(self.storage.clone()), settings)
+    }
+
+    /// One ingestion cycle over `feeds`, as the service runs it.
+    pub async fn run_cycle(&self, pipeline: &Pipeline, feeds: &[Feed]) -> Vec<FeedRun> {
+        run_cycle(pipeline, feeds).await
+    }
+
+    /// Rows in `table`.
+    pub async fn count(&self, table: &str) -> anyhow::Result<i64> {
+        let (count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {table}"))
+            .fetch_one(self.storage.pool())
+            .await?;
+        Ok(count)
+    }
+}
+
+/// Settings for `TestKit::settings`, on `database_url`.
+fn settings(database_url: &str, feeds: Vec<Feed>) -> anyhow::Result<Settings> {
+    let toml = format!(
+        "database_url = \"{}\"\ningest_interval = \"1h\"\nserver_bind = \"127.0.0.1:0\"\ncanary_cycles = 0\n",
+        database_url
+    );
+    let mut settings: Settings = Config::builder()
+        .add_source(File::from_str(&toml, FileFormat::Toml))
+        .build()?
+        .try_deserialize()?;
+    settings.feeds = feeds;
+    Ok(settings)
+}
+
+/// The pipeline of `TestKit::pipeline`, storing in `storage`.
+fn pipeline(storage: Arc<dyn Storage>, settings: &Settings) -> anyhow::Result<Pipeline> {
+    Ok(Pipeline {
+        storage,
+        schedule: Schedule::new(&settings.feeds, settings.ingest_interval)
+            .with_health(settings.feed_health.clone()),
+        global_quota: settings.quota.clone(),
+        sinks: Sinks::from_settings(settings)?,
+        canary_cycles: settings.canary_cycles,
+        fetch_history_rows: settings.fetch_history_rows,
+        budget: CycleBudget::new(settings.cycle_limits.clone()),
+        clients: HttpClients::from_settings(settings)?,
+        retry: settings.retry.clone(),
+        capture_headers: settings.http.capture_headers.clone(),
+        entry_timeout: settings.entry_timeout,
+        shutdown: Shutdown::default(),
+        dry_run: settings.dry_run,
+        spool: Spool::default(),
+        summaries: SummaryQueue::default(),
+        embeddings: EmbeddingQueue::default(),
+        enclosures: EnclosureDownloads::default(),
+        unshortener: Unshortener::from_settings(settings.unshorten.as_ref()),
+        last_runs: LastRuns::default(),
+    })
+}
+
+/// One ingestion cycle over `feeds`, as the service runs it.
+async fn run_cycle(pipeline: &Pipeline, feeds: &[Feed]) -> Vec<FeedRun> {
+    pipeline.start_cycle().await;
+    let runs = join_all(feeds.iter().map(|feed| pipeline.run_feed(feed))).await;
+    pipeline.finish_cycle().await;
+    runs
+}
+
+/// Content type and body served at each path.
+type Documents = Arc<RwLock<HashMap<String, (String, Vec<u8>)>>>;
+
+/// Location each redirecting path points to.
+type Redirects = Arc<RwLock<HashMap<String, String>>>;
+
+/// Serves feed documents from memory on a local port; unknown paths are 404.
+#[derive(Clone)]
+pub struct FeedServer {
+    addr: SocketAddr,
+    documents: Documents,
+    redirects: Redirects,
+}
+
+impl FeedServer {
+    async fn start() -> anyhow::Result<Self> {
+        let documents = Documents::default();
+        let redirects = Redirects::default();
+        let (served, redirected) = (documents.clone(), redirects.clone());
+        let make_svc = make_service_fn(move |_conn| {
+            let (served, redirected) = (served.clone(), redirected.clone());
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                    let path = req.uri().path();
+                    let location = redirected
+                        .read()
+                        .expect("feed server poisoned")
+                        .get(path)
+                        .cloned();
+                    let document = served
+                        .read()
+                        .expect("feed server poisoned")
+                        .get(path)
+                        .cloned();
+                    async move {
+                        Ok::<_, Infallible>(match (location, document) {
+                            (Some(location), _) => Response::builder()
+                                .status(301)
+                                .header("Location", location)
+                                .body(Body::empty())
+                                .unwrap(),
+                            (None, Some((content_type, body))) => Response::builder()
+                                .header("Content-Type", content_type)
+                                .body(Body::from(body))
+                                .unwrap(),
+                            (None, None) => {
+                                Response::builder().status(404).body(Body::empty()).unwrap()
+                            }
+                        })
+                    }
+                }))
+            }
+        });
+        let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))?.serve(make_svc);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        Ok(FeedServer {
+            addr,
+            documents,
+            redirects,
+        })
+    }
+
+    /// Serve `body` at `path` (e.g. `/rss.xml`) from now on, replacing what was there.
+    pub fn serve(&self, path: &str, content_type: &str, body: impl Into<Vec<u8>>) {
+        self.documents
+            .write()
+            .expect("feed server poisoned")
+            .insert(path.to_string(), (content_type.to_string(), body.into()));
+    }
+
+    /// Answer requests for `path` with a permanent redirect to `location` from now on.
+    pub fn redirect(&self, path: &str, location: &str) {
+        self.redirects
+            .write()
+            .expect("feed server poisoned")
+            .insert(path.to_string(), location.to_string());
+    }
+
+    /// Absolute URL of `path`.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// A feed called `name` fetching `path` from this server.
+    pub fn feed(&self, name: &str, path: &str) -> Feed {
+        Feed {
+            name: name.to_string(),
+            url: self.url(path),
+            ..Default::default()
+        }
+    }
+}
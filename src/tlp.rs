@@ -0,0 +1,140 @@
+//! Traffic Light Protocol (TLP 2.0) markings, used to keep items away from sinks
+//! and API clients not cleared to receive them.
+
+use std::fmt;
+use std::str::FromStr;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef};
+use sqlx::{Decode, Encode, Postgres, Type};
+
+use crate::config;
+
+/// A TLP level, ordered from least to most restricted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Tlp {
+    Clear,
+    Green,
+    Amber,
+    AmberStrict,
+    Red,
+}
+
+impl Tlp {
+    pub const ALL: [Tlp; 5] = [
+        Tlp::Clear,
+        Tlp::Green,
+        Tlp::Amber,
+        Tlp::AmberStrict,
+        Tlp::Red,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Tlp::Clear => "CLEAR",
+            Tlp::Green => "GREEN",
+            Tlp::Amber => "AMBER",
+            Tlp::AmberStrict => "AMBER+STRICT",
+            Tlp::Red => "RED",
+        }
+    }
+}
+
+impl FromStr for Tlp {
+    type Err = String;
+
+    /// Case-insensitive, with or without a `TLP:` prefix; TLP 1.0's WHITE means CLEAR.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let level = s.trim().to_ascii_uppercase();
+        let level = level.strip_prefix("TLP:").unwrap_or(&level);
+        match level.trim() {
+            "CLEAR" | "WHITE" => Ok(Tlp::Clear),
+            "GREEN" => Ok(Tlp::Green),
+            "AMBER" => Ok(Tlp::Amber),
+            "AMBER+STRICT" => Ok(Tlp::AmberStrict),
+            "RED" => Ok(Tlp::Red),
+            _ => Err(format!("unknown TLP level: {}", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for Tlp {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Tlp> for String {
+    fn from(tlp: Tlp) -> Self {
+        tlp.as_str().to_string()
+    }
+}
+
+impl fmt::Display for Tlp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Stored as TEXT in `archive`/`current`
+impl Type<Postgres> for Tlp {
+    fn type_info() -> PgTypeInfo {
+        <&str as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <&str as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Tlp {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        Ok(<&str as Decode<Postgres>>::decode(value)?.parse()?)
+    }
+}
+
+impl Encode<'_, Postgres> for Tlp {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+/// `TLP:AMBER`, `TLP: RED`, `TLP-GREEN`, ... anywhere in free text.
+static MARKER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bTLP\s*[:\-]?\s*(CLEAR|WHITE|GREEN|AMBER\s*\+\s*STRICT|AMBER|RED)\b")
+        .expect("valid TLP regex")
+});
+
+/// The most restrictive TLP marker found in `text`, if any.
+pub fn marking(text: &str) -> Option<Tlp> {
+    MARKER
+        .captures_iter(text)
+        .filter_map(|c| c[1].split_whitespace().collect::<String>().parse().ok())
+        .max()
+}
+
+/// An item's TLP: the stricter of its source's configured level and any marker in
+/// its text or categories. `None` when neither says anything.
+pub fn classify<'a>(
+    source: &config::Feed,
+    texts: impl IntoIterator<Item = &'a str>,
+) -> Option<Tlp> {
+    texts
+        .into_iter()
+        .filter_map(marking)
+        .chain(source.tlp)
+        .max()
+}
+
+/// Whether an item marked `tlp` (unmarked counts as CLEAR) may go to a recipient
+/// cleared up to `max`.
+pub fn permits(max: Option<Tlp>, tlp: Option<Tlp>) -> bool {
+    max.is_none_or(|max| tlp.unwrap_or(Tlp::Clear) <= max)
+}
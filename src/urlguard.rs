@@ -0,0 +1,277 @@
+//! Server-side request forgery protection. Feeds, their redirects and the icon URLs
+//! they name can point at `169.254.169.254` or other internal hosts; such requests are
+//! refused unless the host or address is allowed or `[url_safety]` sets `enabled = false`.
+//!
+//! Hostnames are checked after DNS resolution, by the feed clients' resolver, so a
+//! public name resolving to an internal address is refused as well (and a name with
+//! both is only connected to on its public addresses). URLs naming an address directly
+//! are checked before the request and on every redirect hop. Requests through a proxy
+//! are resolved by the proxy, so for them only addresses in the URL itself are checked.
+//! Refusals are counted in `unsafe_urls_blocked_total` and never retried.
+
+use std::error::Error as StdError;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, RwLock};
+
+use hyper::client::connect::dns::Name;
+use once_cell::sync::Lazy;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use thiserror::Error;
+use url::{Host, Url};
+
+use crate::config::UrlSafetySettings;
+use crate::metrics::UNSAFE_URLS_BLOCKED;
+
+/// Redirects followed per request, as with reqwest's default policy.
+const MAX_REDIRECTS: usize = 10;
+
+static GUARD: Lazy<RwLock<Option<Arc<Guard>>>> = Lazy::new(RwLock::default);
+
+/// A request refused for targeting an internal address.
+#[derive(Debug, Error)]
+#[error("{0} is an internal address; add it to url_safety.allow to fetch it")]
+pub struct Blocked(pub String);
+
+#[derive(Debug)]
+struct Guard {
+    hosts: Vec<String>,
+    networks: Vec<(IpAddr, u8)>,
+}
+
+impl Guard {
+    fn new(settings: &UrlSafetySettings) -> Result<Self, String> {
+        let mut hosts = Vec::new();
+        let mut networks = Vec::new();
+        for entry in &settings.allow {
+            let entry = entry.trim();
+            if entry.contains('/') || entry.parse::<IpAddr>().is_ok() {
+                networks.push(parse_network(entry).ok_or_else(|| {
+                    format!(
+                        "url_safety.allow: '{}' is not an address or CIDR range",
+                        entry
+                    )
+                })?);
+            } else if entry.is_empty() {
+                return Err("url_safety.allow: empty entry".into());
+            } else {
+                hosts.push(entry.trim_end_matches('.').to_ascii_lowercase());
+            }
+        }
+        Ok(Guard { hosts, networks })
+    }
+
+    fn allows_host(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.');
+        self.hosts.iter().any(|h| h.eq_ignore_ascii_case(host))
+    }
+
+    fn allows_addr(&self, addr: IpAddr) -> bool {
+        !is_internal(addr)
+            || self
+                .networks
+                .iter()
+                .any(|(net, prefix)| in_network(addr, *net, *prefix))
+    }
+}
+
+/// Validate `settings` without applying them.
+pub fn validate(settings: &UrlSafetySettings) -> Result<(), String> {
+    Guard::new(settings).map(|_| ())
+}
+
+/// Guard requests per `settings` from now on, or stop guarding them with `None`.
+/// Clients built before this call keep their previous resolver.
+pub fn configure(settings: Option<&UrlSafetySettings>) -> Result<(), String> {
+    let guard = settings.map(Guard::new).transpose()?.map(Arc::new);
+    *GUARD.write().expect("url guard poisoned") = guard;
+    Ok(())
+}
+
+fn current() -> Option<Arc<Guard>> {
+    GUARD.read().expect("url guard poisoned").clone()
+}
+
+/// Whether requests are being guarded.
+pub fn is_enabled() -> bool {
+    current().is_some()
+}
+
+/// Refuse `url` if it names an internal address directly. Hostnames pass here and are
+/// checked when the client resolves them.
+pub fn check(url: &str) -> Result<(), Blocked> {
+    let Some(guard) = current() else {
+        return Ok(());
+    };
+    let Ok(url) = Url::parse(url) else {
+        return Ok(());
+    };
+    check_url(&guard, &url)
+}
+
+fn check_url(guard: &Guard, url: &Url) -> Result<(), Blocked> {
+    let addr = match url.host() {
+        Some(Host::Ipv4(addr)) => IpAddr::V4(addr),
+        Some(Host::Ipv6(addr)) => IpAddr::V6(addr),
+        _ => return Ok(()),
+    };
+    if guard.allows_addr(addr) {
+        Ok(())
+    } else {
+        UNSAFE_URLS_BLOCKED.inc();
+        Err(Blocked(addr.to_string()))
+    }
+}
+
+/// Whether `err` is, or was caused by, a refused request.
+pub fn is_blocked(err: &(dyn StdError + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if err.is::<Blocked>() {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// A resolver dropping internal addresses, failing with `Blocked` when none are left.
+/// `proxy`'s host is resolved unchecked, as the operator configured it.
+pub fn resolver(proxy: Option<&str>) -> Arc<GuardedResolver> {
+    let proxy_host = proxy
+        .and_then(|p| Url::parse(p).ok())
+        .and_then(|p| p.host_str().map(str::to_ascii_lowercase));
+    Arc::new(GuardedResolver { proxy_host })
+}
+
+/// A redirect policy refusing hops to internal addresses.
+pub fn redirect_policy() -> Policy {
+    Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            return attempt.error("too many redirects");
+        }
+        let checked = current().map_or(Ok(()), |guard| check_url(&guard, attempt.url()));
+        match checked {
+            Ok(()) => attempt.follow(),
+            Err(e) => attempt.error(e),
+        }
+    })
+}
+
+/// See `resolver`.
+#[derive(Debug)]
+pub struct GuardedResolver {
+    proxy_host: Option<String>,
+}
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_ascii_lowercase();
+        let exempt = self.proxy_host.as_deref() == Some(host.as_str());
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            let guard = match current() {
+                Some(guard) if !exempt && !guard.allows_host(&host) => guard,
+                _ => return Ok(Box::new(addrs.into_iter()) as Addrs),
+            };
+            let permitted: Vec<SocketAddr> = addrs
+                .iter()
+                .copied()
+                .filter(|a| guard.allows_addr(a.ip()))
+                .collect();
+            if permitted.is_empty() {
+                UNSAFE_URLS_BLOCKED.inc();
+                let resolved = addrs
+                    .first()
+                    .map(|a| a.ip().to_string())
+                    .unwrap_or_default();
+                return Err(Box::new(Blocked(format!("{} ({})", host, resolved))) as _);
+            }
+            Ok(Box::new(permitted.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Loopback, private, link-local, shared (CGNAT), multicast and other addresses that
+/// are not on the public internet. IPv6 addresses embedding an IPv4 one are judged by
+/// it (see `embedded_v4`).
+pub fn is_internal(addr: IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(v4) => is_internal_v4(v4),
+        IpAddr::V6(v6) => match embedded_v4(v6) {
+            Some(v4) => is_internal_v4(v4),
+            None => is_internal_v6(v6),
+        },
+    }
+}
+
+/// The IPv4 address carried by a mapped (`::ffff:a.b.c.d`), IPv4-compatible
+/// (`::a.b.c.d`), NAT64 (`64:ff9b::/96`) or 6to4 (`2002::/16`) address.
+fn embedded_v4(addr: Ipv6Addr) -> Option<Ipv4Addr> {
+    if let Some(v4) = addr.to_ipv4_mapped() {
+        return Some(v4);
+    }
+    let s = addr.segments();
+    let low = Ipv4Addr::from((u32::from(s[6]) << 16) | u32::from(s[7]));
+    match s {
+        // `::` and `::1` are IPv6's own unspecified and loopback addresses
+        [0, 0, 0, 0, 0, 0, 0, 0 | 1] => None,
+        [0, 0, 0, 0, 0, 0, ..] | [0x64, 0xff9b, 0, 0, 0, 0, ..] => Some(low),
+        [0x2002, hi, lo, ..] => Some(Ipv4Addr::from((u32::from(hi) << 16) | u32::from(lo))),
+        _ => None,
+    }
+}
+
+fn is_internal_v4(addr: Ipv4Addr) -> bool {
+    let [a, b, ..] = addr.octets();
+    addr.is_loopback()
+        || addr.is_private()
+        || addr.is_link_local()
+        || addr.is_unspecified()
+        || addr.is_broadcast()
+        || addr.is_multicast()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 198 && (b == 18 || b == 19))
+        || a >= 240
+}
+
+fn is_internal_v6(addr: Ipv6Addr) -> bool {
+    addr.is_loopback()
+        || addr.is_unspecified()
+        || addr.is_multicast()
+        || addr.is_unique_local()
+        || addr.is_unicast_link_local()
+}
+
+/// `10.0.0.0/8`, `fd00::/8` or a single address.
+fn parse_network(entry: &str) -> Option<(IpAddr, u8)> {
+    let (addr, prefix) = match entry.split_once('/') {
+        Some((addr, prefix)) => (addr.parse::<IpAddr>().ok()?, prefix.parse::<u8>().ok()?),
+        None => {
+            let addr = entry.parse::<IpAddr>().ok()?;
+            (addr, if addr.is_ipv4() { 32 } else { 128 })
+        }
+    };
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    (prefix <= max).then_some((addr, prefix))
+}
+
+fn in_network(addr: IpAddr, net: IpAddr, prefix: u8) -> bool {
+    let addr = match (addr, net) {
+        (IpAddr::V6(v6), IpAddr::V4(_)) => embedded_v4(v6).map_or(addr, IpAddr::V4),
+        _ => addr,
+    };
+    match (addr, net) {
+        (IpAddr::V4(a), IpAddr::V4(n)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(a) & mask == u32::from(n) & mask
+        }
+        (IpAddr::V6(a), IpAddr::V6(n)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(a) & mask == u128::from(n) & mask
+        }
+        _ => false,
+    }
+}
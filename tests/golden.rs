@@ -0,0 +1,612 @@
+//! Golden tests: each fixture under `tests/fixtures` is run through
+//! `process_feed_bytes` (and, for `stix_export` and `defanged_output`, `stix::bundle`
+//! or `enrich::defanged`) and compared with its snapshot under `tests/golden`.
+//!
+//! Regenerate the snapshots after an intended change with
+//! `UPDATE_GOLDEN=1 cargo test --test golden`.
+
+use std::fs;
+use std::path::PathBuf;
+
+use rust_feed_ingestor::charset;
+use rust_feed_ingestor::config::{Feed, FieldMapping, TagRule};
+use rust_feed_ingestor::dedup;
+use rust_feed_ingestor::discovery;
+use rust_feed_ingestor::enrich;
+use rust_feed_ingestor::graph;
+use rust_feed_ingestor::ingestor::{process_feed_bytes, FeedItem};
+use rust_feed_ingestor::links;
+use rust_feed_ingestor::revisions;
+use rust_feed_ingestor::sanitize::{
+    self, LengthLimits, LengthUnit, Overflow, SanitizePolicy, SanitizeProfile,
+};
+use rust_feed_ingestor::stix;
+use rust_feed_ingestor::tagging::Tagger;
+use rust_feed_ingestor::urlguard;
+use serde_json::{json, Value};
+
+fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+fn load(fixture: &str, source: &Feed) -> Vec<FeedItem> {
+    let body = fs::read(root().join("fixtures").join(fixture)).expect("read fixture");
+    process_feed_bytes(&body, source).expect("fixture parses")
+}
+
+fn check(fixture: &str, source: Feed) {
+    let items = load(fixture, &source);
+
+    // `id` and `inserted_at` are assigned per run, so they are left out of the snapshot
+    let mut actual = serde_json::to_value(&items).unwrap();
+    for item in actual.as_array_mut().unwrap() {
+        let item = item.as_object_mut().unwrap();
+        item.remove("id");
+        item.remove("inserted_at");
+    }
+    compare(&format!("{}.json", fixture), actual);
+}
+
+fn compare(snapshot: &str, actual: Value) {
+    let golden = root().join("golden").join(snapshot);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        let text = serde_json::to_string_pretty(&actual).unwrap();
+        fs::write(&golden, text + "\n").expect("write golden");
+        return;
+    }
+    let expected: Value =
+        serde_json::from_str(&fs::read_to_string(&golden).expect("read golden")).unwrap();
+    assert_eq!(
+        actual, expected,
+        "{} no longer matches its golden file",
+        snapshot
+    );
+}
+
+#[test]
+fn rss() {
+    check(
+        "rss.xml",
+        Feed {
+            name: "Example Advisories".into(),
+            url: "https://advisories.example.com/rss.xml".into(),
+            feed_type: Some("official".into()),
+            tags: vec!["vendor".into()],
+            mapping: FieldMapping {
+                published_from: Some("dc:date".into()),
+                author_from: Some("dc:creator".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn dublin_core() {
+    check(
+        "dublin-core.xml",
+        Feed {
+            name: "Example National Library".into(),
+            url: "https://library.example.gov/notices/rss.xml".into(),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn licenses() {
+    check(
+        "licenses.xml",
+        Feed {
+            name: "Example Research Lab".into(),
+            url: "https://lab.example.org/feed.xml".into(),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn vendor_extensions() {
+    let extensions = [
+        ("cve", "vuln:cve-id"),
+        ("cvss_score", "vuln:cvss/cvss:base_metrics/cvss:score"),
+        (
+            "cvss_vector_approximated",
+            "vuln:cvss/cvss:base_metrics/cvss:access-vector@approximated",
+        ),
+        (
+            "cvrf_id",
+            "cvrf:DocumentTracking/cvrf:Identification/cvrf:ID",
+        ),
+        ("missing", "vuln:published-datetime"),
+    ];
+    check(
+        "vendor.xml",
+        Feed {
+            name: "Example Vendor Vulnerability Feed".into(),
+            url: "https://psirt.example.net/rss.xml".into(),
+            extensions: extensions
+                .into_iter()
+                .map(|(name, path)| (name.to_string(), path.to_string()))
+                .collect(),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn synthetic_guids() {
+    check(
+        "broken-ids.xml",
+        Feed {
+            name: "Example Municipal CERT".into(),
+            url: "https://cert.example.city/rss.xml".into(),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn atom() {
+    check(
+        "atom.xml",
+        Feed {
+            name: "Example Research Blog".into(),
+            url: "https://research.example.org/atom.xml".into(),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn json_feed() {
+    check(
+        "feed.json",
+        Feed {
+            name: "Example JSON Feed".into(),
+            url: "https://json.example.net/feed.json".into(),
+            tags: vec!["community".into()],
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn code_friendly_profile() {
+    check(
+        "code.xml",
+        Feed {
+            name: "Example Engineering Blog".into(),
+            url: "https://eng.example.com/rss.xml".into(),
+            sanitize: SanitizeProfile::CodeFriendly,
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn strict_profile() {
+    check(
+        "untrusted.xml",
+        Feed {
+            name: "Example Paste Monitor".into(),
+            url: "https://paste.example.net/rss.xml".into(),
+            sanitize: SanitizeProfile::Strict,
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn custom_policy() {
+    let policy = SanitizePolicy {
+        tags: vec!["details".into(), "summary".into()],
+        tag_attributes: [("code".to_string(), vec!["class".to_string()])].into(),
+        images: Some(false),
+        ..Default::default()
+    };
+    sanitize::configure(Some(&policy)).unwrap();
+    let custom = SanitizeProfile::Custom.sanitizer();
+    assert!(!custom.allows_images());
+    assert_eq!(
+        custom.clean(
+            r#"<details><summary>PoC</summary><pre><code class="language-py">x()</code></pre></details><img src="https://example.com/a.png"><script>x()</script>"#
+        ),
+        r#"<details><summary>PoC</summary><pre><code class="language-py">x()</code></pre></details>"#
+    );
+    let unsafe_policy = SanitizePolicy {
+        tags: vec!["script".into()],
+        ..Default::default()
+    };
+    assert!(unsafe_policy.validate().is_err());
+}
+
+#[test]
+fn legacy_charsets() {
+    let body = fs::read(root().join("fixtures/windows-1251.xml")).expect("read fixture");
+    let source = Feed {
+        name: "National CERT".into(),
+        url: "https://cert.example.ru/rss.xml".into(),
+        ..Default::default()
+    };
+    let titles = |body: &[u8]| -> Vec<String> {
+        process_feed_bytes(body, &source)
+            .expect("transcoded body parses")
+            .into_iter()
+            .map(|item| item.title)
+            .collect()
+    };
+
+    // Named by the Content-Type header
+    let (from_header, encoding) = charset::to_utf8(
+        body.clone(),
+        Some("text/xml; charset=windows-1251"),
+        &source.url,
+    );
+    assert_eq!(encoding.map(|e| e.name()), Some("windows-1251"));
+    assert_eq!(titles(&from_header), ["Критическая уязвимость"]);
+
+    // Named nowhere: detected
+    let (detected, encoding) = charset::to_utf8(body.clone(), None, &source.url);
+    assert_eq!(encoding.map(|e| e.name()), Some("windows-1251"));
+    assert_eq!(detected, from_header);
+
+    // Named by the XML declaration, which then has to say UTF-8
+    let rest = body.strip_prefix(br#"<?xml version="1.0"?>"#).unwrap();
+    let declared = [br#"<?xml version="1.0" encoding="windows-1251"?>"#, rest].concat();
+    let (from_declaration, _) = charset::to_utf8(declared, None, &source.url);
+    assert!(from_declaration.starts_with(br#"<?xml version="1.0" encoding="UTF-8"?>"#));
+    assert_eq!(titles(&from_declaration), ["Критическая уязвимость"]);
+
+    // UTF-8 is kept as it is
+    let (kept, encoding) = charset::to_utf8(from_header.clone(), None, &source.url);
+    assert_eq!((kept, encoding), (from_header, None));
+}
+
+#[test]
+fn feed_autodiscovery() {
+    let page = br#"<!DOCTYPE html>
+<html><head>
+<link rel="stylesheet" href="/style.css">
+<link rel="alternate" hreflang="de" href="/de/">
+<LINK REL="Alternate" TYPE="application/atom+xml" HREF="feeds/posts.atom?tag=a&amp;lang=en">
+<link rel="alternate" type="application/rss+xml" href="/comments.xml">
+</head></html>"#;
+    assert!(discovery::is_html(page));
+    assert!(!discovery::is_html(
+        &fs::read(root().join("fixtures/rss.xml")).unwrap()
+    ));
+    assert_eq!(
+        discovery::feed_link(page, "https://blog.example.com/security/").as_deref(),
+        Some("https://blog.example.com/security/feeds/posts.atom?tag=a&lang=en")
+    );
+    assert_eq!(
+        discovery::feed_link(b"<html><head></head></html>", "https://blog.example.com/"),
+        None
+    );
+}
+
+#[test]
+fn truncated_to_limits() {
+    check(
+        "multilingual.xml",
+        Feed {
+            name: "Multilingual Advisories".into(),
+            url: "https://intl.example.org/rss.xml".into(),
+            limits: LengthLimits {
+                unit: LengthUnit::Graphemes,
+                overflow: Overflow::Truncate,
+                title: 18,
+                summary: 24,
+                content: 24,
+            },
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn stix_export() {
+    let source = Feed {
+        name: "Example Threat Reports".into(),
+        url: "https://intel.example.com/rss.xml".into(),
+        ..Default::default()
+    };
+    let mut items = load("indicators.xml", &source);
+    // Pin the per-run insertion time, from which STIX `created` dates are taken
+    for item in &mut items {
+        item.inserted_at = item.published.expect("fixture dates every item");
+    }
+    compare("indicators.xml.stix.json", stix::bundle(&items));
+}
+
+#[test]
+fn affected_products() {
+    let source = Feed {
+        name: "Example CERT".into(),
+        url: "https://cert.example.net/rss.xml".into(),
+        ..Default::default()
+    };
+    let actual: Vec<Value> = load("products.xml", &source)
+        .iter()
+        .map(|item| {
+            let products: Vec<Value> = enrich::affected_products(&enrich::item_text(item))
+                .into_iter()
+                .map(|p| json!({ "product": p.product, "versions": p.versions }))
+                .collect();
+            json!({ "guid": item.guid, "products": products })
+        })
+        .collect();
+    compare("products.xml.json", Value::Array(actual));
+}
+
+#[test]
+fn defanged_output() {
+    let source = Feed {
+        name: "Example Threat Reports".into(),
+        url: "https://intel.example.com/rss.xml".into(),
+        ..Default::default()
+    };
+    let items: Vec<FeedItem> = load("indicators.xml", &source)
+        .iter()
+        .map(enrich::defanged)
+        .collect();
+    let actual: Vec<Value> = items
+        .iter()
+        .map(
+            |item| json!({ "title": item.title, "summary": item.summary, "content": item.content }),
+        )
+        .collect();
+    compare("indicators.xml.defanged.json", Value::Array(actual));
+}
+
+#[test]
+fn atom_threading() {
+    check(
+        "thread.xml",
+        Feed {
+            name: "Example Security List".into(),
+            url: "https://lists.example.org/security/atom.xml".into(),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn enclosures() {
+    check(
+        "enclosures.xml",
+        Feed {
+            name: "Example CERT Bulletins".into(),
+            url: "https://cert.example.com/rss.xml".into(),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn media_rss() {
+    check(
+        "media.xml",
+        Feed {
+            name: "Example Threat Briefings".into(),
+            url: "https://video.example.org/feed.xml".into(),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn mailing_list_archive() {
+    check(
+        "list.mbox",
+        Feed {
+            name: "oss-security".into(),
+            url: "https://lists.example.org/pipermail/oss-security/{year}-{month_name}.txt".into(),
+            tags: vec!["mailing-list".into()],
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn near_duplicate_simhash() {
+    let advisory = "<p>A critical remote code execution vulnerability in Example Gateway \
+        versions prior to 4.2.1 allows an unauthenticated attacker to run arbitrary commands \
+        through a crafted request to the management interface. Exploitation has been observed \
+        in the wild. Administrators should upgrade to 4.2.1 immediately and restrict access \
+        to the management interface.</p>";
+    let republished = "Reposted from the vendor: a critical remote code execution \
+        vulnerability in Example Gateway versions prior to 4.2.1 allows an unauthenticated \
+        attacker to run arbitrary commands through a crafted request to the management \
+        interface. Exploitation has been observed in the wild. Administrators should upgrade \
+        to 4.2.1 immediately and restrict access to the management interface.";
+    let unrelated = "The quarterly threat landscape report covers phishing campaigns against \
+        financial institutions, the growth of ransomware affiliates and new techniques for \
+        abusing cloud identity providers, with detection guidance for each.";
+    let hash = |text: &str| dedup::simhash(text).expect("long enough to hash");
+    let distance = |a: &str, b: &str| (hash(a) ^ hash(b)).count_ones();
+    // Same template, different advisory
+    let other = advisory
+        .replace("Example Gateway", "Acme Router")
+        .replace("4.2.1", "7.0.3");
+    assert!(distance(advisory, republished) <= 10);
+    assert!(distance(advisory, &other) > 10);
+    assert!(distance(advisory, unrelated) > 16);
+    assert_eq!(dedup::simhash("Patch now."), None);
+}
+
+#[test]
+fn tag_rules() {
+    let rule = |tag: &str, keywords: &[&str], pattern: Option<&str>| TagRule {
+        tag: tag.into(),
+        keywords: keywords.iter().map(|k| k.to_string()).collect(),
+        pattern: pattern.map(String::from),
+    };
+    let tagger = Tagger::new(&[
+        rule("rce", &["remote code execution", "rce"], None),
+        rule("disclosure", &[], Some(r"(?i)information\s+disclosure")),
+        // Whole words only: "Gateway" is not "gate"
+        rule("gate", &["gate"], None),
+        rule("disclosure", &["low severity"], None),
+    ])
+    .unwrap();
+    let source = Feed {
+        url: "https://advisories.example.com/rss.xml".into(),
+        ..Default::default()
+    };
+    let tags: Vec<Vec<String>> = load("rss.xml", &source)
+        .iter()
+        .map(|item| tagger.tags(item))
+        .collect();
+    assert_eq!(tags, [vec!["rce"], vec!["disclosure"]]);
+    assert!(Tagger::new(&[rule("bad", &[], Some("(unclosed"))]).is_err());
+}
+
+#[test]
+fn entity_graph() {
+    let source = Feed {
+        name: "Example Threat Reports".into(),
+        url: "https://intel.example.com/rss.xml".into(),
+        ..Default::default()
+    };
+    let mut items = load("indicators.xml", &source);
+    // As `tag_rules` naming the campaign and its infrastructure would tag them
+    items[0].tags = Some(vec!["loader".into()]);
+    items[1].tags = Some(vec!["loader".into(), "shared-c2".into()]);
+    let edges = graph::edges(&items);
+    compare(
+        "indicators.xml.graph.json",
+        serde_json::to_value(&edges).unwrap(),
+    );
+    let graphml = graph::graphml(&edges);
+    assert!(graphml.contains(
+        r#"<node id="cve:CVE-2025-21298"><data key="label">CVE-2025-21298</data><data key="kind">cve</data></node>"#
+    ));
+    assert!(graphml.contains(r#"<edge source="tag:loader" target="tag:shared-c2">"#));
+}
+
+#[test]
+fn internal_addresses() {
+    let internal = [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:169.254.169.254",
+        "::169.254.169.254",
+        "64:ff9b::a9fe:a9fe",
+        "64:ff9b::127.0.0.1",
+        "2002:a00:1::1",
+        "2002:7f00:1::",
+    ];
+    for addr in internal {
+        assert!(urlguard::is_internal(addr.parse().unwrap()), "{addr}");
+    }
+    for addr in [
+        "93.184.216.34",
+        "172.32.0.1",
+        "100.128.0.1",
+        "2606:4700::1111",
+        "64:ff9b::5db8:d822",
+        "2002:5db8:d822::1",
+    ] {
+        assert!(!urlguard::is_internal(addr.parse().unwrap()), "{addr}");
+    }
+}
+
+#[test]
+fn canonical_links() {
+    let cases = [
+        (
+            "HTTPS://Blog.Example.COM:443/posts/rce/#comments",
+            false,
+            "https://blog.example.com/posts/rce/",
+        ),
+        (
+            "http://blog.example.com:80/posts/rce?",
+            false,
+            "http://blog.example.com/posts/rce",
+        ),
+        (
+            "https://blog.example.com/?p=42#more",
+            false,
+            "https://blog.example.com/?p=42",
+        ),
+        (
+            "https://blog.example.com:8443/posts//",
+            false,
+            "https://blog.example.com:8443/posts//",
+        ),
+        (
+            "http://blog.example.com/posts/rce",
+            true,
+            "https://blog.example.com/posts/rce",
+        ),
+        (
+            "http://blog.example.com:8080/posts/rce",
+            true,
+            "http://blog.example.com:8080/posts/rce",
+        ),
+        ("mid:abc%40example.org", true, "mid:abc%40example.org"),
+    ];
+    for (link, prefer_https, canonical) in cases {
+        assert_eq!(links::canonical(link, prefer_https), canonical, "{link}");
+    }
+}
+
+#[test]
+fn canonical_link_guids() {
+    let rss = |link: &str| {
+        format!(
+            r#"<rss version="2.0"><channel><title>Blog</title>
+            <item><title>RCE in a VPN appliance</title><link>{link}</link></item>
+            </channel></rss>"#
+        )
+    };
+    let guid = |link: &str, prefer_https: bool| {
+        let source = Feed {
+            name: "Blog".into(),
+            url: "https://blog.example.com/feed".into(),
+            prefer_https,
+            ..Default::default()
+        };
+        let items = process_feed_bytes(rss(link).as_bytes(), &source).expect("feed parses");
+        (items[0].guid.clone(), items[0].link.clone())
+    };
+    // A link already canonical keeps the ID feed-rs derives from it
+    let (https, link) = guid("https://blog.example.com/posts/rce/", false);
+    let parsed = feed_rs::parser::parse(rss("https://blog.example.com/posts/rce/").as_bytes());
+    assert_eq!(https, parsed.expect("feed parses").entries[0].id);
+    assert_eq!(link, "https://blog.example.com/posts/rce/");
+    assert_eq!(
+        guid("https://Blog.Example.com/posts/rce/#top", false).0,
+        https
+    );
+    assert_ne!(guid("http://blog.example.com/posts/rce/", false).0, https);
+    assert_eq!(
+        guid("http://blog.example.com/posts/rce/", true),
+        (https, link)
+    );
+}
+
+#[test]
+fn revision_diff_size() {
+    let cases = [
+        ("Patch now.", "Patch now.", 0),
+        ("CVSS 9.8", "CVSS 7.5", 3),
+        ("Patch now.", "Patch now. Exploited in the wild.", 23),
+        ("Fixed in 1.2 and 1.3", "Fixed in 1.3", 8),
+        ("", "New advisory", 12),
+    ];
+    for (old, new, size) in cases {
+        assert_eq!(revisions::diff_size(old, new), size, "{old} -> {new}");
+    }
+}
@@ -0,0 +1,407 @@
+//! End-to-end tests on `testkit`: real ingestion cycles against a throwaway Postgres
+//! (or, for `SqliteKit`, an in-memory SQLite database). The Postgres ones need Docker,
+//! or a server in `TESTKIT_DATABASE_URL`:
+//! `cargo test --features integration --test integration`.
+#![cfg(feature = "integration")]
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rust_feed_ingestor::config::UnshortenSettings;
+use rust_feed_ingestor::db_utils::{self, Cursor, EntryQuery};
+use rust_feed_ingestor::dedup;
+use rust_feed_ingestor::storage::Storage;
+use rust_feed_ingestor::testkit::{SqliteKit, TestKit};
+use rust_feed_ingestor::tlp::Tlp;
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    fs::read(path).expect("read fixture")
+}
+
+/// Near-duplicate detection, which is process-wide, for as long as this is held, even
+/// if the test fails.
+struct NearDuplicates;
+
+impl NearDuplicates {
+    fn enable(max_distance: u32, window: Duration) -> Self {
+        dedup::enable_near_duplicates(max_distance, window);
+        NearDuplicates
+    }
+}
+
+impl Drop for NearDuplicates {
+    fn drop(&mut self) {
+        dedup::disable_near_duplicates();
+    }
+}
+
+async fn statuses(kit: &TestKit, feed: &str) -> Vec<String> {
+    sqlx::query_scalar("SELECT status FROM fetch_history WHERE feed_name = $1 ORDER BY id")
+        .bind(feed)
+        .fetch_all(&kit.pool)
+        .await
+        .expect("read fetch_history")
+}
+
+#[tokio::test]
+async fn cycle_stores_entries_once() {
+    let kit = TestKit::start().await.expect("start testkit");
+    kit.feeds
+        .serve("/rss.xml", "application/rss+xml", fixture("rss.xml"));
+    let feed = kit.feeds.feed("Fixture RSS", "/rss.xml");
+    let settings = kit.settings(vec![feed.clone()]).expect("settings");
+    let pipeline = kit.pipeline(&settings).expect("pipeline");
+
+    // Two valid items; the third, with a blank title, is rejected by sanitization
+    let runs = kit.run_cycle(&pipeline, &settings.feeds).await;
+    assert!(runs[0].reached && runs[0].errors == 1, "{:?}", runs[0]);
+    assert_eq!(kit.count("archive").await.unwrap(), 2);
+    assert_eq!(kit.count("current").await.unwrap(), 2);
+
+    // The same document again: nothing new is archived
+    kit.run_cycle(&pipeline, &settings.feeds).await;
+    assert_eq!(kit.count("archive").await.unwrap(), 2);
+    assert_eq!(statuses(&kit, &feed.name).await, ["fetched", "unchanged"]);
+    kit.close().await.expect("close testkit");
+}
+
+#[tokio::test]
+async fn edited_entries_are_revised() {
+    let kit = TestKit::start().await.expect("start testkit");
+    let original = String::from_utf8(fixture("rss.xml")).unwrap();
+    kit.feeds
+        .serve("/rss.xml", "application/rss+xml", original.clone());
+    let feed = kit.feeds.feed("Fixture RSS", "/rss.xml");
+    let settings = kit.settings(vec![feed]).expect("settings");
+    let pipeline = kit.pipeline(&settings).expect("pipeline");
+    kit.run_cycle(&pipeline, &settings.feeds).await;
+    assert_eq!(kit.count("revisions").await.unwrap(), 0);
+
+    // The advisory is updated upstream: archive keeps the first version
+    let edited = original.replace("Low severity issue.", "High severity, exploited.");
+    kit.feeds.serve("/rss.xml", "application/rss+xml", edited);
+    kit.run_cycle(&pipeline, &settings.feeds).await;
+    let run = pipeline.last_runs.get("Fixture RSS").expect("last run");
+    let outcomes: Vec<Option<&str>> = run.entries.iter().map(|e| e.dedup.as_deref()).collect();
+    assert_eq!(outcomes, [Some("known"), Some("updated"), None]);
+    let revisions: Vec<(String, Option<String>, i64)> =
+        sqlx::query_as("SELECT guid, summary, diff_bytes FROM revisions")
+            .fetch_all(&kit.pool)
+            .await
+            .expect("read revisions");
+    assert_eq!(
+        revisions,
+        [(
+            "EXA-2025-002".to_string(),
+            Some("TLP:GREEN. High severity, exploited.".to_string()),
+            48, // the summary's 24 bytes, and the content's taken from it
+        )]
+    );
+    let (archived,): (Option<String>,) =
+        sqlx::query_as("SELECT summary FROM archive WHERE guid = 'EXA-2025-002'")
+            .fetch_one(&kit.pool)
+            .await
+            .expect("read archive");
+    assert_eq!(archived.as_deref(), Some("TLP:GREEN. Low severity issue."));
+    kit.close().await.expect("close testkit");
+}
+
+#[tokio::test]
+async fn products_are_extracted_from_new_and_revised_entries() {
+    let kit = TestKit::start().await.expect("start testkit");
+    let original = String::from_utf8(fixture("products.xml")).unwrap();
+    kit.feeds
+        .serve("/products.xml", "application/rss+xml", original.clone());
+    let feed = kit.feeds.feed("Fixture CERT", "/products.xml");
+    let settings = kit.settings(vec![feed]).expect("settings");
+    let pipeline = kit.pipeline(&settings).expect("pipeline");
+    kit.run_cycle(&pipeline, &settings.feeds).await;
+    assert_eq!(kit.count("affected_products").await.unwrap(), 6);
+    let query = EntryQuery {
+        product: Some("openssl".into()),
+        ..Default::default()
+    };
+    let listed = db_utils::list_entries(&kit.pool, &query).await.unwrap();
+    let guids: Vec<&str> = listed.iter().map(|item| item.guid.as_str()).collect();
+    assert_eq!(guids, ["https://cert.example.net/advisories/102"]);
+
+    // Unchanged entries in a changed document are not re-extracted; a revised one is
+    sqlx::query("DELETE FROM affected_products")
+        .execute(&kit.pool)
+        .await
+        .unwrap();
+    let edited = original
+        .replace("naming affected products", "naming affected software")
+        .replace("Struts 2.0.0 through 2.5.25", "Struts 2.0.0 through 2.5.30");
+    kit.feeds
+        .serve("/products.xml", "application/rss+xml", edited);
+    kit.run_cycle(&pipeline, &settings.feeds).await;
+    let stored: Vec<(String, String)> =
+        sqlx::query_as("SELECT guid, versions FROM affected_products")
+            .fetch_all(&kit.pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        stored,
+        [(
+            "https://cert.example.net/advisories/101".to_string(),
+            ">= 2.0.0, <= 2.5.30".to_string()
+        )]
+    );
+    kit.close().await.expect("close testkit");
+}
+
+#[cfg(feature = "graphql")]
+#[tokio::test]
+async fn graphql_queries_entries_and_feeds() {
+    use rust_feed_ingestor::graphql;
+    use rust_feed_ingestor::scheduler::Schedule;
+    use serde_json::json;
+
+    let kit = TestKit::start().await.expect("start testkit");
+    let original = String::from_utf8(fixture("rss.xml")).unwrap();
+    kit.feeds
+        .serve("/rss.xml", "application/rss+xml", original.clone());
+    let feed = kit.feeds.feed("Fixture RSS", "/rss.xml");
+    let settings = kit.settings(vec![feed]).expect("settings");
+    let pipeline = kit.pipeline(&settings).expect("pipeline");
+    kit.run_cycle(&pipeline, &settings.feeds).await;
+    let edited = original.replace("Low severity issue.", "High severity, exploited.");
+    kit.feeds.serve("/rss.xml", "application/rss+xml", edited);
+    kit.run_cycle(&pipeline, &settings.feeds).await;
+
+    let schedule = Schedule::new(&settings.feeds, Duration::from_secs(300));
+    let query = |q: &str| {
+        let request = async_graphql::Request::new(q);
+        let (pool, schedule) = (&kit.pool, &schedule);
+        async move {
+            let response = graphql::execute(pool, schedule, request).await;
+            assert!(response.errors.is_empty(), "{:?}", response.errors);
+            response.data.into_json().expect("JSON data")
+        }
+    };
+
+    let page = query(
+        "{ entries(first: 1, filter: { tag: null }) {
+            nodes { guid summary revisions { diffBytes } feed { title } }
+            nextCursor
+        } }",
+    )
+    .await;
+    assert_eq!(
+        page["entries"]["nodes"],
+        json!([{
+            "guid": "EXA-2025-002",
+            "summary": "TLP:GREEN. High severity, exploited.",
+            "revisions": [{ "diffBytes": 48 }],
+            "feed": { "title": "Example Advisories" },
+        }])
+    );
+    let cursor = page["entries"]["nextCursor"].as_str().expect("next cursor");
+    let next = query(&format!(
+        r#"{{ entries(first: 1, after: "{cursor}") {{ nodes {{ guid }} }} }}"#
+    ))
+    .await;
+    assert_eq!(
+        next["entries"]["nodes"],
+        json!([{ "guid": "EXA-2025-001" }])
+    );
+
+    // The archive keeps the first version; feeds report their fetch history
+    let first_seen = query(
+        r#"{ entry(guid: "EXA-2025-002", source: ARCHIVE) { summary }
+            feeds { name history { status } } }"#,
+    )
+    .await;
+    assert_eq!(
+        first_seen,
+        json!({
+            "entry": { "summary": "TLP:GREEN. Low severity issue." },
+            "feeds": [{
+                "name": "Fixture RSS",
+                "history": [{ "status": "fetched" }, { "status": "fetched" }],
+            }],
+        })
+    );
+    kit.close().await.expect("close testkit");
+}
+
+#[tokio::test]
+async fn failing_feed_is_recorded() {
+    let kit = TestKit::start().await.expect("start testkit");
+    let feed = kit.feeds.feed("Missing", "/missing.xml");
+    let settings = kit.settings(vec![feed.clone()]).expect("settings");
+    let pipeline = kit.pipeline(&settings).expect("pipeline");
+
+    let runs = kit.run_cycle(&pipeline, &settings.feeds).await;
+    assert!(!runs[0].reached && runs[0].errors > 0, "{:?}", runs[0]);
+    assert_eq!(kit.count("archive").await.unwrap(), 0);
+    assert_eq!(statuses(&kit, &feed.name).await, ["failed"]);
+    let schedule = pipeline.schedule.snapshot();
+    assert_eq!(schedule[0].consecutive_failures, 1);
+    kit.close().await.expect("close testkit");
+}
+
+#[tokio::test]
+async fn disabled_feed_stays_disabled_after_restart() {
+    use rust_feed_ingestor::config::FeedHealthSettings;
+    use rust_feed_ingestor::scheduler::{self, Schedule};
+
+    let kit = TestKit::start().await.expect("start testkit");
+    let feed = kit.feeds.feed("Missing", "/missing.xml");
+    let mut settings = kit.settings(vec![feed.clone()]).expect("settings");
+    settings.feed_health = Some(FeedHealthSettings {
+        disable_after: 1,
+        ..FeedHealthSettings::default()
+    });
+    let pipeline = kit.pipeline(&settings).expect("pipeline");
+    kit.run_cycle(&pipeline, &settings.feeds).await;
+
+    // A fresh schedule, as built at startup, picks up the saved state
+    let restarted = Schedule::new(&settings.feeds, settings.ingest_interval)
+        .with_health(settings.feed_health.clone());
+    let saved = scheduler::load(&kit.pool).await.expect("load schedules");
+    assert_eq!(restarted.restore(&saved), 1);
+    let entry = restarted.get(&feed.name).expect("feed schedule");
+    assert!(entry.disabled, "{entry:?}");
+    assert_eq!(entry.consecutive_failures, 1);
+    assert!(entry.health < 1.0, "{entry:?}");
+    assert!(restarted.due(chrono::Utc::now()).is_empty());
+    kit.close().await.expect("close testkit");
+}
+
+#[tokio::test]
+async fn shortened_links_are_resolved() {
+    let kit = TestKit::start().await.expect("start testkit");
+    kit.feeds
+        .redirect("/go/rce", &kit.feeds.url("/posts/rce/#top"));
+    kit.feeds
+        .serve("/posts/rce/", "text/html", "<title>RCE</title>");
+    let rss = format!(
+        r#"<rss version="2.0"><channel><title>Aggregator</title>
+        <item><guid>agg-1</guid><title>RCE in a VPN appliance</title><link>{}</link></item>
+        <item><guid>agg-2</guid><title>Patch Tuesday</title><link>{}</link></item>
+        </channel></rss>"#,
+        kit.feeds.url("/go/rce"),
+        kit.feeds.url("/posts/patch"),
+    );
+    kit.feeds.serve("/agg.xml", "application/rss+xml", rss);
+    let feed = kit.feeds.feed("Aggregator", "/agg.xml");
+    let mut settings = kit.settings(vec![feed]).expect("settings");
+    // Only the /go/ links are shortened, but every link on the test server qualifies
+    settings.unshorten = Some(UnshortenSettings {
+        hosts: vec!["127.0.0.1".to_string()],
+        timeout: Duration::from_secs(5),
+        concurrency: 2,
+        cache: 10,
+    });
+    let pipeline = kit.pipeline(&settings).expect("pipeline");
+
+    kit.run_cycle(&pipeline, &settings.feeds).await;
+    let links: Vec<(String, String, Option<String>)> =
+        sqlx::query_as("SELECT guid, link, resolved_link FROM archive ORDER BY guid")
+            .fetch_all(&kit.pool)
+            .await
+            .expect("read archive");
+    assert_eq!(
+        links,
+        [
+            (
+                "agg-1".to_string(),
+                kit.feeds.url("/go/rce"),
+                Some(kit.feeds.url("/posts/rce/")),
+            ),
+            ("agg-2".to_string(), kit.feeds.url("/posts/patch"), None),
+        ]
+    );
+
+    // The vendor's own entry for the page is linked to the aggregator's
+    let _near_duplicates = NearDuplicates::enable(0, Duration::from_secs(3600));
+    let rss = format!(
+        r#"<rss version="2.0"><channel><title>Vendor</title>
+        <item><guid>vendor-1</guid><title>Security advisory</title><link>{}</link></item>
+        </channel></rss>"#,
+        kit.feeds.url("/posts/rce/"),
+    );
+    kit.feeds.serve("/vendor.xml", "application/rss+xml", rss);
+    let vendor = kit.feeds.feed("Vendor", "/vendor.xml");
+    kit.run_cycle(&pipeline, &[vendor]).await;
+    let original: Option<String> =
+        sqlx::query_scalar("SELECT duplicate_of FROM archive WHERE guid = 'vendor-1'")
+            .fetch_one(&kit.pool)
+            .await
+            .expect("read archive");
+    assert_eq!(original.as_deref(), Some("agg-1"));
+    kit.close().await.expect("close testkit");
+}
+
+#[tokio::test]
+async fn sqlite_stores_lists_and_revises_entries() {
+    let kit = SqliteKit::start().await.expect("start testkit");
+    let report = kit.storage.check_schema().await.expect("check schema");
+    assert!(report.is_compatible(), "{report}");
+    let original = String::from_utf8(fixture("rss.xml")).unwrap();
+    kit.feeds
+        .serve("/rss.xml", "application/rss+xml", original.clone());
+    let mut feed = kit.feeds.feed("Fixture RSS", "/rss.xml");
+    feed.tags = vec!["vendor".to_string()];
+    let settings = kit.settings(vec![feed.clone()]).expect("settings");
+    let pipeline = kit.pipeline(&settings).expect("pipeline");
+
+    let runs = kit.run_cycle(&pipeline, &settings.feeds).await;
+    assert!(runs[0].reached && runs[0].errors == 1, "{:?}", runs[0]);
+    assert_eq!(kit.count("archive").await.unwrap(), 2);
+    let query = EntryQuery {
+        tag: Some("vendor".into()),
+        limit: 1,
+        ..Default::default()
+    };
+    let first = kit.storage.list_entries(&query).await.unwrap();
+    assert_eq!(first[0].guid, "EXA-2025-002");
+    assert_eq!(first[0].tlp, Some(Tlp::Green));
+    let query = EntryQuery {
+        cursor: first.last().map(Cursor::after),
+        ..query
+    };
+    let second = kit.storage.list_entries(&query).await.unwrap();
+    assert_eq!(second[0].guid, "EXA-2025-001");
+    assert_eq!(
+        second[0].categories.as_deref(),
+        Some(&["rce".to_string(), "gateway".to_string()][..])
+    );
+    let query = EntryQuery {
+        q: Some("GATEWAY".into()),
+        ..Default::default()
+    };
+    let found = kit.storage.list_entries(&query).await.unwrap();
+    assert_eq!(found.len(), 1);
+
+    // As on Postgres: archive keeps the first version, revisions the edit
+    let edited = original.replace("Low severity issue.", "High severity, exploited.");
+    kit.feeds.serve("/rss.xml", "application/rss+xml", edited);
+    kit.run_cycle(&pipeline, &settings.feeds).await;
+    let run = pipeline.last_runs.get("Fixture RSS").expect("last run");
+    let outcomes: Vec<Option<&str>> = run.entries.iter().map(|e| e.dedup.as_deref()).collect();
+    assert_eq!(outcomes, [Some("known"), Some("updated"), None]);
+    let revisions: Vec<(String, i64)> = sqlx::query_as("SELECT guid, diff_bytes FROM revisions")
+        .fetch_all(kit.storage.pool())
+        .await
+        .expect("read revisions");
+    assert_eq!(revisions, [("EXA-2025-002".to_string(), 48)]);
+    let archive = EntryQuery {
+        archive: true,
+        ..Default::default()
+    };
+    let archived = kit.storage.list_entries(&archive).await.unwrap();
+    assert_eq!(
+        archived[0].summary.as_deref(),
+        Some("TLP:GREEN. Low severity issue.")
+    );
+    let history = kit.storage.recent_history(&feed.name, 10).await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(kit.storage.load_schedules().await.unwrap().len(), 1);
+}